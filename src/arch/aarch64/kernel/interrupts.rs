@@ -1,4 +1,5 @@
 use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
 use core::arch::asm;
 use core::sync::atomic::{AtomicU64, Ordering};
 
@@ -93,7 +94,8 @@ pub(crate) fn install_handlers() {
 	fn timer_handler() {
 		debug!("Handle timer interrupt");
 
-		// disable timer
+		// disable timer, which is re-armed for the next wakeup or the end of
+		// the time slice by `handle_waiting_tasks`
 		CNTP_CVAL_EL0.set(0);
 		CNTP_CTL_EL0.write(CNTP_CTL_EL0::ENABLE::CLEAR);
 	}
@@ -192,7 +194,9 @@ pub(crate) extern "C" fn do_sync(state: &State) {
 	let pc = ELR_EL1.get();
 
 	/* data abort from lower or current level */
-	if (ec == ESR_EL1::EC::Value::SoftwareStepCurrentEL)
+	if (ec == ESR_EL1::EC::Value::DataAbortCurrentEL)
+		|| (ec == ESR_EL1::EC::Value::DataAbortLowerEL)
+		|| (ec == ESR_EL1::EC::Value::SoftwareStepCurrentEL)
 		|| (ec == ESR_EL1::EC::Value::SoftwareStepLowerEL)
 	{
 		/* check if value in far_el1 is valid */
@@ -208,6 +212,7 @@ pub(crate) extern "C" fn do_sync(state: &State) {
 			error!("Thread ID register {:#x}", TPIDR_EL0.get());
 			error!("Table Base Register {:#x}", TTBR0_EL1.get());
 			error!("Exception Syndrome Register {esr:#x}");
+			scheduler::report_stack_overflow(VirtAddr::new(far));
 
 			if let Some(irqid) = GicV3::get_and_acknowledge_interrupt(InterruptGroup::Group1) {
 				GicV3::end_interrupt(irqid, InterruptGroup::Group1);
@@ -215,6 +220,7 @@ pub(crate) extern "C" fn do_sync(state: &State) {
 				error!("Unable to acknowledge interrupt!");
 			}
 
+			print_backtrace(state);
 			scheduler::abort()
 		} else {
 			error!("Unknown exception");
@@ -238,6 +244,7 @@ pub(crate) extern "C" fn do_sync(state: &State) {
 		core_scheduler().fpu_switch();
 	} else {
 		error!("Unsupported exception class: {ec_raw:#x}, PC={pc:#x}");
+		print_backtrace(state);
 
 		loop {
 			core::hint::spin_loop();
@@ -246,19 +253,26 @@ pub(crate) extern "C" fn do_sync(state: &State) {
 }
 
 #[unsafe(no_mangle)]
-pub(crate) extern "C" fn do_bad_mode(_state: &State, reason: u32) -> ! {
+pub(crate) extern "C" fn do_bad_mode(state: &State, reason: u32) -> ! {
 	error!("Receive unhandled exception: {reason}");
+	print_backtrace(state);
 
 	scheduler::abort()
 }
 
 #[unsafe(no_mangle)]
-pub(crate) extern "C" fn do_error(_state: &State) -> ! {
+pub(crate) extern "C" fn do_error(state: &State) -> ! {
 	error!("Receive error interrupt");
+	print_backtrace(state);
 
 	scheduler::abort()
 }
 
+/// Prints the call chain of the interrupted context.
+fn print_backtrace(state: &State) {
+	crate::backtrace::print_exception(state.elr_el1 as usize, state.x29 as usize);
+}
+
 pub fn wakeup_core(core_id: CoreId) {
 	debug!("Wakeup core {core_id}");
 	let reschedid = IntId::sgi(SGI_RESCHED.into());
@@ -522,6 +536,25 @@ impl IrqStatistics {
 	}
 }
 
+/// Returns the name and the per-core counters of every interrupt that occurred at least once.
+pub(crate) fn irq_statistics() -> Vec<(u8, Option<&'static str>, Vec<u64>)> {
+	let irq_counters = IRQ_COUNTERS.lock();
+	(0..=u8::MAX)
+		.filter_map(|irq| {
+			let counters = irq_counters
+				.values()
+				.map(|irq_statistics| {
+					irq_statistics.counters[usize::from(irq)].load(Ordering::Relaxed)
+				})
+				.collect::<Vec<_>>();
+			counters
+				.iter()
+				.any(|counter| *counter > 0)
+				.then(|| (irq, get_irq_name(irq), counters))
+		})
+		.collect()
+}
+
 pub(crate) fn print_statistics() {
 	info!("Number of interrupts");
 	for (core_id, irg_statistics) in IRQ_COUNTERS.lock().iter() {
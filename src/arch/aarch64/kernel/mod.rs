@@ -1,3 +1,4 @@
+pub mod backtrace;
 pub mod core_local;
 pub mod interrupts;
 #[cfg(feature = "kernel-stack")]
@@ -1,3 +1,4 @@
+use alloc::string::{String, ToString};
 use core::arch::asm;
 use core::fmt;
 use core::mem::offset_of;
@@ -127,6 +128,15 @@ impl FPUState {
 			);
 		}
 	}
+
+	/// Saves the FPU state of a task, which is not running, but still owns
+	/// the FPU. Afterwards, floating point instructions trap, so that the
+	/// next use of the FPU restores the state of the respective task.
+	pub fn save_and_release(&mut self) {
+		CPACR_EL1.modify(CPACR_EL1::FPEN::TrapNothing);
+		self.save();
+		CPACR_EL1.modify(CPACR_EL1::FPEN::TrapEl0El1);
+	}
 }
 
 // System counter frequency in KHz
@@ -349,6 +359,21 @@ pub fn set_oneshot_timer(wakeup_time: Option<u64>) {
 	});
 }
 
+/// Returns the compatible string of the first processor in the device tree.
+pub fn model_name() -> Option<String> {
+	let fdt = env::fdt()?;
+	let cpu0 = fdt.cpus().next()?;
+	let compatible = cpu0.property("compatible")?.as_str()?;
+	Some(compatible.to_string())
+}
+
+/// Returns a space-separated list of the supported processor features.
+///
+/// Feature detection is not implemented on AArch64 yet.
+pub fn features() -> String {
+	String::new()
+}
+
 pub fn print_information() {
 	let fdt = env::fdt().unwrap();
 	let cpu0 = fdt.cpus().next().unwrap();
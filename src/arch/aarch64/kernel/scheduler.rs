@@ -15,6 +15,8 @@ use crate::mm::physicalmem::PHYSICAL_FREE_LIST;
 use crate::mm::virtualmem::KERNEL_FREE_LIST;
 #[cfg(target_os = "none")]
 use crate::scheduler::PerCoreSchedulerExt;
+#[cfg(feature = "kernel-stack")]
+use crate::scheduler::task::STACK_CANARY;
 use crate::scheduler::task::{Task, TaskFrame};
 use crate::{DEFAULT_STACK_SIZE, KERNEL_STACK_SIZE};
 
@@ -100,6 +102,8 @@ pub struct BootStack {
 	stack: VirtAddr,
 }
 
+/// Stacks of a task, which are allocated by the kernel, in the layout
+/// `[guard | kernel stack | guard | user stack]`
 pub struct CommonStack {
 	/// start address of allocated virtual memory region
 	virt_addr: VirtAddr,
@@ -171,11 +175,15 @@ impl TaskStacks {
 			);
 		}
 
-		TaskStacks::Common(CommonStack {
+		let stacks = TaskStacks::Common(CommonStack {
 			virt_addr,
 			phys_addr,
 			total_size,
-		})
+		});
+		#[cfg(feature = "kernel-stack")]
+		stacks.set_canary();
+
+		stacks
 	}
 
 	pub fn from_boot_stacks() -> TaskStacks {
@@ -214,6 +222,42 @@ impl TaskStacks {
 			TaskStacks::Common(_) => DEFAULT_STACK_SIZE,
 		}
 	}
+
+	/// Returns the name of the stack, whose guard page contains `addr`.
+	pub fn guard_page(&self, addr: VirtAddr) -> Option<&'static str> {
+		let TaskStacks::Common(_) = self else {
+			return None;
+		};
+
+		[
+			("kernel", self.get_kernel_stack()),
+			("user", self.get_user_stack()),
+		]
+		.into_iter()
+		.find(|(_, stack)| (*stack - BasePageSize::SIZE..*stack).contains(&addr))
+		.map(|(name, _)| name)
+	}
+
+	/// Writes the canary to the bottom of the kernel stack.
+	#[cfg(feature = "kernel-stack")]
+	fn set_canary(&self) {
+		unsafe {
+			self.get_kernel_stack()
+				.as_mut_ptr::<u64>()
+				.write_volatile(STACK_CANARY);
+		}
+	}
+
+	/// Returns `false` if the canary at the bottom of the kernel stack has been overwritten.
+	#[cfg(feature = "kernel-stack")]
+	pub fn check_canary(&self) -> bool {
+		match self {
+			TaskStacks::Boot(_) => true,
+			TaskStacks::Common(_) => unsafe {
+				self.get_kernel_stack().as_ptr::<u64>().read_volatile() == STACK_CANARY
+			},
+		}
+	}
 }
 
 impl Drop for TaskStacks {
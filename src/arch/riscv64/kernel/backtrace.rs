@@ -0,0 +1,31 @@
+//! Frame-pointer based stack walking
+
+use core::arch::asm;
+
+/// Returns the frame pointer of the calling function.
+#[inline(always)]
+pub fn frame_pointer() -> usize {
+	let fp: usize;
+	unsafe {
+		asm!("mv {}, s0", out(reg) fp, options(nomem, nostack, preserves_flags));
+	}
+	fp
+}
+
+/// Returns the frame pointer of the caller and the return address, which are
+/// stored in the frame record below `fp`.
+///
+/// # Safety
+///
+/// `fp` has to point directly above a valid frame record.
+pub unsafe fn unwind(fp: usize) -> (usize, usize) {
+	let record = frame_record(fp) as *const usize;
+	unsafe { (record.read(), record.add(1).read()) }
+}
+
+/// Returns the address of the frame record, which is referenced by `fp`.
+///
+/// On RISC-V, the frame pointer points to the end of the frame record.
+pub fn frame_record(fp: usize) -> usize {
+	fp.wrapping_sub(2 * core::mem::size_of::<usize>())
+}
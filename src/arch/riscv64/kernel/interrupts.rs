@@ -3,6 +3,7 @@ use alloc::vec::Vec;
 use ahash::RandomState;
 use hashbrown::HashMap;
 use hermit_sync::{InterruptTicketMutex, OnceCell, SpinMutex};
+use memory_addresses::VirtAddr;
 use riscv::asm::wfi;
 use riscv::interrupt::{Exception, Interrupt, Trap};
 use riscv::register::{scause, sie, sip, sstatus, stval};
@@ -178,6 +179,17 @@ pub extern "C" fn trap_handler(tf: &mut TrapFrame) {
 			error!("stval = {stval:x}");
 			error!("sepc = {sepc:x}");
 			error!("SSTATUS FS = {:?}", sstatus::read().fs());
+			if let Trap::Exception(
+				Exception::LoadPageFault
+				| Exception::StorePageFault
+				| Exception::InstructionPageFault,
+			) = cause
+			{
+				if let Ok(addr) = VirtAddr::try_new(stval as u64) {
+					scheduler::report_stack_overflow(addr);
+				}
+			}
+			crate::backtrace::print_exception(sepc, tf.general.s0);
 			scheduler::abort();
 		}
 	}
@@ -232,4 +244,9 @@ fn external_handler() {
 	}
 }
 
+/// Interrupts are not counted on RISC-V yet.
+pub(crate) fn irq_statistics() -> Vec<(u8, Option<&'static str>, Vec<u64>)> {
+	Vec::new()
+}
+
 pub(crate) fn print_statistics() {}
@@ -1,3 +1,4 @@
+pub mod backtrace;
 pub mod core_local;
 mod devicetree;
 pub mod interrupts;
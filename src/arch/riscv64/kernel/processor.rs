@@ -1,3 +1,4 @@
+use alloc::string::{String, ToString};
 use core::arch::asm;
 use core::convert::TryInto;
 use core::num::NonZeroU64;
@@ -5,6 +6,7 @@ use core::num::NonZeroU64;
 use riscv::register::{sie, sstatus, time};
 
 use crate::arch::riscv64::kernel::{HARTS_AVAILABLE, get_timebase_freq};
+use crate::env;
 use crate::scheduler::CoreId;
 
 /// Current FPU state. Saved at context switch when changed
@@ -209,6 +211,22 @@ impl FPUState {
 	}
 }
 
+/// Returns the compatible string of the first hart in the device tree.
+pub fn model_name() -> Option<String> {
+	let fdt = env::fdt()?;
+	let cpu0 = fdt.cpus().next()?;
+	let compatible = cpu0.property("compatible")?.as_str()?;
+	Some(compatible.to_string())
+}
+
+/// Returns the ISA string of the first hart in the device tree.
+pub fn features() -> String {
+	env::fdt()
+		.and_then(|fdt| fdt.cpus().next()?.property("riscv,isa")?.as_str())
+		.unwrap_or_default()
+		.to_string()
+}
+
 pub fn seed_entropy() -> Option<[u8; 32]> {
 	None
 }
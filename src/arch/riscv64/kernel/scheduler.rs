@@ -9,6 +9,8 @@ use crate::arch::riscv64::kernel::processor::set_oneshot_timer;
 use crate::arch::riscv64::mm::paging::{BasePageSize, PageSize, PageTableEntryFlags};
 use crate::mm::physicalmem::PHYSICAL_FREE_LIST;
 use crate::mm::virtualmem::KERNEL_FREE_LIST;
+#[cfg(feature = "kernel-stack")]
+use crate::scheduler::task::STACK_CANARY;
 use crate::scheduler::task::{Task, TaskFrame};
 use crate::{DEFAULT_STACK_SIZE, KERNEL_STACK_SIZE};
 
@@ -84,6 +86,8 @@ pub struct BootStack {
 	stack: VirtAddr,
 }
 
+/// Stacks of a task, which are allocated by the kernel, in the layout
+/// `[guard | IST0 | guard | kernel stack | guard | user stack]`
 pub struct CommonStack {
 	/// start address of allocated virtual memory region
 	virt_addr: VirtAddr,
@@ -171,11 +175,15 @@ impl TaskStacks {
 
 		debug!("Creating stacks finished");
 
-		TaskStacks::Common(CommonStack {
+		let stacks = TaskStacks::Common(CommonStack {
 			virt_addr,
 			phys_addr,
 			total_size,
-		})
+		});
+		#[cfg(feature = "kernel-stack")]
+		stacks.set_canary();
+
+		stacks
 	}
 
 	pub fn from_boot_stacks() -> TaskStacks {
@@ -219,6 +227,43 @@ impl TaskStacks {
 			TaskStacks::Common(_) => DEFAULT_STACK_SIZE,
 		}
 	}
+
+	/// Returns the name of the stack, whose guard page contains `addr`.
+	pub fn guard_page(&self, addr: VirtAddr) -> Option<&'static str> {
+		let TaskStacks::Common(stacks) = self else {
+			return None;
+		};
+
+		[
+			("interrupt", stacks.virt_addr + BasePageSize::SIZE),
+			("kernel", self.get_kernel_stack()),
+			("user", self.get_user_stack()),
+		]
+		.into_iter()
+		.find(|(_, stack)| (*stack - BasePageSize::SIZE..*stack).contains(&addr))
+		.map(|(name, _)| name)
+	}
+
+	/// Writes the canary to the bottom of the kernel stack.
+	#[cfg(feature = "kernel-stack")]
+	fn set_canary(&self) {
+		unsafe {
+			self.get_kernel_stack()
+				.as_mut_ptr::<u64>()
+				.write_volatile(STACK_CANARY);
+		}
+	}
+
+	/// Returns `false` if the canary at the bottom of the kernel stack has been overwritten.
+	#[cfg(feature = "kernel-stack")]
+	pub fn check_canary(&self) -> bool {
+		match self {
+			TaskStacks::Boot(_) => true,
+			TaskStacks::Common(_) => unsafe {
+				self.get_kernel_stack().as_ptr::<u64>().read_volatile() == STACK_CANARY
+			},
+		}
+	}
 }
 
 impl Clone for TaskStacks {
@@ -356,8 +401,10 @@ unsafe extern "C" {
 
 pub fn timer_handler() {
 	//increment_irq_counter(apic::TIMER_INTERRUPT_NUMBER.into());
-	core_scheduler().handle_waiting_tasks();
+	// Clear the pending interrupt. Handling the waiting tasks re-arms the timer
+	// for the next wakeup or the end of the time slice, whichever comes first.
 	set_oneshot_timer(None);
+	core_scheduler().handle_waiting_tasks();
 	core_scheduler().scheduler();
 }
 
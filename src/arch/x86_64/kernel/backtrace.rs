@@ -0,0 +1,29 @@
+//! Frame-pointer based stack walking
+
+use core::arch::asm;
+
+/// Returns the frame pointer of the calling function.
+#[inline(always)]
+pub fn frame_pointer() -> usize {
+	let fp: usize;
+	unsafe {
+		asm!("mov {}, rbp", out(reg) fp, options(nomem, nostack, preserves_flags));
+	}
+	fp
+}
+
+/// Returns the frame pointer of the caller and the return address, which are
+/// stored in the frame record at `fp`.
+///
+/// # Safety
+///
+/// `fp` has to point to a valid frame record.
+pub unsafe fn unwind(fp: usize) -> (usize, usize) {
+	let record = fp as *const usize;
+	unsafe { (record.read(), record.add(1).read()) }
+}
+
+/// Returns the address of the frame record, which is referenced by `fp`.
+pub fn frame_record(fp: usize) -> usize {
+	fp
+}
@@ -1,4 +1,5 @@
 use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
 use core::arch::asm;
 use core::sync::atomic::{AtomicU64, Ordering};
 
@@ -13,7 +14,7 @@ use x86_64::structures::idt::InterruptDescriptorTable;
 pub use x86_64::structures::idt::InterruptStackFrame as ExceptionStackFrame;
 
 use crate::arch::x86_64::kernel::core_local::{core_scheduler, increment_irq_counter};
-use crate::arch::x86_64::kernel::{apic, processor};
+use crate::arch::x86_64::kernel::{apic, backtrace, processor};
 use crate::arch::x86_64::mm::paging::{BasePageSize, PageSize, page_fault_handler};
 use crate::arch::x86_64::swapgs;
 use crate::drivers::InterruptHandlerQueue;
@@ -189,53 +190,54 @@ fn handle_interrupt(stack_frame: ExceptionStackFrame, index: u8, _error_code: Op
 	crate::arch::x86_64::swapgs(&stack_frame);
 }
 
+#[inline(always)]
 fn abort(stack_frame: ExceptionStackFrame, index: u8, error_code: Option<u64>) {
 	error!("Exception {index}");
 	error!("Error code: {error_code:?}");
 	error!("Stack frame: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn divide_error_exception(stack_frame: ExceptionStackFrame) {
 	swapgs(&stack_frame);
 	error!("Divide Error (#DE) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn debug_exception(stack_frame: ExceptionStackFrame) {
 	swapgs(&stack_frame);
 	error!("Debug (#DB) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn nmi_exception(stack_frame: ExceptionStackFrame) {
 	swapgs(&stack_frame);
 	error!("Non-Maskable Interrupt (NMI) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn breakpoint_exception(stack_frame: ExceptionStackFrame) {
 	swapgs(&stack_frame);
 	error!("Breakpoint (#BP) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn overflow_exception(stack_frame: ExceptionStackFrame) {
 	swapgs(&stack_frame);
 	error!("Overflow (#OF) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn bound_range_exceeded_exception(stack_frame: ExceptionStackFrame) {
 	swapgs(&stack_frame);
 	error!("BOUND Range Exceeded (#BR) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn invalid_opcode_exception(stack_frame: ExceptionStackFrame) {
 	swapgs(&stack_frame);
 	error!("Invalid Opcode (#UD) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn device_not_available_exception(stack_frame: ExceptionStackFrame) {
@@ -258,7 +260,7 @@ extern "x86-interrupt" fn device_not_available_exception(stack_frame: ExceptionS
 extern "x86-interrupt" fn invalid_tss_exception(stack_frame: ExceptionStackFrame, _code: u64) {
 	swapgs(&stack_frame);
 	error!("Invalid TSS (#TS) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn segment_not_present_exception(
@@ -267,7 +269,7 @@ extern "x86-interrupt" fn segment_not_present_exception(
 ) {
 	swapgs(&stack_frame);
 	error!("Segment Not Present (#NP) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn stack_segment_fault_exception(
@@ -276,7 +278,7 @@ extern "x86-interrupt" fn stack_segment_fault_exception(
 ) {
 	swapgs(&stack_frame);
 	error!("Stack Segment Fault (#SS) Exception: {stack_frame:#?}, error {error_code:#X}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn general_protection_exception(
@@ -290,7 +292,7 @@ extern "x86-interrupt" fn general_protection_exception(
 		processor::readfs(),
 		processor::readgs()
 	);
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn double_fault_exception(
@@ -299,37 +301,48 @@ extern "x86-interrupt" fn double_fault_exception(
 ) -> ! {
 	swapgs(&stack_frame);
 	error!("Double Fault (#DF) Exception: {stack_frame:#?}, error {error_code:#X}");
-	scheduler::abort()
+	abort_with_backtrace(&stack_frame)
 }
 
 extern "x86-interrupt" fn floating_point_exception(stack_frame: ExceptionStackFrame) {
 	swapgs(&stack_frame);
 	error!("Floating-Point Error (#MF) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn alignment_check_exception(stack_frame: ExceptionStackFrame, _code: u64) {
 	swapgs(&stack_frame);
 	error!("Alignment Check (#AC) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn machine_check_exception(stack_frame: ExceptionStackFrame) -> ! {
 	swapgs(&stack_frame);
 	error!("Machine Check (#MC) Exception: {stack_frame:#?}");
-	scheduler::abort()
+	abort_with_backtrace(&stack_frame)
 }
 
 extern "x86-interrupt" fn simd_floating_point_exception(stack_frame: ExceptionStackFrame) {
 	swapgs(&stack_frame);
 	error!("SIMD Floating-Point (#XM) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
 }
 
 extern "x86-interrupt" fn virtualization_exception(stack_frame: ExceptionStackFrame) {
 	swapgs(&stack_frame);
 	error!("Virtualization (#VE) Exception: {stack_frame:#?}");
-	scheduler::abort();
+	abort_with_backtrace(&stack_frame);
+}
+
+/// Prints the call chain of the interrupted context and aborts the current task.
+///
+/// Has to be inlined into an exception handler, whose frame record links to
+/// the frame record of the interrupted context.
+#[inline(always)]
+pub(crate) fn abort_with_backtrace(stack_frame: &ExceptionStackFrame) -> ! {
+	let (fp, _) = unsafe { backtrace::unwind(backtrace::frame_pointer()) };
+	crate::backtrace::print_exception(stack_frame.instruction_pointer.as_u64() as usize, fp);
+	scheduler::abort()
 }
 
 pub(crate) fn add_irq_name(irq_number: u8, name: &'static str) {
@@ -362,6 +375,25 @@ impl IrqStatistics {
 	}
 }
 
+/// Returns the name and the per-core counters of every interrupt that occurred at least once.
+pub(crate) fn irq_statistics() -> Vec<(u8, Option<&'static str>, Vec<u64>)> {
+	let irq_counters = IRQ_COUNTERS.lock();
+	(0..=u8::MAX)
+		.filter_map(|irq| {
+			let counters = irq_counters
+				.values()
+				.map(|irq_statistics| {
+					irq_statistics.counters[usize::from(irq)].load(Ordering::Relaxed)
+				})
+				.collect::<Vec<_>>();
+			counters
+				.iter()
+				.any(|counter| *counter > 0)
+				.then(|| (irq, get_irq_name(irq), counters))
+		})
+		.collect()
+}
+
 pub(crate) fn print_statistics() {
 	panic_println!("Number of interrupts");
 	for (core_id, irg_statistics) in IRQ_COUNTERS.lock().iter() {
@@ -13,6 +13,7 @@ use crate::env::{self, is_uhyve};
 #[cfg(feature = "acpi")]
 pub mod acpi;
 pub mod apic;
+pub mod backtrace;
 pub mod core_local;
 pub mod gdt;
 pub mod interrupts;
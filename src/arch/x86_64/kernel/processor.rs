@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 use alloc::boxed::Box;
+use alloc::string::{String, ToString};
 use core::arch::asm;
 use core::arch::x86_64::{
 	__rdtscp, _fxrstor, _fxsave, _mm_lfence, _rdseed64_step, _rdtsc, _xrstor, _xsave, _xsavec,
@@ -209,6 +210,19 @@ impl FPUState {
 		}
 	}
 
+	/// Saves the FPU state of a task, which is not running, but still owns
+	/// the FPU. Afterwards, the FPU is disabled, so that the next use of the
+	/// FPU restores the state of the respective task.
+	pub fn save_and_release(&mut self) {
+		unsafe {
+			asm!("clts", options(nomem, nostack));
+		}
+		self.save();
+		unsafe {
+			Cr0::update(|flags| flags.insert(Cr0Flags::TASK_SWITCHED));
+		}
+	}
+
 	pub fn restore_common(&self) {
 		unsafe {
 			_fxrstor(self.xsave_area.as_ptr().cast::<u8>());
@@ -961,6 +975,21 @@ pub fn print_information() {
 	infofooter!();
 }
 
+/// Returns the brand string of the processor.
+pub fn model_name() -> Option<String> {
+	CpuId::new()
+		.get_processor_brand_string()
+		.map(|brand_string| brand_string.as_str().trim().to_string())
+}
+
+/// Returns a space-separated list of the supported processor features.
+pub fn features() -> String {
+	CpuFeaturePrinter::new(&CpuId::new())
+		.to_string()
+		.trim_end()
+		.to_lowercase()
+}
+
 pub fn seed_entropy() -> Option<[u8; 32]> {
 	let mut buf = [0; 32];
 	if FEATURES.supports_rdseed {
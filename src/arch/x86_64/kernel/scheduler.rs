@@ -18,6 +18,8 @@ use crate::env;
 use crate::mm::physicalmem::PHYSICAL_FREE_LIST;
 use crate::mm::virtualmem::KERNEL_FREE_LIST;
 use crate::scheduler::PerCoreSchedulerExt;
+#[cfg(feature = "kernel-stack")]
+use crate::scheduler::task::STACK_CANARY;
 use crate::scheduler::task::{Task, TaskFrame};
 
 #[repr(C, packed)]
@@ -70,6 +72,8 @@ pub struct BootStack {
 	ist1: VirtAddr,
 }
 
+/// Stacks of a task, which are allocated by the kernel, in the layout
+/// `[guard | IST1 | guard | kernel stack | guard | user stack | guard]`
 pub struct CommonStack {
 	/// start address of allocated virtual memory region
 	virt_addr: VirtAddr,
@@ -151,11 +155,15 @@ impl TaskStacks {
 			);
 		}
 
-		TaskStacks::Common(CommonStack {
+		let stacks = TaskStacks::Common(CommonStack {
 			virt_addr,
 			phys_addr,
 			total_size,
-		})
+		});
+		#[cfg(feature = "kernel-stack")]
+		stacks.set_canary();
+
+		stacks
 	}
 
 	pub fn from_boot_stacks() -> TaskStacks {
@@ -213,6 +221,43 @@ impl TaskStacks {
 	pub fn get_interrupt_stack_size(&self) -> usize {
 		IST_SIZE
 	}
+
+	/// Returns the name of the stack, whose guard page contains `addr`.
+	pub fn guard_page(&self, addr: VirtAddr) -> Option<&'static str> {
+		let TaskStacks::Common(_) = self else {
+			return None;
+		};
+
+		[
+			("interrupt", self.get_interrupt_stack()),
+			("kernel", self.get_kernel_stack()),
+			("user", self.get_user_stack()),
+		]
+		.into_iter()
+		.find(|(_, stack)| (*stack - BasePageSize::SIZE..*stack).contains(&addr))
+		.map(|(name, _)| name)
+	}
+
+	/// Writes the canary to the bottom of the kernel stack.
+	#[cfg(feature = "kernel-stack")]
+	fn set_canary(&self) {
+		unsafe {
+			self.get_kernel_stack()
+				.as_mut_ptr::<u64>()
+				.write_volatile(STACK_CANARY);
+		}
+	}
+
+	/// Returns `false` if the canary at the bottom of the kernel stack has been overwritten.
+	#[cfg(feature = "kernel-stack")]
+	pub fn check_canary(&self) -> bool {
+		match self {
+			TaskStacks::Boot(_) => true,
+			TaskStacks::Common(_) => unsafe {
+				self.get_kernel_stack().as_ptr::<u64>().read_volatile() == STACK_CANARY
+			},
+		}
+	}
 }
 
 impl Drop for TaskStacks {
@@ -327,6 +372,8 @@ impl TaskFrame for Task {
 
 extern "x86-interrupt" fn timer_handler(_stack_frame: interrupts::ExceptionStackFrame) {
 	increment_irq_counter(apic::TIMER_INTERRUPT_NUMBER);
+	// The one-shot timer has expired. Handling the waiting tasks re-arms it for
+	// the next wakeup or the end of the time slice, whichever comes first.
 	core_scheduler().handle_waiting_tasks();
 	apic::eoi();
 	core_scheduler().reschedule();
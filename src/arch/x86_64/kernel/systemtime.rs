@@ -193,6 +193,16 @@ pub fn init() {
 }
 
 /// Returns the current time in microseconds since UNIX epoch.
+#[cfg(target_os = "none")]
 pub fn now_micros() -> u64 {
 	*BOOT_TIME.get().unwrap() + super::processor::get_timer_ticks()
 }
+
+/// Returns the current time of the host, because unit tests do not boot.
+#[cfg(not(target_os = "none"))]
+pub fn now_micros() -> u64 {
+	let now = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap();
+	u64::try_from(now.as_micros()).unwrap()
+}
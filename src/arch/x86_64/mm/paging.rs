@@ -15,6 +15,7 @@ use x86_64::structures::paging::{
 	Mapper, OffsetPageTable, Page, PageTable, PhysFrame, RecursivePageTable, Size4KiB, Translate,
 };
 
+use crate::arch::x86_64::kernel::interrupts::abort_with_backtrace;
 use crate::arch::x86_64::kernel::processor;
 use crate::arch::x86_64::mm::{PhysAddr, VirtAddr};
 use crate::mm::physicalmem;
@@ -26,7 +27,6 @@ pub trait PageTableEntryFlagsExt {
 
 	fn normal(&mut self) -> &mut Self;
 
-	#[cfg(feature = "acpi")]
 	fn read_only(&mut self) -> &mut Self;
 
 	fn writable(&mut self) -> &mut Self;
@@ -55,7 +55,6 @@ impl PageTableEntryFlagsExt for PageTableEntryFlags {
 		self
 	}
 
-	#[cfg(feature = "acpi")]
 	fn read_only(&mut self) -> &mut Self {
 		self.remove(PageTableEntryFlags::WRITABLE);
 		self
@@ -279,13 +278,15 @@ pub(crate) extern "x86-interrupt" fn page_fault_handler(
 	stack_frame: ExceptionStackFrame,
 	error_code: PageFaultErrorCode,
 ) {
+	let addr = Cr2::read().unwrap();
 	error!("Page fault (#PF)!");
-	error!("page_fault_linear_address = {:p}", Cr2::read().unwrap());
+	error!("page_fault_linear_address = {addr:p}");
 	error!("error_code = {error_code:?}");
 	error!("fs = {:#X}", processor::readfs());
 	error!("gs = {:#X}", processor::readgs());
 	error!("stack_frame = {stack_frame:#?}");
-	scheduler::abort();
+	scheduler::report_stack_overflow(VirtAddr::new(addr.as_u64()));
+	abort_with_backtrace(&stack_frame);
 }
 
 #[cfg(feature = "common-os")]
@@ -298,13 +299,15 @@ pub(crate) extern "x86-interrupt" fn page_fault_handler(
 			core::arch::asm!("swapgs", options(nostack));
 		}
 	}
+	let addr = Cr2::read().unwrap();
 	error!("Page fault (#PF)!");
-	error!("page_fault_linear_address = {:p}", Cr2::read().unwrap());
+	error!("page_fault_linear_address = {addr:p}");
 	error!("error_code = {error_code:?}");
 	error!("fs = {:#X}", processor::readfs());
 	error!("gs = {:#X}", processor::readgs());
 	error!("stack_frame = {stack_frame:#?}");
-	scheduler::abort();
+	scheduler::report_stack_overflow(VirtAddr::new(addr.as_u64()));
+	abort_with_backtrace(&stack_frame);
 }
 
 pub fn init() {
@@ -0,0 +1,121 @@
+//! Frame-pointer based kernel backtraces
+//!
+//! Return addresses are symbolized with the symbol table, which `xtask build`
+//! embeds into the kernel. Kernels built without `xtask` print raw addresses.
+//! Backtraces are printed with `panic_println!`, as they are usually printed
+//! from contexts, in which the console may already be locked.
+
+use memory_addresses::VirtAddr;
+
+use crate::arch::kernel::backtrace::{frame_pointer, frame_record, unwind};
+use crate::arch::mm::paging::virtual_to_physical;
+
+/// Maximum number of frames, which are printed.
+const MAX_FRAMES: usize = 64;
+
+/// Prints the call chain of the current function.
+#[inline(always)]
+pub(crate) fn print() {
+	panic_println!("Backtrace:");
+	walk(frame_pointer(), 0);
+}
+
+/// Prints the call chain of an interrupted context, which executed `pc` with
+/// the frame pointer `fp`.
+pub(crate) fn print_exception(pc: usize, fp: usize) {
+	panic_println!("Backtrace:");
+	print_frame(0, pc);
+	walk(fp, 1);
+}
+
+/// Prints the return addresses of the frame records, which start at the
+/// frame pointer `fp`.
+///
+/// The walk stops at the first frame record, which is not mapped or does
+/// not lie above the previous one.
+fn walk(mut fp: usize, first: usize) {
+	for i in first..MAX_FRAMES {
+		let record = frame_record(fp);
+		if fp == 0 || record % align_of::<usize>() != 0 || !is_mapped(record) {
+			break;
+		}
+
+		let (next, ret) = unsafe { unwind(fp) };
+		if ret == 0 {
+			break;
+		}
+		// The return address points behind the call, which may already
+		// belong to the next function.
+		print_frame(i, ret - 1);
+
+		// Stacks grow downwards, so the caller's frame has to be above.
+		if next <= fp {
+			break;
+		}
+		fp = next;
+	}
+}
+
+fn print_frame(i: usize, addr: usize) {
+	match symtab::lookup(addr) {
+		Some((name, offset)) => panic_println!("{i:>4}: {addr:#018x} - {name}+{offset:#x}"),
+		None => panic_println!("{i:>4}: {addr:#018x} - <unknown>"),
+	}
+}
+
+/// Returns `true` if the frame record at `addr` can be read.
+fn is_mapped(addr: usize) -> bool {
+	let end = addr.wrapping_add(2 * size_of::<usize>() - 1);
+	[addr, end].into_iter().all(|addr| {
+		VirtAddr::try_new(addr as u64).is_ok_and(|addr| virtual_to_physical(addr).is_some())
+	})
+}
+
+#[cfg(hermit_symtab)]
+mod symtab {
+	use core::{slice, str};
+
+	/// Function of the kernel, as generated by `xtask build --symtab`
+	#[repr(C)]
+	struct Symbol {
+		addr: usize,
+		size: usize,
+		name_offset: usize,
+		name_len: usize,
+	}
+
+	/// Layout of the symbol table, as generated by `xtask build --symtab`
+	#[repr(C)]
+	struct SymbolTable {
+		symbols: *const Symbol,
+		len: usize,
+		names: *const u8,
+		names_len: usize,
+	}
+
+	unsafe extern "C" {
+		static hermit_symtab: SymbolTable;
+	}
+
+	/// Returns the name of the function, which contains `addr`, and the offset
+	/// of `addr` within this function.
+	pub(super) fn lookup(addr: usize) -> Option<(&'static str, usize)> {
+		let symtab = unsafe { &hermit_symtab };
+		let symbols = unsafe { slice::from_raw_parts(symtab.symbols, symtab.len) };
+		let names = unsafe { slice::from_raw_parts(symtab.names, symtab.names_len) };
+
+		let symbol = symbols
+			.iter()
+			.find(|symbol| (symbol.addr..symbol.addr + symbol.size).contains(&addr))?;
+		let name = names.get(symbol.name_offset..symbol.name_offset + symbol.name_len)?;
+
+		Some((str::from_utf8(name).ok()?, addr - symbol.addr))
+	}
+}
+
+#[cfg(not(hermit_symtab))]
+mod symtab {
+	pub(super) fn lookup(_addr: usize) -> Option<(&'static str, usize)> {
+		None
+	}
+}
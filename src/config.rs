@@ -15,6 +15,7 @@ pub(crate) const USER_STACK_SIZE: usize = 0x0010_0000;
 	feature = "fuse",
 	feature = "vsock",
 	feature = "console",
+	feature = "virtio-blk",
 ))]
 pub(crate) const VIRTIO_MAX_QUEUE_SIZE: u16 = if cfg!(feature = "pci") { 2048 } else { 1024 };
 
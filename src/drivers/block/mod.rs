@@ -0,0 +1,131 @@
+//! Generic interface of block devices, on which disk file systems are mounted.
+//!
+//! Drivers register their devices by name. File systems look them up by the
+//! name given in the boot arguments.
+
+#[cfg(feature = "virtio-blk")]
+pub(crate) mod virtio_blk;
+#[cfg(feature = "virtio-blk")]
+mod virtio_pci;
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+
+use hermit_sync::InterruptSpinMutex;
+
+use crate::errno::Errno;
+use crate::io;
+
+static BLOCK_DEVICES: InterruptSpinMutex<BTreeMap<String, Arc<dyn BlockDevice>>> =
+	InterruptSpinMutex::new(BTreeMap::new());
+
+pub(crate) trait BlockDevice: Send + Sync + core::fmt::Debug {
+	/// Returns the size of a block in bytes.
+	fn block_size(&self) -> usize;
+
+	/// Returns the number of blocks of the device.
+	fn num_blocks(&self) -> u64;
+
+	/// Reads the blocks starting at `block` into `buf`, whose length is a
+	/// multiple of the block size.
+	fn read_blocks(&self, block: u64, buf: &mut [u8]) -> io::Result<()>;
+
+	/// Reads `buf.len()` bytes starting at the byte `offset`, which does not
+	/// have to be aligned to blocks.
+	fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+		let block_size = u64::try_from(self.block_size()).unwrap();
+		let end = offset
+			.checked_add(u64::try_from(buf.len()).unwrap())
+			.ok_or(Errno::Inval)?;
+		if end > self.num_blocks() * block_size {
+			return Err(Errno::Io);
+		}
+		if buf.is_empty() {
+			return Ok(());
+		}
+
+		let first = offset / block_size;
+		let last = end.div_ceil(block_size);
+		let mut blocks = vec![0u8; usize::try_from((last - first) * block_size).unwrap()];
+		self.read_blocks(first, &mut blocks)?;
+
+		let start = usize::try_from(offset - first * block_size).unwrap();
+		buf.copy_from_slice(&blocks[start..start + buf.len()]);
+		Ok(())
+	}
+}
+
+/// Makes the block device available under `name`.
+#[cfg_attr(not(feature = "virtio-blk"), expect(dead_code))]
+pub(crate) fn register_block_device(name: &str, device: Arc<dyn BlockDevice>) {
+	info!("Register block device {name}");
+	BLOCK_DEVICES.lock().insert(name.to_string(), device);
+}
+
+/// Returns the block device registered under `name`.
+pub(crate) fn get_block_device(name: &str) -> Option<Arc<dyn BlockDevice>> {
+	BLOCK_DEVICES.lock().get(name).cloned()
+}
+
+/// Helpers to test file systems on images, which the tools of the host create.
+#[cfg(all(test, not(target_os = "none")))]
+pub(crate) mod tests {
+	use std::path::Path;
+	use std::process::Command;
+
+	use super::BlockDevice;
+	use crate::io;
+
+	/// Block device, which is backed by an image file in memory
+	#[derive(Debug)]
+	pub(crate) struct ImageDevice(Vec<u8>);
+
+	impl ImageDevice {
+		/// Creates an image in a temporary directory. `populate` fills the
+		/// directory `root`, from which `commands` build the image file.
+		pub fn create(
+			name: &str,
+			populate: impl FnOnce(&Path),
+			commands: impl FnOnce(&Path, &Path) -> Vec<Command>,
+		) -> Self {
+			let dir = std::env::temp_dir().join(format!("hermit-{name}-{}", std::process::id()));
+			let root = dir.join("root");
+			std::fs::create_dir_all(&root).unwrap();
+			populate(&root);
+
+			let image = dir.join("fs.img");
+			for mut cmd in commands(&root, &image) {
+				let status = cmd
+					.status()
+					.unwrap_or_else(|e| panic!("Unable to run {cmd:?}: {e}"));
+				assert!(status.success(), "{cmd:?} failed with {status}");
+			}
+			let data = std::fs::read(&image).unwrap();
+			std::fs::remove_dir_all(&dir).unwrap();
+			Self(data)
+		}
+	}
+
+	impl BlockDevice for ImageDevice {
+		fn block_size(&self) -> usize {
+			512
+		}
+
+		fn num_blocks(&self) -> u64 {
+			(self.0.len() / 512) as u64
+		}
+
+		fn read_blocks(&self, block: u64, buf: &mut [u8]) -> io::Result<()> {
+			let start = usize::try_from(block).unwrap() * 512;
+			buf.copy_from_slice(&self.0[start..start + buf.len()]);
+			Ok(())
+		}
+	}
+
+	/// Returns `len` bytes, whose pattern does not repeat at block boundaries.
+	pub fn pattern(len: u32) -> Vec<u8> {
+		(0..len).map(|i| (i % 251) as u8).collect()
+	}
+}
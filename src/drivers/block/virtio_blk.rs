@@ -0,0 +1,251 @@
+//! Driver for virtio block devices, which are registered as `vda`, `vdb`, ...
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use hermit_sync::InterruptTicketMutex;
+use pci_types::InterruptLine;
+use smallvec::SmallVec;
+use virtio::{le32, le64};
+
+use crate::config::VIRTIO_MAX_QUEUE_SIZE;
+use crate::drivers::Driver;
+use crate::drivers::block::{self, BlockDevice};
+use crate::drivers::virtio::error::VirtioBlkError;
+use crate::drivers::virtio::transport::pci::{ComCfg, IsrStatus, NotifCfg};
+use crate::drivers::virtio::virtqueue::split::SplitVq;
+use crate::drivers::virtio::virtqueue::{
+	AvailBufferToken, BufferElem, BufferType, VirtQueue, Virtq, VqIndex, VqSize,
+};
+use crate::errno::Errno;
+use crate::io;
+use crate::mm::device_alloc::DeviceAlloc;
+
+/// Size of a sector, in which the device addresses its content
+const SECTOR_SIZE: usize = 512;
+
+/// Maximal number of bytes, which are read by a single request
+const MAX_REQUEST_LEN: usize = 64 * 1024;
+
+/// Request type of `VIRTIO_BLK_T_IN`, which reads from the device
+const VIRTIO_BLK_T_IN: u32 = 0;
+
+/// Status `VIRTIO_BLK_S_OK` of a successful request
+const VIRTIO_BLK_S_OK: u8 = 0;
+
+/// Leading part of the device configuration, see Virtio specification v1.1. - 5.2.4
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BlkConfig {
+	/// Capacity of the device in sectors of 512 bytes
+	pub capacity: le64,
+}
+
+/// Header of a request, see Virtio specification v1.1. - 5.2.6
+#[repr(C)]
+#[derive(Debug)]
+struct BlkReqHeader {
+	type_: le32,
+	reserved: le32,
+	sector: le64,
+}
+
+/// Virtio block device driver struct.
+#[allow(dead_code)]
+pub(crate) struct VirtioBlkDriver {
+	pub(super) dev_id: u16,
+	pub(super) capacity: u64,
+	pub(super) com_cfg: ComCfg,
+	pub(super) isr_stat: IsrStatus,
+	pub(super) notif_cfg: NotifCfg,
+	pub(super) vqueues: Vec<VirtQueue>,
+	pub(super) irq: InterruptLine,
+}
+
+impl VirtioBlkDriver {
+	pub fn get_dev_id(&self) -> u16 {
+		self.dev_id
+	}
+
+	pub fn set_failed(&mut self) {
+		self.com_cfg.set_failed();
+	}
+
+	/// Initializes the device in adherence to specification.
+	///
+	/// See Virtio specification v1.1. - 3.1.1.
+	///                      and v1.1. - 5.2.5
+	pub(crate) fn init_dev(&mut self) -> Result<(), VirtioBlkError> {
+		// Reset
+		self.com_cfg.reset_dev();
+
+		// Indicate device, that OS noticed it
+		self.com_cfg.ack_dev();
+
+		// Indicate device, that driver is able to handle it
+		self.com_cfg.set_drv();
+
+		let features = virtio::F::VERSION_1;
+		let device_features = self.com_cfg.dev_features();
+		if !device_features.contains(features) {
+			return Err(VirtioBlkError::IncompatibleFeatureSets(
+				features,
+				device_features,
+			));
+		}
+		self.com_cfg.set_drv_features(features);
+
+		// Indicates the device, that the current feature set is final for the driver
+		// and will not be changed.
+		self.com_cfg.features_ok();
+
+		// Checks if the device has accepted final set. This finishes feature negotiation.
+		if !self.com_cfg.check_features() {
+			return Err(VirtioBlkError::FailFeatureNeg(self.dev_id));
+		}
+
+		// Without VIRTIO_BLK_F_MQ, the device provides a single request queue.
+		let vq = VirtQueue::Split(
+			SplitVq::new(
+				&mut self.com_cfg,
+				&self.notif_cfg,
+				VqSize::from(VIRTIO_MAX_QUEUE_SIZE),
+				VqIndex::from(0u16),
+				features,
+			)
+			.map_err(|_| VirtioBlkError::Unknown)?,
+		);
+		self.vqueues.push(vq);
+
+		// At this point the device is "live"
+		self.com_cfg.drv_ok();
+
+		Ok(())
+	}
+
+	/// Reads the sectors starting at `sector` into `buf`.
+	fn read_sectors(&mut self, sector: u64, buf: &mut [u8]) -> io::Result<()> {
+		let header = Box::new_in(
+			BlkReqHeader {
+				type_: le32::from_ne(VIRTIO_BLK_T_IN),
+				reserved: le32::from_ne(0),
+				sector: le64::from_ne(sector),
+			},
+			DeviceAlloc,
+		);
+		let mut send = SmallVec::new();
+		send.push(BufferElem::Sized(header));
+
+		let status = Box::<u8, _>::new_uninit_in(DeviceAlloc);
+		let recv = SmallVec::from_buf([
+			BufferElem::Vector(Vec::with_capacity_in(buf.len(), DeviceAlloc)),
+			BufferElem::Sized(status),
+		]);
+
+		let buffer_tkn = AvailBufferToken::new(send, recv).map_err(|_| Errno::Io)?;
+		let mut transfer_result = self.vqueues[0]
+			.dispatch_blocking(buffer_tkn, BufferType::Direct)
+			.map_err(|_| Errno::Io)?;
+
+		let data = transfer_result
+			.used_recv_buff
+			.pop_front_vec()
+			.ok_or(Errno::Io)?;
+		// SAFETY: The device writes the status byte after the data.
+		let status = unsafe { transfer_result.used_recv_buff.pop_front_downcast::<u8>() };
+		match status {
+			Some(status) if *status == VIRTIO_BLK_S_OK && data.len() == buf.len() => {
+				buf.copy_from_slice(&data);
+				Ok(())
+			}
+			_ => Err(Errno::Io),
+		}
+	}
+}
+
+impl Driver for VirtioBlkDriver {
+	fn get_interrupt_number(&self) -> InterruptLine {
+		self.irq
+	}
+
+	fn get_name(&self) -> &'static str {
+		"virtio"
+	}
+}
+
+/// Block device, which forwards the requests to a virtio block device
+pub(crate) struct VirtioBlkDevice {
+	num_blocks: u64,
+	driver: InterruptTicketMutex<VirtioBlkDriver>,
+}
+
+impl fmt::Debug for VirtioBlkDevice {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("VirtioBlkDevice")
+			.field("num_blocks", &self.num_blocks)
+			.finish_non_exhaustive()
+	}
+}
+
+impl BlockDevice for VirtioBlkDevice {
+	fn block_size(&self) -> usize {
+		SECTOR_SIZE
+	}
+
+	fn num_blocks(&self) -> u64 {
+		self.num_blocks
+	}
+
+	fn read_blocks(&self, block: u64, buf: &mut [u8]) -> io::Result<()> {
+		if buf.len() % SECTOR_SIZE != 0 {
+			return Err(Errno::Inval);
+		}
+		let end = block
+			.checked_add((buf.len() / SECTOR_SIZE) as u64)
+			.ok_or(Errno::Inval)?;
+		if end > self.num_blocks {
+			return Err(Errno::Io);
+		}
+
+		let mut driver = self.driver.lock();
+		for (i, chunk) in buf.chunks_mut(MAX_REQUEST_LEN).enumerate() {
+			let sector = block + (i * MAX_REQUEST_LEN / SECTOR_SIZE) as u64;
+			driver.read_sectors(sector, chunk)?;
+		}
+		Ok(())
+	}
+}
+
+/// Registers the device as block device, which is named like on Linux.
+pub(crate) fn register(driver: VirtioBlkDriver) {
+	static COUNT: AtomicU8 = AtomicU8::new(0);
+
+	let index = COUNT.fetch_add(1, Ordering::Relaxed);
+	let Some(letter) = b'a'.checked_add(index).filter(u8::is_ascii_lowercase) else {
+		warn!("Too many virtio block devices, ignoring device {index}");
+		return;
+	};
+
+	let device = VirtioBlkDevice {
+		num_blocks: driver.capacity,
+		driver: InterruptTicketMutex::new(driver),
+	};
+	block::register_block_device(&format!("vd{}", char::from(letter)), Arc::new(device));
+}
+
+/// Error module of the virtio block device driver.
+pub mod error {
+	/// Virtio block device error enum.
+	#[derive(Debug, Copy, Clone)]
+	pub enum VirtioBlkError {
+		NoDevCfg(u16),
+		FailFeatureNeg(u16),
+		/// The first field contains the feature bits wanted by the driver.
+		/// but which are incompatible with the device feature set, second field.
+		IncompatibleFeatureSets(virtio::F, virtio::F),
+		Unknown,
+	}
+}
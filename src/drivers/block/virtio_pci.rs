@@ -0,0 +1,75 @@
+use alloc::vec::Vec;
+
+use crate::arch::pci::PciConfigRegion;
+use crate::drivers::block::virtio_blk::{BlkConfig, VirtioBlkDriver};
+use crate::drivers::pci::PciDevice;
+use crate::drivers::virtio::error::{self, VirtioError};
+use crate::drivers::virtio::transport::pci;
+use crate::drivers::virtio::transport::pci::UniCapsColl;
+
+impl VirtioBlkDriver {
+	/// Instantiates a new [`VirtioBlkDriver`] struct, by checking the available
+	/// configuration structures and moving them into the struct.
+	pub fn new(
+		caps_coll: UniCapsColl,
+		device: &PciDevice<PciConfigRegion>,
+	) -> Result<Self, error::VirtioBlkError> {
+		let device_id = device.device_id();
+
+		let UniCapsColl {
+			com_cfg,
+			notif_cfg,
+			isr_cfg,
+			dev_cfg_list,
+			..
+		} = caps_coll;
+
+		let Some(dev_cfg) = dev_cfg_list.iter().find_map(pci::map_dev_cfg::<BlkConfig>) else {
+			error!("No dev config. Aborting!");
+			return Err(error::VirtioBlkError::NoDevCfg(device_id));
+		};
+		// The capacity is fixed, because we don't negotiate a feature, which
+		// allows resizing the device.
+		let capacity = unsafe { core::ptr::read_volatile(&raw const dev_cfg.capacity) }.to_ne();
+
+		Ok(VirtioBlkDriver {
+			dev_id: device_id,
+			capacity,
+			com_cfg,
+			isr_stat: isr_cfg,
+			notif_cfg,
+			vqueues: Vec::new(),
+			irq: device.get_irq().unwrap(),
+		})
+	}
+
+	/// Initializes virtio block device
+	pub fn init(device: &PciDevice<PciConfigRegion>) -> Result<VirtioBlkDriver, VirtioError> {
+		let mut drv = match pci::map_caps(device) {
+			Ok(caps) => match VirtioBlkDriver::new(caps, device) {
+				Ok(driver) => driver,
+				Err(blk_err) => {
+					error!("Initializing new block device driver failed. Aborting!");
+					return Err(VirtioError::BlkDriver(blk_err));
+				}
+			},
+			Err(err) => {
+				error!("Mapping capabilities failed. Aborting!");
+				return Err(err);
+			}
+		};
+
+		match drv.init_dev() {
+			Ok(()) => info!(
+				"Block device with id {:x}, has been initialized by driver!",
+				drv.get_dev_id()
+			),
+			Err(blk_err) => {
+				drv.set_failed();
+				return Err(VirtioError::BlkDriver(blk_err));
+			}
+		}
+
+		Ok(drv)
+	}
+}
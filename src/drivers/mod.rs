@@ -1,5 +1,7 @@
 //! A module containing hermit-rs driver, hermit-rs driver trait and driver specific errors.
 
+#[cfg(feature = "block")]
+pub mod block;
 #[cfg(feature = "console")]
 pub mod console;
 #[cfg(feature = "fuse")]
@@ -19,6 +21,7 @@ pub mod pci;
 	feature = "fuse",
 	feature = "vsock",
 	feature = "console",
+	feature = "virtio-blk",
 ))]
 pub mod virtio;
 #[cfg(feature = "vsock")]
@@ -50,6 +53,7 @@ pub mod error {
 		feature = "fuse",
 		feature = "vsock",
 		feature = "console",
+		feature = "virtio-blk",
 	))]
 	use crate::drivers::virtio::error::VirtioError;
 
@@ -60,6 +64,7 @@ pub mod error {
 		feature = "fuse",
 		feature = "vsock",
 		feature = "console",
+		feature = "virtio-blk",
 	))]
 	#[derive(Debug)]
 	pub enum DriverError {
@@ -72,6 +77,7 @@ pub mod error {
 			feature = "fuse",
 			feature = "vsock",
 			feature = "console",
+			feature = "virtio-blk",
 		))]
 		InitVirtioDevFail(VirtioError),
 		#[cfg(all(target_arch = "x86_64", feature = "rtl8139"))]
@@ -89,6 +95,7 @@ pub mod error {
 		feature = "fuse",
 		feature = "vsock",
 		feature = "console",
+		feature = "virtio-blk",
 	))]
 	impl From<VirtioError> for DriverError {
 		fn from(err: VirtioError) -> Self {
@@ -117,6 +124,7 @@ pub mod error {
 		feature = "fuse",
 		feature = "vsock",
 		feature = "console",
+		feature = "virtio-blk",
 	))]
 	impl core::fmt::Display for DriverError {
 		#[allow(unused_variables)]
@@ -135,6 +143,7 @@ pub mod error {
 					feature = "fuse",
 					feature = "vsock",
 					feature = "console",
+					feature = "virtio-blk",
 				))]
 				DriverError::InitVirtioDevFail(ref err) => {
 					write!(f, "Virtio driver failed: {err:?}")
@@ -38,6 +38,7 @@ use crate::drivers::net::virtio::VirtioNetDriver;
 	feature = "fuse",
 	feature = "vsock",
 	feature = "console",
+	feature = "virtio-blk",
 ))]
 use crate::drivers::virtio::transport::pci as pci_virtio;
 #[cfg(any(
@@ -48,6 +49,7 @@ use crate::drivers::virtio::transport::pci as pci_virtio;
 	feature = "fuse",
 	feature = "vsock",
 	feature = "console",
+	feature = "virtio-blk",
 ))]
 use crate::drivers::virtio::transport::pci::VirtioDriver;
 #[cfg(feature = "vsock")]
@@ -488,6 +490,17 @@ pub(crate) fn get_filesystem_driver() -> Option<&'static InterruptTicketMutex<Vi
 		.find_map(|drv| drv.get_filesystem_driver())
 }
 
+/// Returns all virtio-fs drivers in the order, in which the devices were found.
+#[cfg(feature = "fuse")]
+pub(crate) fn get_filesystem_drivers()
+-> impl Iterator<Item = &'static InterruptTicketMutex<VirtioFsDriver>> {
+	PCI_DRIVERS
+		.get()
+		.into_iter()
+		.flatten()
+		.filter_map(|drv| drv.get_filesystem_driver())
+}
+
 pub(crate) fn init() {
 	// virtio: 4.1.2 PCI Device Discovery
 	without_interrupts(|| {
@@ -508,6 +521,7 @@ pub(crate) fn init() {
 				feature = "fuse",
 				feature = "vsock",
 				feature = "console",
+				feature = "virtio-blk",
 			))]
 			match pci_virtio::init_device(adapter) {
 				#[cfg(all(
@@ -532,6 +546,8 @@ pub(crate) fn init() {
 				Ok(VirtioDriver::FileSystem(drv)) => {
 					register_driver(PciDriver::VirtioFs(InterruptTicketMutex::new(drv)));
 				}
+				#[cfg(feature = "virtio-blk")]
+				Ok(VirtioDriver::Block(drv)) => crate::drivers::block::virtio_blk::register(*drv),
 				_ => {}
 			}
 		}
@@ -7,6 +7,8 @@ pub mod virtqueue;
 pub mod error {
 	use core::fmt;
 
+	#[cfg(feature = "virtio-blk")]
+	pub use crate::drivers::block::virtio_blk::error::VirtioBlkError;
 	#[cfg(feature = "console")]
 	pub use crate::drivers::console::error::VirtioConsoleError;
 	#[cfg(feature = "fuse")]
@@ -46,6 +48,8 @@ pub mod error {
 		VsockDriver(VirtioVsockError),
 		#[cfg(feature = "console")]
 		ConsoleDriver(VirtioConsoleError),
+		#[cfg(feature = "virtio-blk")]
+		BlkDriver(VirtioBlkError),
 		#[cfg(not(feature = "pci"))]
 		Unknown,
 	}
@@ -169,6 +173,26 @@ pub mod error {
 						)
 					}
 				},
+				#[cfg(feature = "virtio-blk")]
+				VirtioError::BlkDriver(blk_error) => match blk_error {
+					VirtioBlkError::NoDevCfg(id) => write!(
+						f,
+						"Virtio block device driver failed, for device {id:x}, due to a missing or malformed device config!"
+					),
+					VirtioBlkError::FailFeatureNeg(id) => write!(
+						f,
+						"Virtio block device driver failed, for device {id:x}, device did not acknowledge negotiated feature set!"
+					),
+					VirtioBlkError::IncompatibleFeatureSets(driver_features, device_features) => {
+						write!(
+							f,
+							"Feature set: {driver_features:?} , is incompatible with the device features: {device_features:?}"
+						)
+					}
+					VirtioBlkError::Unknown => {
+						write!(f, "Virtio block device driver failed due unknown reason!")
+					}
+				},
 				#[cfg(feature = "vsock")]
 				VirtioError::VsockDriver(vsock_error) => match vsock_error {
 					#[cfg(feature = "pci")]
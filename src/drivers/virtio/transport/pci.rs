@@ -3,7 +3,7 @@
 //! The module contains ...
 #![allow(dead_code)]
 
-#[cfg(any(feature = "vsock", feature = "console"))]
+#[cfg(any(feature = "vsock", feature = "console", feature = "virtio-blk"))]
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::ptr::NonNull;
@@ -21,6 +21,8 @@ use volatile::{VolatilePtr, VolatileRef};
 
 use crate::arch::memory_barrier;
 use crate::arch::pci::PciConfigRegion;
+#[cfg(feature = "virtio-blk")]
+use crate::drivers::block::virtio_blk::VirtioBlkDriver;
 #[cfg(feature = "console")]
 use crate::drivers::console::VirtioConsoleDriver;
 use crate::drivers::error::DriverError;
@@ -883,6 +885,19 @@ pub(crate) fn init_device(
 				}
 			}
 		}
+		#[cfg(feature = "virtio-blk")]
+		virtio::Id::Block => match VirtioBlkDriver::init(device) {
+			Ok(virt_blk_drv) => {
+				info!("Virtio block device driver initialized.");
+				Ok(VirtioDriver::Block(Box::new(virt_blk_drv)))
+			}
+			Err(virtio_error) => {
+				error!(
+					"Virtio block device driver could not be initialized with device: {device_id:x}"
+				);
+				Err(DriverError::InitVirtioDevFail(virtio_error))
+			}
+		},
 		id => {
 			warn!("Virtio device {id:?} is not supported, skipping!");
 
@@ -907,4 +922,6 @@ pub(crate) enum VirtioDriver {
 	Vsock(Box<VirtioVsockDriver>),
 	#[cfg(feature = "fuse")]
 	FileSystem(VirtioFsDriver),
+	#[cfg(feature = "virtio-blk")]
+	Block(Box<VirtioBlkDriver>),
 }
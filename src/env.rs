@@ -40,6 +40,10 @@ struct Cli {
 	args: Vec<String>,
 	#[allow(dead_code)]
 	mmio: Vec<String>,
+	/// Block device containing the root file system
+	root: Option<String>,
+	/// File systems given by `mount=` in the order of the arguments
+	mounts: Vec<String>,
 }
 
 /// Whether Hermit is running under the "uhyve" hypervisor.
@@ -96,6 +100,8 @@ impl Default for Cli {
 
 		let mut args = Vec::new();
 		let mut mmio = Vec::new();
+		let mut root = None;
+		let mut mounts = Vec::new();
 		while let Some(word) = words.next() {
 			if word.as_str().starts_with("virtio_mmio.device=") {
 				let v: Vec<&str> = word.as_str().split('=').collect();
@@ -137,6 +143,8 @@ impl Default for Cli {
 							};
 							env_vars.insert(key.to_string(), value.to_string());
 						}
+						"root" => root = Some(value.to_string()),
+						"mount" => mounts.push(value.to_string()),
 						_ => error!("could not parse bootarg: {word}"),
 					}
 				}
@@ -153,6 +161,8 @@ impl Default for Cli {
 			args,
 			#[allow(dead_code)]
 			mmio,
+			root,
+			mounts,
 		}
 	}
 }
@@ -177,6 +187,18 @@ pub fn args() -> &'static [String] {
 	CLI.get().unwrap().args.as_slice()
 }
 
+/// Returns the block device of the root file system if given through the
+/// `root=<block device>` command-line parameter.
+pub fn root() -> Option<&'static str> {
+	CLI.get().unwrap().root.as_deref()
+}
+
+/// Returns the file systems given through the repeatable
+/// `mount=<source>:<target>:<type>[:<options>]` command-line parameter.
+pub fn mounts() -> &'static [String] {
+	CLI.get().unwrap().mounts.as_slice()
+}
+
 /// Returns the configuration of all mmio devices
 #[allow(dead_code)]
 pub fn mmio() -> &'static [String] {
@@ -130,12 +130,25 @@ pub(crate) fn block_on<F, T>(future: F, timeout: Option<Duration>) -> io::Result
 where
 	F: Future<Output = io::Result<T>>,
 {
+	let mut future = pin!(future);
+
+	// Unit tests on the host have neither an executor nor a scheduler, so
+	// the future is simply polled until it is ready.
+	if cfg!(not(target_os = "none")) {
+		let mut cx = Context::from_waker(Waker::noop());
+		loop {
+			if let Poll::Ready(t) = future.as_mut().poll(&mut cx) {
+				return t;
+			}
+			core::hint::spin_loop();
+		}
+	}
+
 	let backoff = Backoff::new();
 	let start = crate::arch::kernel::systemtime::now_micros();
 	let task_notify = Arc::new(TaskNotify::new());
 	let waker = task_notify.clone().into();
 	let mut cx = Context::from_waker(&waker);
-	let mut future = pin!(future);
 
 	loop {
 		// check future
@@ -1,4 +1,6 @@
 use alloc::boxed::Box;
+#[cfg(feature = "tcp")]
+use alloc::string::String;
 #[cfg(feature = "dns")]
 use alloc::vec::Vec;
 use core::future;
@@ -18,7 +20,9 @@ use smoltcp::socket::tcp;
 use smoltcp::socket::udp;
 use smoltcp::time::{Duration, Instant};
 #[cfg(feature = "dns")]
-use smoltcp::wire::{DnsQueryType, IpAddress};
+use smoltcp::wire::DnsQueryType;
+#[cfg(any(feature = "dns", feature = "tcp"))]
+use smoltcp::wire::IpAddress;
 #[cfg(feature = "dhcpv4")]
 use smoltcp::wire::{IpCidr, Ipv4Address, Ipv4Cidr};
 
@@ -268,6 +272,75 @@ pub(crate) fn init() {
 	}
 }
 
+/// Formats the IPv4 TCP sockets in the layout of Linux's `/proc/net/tcp`.
+#[cfg(feature = "tcp")]
+pub(crate) fn tcp_socket_table() -> String {
+	use core::fmt::Write;
+
+	fn endpoint(addr: Option<IpAddress>, port: u16) -> Option<String> {
+		let octets = match addr {
+			Some(IpAddress::Ipv4(addr)) => addr.octets(),
+			Some(IpAddress::Ipv6(_)) => return None,
+			None => [0; 4],
+		};
+		Some(format!("{:08X}:{port:04X}", u32::from_le_bytes(octets)))
+	}
+
+	let mut table = String::from(
+		"  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n",
+	);
+
+	let mut guard = NIC.lock();
+	let Ok(nic) = guard.as_nic_mut() else {
+		return table;
+	};
+
+	let sockets = nic
+		.sockets
+		.iter()
+		.filter_map(|(_, socket)| tcp::Socket::downcast(socket));
+	for (sl, socket) in sockets.enumerate() {
+		let state = match socket.state() {
+			tcp::State::Closed => 0x07,
+			tcp::State::Listen => 0x0a,
+			tcp::State::SynSent => 0x02,
+			tcp::State::SynReceived => 0x03,
+			tcp::State::Established => 0x01,
+			tcp::State::FinWait1 => 0x04,
+			tcp::State::FinWait2 => 0x05,
+			tcp::State::CloseWait => 0x08,
+			tcp::State::Closing => 0x0b,
+			tcp::State::LastAck => 0x09,
+			tcp::State::TimeWait => 0x06,
+		};
+
+		let local = match socket.local_endpoint() {
+			Some(local) => endpoint(Some(local.addr), local.port),
+			None => {
+				let listen = socket.listen_endpoint();
+				endpoint(listen.addr, listen.port)
+			}
+		};
+		let remote = match socket.remote_endpoint() {
+			Some(remote) => endpoint(Some(remote.addr), remote.port),
+			None => endpoint(None, 0),
+		};
+		let (Some(local), Some(remote)) = (local, remote) else {
+			continue;
+		};
+
+		writeln!(
+			table,
+			"{sl:4}: {local} {remote} {state:02X} {:08X}:{:08X} 00:00000000 00000000     0        0 0",
+			socket.send_queue(),
+			socket.recv_queue()
+		)
+		.unwrap();
+	}
+
+	table
+}
+
 impl<'a> NetworkInterface<'a> {
 	#[cfg(feature = "udp")]
 	pub(crate) fn create_udp_handle(&mut self) -> Result<Handle, ()> {
@@ -0,0 +1,219 @@
+//! Implements advisory file locks, which are used by `fcntl` and `flock`.
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::future;
+use core::task::{Poll, Waker};
+
+use async_lock::Mutex;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::errno::Errno;
+use crate::executor::block_on;
+use crate::io;
+
+/// Owner of a file lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LockOwner {
+	/// Record locks of `fcntl` belong to the process. Hermit runs a single
+	/// process, so these locks never conflict with each other.
+	Process,
+	/// Locks of `flock` belong to the open file description, which is
+	/// identified by its address.
+	Description(usize),
+}
+
+impl LockOwner {
+	/// Returns `true` if locks of both owners exclude each other.
+	///
+	/// Like on Linux, record locks and `flock` locks are independent.
+	fn conflicts_with(self, other: LockOwner) -> bool {
+		matches!((self, other), (Self::Description(a), Self::Description(b)) if a != b)
+	}
+
+	/// Returns the lock owner, which is passed to FUSE.
+	pub fn id(self) -> u64 {
+		match self {
+			Self::Process => 0,
+			Self::Description(addr) => addr as u64,
+		}
+	}
+}
+
+/// Type of a lock, which uses the values of `F_RDLCK`, `F_WRLCK` and `F_UNLCK`
+#[derive(TryFromPrimitive, IntoPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i16)]
+pub(crate) enum LockType {
+	Read = 0,
+	Write = 1,
+	Unlock = 2,
+}
+
+/// Lock of the byte range `start..end` of a file
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RecordLock {
+	pub kind: LockType,
+	pub start: u64,
+	/// End of the range (exclusive), where `u64::MAX` extends the lock to
+	/// the end of the file, however large it becomes
+	pub end: u64,
+	pub owner: LockOwner,
+}
+
+impl RecordLock {
+	/// Returns a lock of the whole file, as used by `flock`.
+	pub fn whole_file(kind: LockType, owner: LockOwner) -> Self {
+		Self {
+			kind,
+			start: 0,
+			end: u64::MAX,
+			owner,
+		}
+	}
+
+	fn overlaps(&self, other: &RecordLock) -> bool {
+		self.start < other.end && other.start < self.end
+	}
+}
+
+#[derive(Debug, Default)]
+struct LockState {
+	locks: Vec<RecordLock>,
+	queue: VecDeque<Waker>,
+}
+
+impl LockState {
+	fn conflict(&self, lock: &RecordLock) -> Option<RecordLock> {
+		self.locks
+			.iter()
+			.find(|held| {
+				held.owner.conflicts_with(lock.owner)
+					&& held.overlaps(lock)
+					&& (held.kind == LockType::Write || lock.kind == LockType::Write)
+			})
+			.copied()
+	}
+
+	/// Replaces the locks of the owner within the range of `lock` by `lock`.
+	fn replace(&mut self, lock: RecordLock) {
+		let mut locks = Vec::with_capacity(self.locks.len() + 2);
+		for held in self.locks.drain(..) {
+			if held.owner != lock.owner || !held.overlaps(&lock) {
+				locks.push(held);
+				continue;
+			}
+
+			// keep the parts outside of the new lock
+			if held.start < lock.start {
+				locks.push(RecordLock {
+					end: lock.start,
+					..held
+				});
+			}
+			if lock.end < held.end {
+				locks.push(RecordLock {
+					start: lock.end,
+					..held
+				});
+			}
+		}
+
+		if lock.kind != LockType::Unlock {
+			locks.push(lock);
+		}
+		self.locks = locks;
+	}
+
+	fn wake_all(&mut self) {
+		for waker in self.queue.drain(..) {
+			waker.wake();
+		}
+	}
+}
+
+/// Locks of a file, which are shared by all open file descriptions of the file
+#[derive(Debug, Default)]
+pub(crate) struct FileLocks(Mutex<LockState>);
+
+impl FileLocks {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns a lock, which prevents placing `lock`.
+	pub async fn conflict(&self, lock: &RecordLock) -> Option<RecordLock> {
+		self.0.lock().await.conflict(lock)
+	}
+
+	/// Places or removes `lock`.
+	///
+	/// If a conflicting lock is held, this function waits for its release if
+	/// `wait` is set and fails with `EAGAIN` otherwise.
+	pub async fn lock(&self, lock: RecordLock, wait: bool) -> io::Result<()> {
+		loop {
+			let mut guard = self.0.lock().await;
+
+			if lock.kind == LockType::Unlock || guard.conflict(&lock).is_none() {
+				guard.replace(lock);
+				guard.wake_all();
+				return Ok(());
+			}
+			if !wait {
+				return Err(Errno::Again);
+			}
+
+			// Enqueue the waker and release the state before sleeping, so
+			// that the holder of the conflicting lock is able to wake us.
+			let mut guard = Some(guard);
+			future::poll_fn(|cx| {
+				if let Some(mut guard) = guard.take() {
+					guard.queue.push_back(cx.waker().clone());
+					Poll::Pending
+				} else {
+					Poll::Ready(())
+				}
+			})
+			.await;
+		}
+	}
+
+	/// Releases all locks of `owner`.
+	pub async fn release(&self, owner: LockOwner) {
+		let mut guard = self.0.lock().await;
+		guard.locks.retain(|held| held.owner != owner);
+		guard.wake_all();
+	}
+}
+
+/// `flock` locks of an open file description, which are released when the
+/// last object of the description is dropped.
+///
+/// Objects, which are shared by the descriptors of a description, keep this
+/// in an `Arc`, so that the locks outlive clones held by pending operations.
+#[derive(Debug, Default)]
+pub(crate) struct DescriptionLocks(Mutex<Option<(Arc<FileLocks>, LockOwner)>>);
+
+impl DescriptionLocks {
+	/// Remembers that `lock` has been placed in `locks`, if it belongs to a
+	/// description.
+	pub async fn placed(&self, locks: &Arc<FileLocks>, lock: &RecordLock) {
+		if matches!(lock.owner, LockOwner::Description(_)) && lock.kind != LockType::Unlock {
+			*self.0.lock().await = Some((locks.clone(), lock.owner));
+		}
+	}
+}
+
+impl Drop for DescriptionLocks {
+	fn drop(&mut self) {
+		if let Some((locks, owner)) = self.0.get_mut().take() {
+			let _ = block_on(
+				async {
+					locks.release(owner).await;
+					Ok(())
+				},
+				None,
+			);
+		}
+	}
+}
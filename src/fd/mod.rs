@@ -1,5 +1,7 @@
 use alloc::boxed::Box;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::future::{self, Future};
 use core::mem::MaybeUninit;
 use core::task::Poll::{Pending, Ready};
@@ -9,13 +11,16 @@ use async_trait::async_trait;
 #[cfg(feature = "net")]
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
+use self::lock::{LockOwner, LockType, RecordLock};
 use crate::arch::kernel::core_local::core_scheduler;
 use crate::errno::Errno;
 use crate::executor::block_on;
-use crate::fs::{FileAttr, SeekWhence};
+use crate::fs::{FallocateFlags, FileAttr, SeekWhence, StatFs, XattrFlags};
 use crate::io;
+use crate::time::timespec;
 
 mod eventfd;
+pub(crate) mod lock;
 #[cfg(any(feature = "net", feature = "vsock"))]
 pub(crate) mod socket;
 pub(crate) mod stdio;
@@ -64,11 +69,51 @@ bitflags! {
 		const O_NONBLOCK = StatusFlags::O_NONBLOCK.bits();
 		const O_DIRECT = 0o40000;
 		const O_DIRECTORY = 0o200_000;
-		/// `O_CLOEXEC` has no functionality in Hermit and will be silently ignored
+		/// `O_CLOEXEC` sets `FD_CLOEXEC` on the new file descriptor
 		const O_CLOEXEC = 0o2_000_000;
 	}
 }
 
+bitflags! {
+	/// Flags of a file descriptor
+	#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+	pub struct DescriptorFlags: i32 {
+		/// Close the file descriptor on `exec`.
+		///
+		/// Hermit does not support `exec`, so the flag is only tracked.
+		const FD_CLOEXEC = 1;
+	}
+}
+
+/// Entry of the file descriptor table
+#[derive(Clone)]
+pub(crate) struct Descriptor {
+	/// Open file description, which may be shared with other descriptors
+	pub object: Arc<async_lock::RwLock<dyn ObjectInterface>>,
+	/// Flags of this descriptor, which are not shared by duplicates
+	pub flags: DescriptorFlags,
+	/// Absolute path, by which the open file description has been opened
+	pub path: Option<String>,
+}
+
+impl Descriptor {
+	pub fn new(object: Arc<async_lock::RwLock<dyn ObjectInterface>>) -> Self {
+		Self {
+			object,
+			flags: DescriptorFlags::empty(),
+			path: None,
+		}
+	}
+
+	/// Returns a new descriptor of the same open file description.
+	pub fn duplicate(&self) -> Self {
+		Self {
+			path: self.path.clone(),
+			..Self::new(self.object.clone())
+		}
+	}
+}
+
 bitflags! {
 	/// Options for checking file permissions or existence
 	#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
@@ -224,6 +269,11 @@ pub(crate) trait ObjectInterface: Sync + Send + core::fmt::Debug {
 		Err(Errno::Inval)
 	}
 
+	/// `fstatfs` returns statistics about the file system containing the object
+	async fn fstatfs(&self) -> io::Result<StatFs> {
+		Err(Errno::Nosys)
+	}
+
 	/// `getdents` fills the given buffer `_buf` with [`Dirent64`](crate::syscalls::Dirent64)
 	/// formatted entries of a directory, imitating the Linux `getdents64` syscall.
 	/// On success, the number of bytes read is returned.  On end of directory, 0 is returned.  On error, -1 is returned
@@ -321,15 +371,79 @@ pub(crate) trait ObjectInterface: Sync + Send + core::fmt::Debug {
 		Err(Errno::Nosys)
 	}
 
+	/// Allocates, deallocates or zeroes the byte range `offset..offset + len`
+	async fn fallocate(
+		&self,
+		_mode: FallocateFlags,
+		_offset: usize,
+		_len: usize,
+	) -> io::Result<()> {
+		Err(Errno::Opnotsupp)
+	}
+
 	/// Changes access permissions to the file
 	async fn chmod(&self, _access_permission: AccessPermission) -> io::Result<()> {
 		Err(Errno::Nosys)
 	}
 
+	/// Changes the access and modification time, where `None` leaves the time unchanged
+	async fn utimens(&self, _atime: Option<timespec>, _mtime: Option<timespec>) -> io::Result<()> {
+		Err(Errno::Nosys)
+	}
+
+	/// Changes owner and group of the file, where `None` leaves the id unchanged
+	async fn chown(&self, _uid: Option<u32>, _gid: Option<u32>) -> io::Result<()> {
+		Err(Errno::Nosys)
+	}
+
+	/// Returns the value of the extended attribute `name`
+	async fn getxattr(&self, _name: &str) -> io::Result<Vec<u8>> {
+		Err(Errno::Opnotsupp)
+	}
+
+	/// Creates or replaces the extended attribute `name`
+	async fn setxattr(&self, _name: &str, _value: &[u8], _flags: XattrFlags) -> io::Result<()> {
+		Err(Errno::Opnotsupp)
+	}
+
+	/// Returns the names of all extended attributes
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		Err(Errno::Opnotsupp)
+	}
+
+	/// Removes the extended attribute `name`
+	async fn removexattr(&self, _name: &str) -> io::Result<()> {
+		Err(Errno::Opnotsupp)
+	}
+
+	/// Returns a lock held by another owner, which prevents placing `lock`
+	async fn getlk(&self, _lock: RecordLock) -> io::Result<Option<RecordLock>> {
+		Err(Errno::Nolck)
+	}
+
+	/// Places or removes an advisory lock. If `wait` is set, the function
+	/// waits until conflicting locks are released.
+	async fn setlk(&self, _lock: RecordLock, _wait: bool) -> io::Result<()> {
+		Err(Errno::Nolck)
+	}
+
+	/// Releases all locks of `owner`, which is called when a file descriptor is closed
+	async fn release_locks(&self, _owner: LockOwner) {}
+
 	/// `isatty` returns `true` for a terminal device
 	async fn isatty(&self) -> io::Result<bool> {
 		Ok(false)
 	}
+
+	/// Returns the absolute path of a directory, which is used to resolve
+	/// relative paths of the `*at` functions
+	async fn dirpath(&self) -> io::Result<String> {
+		Err(Errno::Notdir)
+	}
+
+	/// Remembers the absolute path of a directory, which has been opened by this path.
+	/// Objects, which are not directories, ignore the path.
+	async fn set_dirpath(&self, _path: &str) {}
 }
 
 pub(crate) fn read(fd: FileDescriptor, buf: &mut [u8]) -> io::Result<usize> {
@@ -354,6 +468,54 @@ pub(crate) fn chmod(fd: FileDescriptor, mode: AccessPermission) -> io::Result<()
 	block_on(async { obj.read().await.chmod(mode).await }, None)
 }
 
+pub(crate) fn utimens(
+	fd: FileDescriptor,
+	atime: Option<timespec>,
+	mtime: Option<timespec>,
+) -> io::Result<()> {
+	let obj = get_object(fd)?;
+
+	block_on(async { obj.read().await.utimens(atime, mtime).await }, None)
+}
+
+pub(crate) fn chown(fd: FileDescriptor, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+	let obj = get_object(fd)?;
+
+	block_on(async { obj.read().await.chown(uid, gid).await }, None)
+}
+
+pub(crate) fn getxattr(fd: FileDescriptor, name: &str) -> io::Result<Vec<u8>> {
+	let obj = get_object(fd)?;
+
+	block_on(async { obj.read().await.getxattr(name).await }, None)
+}
+
+pub(crate) fn setxattr(
+	fd: FileDescriptor,
+	name: &str,
+	value: &[u8],
+	flags: XattrFlags,
+) -> io::Result<()> {
+	let obj = get_object(fd)?;
+
+	block_on(
+		async { obj.read().await.setxattr(name, value, flags).await },
+		None,
+	)
+}
+
+pub(crate) fn listxattr(fd: FileDescriptor) -> io::Result<Vec<String>> {
+	let obj = get_object(fd)?;
+
+	block_on(async { obj.read().await.listxattr().await }, None)
+}
+
+pub(crate) fn removexattr(fd: FileDescriptor, name: &str) -> io::Result<()> {
+	let obj = get_object(fd)?;
+
+	block_on(async { obj.read().await.removexattr(name).await }, None)
+}
+
 pub(crate) fn write(fd: FileDescriptor, buf: &[u8]) -> io::Result<usize> {
 	let obj = get_object(fd)?;
 
@@ -369,6 +531,19 @@ pub(crate) fn truncate(fd: FileDescriptor, length: usize) -> io::Result<()> {
 	block_on(async { obj.read().await.truncate(length).await }, None)
 }
 
+pub(crate) fn fallocate(
+	fd: FileDescriptor,
+	mode: FallocateFlags,
+	offset: usize,
+	len: usize,
+) -> io::Result<()> {
+	let obj = get_object(fd)?;
+	block_on(
+		async { obj.read().await.fallocate(mode, offset, len).await },
+		None,
+	)
+}
+
 async fn poll_fds(fds: &mut [PollFd]) -> io::Result<u64> {
 	future::poll_fn(|cx| {
 		let mut counter: u64 = 0;
@@ -421,6 +596,11 @@ pub fn fstat(fd: FileDescriptor) -> io::Result<FileAttr> {
 	block_on(async { obj.read().await.fstat().await }, None)
 }
 
+pub fn fstatfs(fd: FileDescriptor) -> io::Result<StatFs> {
+	let obj = get_object(fd)?;
+	block_on(async { obj.read().await.fstatfs().await }, None)
+}
+
 /// Wait for some event on a file descriptor.
 ///
 /// `eventfd` creates an linux-like "eventfd object" that can be used
@@ -443,6 +623,9 @@ pub fn eventfd(initval: u64, flags: EventFlags) -> io::Result<FileDescriptor> {
 	let obj = self::eventfd::EventFd::new(initval, flags);
 
 	let fd = core_scheduler().insert_object(Arc::new(async_lock::RwLock::new(obj)))?;
+	if flags.contains(EventFlags::EFD_CLOEXEC) {
+		set_descriptor_flags(fd, DescriptorFlags::FD_CLOEXEC)?;
+	}
 
 	Ok(fd)
 }
@@ -467,14 +650,148 @@ pub(crate) fn dup_object(fd: FileDescriptor) -> io::Result<FileDescriptor> {
 	core_scheduler().dup_object(fd)
 }
 
+/// Duplicates `fd` to the lowest unused file descriptor, which is greater
+/// than or equal to `min_fd`, and sets the flags of the new descriptor.
+pub(crate) fn dup_object_from(
+	fd: FileDescriptor,
+	min_fd: FileDescriptor,
+	flags: DescriptorFlags,
+) -> io::Result<FileDescriptor> {
+	let new_fd = core_scheduler().dup_object_from(fd, min_fd)?;
+	set_descriptor_flags(new_fd, flags)?;
+	Ok(new_fd)
+}
+
 pub(crate) fn dup_object2(fd1: FileDescriptor, fd2: FileDescriptor) -> io::Result<FileDescriptor> {
 	core_scheduler().dup_object2(fd1, fd2)
 }
 
+/// Removes a file descriptor and releases the record locks of the process.
 pub(crate) fn remove_object(
 	fd: FileDescriptor,
 ) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
-	core_scheduler().remove_object(fd)
+	let obj = core_scheduler().remove_object(fd)?;
+
+	// Closing any descriptor releases the record locks of the process on the
+	// file, while `flock` locks live as long as the open file description
+	// and are released, when it is dropped.
+	block_on(
+		async {
+			obj.read().await.release_locks(LockOwner::Process).await;
+			Ok(())
+		},
+		None,
+	)?;
+
+	Ok(obj)
+}
+
+pub(crate) fn descriptor_flags(fd: FileDescriptor) -> io::Result<DescriptorFlags> {
+	core_scheduler().get_descriptor_flags(fd)
+}
+
+pub(crate) fn set_descriptor_flags(fd: FileDescriptor, flags: DescriptorFlags) -> io::Result<()> {
+	core_scheduler().set_descriptor_flags(fd, flags)
+}
+
+pub(crate) fn set_descriptor_path(fd: FileDescriptor, path: &str) -> io::Result<()> {
+	core_scheduler().set_descriptor_path(fd, path)
+}
+
+/// Returns the owner of `flock` locks, which is the open file description.
+fn description_owner(obj: &Arc<async_lock::RwLock<dyn ObjectInterface>>) -> LockOwner {
+	LockOwner::Description(Arc::as_ptr(obj).cast::<()>() as usize)
+}
+
+/// Converts the range of a `struct flock` into an absolute byte range.
+async fn lock_range(
+	obj: &dyn ObjectInterface,
+	whence: SeekWhence,
+	start: i64,
+	len: i64,
+) -> io::Result<(u64, u64)> {
+	let base = match whence {
+		SeekWhence::Set => 0,
+		SeekWhence::Cur => i64::try_from(obj.lseek(0, SeekWhence::Cur).await?).unwrap(),
+		SeekWhence::End => obj.fstat().await?.st_size,
+		_ => return Err(Errno::Inval),
+	};
+	let start = base.checked_add(start).ok_or(Errno::Overflow)?;
+
+	// A negative length locks the bytes before `start`
+	let (start, end) = match len {
+		0 => (start, None),
+		len if len > 0 => (start, Some(start.checked_add(len).ok_or(Errno::Overflow)?)),
+		len => (start.checked_add(len).ok_or(Errno::Overflow)?, Some(start)),
+	};
+
+	let start = u64::try_from(start).map_err(|_| Errno::Inval)?;
+	let end = end.map_or(Ok(u64::MAX), |end| {
+		u64::try_from(end).map_err(|_| Errno::Inval)
+	})?;
+	Ok((start, end))
+}
+
+/// Returns a record lock held by another owner, which prevents placing the
+/// lock described by `kind`, `whence`, `start` and `len`.
+pub(crate) fn getlk(
+	fd: FileDescriptor,
+	kind: LockType,
+	whence: SeekWhence,
+	start: i64,
+	len: i64,
+) -> io::Result<Option<RecordLock>> {
+	let obj = get_object(fd)?;
+
+	block_on(
+		async {
+			let guard = obj.read().await;
+			let (start, end) = lock_range(&*guard, whence, start, len).await?;
+			let lock = RecordLock {
+				kind,
+				start,
+				end,
+				owner: LockOwner::Process,
+			};
+			guard.getlk(lock).await
+		},
+		None,
+	)
+}
+
+/// Places or removes a record lock of the process.
+pub(crate) fn setlk(
+	fd: FileDescriptor,
+	kind: LockType,
+	whence: SeekWhence,
+	start: i64,
+	len: i64,
+	wait: bool,
+) -> io::Result<()> {
+	let obj = get_object(fd)?;
+
+	block_on(
+		async {
+			let guard = obj.read().await;
+			let (start, end) = lock_range(&*guard, whence, start, len).await?;
+			let lock = RecordLock {
+				kind,
+				start,
+				end,
+				owner: LockOwner::Process,
+			};
+			guard.setlk(lock, wait).await
+		},
+		None,
+	)
+}
+
+/// Places or removes a lock of the whole file, which belongs to the open file description.
+pub(crate) fn flock(fd: FileDescriptor, kind: LockType, wait: bool) -> io::Result<()> {
+	let obj = get_object(fd)?;
+	let lock = RecordLock::whole_file(kind, description_owner(&obj));
+
+	block_on(async { obj.read().await.setlk(lock, wait).await }, None)
 }
 
 pub(crate) fn isatty(fd: FileDescriptor) -> io::Result<bool> {
@@ -0,0 +1,340 @@
+//! Implements a device file system, which provides the character devices
+//! below `/dev`.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use async_trait::async_trait;
+
+#[cfg(all(feature = "console", not(feature = "pci")))]
+use crate::drivers::mmio::get_console_driver;
+#[cfg(all(feature = "console", feature = "pci"))]
+use crate::drivers::pci::get_console_driver;
+#[cfg(all(feature = "fuse", feature = "pci"))]
+use crate::drivers::pci::get_filesystem_driver;
+#[cfg(feature = "vsock")]
+use crate::drivers::pci::get_vsock_driver;
+use crate::entropy::{self, Flags};
+use crate::errno::Errno;
+use crate::fd::stdio::{GenericStdin, GenericStdout};
+use crate::fd::{AccessPermission, ObjectInterface, OpenOption, PollEvent};
+use crate::fs::{DirectoryEntry, DirectoryReader, FileAttr, NodeKind, SeekWhence, StatFs, VfsNode};
+use crate::io;
+
+/// Magic number of the device file system
+const DEVFS_SUPER_MAGIC: i64 = 0x1373;
+
+/// Encodes a device number in the same way as glibc's `makedev`.
+const fn makedev(major: u64, minor: u64) -> u64 {
+	((major & 0xffff_f000) << 32)
+		| ((major & 0x0000_0fff) << 8)
+		| ((minor & 0xffff_ff00) << 12)
+		| (minor & 0x0000_00ff)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CharDevice {
+	Null,
+	Zero,
+	Full,
+	Random,
+	Urandom,
+	Tty,
+	Console,
+	#[cfg(feature = "console")]
+	VirtioConsole,
+	#[cfg(feature = "vsock")]
+	Vsock,
+	#[cfg(all(feature = "fuse", feature = "pci"))]
+	Fuse,
+}
+
+impl CharDevice {
+	/// Returns the device number, which matches the one used by Linux.
+	fn rdev(self) -> u64 {
+		match self {
+			Self::Null => makedev(1, 3),
+			Self::Zero => makedev(1, 5),
+			Self::Full => makedev(1, 7),
+			Self::Random => makedev(1, 8),
+			Self::Urandom => makedev(1, 9),
+			Self::Tty => makedev(5, 0),
+			Self::Console => makedev(5, 1),
+			#[cfg(feature = "console")]
+			Self::VirtioConsole => makedev(229, 0),
+			#[cfg(feature = "vsock")]
+			Self::Vsock => makedev(10, 121),
+			#[cfg(all(feature = "fuse", feature = "pci"))]
+			Self::Fuse => makedev(10, 229),
+		}
+	}
+
+	fn is_terminal(self) -> bool {
+		match self {
+			Self::Tty | Self::Console => true,
+			#[cfg(feature = "console")]
+			Self::VirtioConsole => true,
+			_ => false,
+		}
+	}
+
+	fn attributes(self) -> FileAttr {
+		FileAttr {
+			st_mode: AccessPermission::S_IFCHR | AccessPermission::from_bits_retain(0o666),
+			st_nlink: 1,
+			st_rdev: self.rdev(),
+			..Default::default()
+		}
+	}
+}
+
+/// Returns all devices, including the discovered virtio devices.
+fn devices() -> Vec<(&'static str, CharDevice)> {
+	#[allow(unused_mut)]
+	let mut devices = vec![
+		("null", CharDevice::Null),
+		("zero", CharDevice::Zero),
+		("full", CharDevice::Full),
+		("random", CharDevice::Random),
+		("urandom", CharDevice::Urandom),
+		("tty", CharDevice::Tty),
+		("console", CharDevice::Console),
+	];
+
+	#[cfg(feature = "console")]
+	if get_console_driver().is_some() {
+		devices.push(("hvc0", CharDevice::VirtioConsole));
+	}
+	#[cfg(feature = "vsock")]
+	if get_vsock_driver().is_some() {
+		devices.push(("vsock", CharDevice::Vsock));
+	}
+	#[cfg(all(feature = "fuse", feature = "pci"))]
+	if get_filesystem_driver().is_some() {
+		devices.push(("fuse", CharDevice::Fuse));
+	}
+
+	devices
+}
+
+fn lookup(components: &[&str]) -> io::Result<CharDevice> {
+	let [name] = components else {
+		return Err(Errno::Noent);
+	};
+
+	devices()
+		.into_iter()
+		.find_map(|(device_name, device)| (device_name == *name).then_some(device))
+		.ok_or(Errno::Noent)
+}
+
+fn statfs() -> StatFs {
+	StatFs {
+		f_type: DEVFS_SUPER_MAGIC,
+		f_bsize: 4096,
+		f_namelen: 255,
+		f_frsize: 4096,
+		..Default::default()
+	}
+}
+
+#[derive(Debug)]
+struct CharDeviceInterface {
+	device: CharDevice,
+}
+
+#[async_trait]
+impl ObjectInterface for CharDeviceInterface {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		if self.device.is_terminal() {
+			let readable = GenericStdin::new().poll(event).await?;
+			let writable = GenericStdout::new().poll(event).await?;
+			return Ok(readable | writable);
+		}
+
+		let available = PollEvent::POLLIN
+			| PollEvent::POLLRDNORM
+			| PollEvent::POLLRDBAND
+			| PollEvent::POLLOUT
+			| PollEvent::POLLWRNORM
+			| PollEvent::POLLWRBAND;
+		Ok(event & available)
+	}
+
+	async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		match self.device {
+			CharDevice::Null => Ok(0),
+			CharDevice::Zero | CharDevice::Full => {
+				buf.fill(0);
+				Ok(buf.len())
+			}
+			CharDevice::Random | CharDevice::Urandom => {
+				let ret = entropy::read(buf, Flags::empty());
+				usize::try_from(ret).map_err(|_| Errno::Nosys)
+			}
+			device if device.is_terminal() => GenericStdin::new().read(buf).await,
+			_ => Err(Errno::Nosys),
+		}
+	}
+
+	async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+		match self.device {
+			CharDevice::Null | CharDevice::Zero | CharDevice::Random | CharDevice::Urandom => {
+				Ok(buf.len())
+			}
+			CharDevice::Full => Err(Errno::Nospc),
+			device if device.is_terminal() => GenericStdout::new().write(buf).await,
+			_ => Err(Errno::Nosys),
+		}
+	}
+
+	async fn lseek(&self, _offset: isize, _whence: SeekWhence) -> io::Result<isize> {
+		if self.device.is_terminal() {
+			Err(Errno::Spipe)
+		} else {
+			Ok(0)
+		}
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(self.device.attributes())
+	}
+
+	async fn fstatfs(&self) -> io::Result<StatFs> {
+		Ok(statfs())
+	}
+
+	async fn isatty(&self) -> io::Result<bool> {
+		Ok(self.device.is_terminal())
+	}
+}
+
+#[derive(Debug)]
+pub(crate) struct DevDirectory;
+
+impl DevDirectory {
+	pub fn new() -> Self {
+		Self
+	}
+
+	fn readdir() -> Vec<DirectoryEntry> {
+		devices()
+			.into_iter()
+			.map(|(name, _)| DirectoryEntry::new(name.to_string()))
+			.collect()
+	}
+}
+
+impl VfsNode for DevDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		Ok(FileAttr {
+			st_mode: AccessPermission::S_IFDIR | AccessPermission::from_bits_retain(0o755),
+			st_nlink: 1,
+			..Default::default()
+		})
+	}
+
+	fn get_object(&self) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		Ok(Arc::new(async_lock::RwLock::new(DirectoryReader::new(
+			Self::readdir(),
+			self.get_file_attributes()?,
+		))))
+	}
+
+	fn traverse_mkdir(
+		&self,
+		components: &mut Vec<&str>,
+		_mode: AccessPermission,
+	) -> io::Result<()> {
+		if components.is_empty() || lookup(components).is_ok() {
+			Err(Errno::Exist)
+		} else {
+			Err(Errno::Acces)
+		}
+	}
+
+	fn traverse_rmdir(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		lookup(components)?;
+		Err(Errno::Notdir)
+	}
+
+	fn traverse_unlink(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		lookup(components)?;
+		Err(Errno::Acces)
+	}
+
+	fn traverse_readdir(&self, components: &mut Vec<&str>) -> io::Result<Vec<DirectoryEntry>> {
+		if components.is_empty() {
+			Ok(Self::readdir())
+		} else {
+			lookup(components)?;
+			Err(Errno::Notdir)
+		}
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		if components.is_empty() {
+			self.get_file_attributes()
+		} else {
+			Ok(lookup(components)?.attributes())
+		}
+	}
+
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		self.traverse_lstat(components)
+	}
+
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		if !components.is_empty() {
+			lookup(components)?;
+		}
+		Ok(statfs())
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		if !components.is_empty() {
+			lookup(components)?;
+		}
+		Err(Errno::Inval)
+	}
+
+	fn traverse_mount(
+		&self,
+		_components: &mut Vec<&str>,
+		_obj: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	) -> io::Result<()> {
+		Err(Errno::Acces)
+	}
+
+	fn traverse_open(
+		&self,
+		components: &mut Vec<&str>,
+		opt: OpenOption,
+		_mode: AccessPermission,
+	) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		if components.is_empty() {
+			if opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR) {
+				return Err(Errno::Isdir);
+			}
+			return self.get_object();
+		}
+
+		let device = lookup(components)?;
+		if opt.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) {
+			return Err(Errno::Exist);
+		}
+		if opt.contains(OpenOption::O_DIRECTORY) {
+			return Err(Errno::Notdir);
+		}
+
+		Ok(Arc::new(async_lock::RwLock::new(CharDeviceInterface {
+			device,
+		})))
+	}
+}
@@ -0,0 +1,833 @@
+//! Implements a read-only ext2/ext3/ext4 file system on top of a block device.
+//!
+//! Files are either mapped by extents or by the classic block map of ext2.
+//! Hashed directory indexes (htree) are built such that the directory blocks
+//! remain valid linear directories, so directories are always scanned
+//! linearly. The journal is not replayed, so file systems, whose journal
+//! needs recovery, are refused. Symbolic links are resolved within
+//! the file system, i.e., absolute targets are relative to its root.
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+
+use crate::drivers::block::BlockDevice;
+use crate::errno::Errno;
+use crate::fd::{AccessPermission, ObjectInterface, OpenOption, PollEvent};
+use crate::fs::{
+	DirectoryEntry, DirectoryReader, FileAttr, MAX_SYMLINKS, MountFlags, NodeKind, SeekWhence,
+	StatFs, VfsNode,
+};
+use crate::io;
+use crate::time::timespec;
+
+/// Magic number of ext2/ext3/ext4 file systems
+const EXT4_SUPER_MAGIC: u16 = 0xef53;
+
+/// Byte offset of the superblock
+const SUPERBLOCK_OFFSET: u64 = 1024;
+/// Inode number of the root directory
+const ROOT_INO: u32 = 2;
+
+const INCOMPAT_FILETYPE: u32 = 0x2;
+const INCOMPAT_RECOVER: u32 = 0x4;
+const INCOMPAT_EXTENTS: u32 = 0x40;
+const INCOMPAT_64BIT: u32 = 0x80;
+const INCOMPAT_MMP: u32 = 0x100;
+const INCOMPAT_FLEX_BG: u32 = 0x200;
+const INCOMPAT_EA_INODE: u32 = 0x400;
+const INCOMPAT_CSUM_SEED: u32 = 0x2000;
+const INCOMPAT_LARGEDIR: u32 = 0x4000;
+/// Incompatible features, which do not prevent reading the file system
+const INCOMPAT_SUPPORTED: u32 = INCOMPAT_FILETYPE
+	| INCOMPAT_EXTENTS
+	| INCOMPAT_64BIT
+	| INCOMPAT_MMP
+	| INCOMPAT_FLEX_BG
+	| INCOMPAT_EA_INODE
+	| INCOMPAT_CSUM_SEED
+	| INCOMPAT_LARGEDIR;
+
+/// Inode flag of files, whose blocks are counted in file system blocks
+const HUGE_FILE_FL: u32 = 0x4_0000;
+/// Inode flag of files, which are mapped by extents
+const EXTENTS_FL: u32 = 0x8_0000;
+
+const EXTENT_MAGIC: u16 = 0xf30a;
+/// Maximum depth of an extent tree
+const EXTENT_MAX_DEPTH: u16 = 5;
+/// Length of extents, above which the extent is uninitialized
+const EXTENT_INIT_MAX_LEN: u16 = 32768;
+
+/// Number of direct block pointers of the classic block map
+const DIRECT_BLOCKS: u64 = 12;
+
+/// Maximum length of a symbolic link, which is stored within the inode
+const FAST_SYMLINK_MAX: u64 = 60;
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+	u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn is_type(mode: u16, kind: AccessPermission) -> bool {
+	(u32::from(mode) & AccessPermission::S_IFMT.bits()) == kind.bits()
+}
+
+#[derive(Debug, Clone)]
+struct Inode {
+	ino: u32,
+	mode: u16,
+	uid: u32,
+	gid: u32,
+	size: u64,
+	links: u16,
+	/// Number of allocated 512-byte sectors
+	blocks: u64,
+	flags: u32,
+	/// Block map, extent tree or target of a fast symbolic link
+	block: [u8; 60],
+	atime: timespec,
+	mtime: timespec,
+	ctime: timespec,
+}
+
+impl Inode {
+	fn is_dir(&self) -> bool {
+		is_type(self.mode, AccessPermission::S_IFDIR)
+	}
+
+	fn is_symlink(&self) -> bool {
+		is_type(self.mode, AccessPermission::S_IFLNK)
+	}
+}
+
+#[derive(Debug)]
+pub(crate) struct Ext4Fs {
+	device: Arc<dyn BlockDevice>,
+	/// Size of a block in bytes
+	block_size: u64,
+	inode_size: u64,
+	inodes_per_group: u32,
+	/// First block of the inode table of each block group
+	inode_tables: Vec<u64>,
+	/// `true` if directory entries contain the file type
+	filetype: bool,
+	blocks_count: u64,
+	free_blocks: u64,
+	reserved_blocks: u64,
+	inodes_count: u32,
+	free_inodes: u32,
+}
+
+impl Ext4Fs {
+	pub fn new(device: Arc<dyn BlockDevice>) -> io::Result<Self> {
+		let mut sb = [0u8; 1024];
+		device.read_at(SUPERBLOCK_OFFSET, &mut sb)?;
+		if read_u16(&sb, 56) != EXT4_SUPER_MAGIC {
+			return Err(Errno::Inval);
+		}
+
+		let incompat = read_u32(&sb, 96);
+		// Without replaying the journal, the metadata may be stale.
+		if incompat & INCOMPAT_RECOVER != 0 {
+			error!("ext4: the journal needs recovery, which is not supported");
+			return Err(Errno::Inval);
+		}
+		if incompat & !INCOMPAT_SUPPORTED != 0 {
+			error!(
+				"ext4: unsupported incompatible features {:#x}",
+				incompat & !INCOMPAT_SUPPORTED
+			);
+			return Err(Errno::Inval);
+		}
+		let log_block_size = read_u32(&sb, 24);
+		if log_block_size > 6 {
+			return Err(Errno::Inval);
+		}
+		let block_size = 1024u64 << log_block_size;
+		let is_64bit = incompat & INCOMPAT_64BIT != 0;
+		let hi = |offset: usize| {
+			if is_64bit {
+				u64::from(read_u32(&sb, offset)) << 32
+			} else {
+				0
+			}
+		};
+
+		// Revision 0 has fixed inode sizes.
+		let inode_size = if read_u32(&sb, 76) == 0 {
+			128
+		} else {
+			u64::from(read_u16(&sb, 88))
+		};
+		let desc_size = if is_64bit {
+			u64::from(read_u16(&sb, 254))
+		} else {
+			32
+		};
+		let first_data_block = u64::from(read_u32(&sb, 20));
+		let blocks_per_group = u64::from(read_u32(&sb, 32));
+		let inodes_per_group = read_u32(&sb, 40);
+		let blocks_count = u64::from(read_u32(&sb, 4)) | hi(336);
+		if inode_size < 128
+			|| !inode_size.is_power_of_two()
+			|| desc_size < 32
+			|| blocks_per_group == 0
+			|| inodes_per_group == 0
+			|| blocks_count <= first_data_block
+		{
+			return Err(Errno::Inval);
+		}
+
+		// The group descriptors follow the block of the superblock.
+		let groups = (blocks_count - first_data_block).div_ceil(blocks_per_group);
+		let mut descs = vec![0u8; usize::try_from(groups * desc_size).unwrap()];
+		device.read_at((first_data_block + 1) * block_size, &mut descs)?;
+		let inode_tables = descs
+			.chunks_exact(usize::try_from(desc_size).unwrap())
+			.map(|desc| {
+				let hi = if desc_size >= 64 {
+					u64::from(read_u32(desc, 40)) << 32
+				} else {
+					0
+				};
+				u64::from(read_u32(desc, 8)) | hi
+			})
+			.collect();
+
+		Ok(Self {
+			device,
+			block_size,
+			inode_size,
+			inodes_per_group,
+			inode_tables,
+			filetype: incompat & INCOMPAT_FILETYPE != 0,
+			blocks_count,
+			free_blocks: u64::from(read_u32(&sb, 12)) | hi(344),
+			reserved_blocks: u64::from(read_u32(&sb, 8)) | hi(340),
+			inodes_count: read_u32(&sb, 0),
+			free_inodes: read_u32(&sb, 16),
+		})
+	}
+
+	fn read_block(&self, block: u64, buf: &mut [u8]) -> io::Result<()> {
+		self.device.read_at(block * self.block_size, buf)
+	}
+
+	fn read_inode(&self, ino: u32) -> io::Result<Inode> {
+		if ino == 0 {
+			return Err(Errno::Io);
+		}
+		let group = usize::try_from((ino - 1) / self.inodes_per_group).unwrap();
+		let index = u64::from((ino - 1) % self.inodes_per_group);
+		let table = *self.inode_tables.get(group).ok_or(Errno::Io)?;
+
+		let mut raw = vec![0u8; usize::try_from(self.inode_size).unwrap()];
+		self.device
+			.read_at(table * self.block_size + index * self.inode_size, &mut raw)?;
+
+		// Large inodes store the nanoseconds and the epoch bits of the timestamps.
+		let extra_end = if raw.len() > 128 {
+			128 + usize::from(read_u16(&raw, 128))
+		} else {
+			128
+		};
+		let time = |offset: usize, extra: usize| {
+			let mut ts = timespec {
+				tv_sec: read_u32(&raw, offset).cast_signed().into(),
+				tv_nsec: 0,
+			};
+			if extra + 4 <= extra_end && extra + 4 <= raw.len() {
+				let extra = read_u32(&raw, extra);
+				ts.tv_sec += i64::from(extra & 0x3) << 32;
+				ts.tv_nsec = (extra >> 2).cast_signed();
+			}
+			ts
+		};
+
+		let flags = read_u32(&raw, 32);
+		let mut blocks = u64::from(read_u32(&raw, 28)) | (u64::from(read_u16(&raw, 116)) << 32);
+		if flags & HUGE_FILE_FL != 0 {
+			blocks *= self.block_size / 512;
+		}
+
+		Ok(Inode {
+			ino,
+			mode: read_u16(&raw, 0),
+			uid: u32::from(read_u16(&raw, 2)) | (u32::from(read_u16(&raw, 120)) << 16),
+			gid: u32::from(read_u16(&raw, 24)) | (u32::from(read_u16(&raw, 122)) << 16),
+			size: u64::from(read_u32(&raw, 4)) | (u64::from(read_u32(&raw, 108)) << 32),
+			links: read_u16(&raw, 26),
+			blocks,
+			flags,
+			block: raw[40..100].try_into().unwrap(),
+			atime: time(8, 140),
+			mtime: time(16, 136),
+			ctime: time(12, 132),
+		})
+	}
+
+	/// Returns the physical block of the logical block `lblock` or `None` for holes.
+	fn map_block(&self, inode: &Inode, lblock: u64) -> io::Result<Option<u64>> {
+		if inode.flags & EXTENTS_FL != 0 {
+			self.map_extent(inode, lblock)
+		} else {
+			self.map_indirect(inode, lblock)
+		}
+	}
+
+	fn map_extent(&self, inode: &Inode, lblock: u64) -> io::Result<Option<u64>> {
+		let mut node = inode.block.to_vec();
+		for _ in 0..=EXTENT_MAX_DEPTH {
+			if read_u16(&node, 0) != EXTENT_MAGIC {
+				return Err(Errno::Io);
+			}
+			let entries = usize::from(read_u16(&node, 2));
+			let depth = read_u16(&node, 6);
+			if 12 * (entries + 1) > node.len() {
+				return Err(Errno::Io);
+			}
+			let entries = node[12..12 * (entries + 1)].chunks_exact(12);
+
+			if depth == 0 {
+				for extent in entries {
+					let first = u64::from(read_u32(extent, 0));
+					let len = read_u16(extent, 4);
+					let (len, initialized) = if len > EXTENT_INIT_MAX_LEN {
+						(len - EXTENT_INIT_MAX_LEN, false)
+					} else {
+						(len, true)
+					};
+					if (first..first + u64::from(len)).contains(&lblock) {
+						// Uninitialized extents are read as zeros.
+						let start =
+							(u64::from(read_u16(extent, 6)) << 32) | u64::from(read_u32(extent, 8));
+						return Ok(initialized.then_some(start + lblock - first));
+					}
+				}
+				return Ok(None);
+			}
+
+			// Index entries are sorted by their first logical block.
+			let Some(index) = entries
+				.take_while(|index| u64::from(read_u32(index, 0)) <= lblock)
+				.last()
+			else {
+				return Ok(None);
+			};
+			let leaf = (u64::from(read_u16(index, 8)) << 32) | u64::from(read_u32(index, 4));
+			node = vec![0u8; usize::try_from(self.block_size).unwrap()];
+			self.read_block(leaf, &mut node)?;
+		}
+
+		Err(Errno::Io)
+	}
+
+	fn map_indirect(&self, inode: &Inode, lblock: u64) -> io::Result<Option<u64>> {
+		let per_block = self.block_size / 4;
+
+		// Determine the slot in the inode and the indices within the indirect blocks.
+		let (slot, mut indices) = if lblock < DIRECT_BLOCKS {
+			(lblock, Vec::new())
+		} else {
+			let mut rest = lblock - DIRECT_BLOCKS;
+			let mut level = 1;
+			let mut span = per_block;
+			while rest >= span {
+				rest -= span;
+				level += 1;
+				span *= per_block;
+				if level > 3 {
+					return Err(Errno::Fbig);
+				}
+			}
+			let mut indices = Vec::new();
+			for _ in 0..level {
+				indices.push(rest % per_block);
+				rest /= per_block;
+			}
+			(DIRECT_BLOCKS + level - 1, indices)
+		};
+
+		let mut block = u64::from(read_u32(&inode.block, usize::try_from(slot).unwrap() * 4));
+		while let Some(index) = indices.pop() {
+			if block == 0 {
+				return Ok(None);
+			}
+			let mut entry = [0u8; 4];
+			self.device
+				.read_at(block * self.block_size + index * 4, &mut entry)?;
+			block = u32::from_le_bytes(entry).into();
+		}
+
+		Ok((block != 0).then_some(block))
+	}
+
+	/// Reads the content of `inode` at `offset`, which is limited by the length
+	/// of `buf` and the size of the file.
+	fn read_data(&self, inode: &Inode, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+		if offset >= inode.size {
+			return Ok(0);
+		}
+		let len = buf.len().min(usize::try_from(inode.size - offset).unwrap());
+
+		let mut done = 0;
+		while done < len {
+			let pos = offset + done as u64;
+			let within = pos % self.block_size;
+			let chunk = (len - done).min(usize::try_from(self.block_size - within).unwrap());
+			let buf = &mut buf[done..done + chunk];
+			match self.map_block(inode, pos / self.block_size)? {
+				Some(block) => self.device.read_at(block * self.block_size + within, buf)?,
+				None => buf.fill(0),
+			}
+			done += chunk;
+		}
+
+		Ok(done)
+	}
+
+	fn read_link(&self, inode: &Inode) -> io::Result<String> {
+		if !inode.is_symlink() {
+			return Err(Errno::Inval);
+		}
+
+		let target = if inode.size < FAST_SYMLINK_MAX && inode.flags & EXTENTS_FL == 0 {
+			inode.block[..usize::try_from(inode.size).unwrap()].to_vec()
+		} else {
+			let mut target = vec![0u8; usize::try_from(inode.size).map_err(|_| Errno::Io)?];
+			self.read_data(inode, 0, &mut target)?;
+			target
+		};
+
+		String::from_utf8(target).map_err(|_| Errno::Io)
+	}
+
+	/// Returns the names and inode numbers of the entries of `dir` without `.` and `..`.
+	fn read_dir(&self, dir: &Inode) -> io::Result<Vec<(String, u32)>> {
+		if !dir.is_dir() {
+			return Err(Errno::Notdir);
+		}
+
+		let block_size = usize::try_from(self.block_size).unwrap();
+		let mut data = vec![0u8; usize::try_from(dir.size).map_err(|_| Errno::Io)?];
+		self.read_data(dir, 0, &mut data)?;
+
+		let mut entries = Vec::new();
+		for block in data.chunks(block_size) {
+			let mut pos = 0;
+			while pos + 8 <= block.len() {
+				let entry = &block[pos..];
+				let ino = read_u32(entry, 0);
+				// Blocks of 64 KiB encode their full length as 0 or 65535.
+				let rec_len = match usize::from(read_u16(entry, 4)) {
+					0 | 65535 if block_size == 65536 => 65536,
+					len => len,
+				};
+				let name_len = if self.filetype {
+					usize::from(entry[6])
+				} else {
+					usize::from(read_u16(entry, 6))
+				};
+				if rec_len < 8 || rec_len > entry.len() || 8 + name_len > rec_len {
+					return Err(Errno::Io);
+				}
+
+				// Unused entries, checksum tails and the internal nodes of
+				// hashed indexes have no inode.
+				let name = &entry[8..8 + name_len];
+				if ino != 0 && name != b"." && name != b".." {
+					entries.push((String::from_utf8_lossy(name).into_owned(), ino));
+				}
+				pos += rec_len;
+			}
+		}
+
+		Ok(entries)
+	}
+
+	/// Looks up the inode given by the reversed path components. A symbolic
+	/// link as last component is only followed if `follow` is set.
+	fn lookup(&self, components: &[&str], follow: bool) -> io::Result<Inode> {
+		let mut pending: Vec<String> = components.iter().map(|c| (*c).to_owned()).collect();
+		// Inodes of the directories from the root to the current directory
+		let mut path = vec![self.read_inode(ROOT_INO)?];
+		let mut links = 0;
+
+		while let Some(name) = pending.pop() {
+			if name.is_empty() {
+				continue;
+			}
+			// Only directories contain `.`, `..` and further components.
+			if !path.last().unwrap().is_dir() {
+				return Err(Errno::Notdir);
+			}
+			match name.as_str() {
+				"." => continue,
+				".." => {
+					if path.len() > 1 {
+						path.pop();
+					}
+					continue;
+				}
+				_ => {}
+			}
+
+			let dir = path.last().unwrap();
+			let (_, ino) = self
+				.read_dir(dir)?
+				.into_iter()
+				.find(|(entry, _)| *entry == name)
+				.ok_or(Errno::Noent)?;
+			let inode = self.read_inode(ino)?;
+
+			if inode.is_symlink() && (follow || !pending.is_empty()) {
+				links += 1;
+				if links > MAX_SYMLINKS {
+					return Err(Errno::Loop);
+				}
+				let target = self.read_link(&inode)?;
+				if target.starts_with('/') {
+					path.truncate(1);
+				}
+				pending.extend(target.split('/').rev().map(ToString::to_string));
+				continue;
+			}
+
+			path.push(inode);
+		}
+
+		Ok(path.pop().unwrap())
+	}
+
+	fn entries(&self, dir: &Inode) -> io::Result<Vec<DirectoryEntry>> {
+		Ok(self
+			.read_dir(dir)?
+			.into_iter()
+			.map(|(name, _)| DirectoryEntry::new(name))
+			.collect())
+	}
+
+	fn attributes(&self, inode: &Inode) -> FileAttr {
+		FileAttr {
+			st_ino: inode.ino.into(),
+			st_nlink: inode.links.into(),
+			st_mode: AccessPermission::from_bits_retain(inode.mode.into()),
+			st_uid: inode.uid,
+			st_gid: inode.gid,
+			st_size: inode.size.try_into().unwrap(),
+			st_blksize: self.block_size.try_into().unwrap(),
+			st_blocks: inode.blocks.try_into().unwrap(),
+			st_atim: inode.atime,
+			st_mtim: inode.mtime,
+			st_ctim: inode.ctime,
+			..Default::default()
+		}
+	}
+
+	fn statfs(&self) -> StatFs {
+		StatFs {
+			f_type: EXT4_SUPER_MAGIC.into(),
+			f_bsize: self.block_size.try_into().unwrap(),
+			f_blocks: self.blocks_count,
+			f_bfree: self.free_blocks,
+			f_bavail: self.free_blocks.saturating_sub(self.reserved_blocks),
+			f_files: self.inodes_count.into(),
+			f_ffree: self.free_inodes.into(),
+			f_namelen: 255,
+			f_frsize: self.block_size.try_into().unwrap(),
+			f_flags: MountFlags::MS_RDONLY.bits().try_into().unwrap(),
+			..Default::default()
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Ext4FileInterface {
+	fs: Arc<Ext4Fs>,
+	inode: Inode,
+	/// Position within the file
+	pos: Mutex<usize>,
+}
+
+#[async_trait]
+impl ObjectInterface for Ext4FileInterface {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		let ret = if (*self.pos.lock().await as u64) < self.inode.size {
+			event.intersection(PollEvent::POLLIN | PollEvent::POLLRDNORM | PollEvent::POLLRDBAND)
+		} else {
+			PollEvent::empty()
+		};
+
+		Ok(ret)
+	}
+
+	async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		let mut pos_guard = self.pos.lock().await;
+		let len = self.fs.read_data(&self.inode, *pos_guard as u64, buf)?;
+		*pos_guard += len;
+
+		Ok(len)
+	}
+
+	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+		let mut pos_guard = self.pos.lock().await;
+
+		let new_pos = match whence {
+			SeekWhence::Set => offset,
+			SeekWhence::Cur => isize::try_from(*pos_guard).unwrap() + offset,
+			SeekWhence::End => isize::try_from(self.inode.size).unwrap() + offset,
+			_ => return Err(Errno::Inval),
+		};
+
+		*pos_guard = usize::try_from(new_pos).map_err(|_| Errno::Inval)?;
+		Ok(new_pos)
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(self.fs.attributes(&self.inode))
+	}
+
+	async fn fstatfs(&self) -> io::Result<StatFs> {
+		Ok(self.fs.statfs())
+	}
+}
+
+/// Root directory of an ext2/ext3/ext4 file system
+#[derive(Debug)]
+pub(crate) struct Ext4Directory {
+	fs: Arc<Ext4Fs>,
+}
+
+impl Ext4Directory {
+	pub fn new(device: Arc<dyn BlockDevice>) -> io::Result<Self> {
+		Ok(Self {
+			fs: Arc::new(Ext4Fs::new(device)?),
+		})
+	}
+}
+
+impl VfsNode for Ext4Directory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		Ok(self.fs.attributes(&self.fs.read_inode(ROOT_INO)?))
+	}
+
+	fn get_object(&self) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		self.traverse_open(
+			&mut Vec::new(),
+			OpenOption::O_DIRECTORY,
+			AccessPermission::empty(),
+		)
+	}
+
+	fn traverse_mkdir(
+		&self,
+		components: &mut Vec<&str>,
+		_mode: AccessPermission,
+	) -> io::Result<()> {
+		match self.fs.lookup(components, false) {
+			Ok(_) => Err(Errno::Exist),
+			Err(_) => Err(Errno::Rofs),
+		}
+	}
+
+	fn traverse_rmdir(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		self.fs.lookup(components, false)?;
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_unlink(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		self.fs.lookup(components, false)?;
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_readdir(&self, components: &mut Vec<&str>) -> io::Result<Vec<DirectoryEntry>> {
+		self.fs.entries(&self.fs.lookup(components, true)?)
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		Ok(self.fs.attributes(&self.fs.lookup(components, false)?))
+	}
+
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		Ok(self.fs.attributes(&self.fs.lookup(components, true)?))
+	}
+
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		self.fs.lookup(components, true)?;
+		Ok(self.fs.statfs())
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		self.fs.read_link(&self.fs.lookup(components, false)?)
+	}
+
+	fn traverse_open(
+		&self,
+		components: &mut Vec<&str>,
+		opt: OpenOption,
+		_mode: AccessPermission,
+	) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		let inode = match self.fs.lookup(components, true) {
+			Ok(inode) => inode,
+			Err(Errno::Noent) if opt.contains(OpenOption::O_CREAT) => return Err(Errno::Rofs),
+			Err(e) => return Err(e),
+		};
+
+		if opt.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) {
+			return Err(Errno::Exist);
+		}
+		if inode.is_dir() {
+			if opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR) {
+				return Err(Errno::Isdir);
+			}
+			return Ok(Arc::new(async_lock::RwLock::new(DirectoryReader::new(
+				self.fs.entries(&inode)?,
+				self.fs.attributes(&inode),
+			))));
+		}
+		if opt.contains(OpenOption::O_DIRECTORY) {
+			return Err(Errno::Notdir);
+		}
+		if opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR | OpenOption::O_TRUNC) {
+			return Err(Errno::Rofs);
+		}
+
+		Ok(Arc::new(async_lock::RwLock::new(Ext4FileInterface {
+			fs: self.fs.clone(),
+			inode,
+			pos: Mutex::new(0),
+		})))
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use std::process::Command;
+
+	use super::*;
+	use crate::drivers::block::tests::{ImageDevice, pattern};
+
+	/// Size of a file, which spans several blocks
+	const LARGE_SIZE: u32 = 100_000;
+
+	/// Creates an image with `mke2fs` of the host, which is populated with
+	/// a file, a symbolic link and a directory with enough entries to be
+	/// indexed.
+	fn create_image(fs_type: &str) -> ImageDevice {
+		ImageDevice::create(
+			fs_type,
+			|root| {
+				std::fs::create_dir(root.join("dir")).unwrap();
+				std::fs::write(root.join("hello.txt"), b"Hello, ext4!\n").unwrap();
+				std::fs::write(root.join("dir").join("large.bin"), pattern(LARGE_SIZE)).unwrap();
+				for i in 0..200 {
+					std::fs::write(root.join("dir").join(format!("file-{i:03}")), b"").unwrap();
+				}
+				std::os::unix::fs::symlink("dir/large.bin", root.join("link")).unwrap();
+				std::os::unix::fs::symlink("/hello.txt", root.join("dir").join("abs")).unwrap();
+			},
+			|root, image| {
+				let mut mke2fs = Command::new("mke2fs");
+				mke2fs
+					.args(["-q", "-F", "-t", fs_type, "-b", "1024", "-d"])
+					.arg(root)
+					.arg(image)
+					.arg("4M");
+				vec![mke2fs]
+			},
+		)
+	}
+
+	fn read_all(root: &Ext4Directory, path: &[&str]) -> Vec<u8> {
+		let inode = root.fs.lookup(path, true).unwrap();
+		let mut data = vec![0u8; inode.size as usize];
+		let len = root.fs.read_data(&inode, 0, &mut data).unwrap();
+		assert_eq!(len, data.len());
+		data
+	}
+
+	fn check_image(fs_type: &str) {
+		let root = Ext4Directory::new(Arc::new(create_image(fs_type))).unwrap();
+
+		let names: Vec<String> = root
+			.traverse_readdir(&mut vec![])
+			.unwrap()
+			.into_iter()
+			.map(|entry| entry.name)
+			.filter(|name| name != "lost+found")
+			.collect::<std::collections::BTreeSet<_>>()
+			.into_iter()
+			.collect();
+		assert_eq!(names, ["dir", "hello.txt", "link"]);
+		assert_eq!(root.traverse_readdir(&mut vec!["dir"]).unwrap().len(), 202);
+
+		assert_eq!(read_all(&root, &["hello.txt"]), b"Hello, ext4!\n");
+		assert_eq!(read_all(&root, &["large.bin", "dir"]), pattern(LARGE_SIZE));
+		assert_eq!(read_all(&root, &["link"]), pattern(LARGE_SIZE));
+		assert_eq!(read_all(&root, &["abs", "dir"]), b"Hello, ext4!\n");
+		assert_eq!(
+			read_all(&root, &["hello.txt", "..", "dir"]),
+			b"Hello, ext4!\n"
+		);
+		// `dir/abs` resolves to a regular file, which has no parent entry.
+		assert_eq!(
+			root.fs
+				.lookup(&["hello.txt", "..", "abs", "dir"], true)
+				.unwrap_err(),
+			Errno::Notdir
+		);
+
+		assert_eq!(
+			root.traverse_readlink(&mut vec!["link"]).unwrap(),
+			"dir/large.bin"
+		);
+		let attr = root.traverse_lstat(&mut vec!["link"]).unwrap();
+		assert_eq!(
+			(attr.st_mode & AccessPermission::S_IFMT).bits(),
+			AccessPermission::S_IFLNK.bits()
+		);
+		let attr = root.traverse_stat(&mut vec!["link"]).unwrap();
+		assert_eq!(attr.st_size, LARGE_SIZE.into());
+
+		assert_eq!(
+			root.traverse_stat(&mut vec!["missing"]).unwrap_err(),
+			Errno::Noent
+		);
+		assert_eq!(
+			root.traverse_open(
+				&mut vec!["hello.txt"],
+				OpenOption::O_RDWR,
+				AccessPermission::empty()
+			)
+			.unwrap_err(),
+			Errno::Rofs
+		);
+	}
+
+	#[test]
+	#[ignore = "requires mke2fs"]
+	fn test_ext2_image() {
+		check_image("ext2");
+	}
+
+	#[test]
+	#[ignore = "requires mke2fs"]
+	fn test_ext4_image() {
+		check_image("ext4");
+	}
+}
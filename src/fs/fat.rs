@@ -0,0 +1,695 @@
+//! Implements a read-only FAT32 file system on top of a block device.
+//!
+//! Long file names are supported. Names are compared case-insensitively,
+//! like on other operating systems. Timestamps of FAT are in local time,
+//! which is interpreted as UTC.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+use time::{Date, Month, PrimitiveDateTime, Time};
+
+use crate::drivers::block::BlockDevice;
+use crate::errno::Errno;
+use crate::fd::{AccessPermission, ObjectInterface, OpenOption, PollEvent};
+use crate::fs::{
+	DirectoryEntry, DirectoryReader, FileAttr, MountFlags, NodeKind, SeekWhence, StatFs, VfsNode,
+};
+use crate::io;
+use crate::time::timespec;
+
+/// Magic number of FAT file systems
+const MSDOS_SUPER_MAGIC: i64 = 0x4d44;
+
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+/// Combination of attributes, which marks a long file name entry
+const ATTR_LONG_NAME: u8 = 0x0f;
+
+/// Size of a directory entry
+const DIR_ENTRY_SIZE: usize = 32;
+/// Number of UCS-2 characters in a long file name entry
+const LFN_CHARS: usize = 13;
+
+/// Smallest cluster value, which marks the end of a cluster chain
+const END_OF_CHAIN: u32 = 0x0fff_fff8;
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+	u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+/// Converts a FAT date and time to a timestamp.
+fn fat_time(date: u16, time: u16, tenths: u8) -> timespec {
+	let year = 1980 + i32::from(date >> 9);
+	let Ok(month) = Month::try_from(u8::try_from((date >> 5) & 0xf).unwrap()) else {
+		return timespec::default();
+	};
+	let day = u8::try_from(date & 0x1f).unwrap();
+	let Ok(date) = Date::from_calendar_date(year, month, day) else {
+		return timespec::default();
+	};
+
+	let hour = u8::try_from(time >> 11).unwrap();
+	let minute = u8::try_from((time >> 5) & 0x3f).unwrap();
+	let second = u8::try_from(time & 0x1f).unwrap() * 2 + tenths / 100;
+	let Ok(time) = Time::from_hms(hour, minute, second) else {
+		return timespec::default();
+	};
+
+	timespec {
+		tv_sec: PrimitiveDateTime::new(date, time)
+			.assume_utc()
+			.unix_timestamp(),
+		tv_nsec: i32::from(tenths % 100) * 10_000_000,
+	}
+}
+
+/// Returns the checksum of a short name, which is stored in its long name entries.
+fn short_name_checksum(name: &[u8]) -> u8 {
+	name[..11]
+		.iter()
+		.fold(0u8, |sum, &c| sum.rotate_right(1).wrapping_add(c))
+}
+
+/// Converts a short name in 8.3 format to a string.
+fn short_name(entry: &[u8]) -> String {
+	/// Flags of the reserved byte, which select lower case
+	const LOWER_BASE: u8 = 0x08;
+	const LOWER_EXT: u8 = 0x10;
+
+	let convert = |bytes: &[u8], lower: bool| -> String {
+		bytes
+			.iter()
+			.map(|&c| {
+				// A leading 0x05 stands for 0xe5, which marks deleted entries.
+				let c = if c == 0x05 { 0xe5 } else { c };
+				let c = char::from(c);
+				if lower { c.to_ascii_lowercase() } else { c }
+			})
+			.collect::<String>()
+			.trim_end()
+			.to_string()
+	};
+
+	let base = convert(&entry[0..8], entry[12] & LOWER_BASE != 0);
+	let ext = convert(&entry[8..11], entry[12] & LOWER_EXT != 0);
+	if ext.is_empty() {
+		base
+	} else {
+		base + "." + &ext
+	}
+}
+
+#[derive(Debug, Clone)]
+struct FatEntry {
+	name: String,
+	attr: u8,
+	/// First cluster of the content, which is zero for empty files
+	cluster: u32,
+	size: u32,
+	atime: timespec,
+	mtime: timespec,
+	ctime: timespec,
+}
+
+impl FatEntry {
+	fn parse(entry: &[u8], name: String) -> Self {
+		Self {
+			name,
+			attr: entry[11],
+			cluster: (u32::from(read_u16(entry, 20)) << 16) | u32::from(read_u16(entry, 26)),
+			size: read_u32(entry, 28),
+			atime: fat_time(read_u16(entry, 18), 0, 0),
+			mtime: fat_time(read_u16(entry, 24), read_u16(entry, 22), 0),
+			ctime: fat_time(read_u16(entry, 16), read_u16(entry, 14), entry[13]),
+		}
+	}
+
+	fn is_dir(&self) -> bool {
+		self.attr & ATTR_DIRECTORY != 0
+	}
+}
+
+#[derive(Debug)]
+pub(crate) struct FatFs {
+	device: Arc<dyn BlockDevice>,
+	/// Size of a sector in bytes
+	sector_size: u32,
+	/// Size of a cluster in bytes
+	cluster_size: u32,
+	/// Byte offset of the first file allocation table
+	fat_offset: u64,
+	/// Byte offset of the first cluster
+	data_offset: u64,
+	root_cluster: u32,
+	/// Number of data clusters
+	cluster_count: u32,
+	/// Number of free clusters according to the FS information sector
+	free_clusters: Option<u32>,
+}
+
+impl FatFs {
+	pub fn new(device: Arc<dyn BlockDevice>) -> io::Result<Self> {
+		let mut boot = [0u8; 512];
+		device.read_at(0, &mut boot)?;
+		if boot[510..512] != [0x55, 0xaa] {
+			return Err(Errno::Inval);
+		}
+
+		let bytes_per_sector = u32::from(read_u16(&boot, 11));
+		let sectors_per_cluster = u32::from(boot[13]);
+		let reserved_sectors = u32::from(read_u16(&boot, 14));
+		let num_fats = u32::from(boot[16]);
+		let root_entries = read_u16(&boot, 17);
+		let total_sectors = match read_u16(&boot, 19) {
+			0 => read_u32(&boot, 32),
+			sectors => u32::from(sectors),
+		};
+		let fat_size = read_u32(&boot, 36);
+
+		// Like Linux, we recognize FAT32 by the size of the FAT in the extended
+		// boot record, which is zero for FAT12 and FAT16.
+		if !bytes_per_sector.is_power_of_two()
+			|| !(512..=4096).contains(&bytes_per_sector)
+			|| !sectors_per_cluster.is_power_of_two()
+			|| read_u16(&boot, 22) != 0
+			|| root_entries != 0
+			|| fat_size == 0
+		{
+			return Err(Errno::Inval);
+		}
+
+		let data_sector = reserved_sectors + num_fats * fat_size;
+		let cluster_count =
+			total_sectors.checked_sub(data_sector).ok_or(Errno::Inval)? / sectors_per_cluster;
+
+		let free_clusters = match read_u16(&boot, 48) {
+			0 | 0xffff => None,
+			sector => {
+				let mut info = [0u8; 512];
+				device.read_at(u64::from(sector) * u64::from(bytes_per_sector), &mut info)?;
+				let valid =
+					read_u32(&info, 0) == 0x4161_5252 && read_u32(&info, 484) == 0x6141_7272;
+				let free = read_u32(&info, 488);
+				(valid && free <= cluster_count).then_some(free)
+			}
+		};
+
+		Ok(Self {
+			device,
+			sector_size: bytes_per_sector,
+			cluster_size: bytes_per_sector * sectors_per_cluster,
+			fat_offset: u64::from(reserved_sectors) * u64::from(bytes_per_sector),
+			data_offset: u64::from(data_sector) * u64::from(bytes_per_sector),
+			root_cluster: read_u32(&boot, 44),
+			cluster_count,
+			free_clusters,
+		})
+	}
+
+	fn is_valid_cluster(&self, cluster: u32) -> bool {
+		(2..self.cluster_count + 2).contains(&cluster)
+	}
+
+	/// Returns the clusters of the chain starting at `first`.
+	fn cluster_chain(&self, first: u32) -> io::Result<Vec<u32>> {
+		let mut chain = Vec::new();
+		// The FAT is read sector by sector, because the clusters of a file
+		// mostly follow each other and their entries share a sector.
+		let entries_per_sector = self.sector_size / 4;
+		let mut sector = vec![0u8; self.sector_size as usize];
+		let mut cached = None;
+		let mut cluster = first;
+		while cluster != 0 && cluster < END_OF_CHAIN {
+			// A chain longer than the number of clusters contains a loop.
+			if !self.is_valid_cluster(cluster) || chain.len() > self.cluster_count as usize {
+				return Err(Errno::Io);
+			}
+			chain.push(cluster);
+
+			let index = cluster / entries_per_sector;
+			if cached != Some(index) {
+				self.device.read_at(
+					self.fat_offset + u64::from(index) * u64::from(self.sector_size),
+					&mut sector,
+				)?;
+				cached = Some(index);
+			}
+			let entry = (cluster % entries_per_sector) as usize * 4;
+			cluster = read_u32(&sector, entry) & 0x0fff_ffff;
+		}
+
+		Ok(chain)
+	}
+
+	fn cluster_offset(&self, cluster: u32) -> u64 {
+		self.data_offset + u64::from(cluster - 2) * u64::from(self.cluster_size)
+	}
+
+	/// Reads the content of a file or directory at `offset`, which is
+	/// limited by the length of `buf` and the end of the chain.
+	fn read_chain(&self, chain: &[u32], offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+		let cluster_size = u64::from(self.cluster_size);
+		let mut done = 0;
+		while done < buf.len() {
+			let pos = offset + done as u64;
+			let Some(&cluster) = chain.get(usize::try_from(pos / cluster_size).unwrap()) else {
+				break;
+			};
+			let within = pos % cluster_size;
+			let len = (buf.len() - done).min(usize::try_from(cluster_size - within).unwrap());
+			self.device.read_at(
+				self.cluster_offset(cluster) + within,
+				&mut buf[done..done + len],
+			)?;
+			done += len;
+		}
+
+		Ok(done)
+	}
+
+	fn root(&self) -> FatEntry {
+		FatEntry {
+			name: String::new(),
+			attr: ATTR_DIRECTORY,
+			cluster: self.root_cluster,
+			size: 0,
+			atime: timespec::default(),
+			mtime: timespec::default(),
+			ctime: timespec::default(),
+		}
+	}
+
+	/// Returns the entries of the directory `dir` without `.` and `..`.
+	fn read_dir(&self, dir: &FatEntry) -> io::Result<Vec<FatEntry>> {
+		let chain = self.cluster_chain(dir.cluster)?;
+		let mut data = vec![0u8; chain.len() * self.cluster_size as usize];
+		self.read_chain(&chain, 0, &mut data)?;
+
+		let mut entries = Vec::new();
+		// Characters and checksum of the long name preceding the next short entry
+		let mut long_name: Option<(Vec<u16>, u8)> = None;
+		for entry in data.chunks_exact(DIR_ENTRY_SIZE) {
+			match entry[0] {
+				0x00 => break,
+				0xe5 => {
+					long_name = None;
+					continue;
+				}
+				_ => {}
+			}
+
+			if entry[11] & ATTR_LONG_NAME == ATTR_LONG_NAME {
+				let seq = usize::from(entry[0] & 0x1f);
+				if seq == 0 {
+					long_name = None;
+					continue;
+				}
+				// The entry with the last part of the name comes first.
+				if entry[0] & 0x40 != 0 {
+					long_name = Some((vec![0xffff; seq * LFN_CHARS], entry[13]));
+				}
+				if let Some((chars, checksum)) = &mut long_name
+					&& *checksum == entry[13]
+					&& seq * LFN_CHARS <= chars.len()
+				{
+					let part = &mut chars[(seq - 1) * LFN_CHARS..seq * LFN_CHARS];
+					let offsets = (1..11)
+						.step_by(2)
+						.chain((14..26).step_by(2))
+						.chain((28..32).step_by(2));
+					for (c, offset) in part.iter_mut().zip(offsets) {
+						*c = read_u16(entry, offset);
+					}
+				} else {
+					long_name = None;
+				}
+				continue;
+			}
+
+			let long_name = long_name.take();
+			if entry[11] & ATTR_VOLUME_ID != 0 {
+				continue;
+			}
+
+			let name = match long_name {
+				Some((chars, checksum)) if checksum == short_name_checksum(entry) => {
+					let len = chars
+						.iter()
+						.position(|&c| c == 0 || c == 0xffff)
+						.unwrap_or(chars.len());
+					String::from_utf16_lossy(&chars[..len])
+				}
+				_ => short_name(entry),
+			};
+			if name == "." || name == ".." {
+				continue;
+			}
+
+			entries.push(FatEntry::parse(entry, name));
+		}
+
+		Ok(entries)
+	}
+
+	/// Looks up the entry given by the reversed path components.
+	fn lookup(&self, components: &[&str]) -> io::Result<FatEntry> {
+		let mut entry = self.root();
+		for component in components.iter().rev() {
+			if !entry.is_dir() {
+				return Err(Errno::Notdir);
+			}
+			entry = self
+				.read_dir(&entry)?
+				.into_iter()
+				.find(|child| child.name.eq_ignore_ascii_case(component))
+				.ok_or(Errno::Noent)?;
+		}
+
+		Ok(entry)
+	}
+
+	fn attributes(&self, entry: &FatEntry) -> FileAttr {
+		let st_mode = if entry.is_dir() {
+			AccessPermission::S_IFDIR | AccessPermission::from_bits_retain(0o555)
+		} else {
+			AccessPermission::S_IFREG | AccessPermission::from_bits_retain(0o444)
+		};
+
+		FileAttr {
+			st_ino: entry.cluster.into(),
+			st_nlink: 1,
+			st_mode,
+			st_size: entry.size.into(),
+			st_blksize: self.cluster_size.into(),
+			st_blocks: i64::from(entry.size.div_ceil(512)),
+			st_atim: entry.atime,
+			st_mtim: entry.mtime,
+			st_ctim: entry.ctime,
+			..Default::default()
+		}
+	}
+
+	fn statfs(&self) -> StatFs {
+		let free = self.free_clusters.unwrap_or(0).into();
+		StatFs {
+			f_type: MSDOS_SUPER_MAGIC,
+			f_bsize: self.cluster_size.into(),
+			f_blocks: self.cluster_count.into(),
+			f_bfree: free,
+			f_bavail: free,
+			f_namelen: 255,
+			f_frsize: self.cluster_size.into(),
+			f_flags: MountFlags::MS_RDONLY.bits().try_into().unwrap(),
+			..Default::default()
+		}
+	}
+}
+
+#[derive(Debug)]
+struct FatFileInterface {
+	fs: Arc<FatFs>,
+	/// Position within the file
+	pos: Mutex<usize>,
+	/// Clusters of the file content
+	chain: Vec<u32>,
+	attr: FileAttr,
+}
+
+#[async_trait]
+impl ObjectInterface for FatFileInterface {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		let ret = if *self.pos.lock().await < self.attr.st_size as usize {
+			event.intersection(PollEvent::POLLIN | PollEvent::POLLRDNORM | PollEvent::POLLRDBAND)
+		} else {
+			PollEvent::empty()
+		};
+
+		Ok(ret)
+	}
+
+	async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		let mut pos_guard = self.pos.lock().await;
+		let pos = *pos_guard;
+		let size = self.attr.st_size as usize;
+
+		if pos >= size {
+			return Ok(0);
+		}
+
+		let len = buf.len().min(size - pos);
+		let len = self
+			.fs
+			.read_chain(&self.chain, pos as u64, &mut buf[..len])?;
+		*pos_guard = pos + len;
+
+		Ok(len)
+	}
+
+	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+		let mut pos_guard = self.pos.lock().await;
+
+		let new_pos = match whence {
+			SeekWhence::Set => offset,
+			SeekWhence::Cur => isize::try_from(*pos_guard).unwrap() + offset,
+			SeekWhence::End => self.attr.st_size as isize + offset,
+			_ => return Err(Errno::Inval),
+		};
+
+		*pos_guard = usize::try_from(new_pos).map_err(|_| Errno::Inval)?;
+		Ok(new_pos)
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(self.attr)
+	}
+
+	async fn fstatfs(&self) -> io::Result<StatFs> {
+		Ok(self.fs.statfs())
+	}
+}
+
+/// Mount point of a FAT file system
+#[derive(Debug)]
+pub(crate) struct FatDirectory {
+	fs: Arc<FatFs>,
+}
+
+impl FatDirectory {
+	pub fn new(device: Arc<dyn BlockDevice>) -> io::Result<Self> {
+		Ok(Self {
+			fs: Arc::new(FatFs::new(device)?),
+		})
+	}
+}
+
+impl VfsNode for FatDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		Ok(self.fs.attributes(&self.fs.root()))
+	}
+
+	fn get_object(&self) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		self.traverse_open(
+			&mut Vec::new(),
+			OpenOption::O_DIRECTORY,
+			AccessPermission::empty(),
+		)
+	}
+
+	fn traverse_mkdir(
+		&self,
+		components: &mut Vec<&str>,
+		_mode: AccessPermission,
+	) -> io::Result<()> {
+		match self.fs.lookup(components) {
+			Ok(_) => Err(Errno::Exist),
+			Err(_) => Err(Errno::Rofs),
+		}
+	}
+
+	fn traverse_rmdir(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		self.fs.lookup(components)?;
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_unlink(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		self.fs.lookup(components)?;
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_readdir(&self, components: &mut Vec<&str>) -> io::Result<Vec<DirectoryEntry>> {
+		let dir = self.fs.lookup(components)?;
+		if !dir.is_dir() {
+			return Err(Errno::Notdir);
+		}
+
+		Ok(self
+			.fs
+			.read_dir(&dir)?
+			.into_iter()
+			.map(|entry| DirectoryEntry::new(entry.name))
+			.collect())
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		Ok(self.fs.attributes(&self.fs.lookup(components)?))
+	}
+
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		self.traverse_lstat(components)
+	}
+
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		self.fs.lookup(components)?;
+		Ok(self.fs.statfs())
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		self.fs.lookup(components)?;
+		Err(Errno::Inval)
+	}
+
+	fn traverse_open(
+		&self,
+		components: &mut Vec<&str>,
+		opt: OpenOption,
+		_mode: AccessPermission,
+	) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		let entry = match self.fs.lookup(components) {
+			Ok(entry) => entry,
+			Err(Errno::Noent) if opt.contains(OpenOption::O_CREAT) => return Err(Errno::Rofs),
+			Err(e) => return Err(e),
+		};
+
+		if opt.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) {
+			return Err(Errno::Exist);
+		}
+		if entry.is_dir() {
+			if opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR) {
+				return Err(Errno::Isdir);
+			}
+			let entries = self
+				.fs
+				.read_dir(&entry)?
+				.into_iter()
+				.map(|entry| DirectoryEntry::new(entry.name))
+				.collect();
+			return Ok(Arc::new(async_lock::RwLock::new(DirectoryReader::new(
+				entries,
+				self.fs.attributes(&entry),
+			))));
+		}
+		if opt.contains(OpenOption::O_DIRECTORY) {
+			return Err(Errno::Notdir);
+		}
+		if opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR | OpenOption::O_TRUNC) {
+			return Err(Errno::Rofs);
+		}
+
+		Ok(Arc::new(async_lock::RwLock::new(FatFileInterface {
+			fs: self.fs.clone(),
+			pos: Mutex::new(0),
+			chain: self.fs.cluster_chain(entry.cluster)?,
+			attr: self.fs.attributes(&entry),
+		})))
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use std::process::Command;
+
+	use super::*;
+	use crate::drivers::block::tests::{ImageDevice, pattern};
+
+	/// Size of a file, which spans several clusters
+	const LARGE_SIZE: u32 = 20_000;
+
+	/// Creates a FAT32 image with `mkfs.fat` and `mcopy` of the host.
+	fn create_image() -> ImageDevice {
+		ImageDevice::create(
+			"fat",
+			|root| {
+				std::fs::create_dir(root.join("Sub Directory")).unwrap();
+				std::fs::write(root.join("hello.txt"), b"Hello, FAT!\n").unwrap();
+				std::fs::write(
+					root.join("Sub Directory").join("A Long File Name.bin"),
+					pattern(LARGE_SIZE),
+				)
+				.unwrap();
+			},
+			|root, image| {
+				let mut mkfs = Command::new("mkfs.fat");
+				mkfs.args(["-F", "32", "-S", "512", "-s", "1", "-C"])
+					.arg(image)
+					.arg("34000");
+				let mut mcopy = Command::new("mcopy");
+				mcopy
+					.args(["-s", "-i"])
+					.arg(image)
+					.arg(root.join("hello.txt"))
+					.arg(root.join("Sub Directory"))
+					.arg("::/");
+				vec![mkfs, mcopy]
+			},
+		)
+	}
+
+	fn read_all(root: &FatDirectory, path: &[&str]) -> Vec<u8> {
+		let entry = root.fs.lookup(path).unwrap();
+		let chain = root.fs.cluster_chain(entry.cluster).unwrap();
+		let mut data = vec![0u8; entry.size as usize];
+		let len = root.fs.read_chain(&chain, 0, &mut data).unwrap();
+		assert_eq!(len, data.len());
+		data
+	}
+
+	#[test]
+	#[ignore = "requires mkfs.fat and mcopy"]
+	fn test_fat32_image() {
+		let root = FatDirectory::new(Arc::new(create_image())).unwrap();
+
+		let mut names: Vec<String> = root
+			.traverse_readdir(&mut vec![])
+			.unwrap()
+			.into_iter()
+			.map(|entry| entry.name)
+			.collect();
+		names.sort();
+		assert_eq!(names, ["Sub Directory", "hello.txt"]);
+
+		assert_eq!(read_all(&root, &["HELLO.TXT"]), b"Hello, FAT!\n");
+
+		let path = ["A Long File Name.bin", "Sub Directory"];
+		let attr = root.traverse_stat(&mut path.to_vec()).unwrap();
+		assert_eq!(attr.st_size, LARGE_SIZE.into());
+		assert_eq!(read_all(&root, &path), pattern(LARGE_SIZE));
+
+		assert_eq!(
+			root.traverse_open(
+				&mut vec!["hello.txt"],
+				OpenOption::O_RDWR,
+				AccessPermission::empty()
+			)
+			.unwrap_err(),
+			Errno::Rofs
+		);
+		assert_eq!(
+			root.traverse_stat(&mut vec!["missing"]).unwrap_err(),
+			Errno::Noent
+		);
+	}
+}
@@ -0,0 +1,401 @@
+//! Mounts the file systems given by `mount=` boot arguments.
+//!
+//! Like a line of `/etc/fstab`, each argument describes a file system as
+//! `mount=<source>:<target>:<type>[:<options>]`, where the options are
+//! separated by commas, e.g.
+//!
+//! ```text
+//! mount=data:/data:virtiofs:ro mount=none:/scratch:tmpfs:size=64m
+//! ```
+//!
+//! The following types are supported:
+//!
+//! - `virtiofs`: the virtio-fs device, whose tag is the source.
+//! - `tmpfs`: an in-memory file system, whose size and number of inodes are
+//!   limited by the options `size` and `nr_inodes`.
+//! - `overlay`: a writable in-memory layer on top of a file system with the
+//!   same source, whose type is given by the option `lower` (`virtiofs` by
+//!   default).
+//! - `uhyve`: the file system of uhyve, which maps the target to the host
+//!   itself.
+//! - `vfat` and `ext4`: a FAT32 or ext2/3/4 file system on the block device
+//!   given as source, which is always mounted read-only.
+//!
+//! A file system with the target `/` replaces the in-memory root file system.
+//! As the root file system has to host further mount points, only `tmpfs`,
+//! `overlay`, `vfat` and `ext4` are supported at `/`, where a disk file system
+//! is covered by a writable in-memory layer. The boot argument
+//! `root=<block device>` is a shorthand for `mount=<block device>:/:ext4` and
+//! the variable `HERMIT_FAT=<block device>:<mount point>` for
+//! `mount=<block device>:<mount point>:vfat`.
+//!
+//! File systems mounted with `ro` reject all modifications with `EROFS`.
+//! The options `nosuid`, `nodev` and `noexec` are recorded in the mount
+//! table. Hermit neither executes programs nor opens device files of mounted
+//! file systems, so they have no further effect.
+//!
+//! If no `mount=` argument is given, all virtio-fs devices are mounted at
+//! their tag and the file system of uhyve at `UHYVE_MOUNT`.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[cfg(any(feature = "fat", feature = "ext4"))]
+use crate::drivers::block;
+use crate::errno::Errno;
+use crate::fd::AccessPermission;
+use crate::fs::mem::{MemDirectory, MemUsage};
+use crate::fs::overlay::OverlayDirectory;
+use crate::fs::readonly::ReadOnlyDirectory;
+use crate::fs::uhyve::UhyveDirectory;
+use crate::fs::{self, MountEntry, MountFlags, VfsNode};
+use crate::io;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum FsType {
+	Virtiofs,
+	Tmpfs,
+	Overlay,
+	Uhyve,
+	Vfat,
+	Ext4,
+}
+
+impl FsType {
+	fn parse(name: &str) -> io::Result<Self> {
+		match name {
+			"virtiofs" => Ok(Self::Virtiofs),
+			"tmpfs" => Ok(Self::Tmpfs),
+			"overlay" => Ok(Self::Overlay),
+			"uhyve" | "uhyvefs" => Ok(Self::Uhyve),
+			"vfat" => Ok(Self::Vfat),
+			"ext4" => Ok(Self::Ext4),
+			_ => Err(Errno::Nodev),
+		}
+	}
+
+	/// Returns the name of the type in the mount table.
+	fn name(self) -> &'static str {
+		match self {
+			Self::Virtiofs => "virtiofs",
+			Self::Tmpfs => "tmpfs",
+			Self::Overlay => "overlay",
+			Self::Uhyve => "uhyvefs",
+			Self::Vfat => "vfat",
+			Self::Ext4 => "ext4",
+		}
+	}
+
+	/// Returns `true` for file systems on block devices, which are read-only.
+	fn is_disk(self) -> bool {
+		matches!(self, Self::Vfat | Self::Ext4)
+	}
+}
+
+/// File system described by a `mount=` argument
+#[derive(Debug, Eq, PartialEq)]
+struct MountSpec<'a> {
+	source: &'a str,
+	/// Absolute path of the mount point
+	target: &'a str,
+	fs_type: FsType,
+	flags: MountFlags,
+	/// Maximum size of a tmpfs
+	size: Option<&'a str>,
+	/// Maximum number of inodes of a tmpfs
+	nr_inodes: Option<&'a str>,
+	/// Type of the lower layer of an overlay
+	lower: FsType,
+}
+
+impl<'a> MountSpec<'a> {
+	fn parse(spec: &'a str) -> io::Result<Self> {
+		let mut fields = spec.splitn(4, ':');
+		let (Some(source), Some(target), Some(fs_type)) =
+			(fields.next(), fields.next(), fields.next())
+		else {
+			return Err(Errno::Inval);
+		};
+		let target = match target.trim_end_matches('/') {
+			"" if target.starts_with('/') => "/",
+			target => target,
+		};
+		if source.is_empty() || !target.starts_with('/') {
+			return Err(Errno::Inval);
+		}
+
+		let mut spec = Self {
+			source,
+			target,
+			fs_type: FsType::parse(fs_type)?,
+			flags: MountFlags::empty(),
+			size: None,
+			nr_inodes: None,
+			lower: FsType::Virtiofs,
+		};
+
+		for option in fields
+			.next()
+			.into_iter()
+			.flat_map(|options| options.split(','))
+		{
+			let (name, value) = match option.split_once('=') {
+				Some((name, value)) => (name, Some(value)),
+				None => (option, None),
+			};
+
+			match (name, value, spec.fs_type) {
+				("defaults" | "", None, _) => {}
+				("ro", None, _) => spec.flags.insert(MountFlags::MS_RDONLY),
+				("rw", None, _) => spec.flags.remove(MountFlags::MS_RDONLY),
+				("nosuid", None, _) => spec.flags.insert(MountFlags::MS_NOSUID),
+				("suid", None, _) => spec.flags.remove(MountFlags::MS_NOSUID),
+				("nodev", None, _) => spec.flags.insert(MountFlags::MS_NODEV),
+				("dev", None, _) => spec.flags.remove(MountFlags::MS_NODEV),
+				("noexec", None, _) => spec.flags.insert(MountFlags::MS_NOEXEC),
+				("exec", None, _) => spec.flags.remove(MountFlags::MS_NOEXEC),
+				("size", Some(size), FsType::Tmpfs) => spec.size = Some(size),
+				("nr_inodes", Some(inodes), FsType::Tmpfs) => spec.nr_inodes = Some(inodes),
+				("lower", Some(lower), FsType::Overlay) => {
+					spec.lower = match FsType::parse(lower)? {
+						FsType::Tmpfs | FsType::Overlay => return Err(Errno::Inval),
+						lower => lower,
+					};
+				}
+				_ => return Err(Errno::Inval),
+			}
+		}
+
+		Ok(spec)
+	}
+
+	/// Returns the root directory of the file system of the type `fs_type`.
+	fn root(
+		&self,
+		fs_type: FsType,
+	) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		match fs_type {
+			FsType::Virtiofs => virtiofs(self.source),
+			FsType::Tmpfs => {
+				let usage = MemUsage::with_limits(self.size, self.nr_inodes)?;
+				Ok(Box::new(MemDirectory::with_usage(
+					AccessPermission::from_bits(0o777).unwrap(),
+					&usage,
+				)?))
+			}
+			FsType::Overlay => Ok(Box::new(OverlayDirectory::new(self.root(self.lower)?))),
+			FsType::Uhyve if crate::env::is_uhyve() => {
+				Ok(Box::new(UhyveDirectory::new(Some(self.target.to_string()))))
+			}
+			FsType::Uhyve => Err(Errno::Nodev),
+			FsType::Vfat => vfat(self.source),
+			FsType::Ext4 => ext4(self.source),
+		}
+	}
+
+	/// Returns the mount flags, which are extended by `MS_RDONLY` for disk
+	/// file systems.
+	fn flags(&self) -> MountFlags {
+		if self.fs_type.is_disk() {
+			self.flags | MountFlags::MS_RDONLY
+		} else {
+			self.flags
+		}
+	}
+
+	fn mount(&self) -> io::Result<()> {
+		let node = self.root(self.fs_type)?;
+
+		info!(
+			"Mounting {} {} at {}",
+			self.fs_type.name(),
+			self.source,
+			self.target
+		);
+		fs::mount(
+			self.source,
+			self.target,
+			self.fs_type.name(),
+			self.flags(),
+			node,
+		)
+	}
+
+	/// Returns the root directory of the whole file system and its mount table entry.
+	fn mount_root(
+		&self,
+	) -> io::Result<(
+		MountEntry,
+		Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	)> {
+		let mut node = match self.fs_type {
+			FsType::Tmpfs | FsType::Overlay => self.root(self.fs_type)?,
+			FsType::Vfat | FsType::Ext4 => {
+				Box::new(OverlayDirectory::new(self.root(self.fs_type)?))
+			}
+			// Only in-memory directories can host the mount points of /proc and /dev.
+			FsType::Virtiofs | FsType::Uhyve => return Err(Errno::Inval),
+		};
+		if self.flags.contains(MountFlags::MS_RDONLY) {
+			node = Box::new(ReadOnlyDirectory::new(node));
+		}
+
+		info!(
+			"Using {} {} as root file system",
+			self.fs_type.name(),
+			self.source
+		);
+		let entry = MountEntry {
+			source: self.source.to_string(),
+			target: self.target.to_string(),
+			fs_type: self.fs_type.name(),
+			flags: self.flags,
+		};
+		Ok((entry, node))
+	}
+}
+
+/// Returns the root directory of the virtio-fs device with the tag `tag`.
+#[cfg(all(feature = "fuse", feature = "pci"))]
+fn virtiofs(tag: &str) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+	Ok(Box::new(fs::fuse::directory(tag)?))
+}
+
+#[cfg(not(all(feature = "fuse", feature = "pci")))]
+fn virtiofs(_tag: &str) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+	Err(Errno::Nodev)
+}
+
+/// Returns the root directory of the FAT file system on the block device `device`.
+#[cfg(feature = "fat")]
+fn vfat(device: &str) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+	let device = block::get_block_device(device).ok_or(Errno::Noent)?;
+	Ok(Box::new(fs::fat::FatDirectory::new(device)?))
+}
+
+#[cfg(not(feature = "fat"))]
+fn vfat(_device: &str) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+	Err(Errno::Nodev)
+}
+
+/// Returns the root directory of the ext4 file system on the block device `device`.
+#[cfg(feature = "ext4")]
+fn ext4(device: &str) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+	let device = block::get_block_device(device).ok_or(Errno::Noent)?;
+	Ok(Box::new(fs::ext4::Ext4Directory::new(device)?))
+}
+
+#[cfg(not(feature = "ext4"))]
+fn ext4(_device: &str) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+	Err(Errno::Nodev)
+}
+
+/// Returns the `mount=` arguments, which are preceded by the equivalents of
+/// the boot argument `root=` and the variable `HERMIT_FAT`.
+pub(crate) fn specs() -> Vec<String> {
+	let mut specs = Vec::new();
+	if let Some(device) = crate::env::root() {
+		specs.push(format!("{device}:/:ext4"));
+	}
+	if let Some(var) = hermit_var!("HERMIT_FAT") {
+		match var.split_once(':') {
+			Some((device, mount_point)) => specs.push(format!("{device}:{mount_point}:vfat")),
+			None => error!("Invalid FAT mount {var}, expected <block device>:<mount point>"),
+		}
+	}
+	specs.extend(crate::env::mounts().iter().cloned());
+	specs
+}
+
+/// Returns the root directory of the first file system of `specs`, which is
+/// mounted at `/`, and its mount table entry.
+pub(crate) fn root(
+	specs: &[String],
+) -> Option<(
+	MountEntry,
+	Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+)> {
+	specs.iter().find_map(|spec| match MountSpec::parse(spec) {
+		Ok(parsed) if parsed.target == "/" => parsed
+			.mount_root()
+			.inspect_err(|e| error!("Unable to mount {spec}: {e:?}"))
+			.ok(),
+		_ => None,
+	})
+}
+
+/// Mounts the file systems of `specs` in their order, except the root file
+/// system, which has been mounted by [`root`].
+pub(crate) fn init(specs: &[String]) {
+	for spec in specs {
+		let result = MountSpec::parse(spec).and_then(|spec| {
+			if spec.target == "/" {
+				Ok(())
+			} else {
+				spec.mount()
+			}
+		});
+		if let Err(e) = result {
+			error!("Unable to mount {spec}: {e:?}");
+		}
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse() {
+		assert_eq!(
+			MountSpec::parse("data:/data/:virtiofs:ro,noexec"),
+			Ok(MountSpec {
+				source: "data",
+				target: "/data",
+				fs_type: FsType::Virtiofs,
+				flags: MountFlags::MS_RDONLY | MountFlags::MS_NOEXEC,
+				size: None,
+				nr_inodes: None,
+				lower: FsType::Virtiofs,
+			})
+		);
+		assert_eq!(
+			MountSpec::parse("none:/scratch:tmpfs:size=64m,nr_inodes=1024,ro,rw"),
+			Ok(MountSpec {
+				source: "none",
+				target: "/scratch",
+				fs_type: FsType::Tmpfs,
+				flags: MountFlags::empty(),
+				size: Some("64m"),
+				nr_inodes: Some("1024"),
+				lower: FsType::Virtiofs,
+			})
+		);
+		assert_eq!(
+			MountSpec::parse("host:/root:overlay:lower=uhyve").map(|spec| spec.lower),
+			Ok(FsType::Uhyve)
+		);
+		assert_eq!(
+			MountSpec::parse("vda:/:overlay:lower=ext4").map(|spec| (spec.target, spec.lower)),
+			Ok(("/", FsType::Ext4))
+		);
+		let spec = MountSpec::parse("vdb:/boot/:vfat:rw").unwrap();
+		assert_eq!((spec.target, spec.fs_type), ("/boot", FsType::Vfat));
+		assert_eq!(spec.flags(), MountFlags::MS_RDONLY);
+
+		assert_eq!(MountSpec::parse("data:/data"), Err(Errno::Inval));
+		assert_eq!(MountSpec::parse("data::virtiofs"), Err(Errno::Inval));
+		assert_eq!(MountSpec::parse("data:data:virtiofs"), Err(Errno::Inval));
+		assert_eq!(MountSpec::parse("data:/data:ext3"), Err(Errno::Nodev));
+		assert_eq!(
+			MountSpec::parse("data:/data:virtiofs:size=1m"),
+			Err(Errno::Inval)
+		);
+		assert_eq!(MountSpec::parse("data:/data:tmpfs:sync"), Err(Errno::Inval));
+		assert_eq!(
+			MountSpec::parse("data:/data:overlay:lower=tmpfs"),
+			Err(Errno::Inval)
+		);
+	}
+}
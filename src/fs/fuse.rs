@@ -15,20 +15,21 @@ use async_lock::Mutex;
 use async_trait::async_trait;
 use embedded_io::{ErrorType, Read, Write};
 use fuse_abi::linux::*;
+use hermit_sync::{InterruptTicketMutex, OnceCell};
 
 use crate::alloc::string::ToString;
-#[cfg(not(feature = "pci"))]
-use crate::arch::kernel::mmio::get_filesystem_driver;
-#[cfg(feature = "pci")]
-use crate::drivers::pci::get_filesystem_driver;
+use crate::drivers::fs::virtio_fs::VirtioFsDriver;
+use crate::drivers::pci::get_filesystem_drivers;
 use crate::drivers::virtio::virtqueue::error::VirtqError;
 use crate::errno::Errno;
 use crate::executor::block_on;
 use crate::fd::PollEvent;
+use crate::fd::lock::{LockOwner, LockType, RecordLock};
 use crate::fs::fuse::ops::SetAttrValidFields;
 use crate::fs::{
-	self, AccessPermission, DirectoryEntry, FileAttr, NodeKind, ObjectInterface, OpenOption,
-	SeekWhence, VfsNode,
+	self, AccessPermission, DirectoryEntry, FallocateFlags, FileAttr, MountFlags, NodeKind,
+	ObjectInterface, OpenOption, SeekWhence, StatFs, VfsNode, XATTR_LIST_MAX, XATTR_SIZE_MAX,
+	XattrFlags,
 };
 use crate::mm::device_alloc::DeviceAlloc;
 use crate::syscalls::Dirent64;
@@ -44,6 +45,9 @@ const MAX_WRITE_LEN: usize = 1024 * 64;
 
 const U64_SIZE: usize = mem::size_of::<u64>();
 
+/// Time in microseconds between two attempts to place a contended lock
+const SETLK_RETRY_INTERVAL: u64 = 10_000;
+
 const S_IFLNK: u32 = 0o120_000;
 const S_IFMT: u32 = 0o170_000;
 
@@ -70,7 +74,8 @@ pub(crate) mod ops {
 
 	use super::Cmd;
 	use crate::fd::PollEvent;
-	use crate::fs::{FileAttr, SeekWhence};
+	use crate::fd::lock::{LockOwner, RecordLock};
+	use crate::fs::{FallocateFlags, FileAttr, SeekWhence};
 
 	#[repr(C)]
 	#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq)]
@@ -248,6 +253,39 @@ pub(crate) mod ops {
 		}
 	}
 
+	#[derive(Debug)]
+	pub(crate) struct Fallocate;
+
+	impl Op for Fallocate {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_FALLOCATE;
+		type InStruct = fuse_fallocate_in;
+		type InPayload = ();
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Fallocate {
+		pub(crate) fn create(
+			nid: u64,
+			fh: u64,
+			mode: FallocateFlags,
+			offset: u64,
+			length: u64,
+		) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(
+				nid,
+				fuse_fallocate_in {
+					fh,
+					offset,
+					length,
+					mode: mode.bits().try_into().unwrap(),
+					..Default::default()
+				},
+			);
+			(cmd, 0)
+		}
+	}
+
 	#[derive(Debug)]
 	pub(crate) struct Getattr;
 
@@ -325,8 +363,8 @@ pub(crate) mod ops {
 					size: attr.st_size as u64,
 					atime: attr.st_atim.tv_sec as u64,
 					atimensec: attr.st_atim.tv_nsec as u32,
-					mtime: attr.st_ctim.tv_sec as u64,
-					mtimensec: attr.st_ctim.tv_nsec as u32,
+					mtime: attr.st_mtim.tv_sec as u64,
+					mtimensec: attr.st_mtim.tv_nsec as u32,
 					ctime: attr.st_ctim.tv_sec as u64,
 					ctimensec: attr.st_ctim.tv_nsec as u32,
 					mode: attr.st_mode.bits(),
@@ -472,6 +510,201 @@ pub(crate) mod ops {
 		}
 	}
 
+	#[derive(Debug)]
+	pub(crate) struct Rename;
+
+	impl Op for Rename {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_RENAME;
+		type InStruct = fuse_rename_in;
+		type InPayload = [u8];
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Rename {
+		pub(crate) fn create(old: CString, new: CString) -> (Cmd<Self>, u32) {
+			let mut names = old.into_bytes_with_nul();
+			names.extend_from_slice(new.as_bytes_with_nul());
+			let cmd = Cmd::with_boxed_slice(
+				FUSE_ROOT_ID,
+				fuse_rename_in {
+					newdir: FUSE_ROOT_ID,
+				},
+				names.into_boxed_slice(),
+			);
+			(cmd, 0)
+		}
+	}
+
+	/// Converts a lock into the request of `FUSE_GETLK` or `FUSE_SETLK`.
+	fn lk_in(fh: u64, lock: &RecordLock) -> fuse_lk_in {
+		fuse_lk_in {
+			fh,
+			owner: lock.owner.id(),
+			lk: fuse_file_lock {
+				start: lock.start,
+				// FUSE uses an inclusive end, where `OFFSET_MAX` locks up to the end of the file
+				end: if lock.end == u64::MAX {
+					i64::MAX as u64
+				} else {
+					lock.end - 1
+				},
+				type_: i16::from(lock.kind) as u32,
+				pid: 0,
+			},
+			lk_flags: if matches!(lock.owner, LockOwner::Description(_)) {
+				FUSE_LK_FLOCK
+			} else {
+				0
+			},
+			padding: 0,
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Getlk;
+
+	impl Op for Getlk {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_GETLK;
+		type InStruct = fuse_lk_in;
+		type InPayload = ();
+		type OutStruct = fuse_lk_out;
+		type OutPayload = ();
+	}
+
+	impl Getlk {
+		pub(crate) fn create(nid: u64, fh: u64, lock: &RecordLock) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(nid, lk_in(fh, lock));
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Setlk;
+
+	impl Op for Setlk {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_SETLK;
+		type InStruct = fuse_lk_in;
+		type InPayload = ();
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Setlk {
+		pub(crate) fn create(nid: u64, fh: u64, lock: &RecordLock) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(nid, lk_in(fh, lock));
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Getxattr;
+
+	impl Op for Getxattr {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_GETXATTR;
+		type InStruct = fuse_getxattr_in;
+		type InPayload = CString;
+		type OutStruct = ();
+		type OutPayload = [u8];
+	}
+
+	impl Getxattr {
+		pub(crate) fn create(nid: u64, name: CString, size: u32) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::with_cstring(
+				nid,
+				fuse_getxattr_in {
+					size,
+					..Default::default()
+				},
+				name,
+			);
+			(cmd, size)
+		}
+	}
+
+	/// Request of `FUSE_SETXATTR` without the extended fields of protocol
+	/// version 7.33, which are only used if `FUSE_SETXATTR_EXT` was negotiated
+	#[repr(C)]
+	#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq)]
+	pub(crate) struct SetxattrIn {
+		pub size: u32,
+		pub flags: u32,
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Setxattr;
+
+	impl Op for Setxattr {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_SETXATTR;
+		type InStruct = SetxattrIn;
+		type InPayload = [u8];
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Setxattr {
+		pub(crate) fn create(
+			nid: u64,
+			name: CString,
+			value: &[u8],
+			flags: u32,
+		) -> (Cmd<Self>, u32) {
+			let mut payload = name.into_bytes_with_nul();
+			payload.extend_from_slice(value);
+			let cmd = Cmd::with_boxed_slice(
+				nid,
+				SetxattrIn {
+					size: value.len().try_into().unwrap(),
+					flags,
+				},
+				payload.into_boxed_slice(),
+			);
+			(cmd, 0)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Listxattr;
+
+	impl Op for Listxattr {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_LISTXATTR;
+		type InStruct = fuse_getxattr_in;
+		type InPayload = ();
+		type OutStruct = ();
+		type OutPayload = [u8];
+	}
+
+	impl Listxattr {
+		pub(crate) fn create(nid: u64, size: u32) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(
+				nid,
+				fuse_getxattr_in {
+					size,
+					..Default::default()
+				},
+			);
+			(cmd, size)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(crate) struct Removexattr;
+
+	impl Op for Removexattr {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_REMOVEXATTR;
+		type InStruct = ();
+		type InPayload = CString;
+		type OutStruct = ();
+		type OutPayload = ();
+	}
+
+	impl Removexattr {
+		pub(crate) fn create(nid: u64, name: CString) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::with_cstring(nid, (), name);
+			(cmd, 0)
+		}
+	}
+
 	#[derive(Debug)]
 	pub(crate) struct Lookup;
 
@@ -489,6 +722,44 @@ pub(crate) mod ops {
 			(cmd, 0)
 		}
 	}
+
+	#[derive(Debug)]
+	pub(crate) struct Statfs;
+
+	impl Op for Statfs {
+		const OP_CODE: fuse_opcode = fuse_opcode::FUSE_STATFS;
+		type InStruct = ();
+		type InPayload = ();
+		type OutStruct = fuse_statfs_out;
+		type OutPayload = ();
+	}
+
+	impl Statfs {
+		pub(crate) fn create(nid: u64) -> (Cmd<Self>, u32) {
+			let cmd = Cmd::new(nid, ());
+			(cmd, 0)
+		}
+	}
+}
+
+/// Magic number of FUSE file systems
+const FUSE_SUPER_MAGIC: i64 = 0x6573_5546;
+
+impl From<fuse_kstatfs> for StatFs {
+	fn from(st: fuse_kstatfs) -> StatFs {
+		StatFs {
+			f_type: FUSE_SUPER_MAGIC,
+			f_bsize: st.bsize.into(),
+			f_blocks: st.blocks,
+			f_bfree: st.bfree,
+			f_bavail: st.bavail,
+			f_files: st.files,
+			f_ffree: st.ffree,
+			f_namelen: st.namelen.into(),
+			f_frsize: st.frsize.into(),
+			..Default::default()
+		}
+	}
 }
 
 impl From<fuse_attr> for FileAttr {
@@ -644,23 +915,16 @@ impl From<FuseError> for Errno {
 	}
 }
 
-fn lookup(name: CString) -> Option<u64> {
+fn lookup(driver: &InterruptTicketMutex<VirtioFsDriver>, name: CString) -> Option<u64> {
 	let (cmd, rsp_payload_len) = ops::Lookup::create(name);
-	let rsp = get_filesystem_driver()
-		.unwrap()
-		.lock()
-		.send_command(cmd, rsp_payload_len)
-		.ok()?;
+	let rsp = driver.lock().send_command(cmd, rsp_payload_len).ok()?;
 	Some(rsp.headers.op_header.nodeid)
 }
 
-fn readlink(nid: u64) -> io::Result<String> {
+fn readlink(driver: &InterruptTicketMutex<VirtioFsDriver>, nid: u64) -> io::Result<String> {
 	let len = MAX_READ_LEN as u32;
 	let (cmd, rsp_payload_len) = ops::Readlink::create(nid, len);
-	let rsp = get_filesystem_driver()
-		.unwrap()
-		.lock()
-		.send_command(cmd, rsp_payload_len)?;
+	let rsp = driver.lock().send_command(cmd, rsp_payload_len)?;
 	let len: usize = if rsp.headers.out_header.len as usize - mem::size_of::<fuse_out_header>()
 		>= usize::try_from(len).unwrap()
 	{
@@ -672,22 +936,202 @@ fn readlink(nid: u64) -> io::Result<String> {
 	Ok(String::from_utf8(rsp.payload.unwrap()[..len].to_vec()).unwrap())
 }
 
+fn statfs(driver: &InterruptTicketMutex<VirtioFsDriver>, nid: u64) -> io::Result<StatFs> {
+	let (cmd, rsp_payload_len) = ops::Statfs::create(nid);
+	let rsp = driver.lock().send_command(cmd, rsp_payload_len)?;
+	if rsp.headers.out_header.error != 0 {
+		return Err(Errno::try_from(-rsp.headers.out_header.error).unwrap());
+	}
+	Ok(rsp.headers.op_header.st.into())
+}
+
+/// Changes the attributes of the node `nid` without an open file handle.
+fn setattr(
+	driver: &InterruptTicketMutex<VirtioFsDriver>,
+	nid: u64,
+	attr: FileAttr,
+	valid: SetAttrValidFields,
+) -> io::Result<()> {
+	let (cmd, rsp_payload_len) = ops::Setattr::create(nid, 0, attr, valid);
+	let rsp = driver.lock().send_command(cmd, rsp_payload_len)?;
+	if rsp.headers.out_header.error != 0 {
+		return Err(Errno::try_from(-rsp.headers.out_header.error).unwrap());
+	}
+	Ok(())
+}
+
+/// Returns the payload of a response, whose length is only known from the header.
+fn rsp_payload<O: ops::Op>(rsp: Rsp<O>) -> Vec<u8> {
+	let len = rsp.headers.out_header.len as usize - mem::size_of::<fuse_out_header>();
+	rsp.payload
+		.map(|payload| payload[..len.min(payload.len())].to_vec())
+		.unwrap_or_default()
+}
+
+/// Returns the value of the extended attribute `name` of the node `nid`.
+fn getxattr(
+	driver: &InterruptTicketMutex<VirtioFsDriver>,
+	nid: u64,
+	name: &str,
+) -> io::Result<Vec<u8>> {
+	let name = CString::new(name).map_err(|_| Errno::Inval)?;
+	let (cmd, rsp_payload_len) = ops::Getxattr::create(nid, name, XATTR_SIZE_MAX as u32);
+	let rsp = driver.lock().send_command(cmd, rsp_payload_len)?;
+	Ok(rsp_payload(rsp))
+}
+
+/// Creates or replaces the extended attribute `name` of the node `nid`.
+fn setxattr(
+	driver: &InterruptTicketMutex<VirtioFsDriver>,
+	nid: u64,
+	name: &str,
+	value: &[u8],
+	flags: XattrFlags,
+) -> io::Result<()> {
+	let name = CString::new(name).map_err(|_| Errno::Inval)?;
+	let (cmd, rsp_payload_len) =
+		ops::Setxattr::create(nid, name, value, flags.bits().try_into().unwrap());
+	driver.lock().send_command(cmd, rsp_payload_len)?;
+	Ok(())
+}
+
+/// Returns the names of all extended attributes of the node `nid`.
+fn listxattr(driver: &InterruptTicketMutex<VirtioFsDriver>, nid: u64) -> io::Result<Vec<String>> {
+	let (cmd, rsp_payload_len) = ops::Listxattr::create(nid, XATTR_LIST_MAX as u32);
+	let rsp = driver.lock().send_command(cmd, rsp_payload_len)?;
+
+	// The names are separated by null bytes.
+	rsp_payload(rsp)
+		.split(|&c| c == 0)
+		.filter(|name| !name.is_empty())
+		.map(|name| String::from_utf8(name.to_vec()).map_err(|_| Errno::Io))
+		.collect()
+}
+
+/// Removes the extended attribute `name` of the node `nid`.
+fn removexattr(
+	driver: &InterruptTicketMutex<VirtioFsDriver>,
+	nid: u64,
+	name: &str,
+) -> io::Result<()> {
+	let name = CString::new(name).map_err(|_| Errno::Inval)?;
+	let (cmd, rsp_payload_len) = ops::Removexattr::create(nid, name);
+	driver.lock().send_command(cmd, rsp_payload_len)?;
+	Ok(())
+}
+
+/// Returns the attributes of a `FUSE_SETATTR` request, which changes the
+/// timestamps, where `None` leaves the time unchanged.
+fn utimens_attr(
+	atime: Option<timespec>,
+	mtime: Option<timespec>,
+) -> (FileAttr, SetAttrValidFields) {
+	let mut attr = FileAttr::default();
+	let mut valid = SetAttrValidFields::empty();
+	if let Some(atime) = atime {
+		attr.st_atim = atime;
+		valid |= SetAttrValidFields::FATTR_ATIME;
+	}
+	if let Some(mtime) = mtime {
+		attr.st_mtim = mtime;
+		valid |= SetAttrValidFields::FATTR_MTIME;
+	}
+	(attr, valid)
+}
+
+/// Returns the attributes of a `FUSE_SETATTR` request, which changes owner
+/// and group, where `None` leaves the id unchanged.
+fn chown_attr(uid: Option<u32>, gid: Option<u32>) -> (FileAttr, SetAttrValidFields) {
+	let mut attr = FileAttr::default();
+	let mut valid = SetAttrValidFields::empty();
+	if let Some(uid) = uid {
+		attr.st_uid = uid;
+		valid |= SetAttrValidFields::FATTR_UID;
+	}
+	if let Some(gid) = gid {
+		attr.st_gid = gid;
+		valid |= SetAttrValidFields::FATTR_GID;
+	}
+	(attr, valid)
+}
+
 #[derive(Debug)]
 struct FuseFileHandleInner {
+	driver: &'static InterruptTicketMutex<VirtioFsDriver>,
 	fuse_nid: Option<u64>,
 	fuse_fh: Option<u64>,
 	offset: usize,
+	/// Set if a lock has been placed through this handle
+	locked: bool,
+	/// Owner of the `flock` locks placed through this handle, which are
+	/// released when the handle is dropped
+	description: Option<LockOwner>,
 }
 
 impl FuseFileHandleInner {
-	pub fn new() -> Self {
+	pub fn new(driver: &'static InterruptTicketMutex<VirtioFsDriver>) -> Self {
 		Self {
+			driver,
 			fuse_nid: None,
 			fuse_fh: None,
 			offset: 0,
+			locked: false,
+			description: None,
 		}
 	}
 
+	fn getlk(&self, lock: &RecordLock) -> io::Result<Option<RecordLock>> {
+		let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) else {
+			return Err(Errno::Badf);
+		};
+
+		let (cmd, rsp_payload_len) = ops::Getlk::create(nid, fh, lock);
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
+		if rsp.headers.out_header.error != 0 {
+			return Err(Errno::try_from(-rsp.headers.out_header.error).unwrap());
+		}
+
+		let lk = rsp.headers.op_header.lk;
+		let kind = i16::try_from(lk.type_)
+			.ok()
+			.and_then(|kind| LockType::try_from(kind).ok())
+			.ok_or(Errno::Io)?;
+		if kind == LockType::Unlock {
+			return Ok(None);
+		}
+
+		Ok(Some(RecordLock {
+			kind,
+			start: lk.start,
+			end: if lk.end >= i64::MAX as u64 {
+				u64::MAX
+			} else {
+				lk.end + 1
+			},
+			owner: LockOwner::Process,
+		}))
+	}
+
+	fn setlk(&mut self, lock: &RecordLock) -> io::Result<()> {
+		let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) else {
+			return Err(Errno::Badf);
+		};
+
+		let (cmd, rsp_payload_len) = ops::Setlk::create(nid, fh, lock);
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
+		if rsp.headers.out_header.error != 0 {
+			return Err(Errno::try_from(-rsp.headers.out_header.error).unwrap());
+		}
+
+		if lock.kind != LockType::Unlock {
+			self.locked = true;
+			if matches!(lock.owner, LockOwner::Description(_)) {
+				self.description = Some(lock.owner);
+			}
+		}
+		Ok(())
+	}
+
 	async fn poll(&self, events: PollEvent) -> io::Result<PollEvent> {
 		static KH: AtomicU64 = AtomicU64::new(0);
 		let kh = KH.fetch_add(1, Ordering::SeqCst);
@@ -695,10 +1139,7 @@ impl FuseFileHandleInner {
 		future::poll_fn(|cx| {
 			if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
 				let (cmd, rsp_payload_len) = ops::Poll::create(nid, fh, kh, events);
-				let rsp = get_filesystem_driver()
-					.ok_or(Errno::Nosys)?
-					.lock()
-					.send_command(cmd, rsp_payload_len)?;
+				let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 
 				if rsp.headers.out_header.error < 0 {
 					Poll::Ready(Err(Errno::Io))
@@ -735,19 +1176,29 @@ impl FuseFileHandleInner {
 			SeekWhence::End | SeekWhence::Data | SeekWhence::Hole => {
 				if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
 					let (cmd, rsp_payload_len) = ops::Lseek::create(nid, fh, offset, whence);
-					let rsp = get_filesystem_driver()
-						.ok_or(Errno::Nosys)?
-						.lock()
-						.send_command(cmd, rsp_payload_len)?;
-
-					if rsp.headers.out_header.error < 0 {
-						return Err(Errno::Io);
-					}
-
-					let rsp_offset = rsp.headers.op_header.offset;
-					self.offset = rsp.headers.op_header.offset.try_into().unwrap();
-
-					Ok(rsp_offset.try_into().unwrap())
+					let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
+
+					let rsp_offset = match rsp.headers.out_header.error {
+						0 => rsp.headers.op_header.offset.try_into().unwrap(),
+						// Like Linux, fall back to a file without holes if the
+						// server does not implement `FUSE_LSEEK`.
+						error if -error == i32::from(Errno::Nosys) => {
+							let size = isize::try_from(self.fstat()?.st_size).unwrap();
+							if whence == SeekWhence::End {
+								size + offset
+							} else if !(0..size).contains(&offset) {
+								return Err(Errno::Nxio);
+							} else if whence == SeekWhence::Data {
+								offset
+							} else {
+								size
+							}
+						}
+						error => return Err(Errno::try_from(-error).unwrap_or(Errno::Io)),
+					};
+
+					self.offset = rsp_offset.try_into().map_err(|_| Errno::Inval)?;
+					Ok(rsp_offset)
 				} else {
 					Err(Errno::Io)
 				}
@@ -765,14 +1216,33 @@ impl FuseFileHandleInner {
 		}
 	}
 
+	fn fallocate(&mut self, mode: FallocateFlags, offset: usize, len: usize) -> io::Result<()> {
+		debug!("FUSE fallocate: mode: {mode:?}, offset: {offset}, len: {len}");
+		let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) else {
+			return Err(Errno::Badf);
+		};
+
+		let (cmd, rsp_payload_len) = ops::Fallocate::create(
+			nid,
+			fh,
+			mode,
+			offset.try_into().unwrap(),
+			len.try_into().unwrap(),
+		);
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
+		match rsp.headers.out_header.error {
+			0 => Ok(()),
+			// Servers without `FUSE_FALLOCATE` do not support any mode.
+			error if -error == i32::from(Errno::Nosys) => Err(Errno::Opnotsupp),
+			error => Err(Errno::try_from(-error).unwrap_or(Errno::Io)),
+		}
+	}
+
 	fn fstat(&mut self) -> io::Result<FileAttr> {
 		debug!("FUSE getattr");
 		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
 			let (cmd, rsp_payload_len) = ops::Getattr::create(nid, fh, FUSE_GETATTR_FH);
-			let rsp = get_filesystem_driver()
-				.ok_or(Errno::Nosys)?
-				.lock()
-				.send_command(cmd, rsp_payload_len)?;
+			let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 			if rsp.headers.out_header.error < 0 {
 				return Err(Errno::Io);
 			}
@@ -786,10 +1256,7 @@ impl FuseFileHandleInner {
 		debug!("FUSE setattr");
 		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
 			let (cmd, rsp_payload_len) = ops::Setattr::create(nid, fh, attr, valid);
-			let rsp = get_filesystem_driver()
-				.ok_or(Errno::Nosys)?
-				.lock()
-				.send_command(cmd, rsp_payload_len)?;
+			let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 			if rsp.headers.out_header.error < 0 {
 				return Err(Errno::Io);
 			}
@@ -814,10 +1281,7 @@ impl Read for FuseFileHandleInner {
 		if let (Some(nid), Some(fh)) = (self.fuse_nid, self.fuse_fh) {
 			let (cmd, rsp_payload_len) =
 				ops::Read::create(nid, fh, len.try_into().unwrap(), self.offset as u64);
-			let rsp = get_filesystem_driver()
-				.ok_or(Errno::Nosys)?
-				.lock()
-				.send_command(cmd, rsp_payload_len)?;
+			let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 			let len: usize =
 				if (rsp.headers.out_header.len as usize) - mem::size_of::<fuse_out_header>() >= len
 				{
@@ -853,10 +1317,7 @@ impl Write for FuseFileHandleInner {
 			let truncated_buf = Box::<[u8]>::from(&buf[..truncated_len]);
 			let (cmd, rsp_payload_len) =
 				ops::Write::create(nid, fh, truncated_buf, self.offset as u64);
-			let rsp = get_filesystem_driver()
-				.ok_or(Errno::Nosys)?
-				.lock()
-				.send_command(cmd, rsp_payload_len)?;
+			let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 
 			if rsp.headers.out_header.error < 0 {
 				return Err(Errno::Io);
@@ -883,12 +1344,14 @@ impl Write for FuseFileHandleInner {
 
 impl Drop for FuseFileHandleInner {
 	fn drop(&mut self) {
+		if let Some(owner) = self.description.take() {
+			let _ = self.setlk(&RecordLock::whole_file(LockType::Unlock, owner));
+		}
 		if let Some(fuse_nid) = self.fuse_nid
 			&& let Some(fuse_fh) = self.fuse_fh
 		{
 			let (cmd, rsp_payload_len) = ops::Release::create(fuse_nid, fuse_fh);
-			get_filesystem_driver()
-				.unwrap()
+			self.driver
 				.lock()
 				.send_command(cmd, rsp_payload_len)
 				.unwrap();
@@ -900,8 +1363,8 @@ impl Drop for FuseFileHandleInner {
 struct FuseFileHandle(pub Arc<Mutex<FuseFileHandleInner>>);
 
 impl FuseFileHandle {
-	pub fn new() -> Self {
-		Self(Arc::new(Mutex::new(FuseFileHandleInner::new())))
+	pub fn new(driver: &'static InterruptTicketMutex<VirtioFsDriver>) -> Self {
+		Self(Arc::new(Mutex::new(FuseFileHandleInner::new(driver))))
 	}
 }
 
@@ -927,6 +1390,11 @@ impl ObjectInterface for FuseFileHandle {
 		self.0.lock().await.fstat()
 	}
 
+	async fn fstatfs(&self) -> io::Result<StatFs> {
+		let guard = self.0.lock().await;
+		statfs(guard.driver, guard.fuse_nid.unwrap_or(FUSE_ROOT_ID))
+	}
+
 	async fn truncate(&self, size: usize) -> io::Result<()> {
 		let attr = FileAttr {
 			st_size: size.try_into().unwrap(),
@@ -940,6 +1408,10 @@ impl ObjectInterface for FuseFileHandle {
 			.map(|_| ())
 	}
 
+	async fn fallocate(&self, mode: FallocateFlags, offset: usize, len: usize) -> io::Result<()> {
+		self.0.lock().await.fallocate(mode, offset, len)
+	}
+
 	async fn chmod(&self, access_permission: AccessPermission) -> io::Result<()> {
 		let attr = FileAttr {
 			st_mode: access_permission,
@@ -952,6 +1424,68 @@ impl ObjectInterface for FuseFileHandle {
 			.set_attr(attr, SetAttrValidFields::FATTR_MODE)
 			.map(|_| ())
 	}
+
+	async fn utimens(&self, atime: Option<timespec>, mtime: Option<timespec>) -> io::Result<()> {
+		let (attr, valid) = utimens_attr(atime, mtime);
+		self.0.lock().await.set_attr(attr, valid).map(|_| ())
+	}
+
+	async fn getlk(&self, lock: RecordLock) -> io::Result<Option<RecordLock>> {
+		self.0.lock().await.getlk(&lock)
+	}
+
+	async fn setlk(&self, lock: RecordLock, wait: bool) -> io::Result<()> {
+		loop {
+			match self.0.lock().await.setlk(&lock) {
+				Err(Errno::Again) if wait => {}
+				result => return result,
+			}
+
+			// `FUSE_SETLKW` would block the whole file system and the host
+			// doesn't notify us about released locks. Consequently, the task
+			// sleeps for a while before it repeats the request.
+			let wakeup_time = arch::processor::get_timer_ticks() + SETLK_RETRY_INTERVAL;
+			let core_scheduler = crate::core_scheduler();
+			core_scheduler.block_current_task(Some(wakeup_time));
+			core_scheduler.reschedule();
+		}
+	}
+
+	async fn release_locks(&self, owner: LockOwner) {
+		let mut guard = self.0.lock().await;
+		if guard.locked {
+			let _ = guard.setlk(&RecordLock::whole_file(LockType::Unlock, owner));
+		}
+	}
+
+	async fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+		let (attr, valid) = chown_attr(uid, gid);
+		self.0.lock().await.set_attr(attr, valid).map(|_| ())
+	}
+
+	async fn getxattr(&self, name: &str) -> io::Result<Vec<u8>> {
+		let guard = self.0.lock().await;
+		let nid = guard.fuse_nid.ok_or(Errno::Badf)?;
+		getxattr(guard.driver, nid, name)
+	}
+
+	async fn setxattr(&self, name: &str, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+		let guard = self.0.lock().await;
+		let nid = guard.fuse_nid.ok_or(Errno::Badf)?;
+		setxattr(guard.driver, nid, name, value, flags)
+	}
+
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		let guard = self.0.lock().await;
+		let nid = guard.fuse_nid.ok_or(Errno::Badf)?;
+		listxattr(guard.driver, nid)
+	}
+
+	async fn removexattr(&self, name: &str) -> io::Result<()> {
+		let guard = self.0.lock().await;
+		let nid = guard.fuse_nid.ok_or(Errno::Badf)?;
+		removexattr(guard.driver, nid, name)
+	}
 }
 
 impl Clone for FuseFileHandle {
@@ -963,40 +1497,86 @@ impl Clone for FuseFileHandle {
 
 #[derive(Debug)]
 pub struct FuseDirectoryHandle {
+	driver: &'static InterruptTicketMutex<VirtioFsDriver>,
 	name: Option<String>,
 	read_position: Mutex<usize>,
+	/// Absolute path of the directory within the virtual file system
+	path: OnceCell<String>,
 }
 
 impl FuseDirectoryHandle {
-	pub fn new(name: Option<String>) -> Self {
+	pub fn new(
+		driver: &'static InterruptTicketMutex<VirtioFsDriver>,
+		name: Option<String>,
+	) -> Self {
 		Self {
+			driver,
 			name,
 			read_position: Mutex::new(0),
+			path: OnceCell::new(),
+		}
+	}
+
+	/// Returns the path of the directory on the FUSE server.
+	fn fuse_path(&self) -> CString {
+		if let Some(name) = &self.name {
+			CString::new("/".to_string() + name).unwrap()
+		} else {
+			CString::new("/".to_string()).unwrap()
 		}
 	}
 }
 
 #[async_trait]
 impl ObjectInterface for FuseDirectoryHandle {
+	async fn fstatfs(&self) -> io::Result<StatFs> {
+		statfs(self.driver, FUSE_ROOT_ID)
+	}
+
+	async fn utimens(&self, atime: Option<timespec>, mtime: Option<timespec>) -> io::Result<()> {
+		let nid = lookup(self.driver, self.fuse_path()).ok_or(Errno::Noent)?;
+		let (attr, valid) = utimens_attr(atime, mtime);
+		setattr(self.driver, nid, attr, valid)
+	}
+
+	async fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+		let nid = lookup(self.driver, self.fuse_path()).ok_or(Errno::Noent)?;
+		let (attr, valid) = chown_attr(uid, gid);
+		setattr(self.driver, nid, attr, valid)
+	}
+
+	async fn getxattr(&self, name: &str) -> io::Result<Vec<u8>> {
+		let nid = lookup(self.driver, self.fuse_path()).ok_or(Errno::Noent)?;
+		getxattr(self.driver, nid, name)
+	}
+
+	async fn setxattr(&self, name: &str, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+		let nid = lookup(self.driver, self.fuse_path()).ok_or(Errno::Noent)?;
+		setxattr(self.driver, nid, name, value, flags)
+	}
+
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		let nid = lookup(self.driver, self.fuse_path()).ok_or(Errno::Noent)?;
+		listxattr(self.driver, nid)
+	}
+
+	async fn removexattr(&self, name: &str) -> io::Result<()> {
+		let nid = lookup(self.driver, self.fuse_path()).ok_or(Errno::Noent)?;
+		removexattr(self.driver, nid, name)
+	}
+
 	async fn getdents(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
-		let path: CString = if let Some(name) = &self.name {
-			CString::new("/".to_string() + name).unwrap()
-		} else {
-			CString::new("/".to_string()).unwrap()
-		};
+		let path = self.fuse_path();
 
 		debug!("FUSE opendir: {path:#?}");
 
-		let fuse_nid = lookup(path.clone()).ok_or(Errno::Noent)?;
+		let fuse_nid = lookup(self.driver, path.clone()).ok_or(Errno::Noent)?;
 
 		// Opendir
 		// Flag 0x10000 for O_DIRECTORY might not be necessary
 		let (mut cmd, rsp_payload_len) = ops::Open::create(fuse_nid, 0x10000);
 		cmd.headers.in_header.opcode = fuse_opcode::FUSE_OPENDIR as u32;
-		let rsp = get_filesystem_driver()
-			.ok_or(Errno::Nosys)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 		let fuse_fh = rsp.headers.op_header.fh;
 
 		debug!("FUSE readdir: {path:#?}");
@@ -1009,10 +1589,7 @@ impl ObjectInterface for FuseDirectoryHandle {
 		// read content of the directory
 		let (mut cmd, rsp_payload_len) = ops::Read::create(fuse_nid, fuse_fh, len, 0);
 		cmd.headers.in_header.opcode = fuse_opcode::FUSE_READDIR as u32;
-		let rsp = get_filesystem_driver()
-			.ok_or(Errno::Nosys)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 
 		let len = usize::min(
 			MAX_READ_LEN,
@@ -1074,10 +1651,7 @@ impl ObjectInterface for FuseDirectoryHandle {
 		}
 
 		let (cmd, rsp_payload_len) = ops::Release::create(fuse_nid, fuse_fh);
-		get_filesystem_driver()
-			.unwrap()
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		self.driver.lock().send_command(cmd, rsp_payload_len)?;
 
 		Ok(ret)
 	}
@@ -1094,20 +1668,33 @@ impl ObjectInterface for FuseDirectoryHandle {
 		*self.read_position.lock().await = offset as usize;
 		Ok(offset)
 	}
+
+	async fn dirpath(&self) -> io::Result<String> {
+		self.path.get().cloned().ok_or(Errno::Notdir)
+	}
+
+	async fn set_dirpath(&self, path: &str) {
+		let _ = self.path.set(path.to_string());
+	}
 }
 
 #[derive(Debug)]
 pub(crate) struct FuseDirectory {
+	driver: &'static InterruptTicketMutex<VirtioFsDriver>,
 	prefix: Option<String>,
 	attr: FileAttr,
 }
 
 impl FuseDirectory {
-	pub fn new(prefix: Option<String>) -> Self {
+	pub fn new(
+		driver: &'static InterruptTicketMutex<VirtioFsDriver>,
+		prefix: Option<String>,
+	) -> Self {
 		let microseconds = arch::kernel::systemtime::now_micros();
 		let t = timespec::from_usec(microseconds as i64);
 
 		FuseDirectory {
+			driver,
 			prefix,
 			attr: FileAttr {
 				st_mode: AccessPermission::from_bits(0o777).unwrap() | AccessPermission::S_IFDIR,
@@ -1145,6 +1732,7 @@ impl VfsNode for FuseDirectory {
 
 	fn get_object(&self) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
 		Ok(Arc::new(async_lock::RwLock::new(FuseDirectoryHandle::new(
+			self.driver,
 			self.prefix.clone(),
 		))))
 	}
@@ -1154,16 +1742,13 @@ impl VfsNode for FuseDirectory {
 
 		debug!("FUSE opendir: {path:#?}");
 
-		let fuse_nid = lookup(path.clone()).ok_or(Errno::Noent)?;
+		let fuse_nid = lookup(self.driver, path.clone()).ok_or(Errno::Noent)?;
 
 		// Opendir
 		// Flag 0x10000 for O_DIRECTORY might not be necessary
 		let (mut cmd, rsp_payload_len) = ops::Open::create(fuse_nid, 0x10000);
 		cmd.headers.in_header.opcode = fuse_opcode::FUSE_OPENDIR as u32;
-		let rsp = get_filesystem_driver()
-			.ok_or(Errno::Nosys)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 		let fuse_fh = rsp.headers.op_header.fh;
 
 		debug!("FUSE readdir: {path:#?}");
@@ -1175,10 +1760,7 @@ impl VfsNode for FuseDirectory {
 		// read content of the directory
 		let (mut cmd, rsp_payload_len) = ops::Read::create(fuse_nid, fuse_fh, len, 0);
 		cmd.headers.in_header.opcode = fuse_opcode::FUSE_READDIR as u32;
-		let rsp = get_filesystem_driver()
-			.ok_or(Errno::Nosys)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 
 		let len: usize = if rsp.headers.out_header.len as usize - mem::size_of::<fuse_out_header>()
 			>= usize::try_from(len).unwrap()
@@ -1221,10 +1803,7 @@ impl VfsNode for FuseDirectory {
 		}
 
 		let (cmd, rsp_payload_len) = ops::Release::create(fuse_nid, fuse_fh);
-		get_filesystem_driver()
-			.unwrap()
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		self.driver.lock().send_command(cmd, rsp_payload_len)?;
 
 		Ok(entries)
 	}
@@ -1236,10 +1815,7 @@ impl VfsNode for FuseDirectory {
 
 		// Is there a better way to implement this?
 		let (cmd, rsp_payload_len) = ops::Lookup::create(path);
-		let rsp = get_filesystem_driver()
-			.unwrap()
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 
 		if rsp.headers.out_header.error != 0 {
 			return Err(Errno::try_from(-rsp.headers.out_header.error).unwrap());
@@ -1252,7 +1828,7 @@ impl VfsNode for FuseDirectory {
 			return Ok(FileAttr::from(attr));
 		}
 
-		let path = readlink(entry_out.nodeid)?;
+		let path = readlink(self.driver, entry_out.nodeid)?;
 		let mut components: Vec<&str> = path.split('/').collect();
 		self.traverse_stat(&mut components)
 	}
@@ -1263,13 +1839,19 @@ impl VfsNode for FuseDirectory {
 		debug!("FUSE lstat: {path:#?}");
 
 		let (cmd, rsp_payload_len) = ops::Lookup::create(path);
-		let rsp = get_filesystem_driver()
-			.unwrap()
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 		Ok(FileAttr::from(rsp.headers.op_header.attr))
 	}
 
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE statfs: {path:#?}");
+
+		let nid = lookup(self.driver, path).ok_or(Errno::Noent)?;
+		statfs(self.driver, nid)
+	}
+
 	fn traverse_open(
 		&self,
 		components: &mut Vec<&str>,
@@ -1288,23 +1870,21 @@ impl VfsNode for FuseDirectory {
 			}
 
 			let (cmd, rsp_payload_len) = ops::Lookup::create(path.clone());
-			let rsp = get_filesystem_driver()
-				.unwrap()
-				.lock()
-				.send_command(cmd, rsp_payload_len)?;
+			let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 
 			let attr = FileAttr::from(rsp.headers.op_header.attr);
 			if attr.st_mode.contains(AccessPermission::S_IFDIR) {
 				let mut path = path.into_string().unwrap();
 				path.remove(0);
 				Ok(Arc::new(async_lock::RwLock::new(FuseDirectoryHandle::new(
+					self.driver,
 					Some(path),
 				))))
 			} else {
 				Err(Errno::Notdir)
 			}
 		} else {
-			let file = FuseFileHandle::new();
+			let file = FuseFileHandle::new(self.driver);
 
 			// 1.FUSE_INIT to create session
 			// Already done
@@ -1315,17 +1895,14 @@ impl VfsNode for FuseDirectory {
 				// Create file (opens implicitly, returns results from both lookup and open calls)
 				let (cmd, rsp_payload_len) =
 					ops::Create::create(path, opt.bits().try_into().unwrap(), mode.bits());
-				let rsp = get_filesystem_driver()
-					.ok_or(Errno::Nosys)?
-					.lock()
-					.send_command(cmd, rsp_payload_len)?;
+				let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 
 				let inner = rsp.headers.op_header;
 				file_guard.fuse_nid = Some(inner.entry.nodeid);
 				file_guard.fuse_fh = Some(inner.open.fh);
 			} else {
 				// 2.FUSE_LOOKUP(FUSE_ROOT_ID, “foo”) -> nodeid
-				file_guard.fuse_nid = lookup(path);
+				file_guard.fuse_nid = lookup(self.driver, path);
 
 				if file_guard.fuse_nid.is_none() {
 					warn!("Fuse lookup seems to have failed!");
@@ -1335,10 +1912,7 @@ impl VfsNode for FuseDirectory {
 				// 3.FUSE_OPEN(nodeid, O_RDONLY) -> fh
 				let (cmd, rsp_payload_len) =
 					ops::Open::create(file_guard.fuse_nid.unwrap(), opt.bits().try_into().unwrap());
-				let rsp = get_filesystem_driver()
-					.ok_or(Errno::Nosys)?
-					.lock()
-					.send_command(cmd, rsp_payload_len)?;
+				let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 				file_guard.fuse_fh = Some(rsp.headers.op_header.fh);
 			}
 
@@ -1352,10 +1926,7 @@ impl VfsNode for FuseDirectory {
 		let path = self.traversal_path(components);
 
 		let (cmd, rsp_payload_len) = ops::Unlink::create(path);
-		let rsp = get_filesystem_driver()
-			.ok_or(Errno::Nosys)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 		trace!("unlink answer {rsp:?}");
 
 		Ok(())
@@ -1365,23 +1936,123 @@ impl VfsNode for FuseDirectory {
 		let path = self.traversal_path(components);
 
 		let (cmd, rsp_payload_len) = ops::Rmdir::create(path);
-		let rsp = get_filesystem_driver()
-			.ok_or(Errno::Nosys)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 		trace!("rmdir answer {rsp:?}");
 
 		Ok(())
 	}
 
+	fn traverse_rename(&self, from: &mut Vec<&str>, to: &mut Vec<&str>) -> io::Result<()> {
+		let old = self.traversal_path(from);
+		let new = self.traversal_path(to);
+
+		debug!("FUSE rename: {old:#?} -> {new:#?}");
+
+		let (cmd, rsp_payload_len) = ops::Rename::create(old, new);
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
+		if rsp.headers.out_header.error == 0 {
+			Ok(())
+		} else {
+			Err(Errno::try_from(-rsp.headers.out_header.error).unwrap())
+		}
+	}
+
+	fn traverse_utimens(
+		&self,
+		components: &mut Vec<&str>,
+		atime: Option<timespec>,
+		mtime: Option<timespec>,
+	) -> io::Result<()> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE utimens: {path:#?}");
+
+		let nid = lookup(self.driver, path).ok_or(Errno::Noent)?;
+		let (attr, valid) = utimens_attr(atime, mtime);
+		setattr(self.driver, nid, attr, valid)
+	}
+
+	fn traverse_chown(
+		&self,
+		components: &mut Vec<&str>,
+		uid: Option<u32>,
+		gid: Option<u32>,
+	) -> io::Result<()> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE chown: {path:#?}");
+
+		let nid = lookup(self.driver, path).ok_or(Errno::Noent)?;
+		let (attr, valid) = chown_attr(uid, gid);
+		setattr(self.driver, nid, attr, valid)
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE getxattr: {path:#?} {name}");
+
+		let nid = lookup(self.driver, path).ok_or(Errno::Noent)?;
+		getxattr(self.driver, nid, name)
+	}
+
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE setxattr: {path:#?} {name}");
+
+		let nid = lookup(self.driver, path).ok_or(Errno::Noent)?;
+		setxattr(self.driver, nid, name, value, flags)
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE listxattr: {path:#?}");
+
+		let nid = lookup(self.driver, path).ok_or(Errno::Noent)?;
+		listxattr(self.driver, nid)
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<()> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE removexattr: {path:#?} {name}");
+
+		let nid = lookup(self.driver, path).ok_or(Errno::Noent)?;
+		removexattr(self.driver, nid, name)
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		let path = self.traversal_path(components);
+
+		debug!("FUSE readlink: {path:#?}");
+
+		let (cmd, rsp_payload_len) = ops::Lookup::create(path);
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
+		if rsp.headers.out_header.error != 0 {
+			return Err(Errno::try_from(-rsp.headers.out_header.error).unwrap());
+		}
+
+		let entry_out = rsp.headers.op_header;
+		if entry_out.attr.mode & S_IFMT != S_IFLNK {
+			return Err(Errno::Inval);
+		}
+
+		readlink(self.driver, entry_out.nodeid)
+	}
+
 	fn traverse_mkdir(&self, components: &mut Vec<&str>, mode: AccessPermission) -> io::Result<()> {
 		let path = self.traversal_path(components);
 		let (cmd, rsp_payload_len) = ops::Mkdir::create(path, mode.bits());
 
-		let rsp = get_filesystem_driver()
-			.ok_or(Errno::Nosys)?
-			.lock()
-			.send_command(cmd, rsp_payload_len)?;
+		let rsp = self.driver.lock().send_command(cmd, rsp_payload_len)?;
 		if rsp.headers.out_header.error == 0 {
 			Ok(())
 		} else {
@@ -1390,26 +2061,37 @@ impl VfsNode for FuseDirectory {
 	}
 }
 
+/// Creates a FUSE session with every virtio-fs device.
 pub(crate) fn init() {
 	debug!("Try to initialize fuse filesystem");
 
-	if let Some(driver) = get_filesystem_driver() {
+	for driver in get_filesystem_drivers() {
 		let (cmd, rsp_payload_len) = ops::Init::create();
 		let rsp = driver.lock().send_command(cmd, rsp_payload_len).unwrap();
 		trace!("fuse init answer: {rsp:?}");
+	}
+}
+
+/// Returns the root directory of the virtio-fs device with the tag `tag`.
+pub(crate) fn directory(tag: &str) -> io::Result<FuseDirectory> {
+	get_filesystem_drivers()
+		.find(|driver| driver.lock().get_mount_point() == tag)
+		.map(|driver| FuseDirectory::new(driver, None))
+		.ok_or(Errno::Nodev)
+}
 
+/// Mounts every virtio-fs device at `/<tag>`. If the tag is `/`, all
+/// top-level directories of the host are mounted individually.
+pub(crate) fn mount_default() {
+	for driver in get_filesystem_drivers() {
 		let mount_point = driver.lock().get_mount_point();
 		if mount_point == "/" {
-			let fuse_nid = lookup(c"/".to_owned()).unwrap();
+			let fuse_nid = lookup(driver, c"/".to_owned()).unwrap();
 			// Opendir
 			// Flag 0x10000 for O_DIRECTORY might not be necessary
 			let (mut cmd, rsp_payload_len) = ops::Open::create(fuse_nid, 0x10000);
 			cmd.headers.in_header.opcode = fuse_opcode::FUSE_OPENDIR as u32;
-			let rsp = get_filesystem_driver()
-				.unwrap()
-				.lock()
-				.send_command(cmd, rsp_payload_len)
-				.unwrap();
+			let rsp = driver.lock().send_command(cmd, rsp_payload_len).unwrap();
 			let fuse_fh = rsp.headers.op_header.fh;
 
 			// Linux seems to allocate a single page to store the dirfile
@@ -1419,11 +2101,7 @@ pub(crate) fn init() {
 			// read content of the directory
 			let (mut cmd, rsp_payload_len) = ops::Read::create(fuse_nid, fuse_fh, len, 0);
 			cmd.headers.in_header.opcode = fuse_opcode::FUSE_READDIR as u32;
-			let rsp = get_filesystem_driver()
-				.unwrap()
-				.lock()
-				.send_command(cmd, rsp_payload_len)
-				.unwrap();
+			let rsp = driver.lock().send_command(cmd, rsp_payload_len).unwrap();
 
 			let len: usize = if rsp.headers.out_header.len as usize
 				- mem::size_of::<fuse_out_header>()
@@ -1467,11 +2145,7 @@ pub(crate) fn init() {
 			}
 
 			let (cmd, rsp_payload_len) = ops::Release::create(fuse_nid, fuse_fh);
-			get_filesystem_driver()
-				.unwrap()
-				.lock()
-				.send_command(cmd, rsp_payload_len)
-				.unwrap();
+			driver.lock().send_command(cmd, rsp_payload_len).unwrap();
 
 			// remove predefined directories
 			entries.retain(|x| x != ".");
@@ -1485,40 +2159,41 @@ pub(crate) fn init() {
 			for i in entries {
 				let i_cstr = CString::new(i.clone()).unwrap();
 				let (cmd, rsp_payload_len) = ops::Lookup::create(i_cstr);
-				let rsp = get_filesystem_driver()
-					.unwrap()
-					.lock()
-					.send_command(cmd, rsp_payload_len)
-					.unwrap();
+				let rsp = driver.lock().send_command(cmd, rsp_payload_len).unwrap();
 
 				let attr = FileAttr::from(rsp.headers.op_header.attr);
 				if attr.st_mode.contains(AccessPermission::S_IFDIR) {
 					info!("Fuse mount {i} to /{i}");
-					fs::FILESYSTEM
-						.get()
-						.unwrap()
-						.mount(
-							&("/".to_owned() + i.as_str()),
-							Box::new(FuseDirectory::new(Some(i))),
-						)
-						.expect("Mount failed. Invalid mount_point?");
+					let mount_point = "/".to_owned() + i.as_str();
+					fs::mount(
+						&("virtiofs:".to_owned() + &mount_point),
+						&mount_point,
+						"virtiofs",
+						MountFlags::empty(),
+						Box::new(FuseDirectory::new(driver, Some(i))),
+					)
+					.expect("Mount failed. Invalid mount_point?");
 				} else {
 					warn!("Fuse don't mount {i}. It isn't a directory!");
 				}
 			}
 		} else {
+			let tag = mount_point.clone();
 			let mount_point = if mount_point.starts_with('/') {
 				mount_point
 			} else {
 				"/".to_owned() + &mount_point
 			};
 
-			info!("Mounting virtio-fs at {mount_point}");
-			fs::FILESYSTEM
-				.get()
-				.unwrap()
-				.mount(mount_point.as_str(), Box::new(FuseDirectory::new(None)))
-				.expect("Mount failed. Invalid mount_point?");
+			info!("Mounting virtio-fs {tag} at {mount_point}");
+			fs::mount(
+				&tag,
+				&mount_point,
+				"virtiofs",
+				MountFlags::empty(),
+				Box::new(FuseDirectory::new(driver, None)),
+			)
+			.expect("Mount failed. Invalid mount_point?");
 		}
 	}
 }
@@ -11,33 +11,266 @@
 
 use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
-use core::marker::PhantomData;
-use core::mem::{MaybeUninit, offset_of};
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU64, Ordering};
 
-use align_address::Align;
 use async_lock::{Mutex, RwLock};
 use async_trait::async_trait;
+use hermit_sync::OnceCell;
 
+use crate::arch::mm::paging::{BasePageSize, PageSize};
 use crate::errno::Errno;
 use crate::executor::block_on;
+use crate::fd::lock::{DescriptionLocks, FileLocks, LockOwner, RecordLock};
 use crate::fd::{AccessPermission, ObjectInterface, OpenOption, PollEvent};
-use crate::fs::{DirectoryEntry, FileAttr, FileType, NodeKind, SeekWhence, VfsNode};
-use crate::syscalls::Dirent64;
+use crate::fs::{
+	DirectoryEntry, FallocateFlags, FileAttr, FileType, NodeKind, SeekWhence, StatFs, VfsNode,
+	XattrFlags, relative_path, seek_dirents, write_dirents,
+};
 use crate::time::timespec;
-use crate::{arch, io};
+use crate::{arch, io, mm};
+
+/// Magic number of the in-memory file system, which matches Linux' tmpfs
+const TMPFS_MAGIC: i64 = 0x0102_1994;
+
+/// Size of the blocks, in which the content of files is accounted
+const BLOCK_SIZE: u64 = BasePageSize::SIZE;
+
+/// Size of the chunks, in which the content of RAM files is stored
+const CHUNK_SIZE: usize = BLOCK_SIZE as usize;
+
+/// Parses a size limit given in bytes with an optional suffix `k`, `m` or `g`,
+/// or as percentage of the kernel heap with the suffix `%`.
+fn parse_size(size: &str, heap: usize) -> Option<u64> {
+	let size = size.trim();
+	let (digits, unit) = match size.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+		Some((i, _)) => size.split_at(i),
+		None => (size, ""),
+	};
+	let value: u64 = digits.parse().ok()?;
+
+	match unit.to_ascii_lowercase().as_str() {
+		"" => Some(value),
+		"k" => value.checked_mul(1 << 10),
+		"m" => value.checked_mul(1 << 20),
+		"g" => value.checked_mul(1 << 30),
+		"%" if value <= 100 => Some(u64::try_from(heap).unwrap() / 100 * value),
+		_ => None,
+	}
+}
+
+/// Block and inode accounting of an in-memory file system, which is shared by
+/// all nodes of the same mount.
+///
+/// All in-memory files are allocated on the kernel heap. Like tmpfs, the size
+/// is limited to half of the heap by default, which can be changed by
+/// `HERMIT_TMPFS_SIZE` (e.g., `64m` or `25%`). The number of inodes is limited
+/// to the number of blocks by default or by `HERMIT_TMPFS_NR_INODES`. A limit
+/// of `0` disables the limit.
+#[derive(Debug)]
+pub(crate) struct MemUsage {
+	/// Maximum number of blocks or `None` if unlimited
+	max_blocks: Option<u64>,
+	/// Maximum number of inodes or `None` if unlimited
+	max_inodes: Option<u64>,
+	blocks: AtomicU64,
+	inodes: AtomicU64,
+}
+
+impl MemUsage {
+	pub fn new() -> Arc<Self> {
+		let (_, heap) = mm::heap_usage();
+		let size = hermit_var!("HERMIT_TMPFS_SIZE").filter(|size| {
+			let valid = parse_size(size, heap).is_some();
+			if !valid {
+				warn!("Invalid HERMIT_TMPFS_SIZE {size}, using the default");
+			}
+			valid
+		});
+		let nr_inodes = hermit_var!("HERMIT_TMPFS_NR_INODES").filter(|inodes| {
+			let valid = inodes.trim().parse::<u64>().is_ok();
+			if !valid {
+				warn!("Invalid HERMIT_TMPFS_NR_INODES {inodes}, using the default");
+			}
+			valid
+		});
+
+		Self::with_limits(size.as_deref(), nr_inodes.as_deref()).unwrap()
+	}
+
+	/// Creates the accounting of a file system, which is limited to `size`
+	/// bytes in the format of [`parse_size`] and `nr_inodes` inodes, where
+	/// `None` selects the default.
+	pub fn with_limits(size: Option<&str>, nr_inodes: Option<&str>) -> io::Result<Arc<Self>> {
+		let (_, heap) = mm::heap_usage();
+		// Without a known heap size, there is no sensible default.
+		let default = (heap > 0).then(|| u64::try_from(heap / 2).unwrap());
+
+		let max_size = match size {
+			Some(size) => parse_size(size, heap).ok_or(Errno::Inval)?,
+			None => default.unwrap_or(0),
+		};
+		let max_blocks = (max_size > 0).then(|| max_size.div_ceil(BLOCK_SIZE));
+
+		let max_inodes = match nr_inodes {
+			Some(inodes) => inodes.trim().parse().map_err(|_| Errno::Inval)?,
+			None => max_blocks.unwrap_or(0),
+		};
+
+		Ok(Arc::new(Self {
+			max_blocks,
+			max_inodes: (max_inodes > 0).then_some(max_inodes),
+			blocks: AtomicU64::new(0),
+			inodes: AtomicU64::new(0),
+		}))
+	}
+
+	/// Adds `count` to `used` unless the sum exceeds `max`.
+	fn charge(used: &AtomicU64, max: Option<u64>, count: u64) -> io::Result<()> {
+		used.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+			let used = used.checked_add(count)?;
+			max.is_none_or(|max| used <= max).then_some(used)
+		})
+		.map(|_| ())
+		.map_err(|_| Errno::Nospc)
+	}
+
+	fn alloc_blocks(&self, count: u64) -> io::Result<()> {
+		Self::charge(&self.blocks, self.max_blocks, count)
+	}
+
+	fn free_blocks(&self, count: u64) {
+		self.blocks.fetch_sub(count, Ordering::Relaxed);
+	}
+
+	/// Allocates an inode, which is released when the returned value is dropped.
+	fn alloc_inode(self: &Arc<Self>) -> io::Result<MemInode> {
+		Self::charge(&self.inodes, self.max_inodes, 1)?;
+		Ok(MemInode {
+			usage: self.clone(),
+		})
+	}
+
+	/// Returns the statistics of the file system.
+	pub fn statfs(&self) -> StatFs {
+		let (blocks, free) = match self.max_blocks {
+			Some(max) => (max, max.saturating_sub(self.blocks.load(Ordering::Relaxed))),
+			None => {
+				let (used, total) = mm::heap_usage();
+				(
+					u64::try_from(total).unwrap() / BLOCK_SIZE,
+					u64::try_from(total - used).unwrap() / BLOCK_SIZE,
+				)
+			}
+		};
+		let (files, ffree) = match self.max_inodes {
+			Some(max) => (max, max.saturating_sub(self.inodes.load(Ordering::Relaxed))),
+			None => (0, 0),
+		};
+
+		StatFs {
+			f_type: TMPFS_MAGIC,
+			f_bsize: BLOCK_SIZE.try_into().unwrap(),
+			f_blocks: blocks,
+			f_bfree: free,
+			f_bavail: free,
+			f_files: files,
+			f_ffree: ffree,
+			f_namelen: 255,
+			f_frsize: BLOCK_SIZE.try_into().unwrap(),
+			..Default::default()
+		}
+	}
+}
+
+/// Inode of an in-memory file system, which is released when dropped
+#[derive(Debug)]
+pub(crate) struct MemInode {
+	usage: Arc<MemUsage>,
+}
+
+impl Drop for MemInode {
+	fn drop(&mut self) {
+		self.usage.inodes.fetch_sub(1, Ordering::Relaxed);
+	}
+}
+
+/// Changes the timestamps of `attr`, where `None` leaves the time unchanged.
+fn set_times(attr: &mut FileAttr, atime: Option<timespec>, mtime: Option<timespec>) {
+	if let Some(atime) = atime {
+		attr.st_atim = atime;
+	}
+	if let Some(mtime) = mtime {
+		attr.st_mtim = mtime;
+	}
+	attr.st_ctim = timespec::from_usec(arch::kernel::systemtime::now_micros() as i64);
+}
+
+/// Changes owner and group of `attr`, where `None` leaves the id unchanged.
+fn set_owner(attr: &mut FileAttr, uid: Option<u32>, gid: Option<u32>) {
+	if let Some(uid) = uid {
+		attr.st_uid = uid;
+	}
+	if let Some(gid) = gid {
+		attr.st_gid = gid;
+	}
+	attr.st_ctim = timespec::from_usec(arch::kernel::systemtime::now_micros() as i64);
+}
+
+/// Extended attributes of an in-memory node
+#[derive(Debug, Default)]
+pub(crate) struct Xattrs(BTreeMap<String, Vec<u8>>);
+
+impl Xattrs {
+	pub fn get(&self, name: &str) -> io::Result<Vec<u8>> {
+		self.0.get(name).cloned().ok_or(Errno::Nodata)
+	}
+
+	pub fn set(&mut self, name: &str, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+		let exists = self.0.contains_key(name);
+		if exists && flags.contains(XattrFlags::XATTR_CREATE) {
+			return Err(Errno::Exist);
+		}
+		if !exists && flags.contains(XattrFlags::XATTR_REPLACE) {
+			return Err(Errno::Nodata);
+		}
+
+		self.0.insert(name.to_string(), value.to_vec());
+		Ok(())
+	}
+
+	pub fn list(&self) -> Vec<String> {
+		self.0.keys().cloned().collect()
+	}
+
+	pub fn remove(&mut self, name: &str) -> io::Result<()> {
+		self.0.remove(name).map(|_| ()).ok_or(Errno::Nodata)
+	}
+}
 
 #[derive(Debug)]
 pub(crate) struct RomFileInner {
 	pub data: &'static [u8],
 	pub attr: FileAttr,
+	/// Advisory locks of the file
+	pub locks: Arc<FileLocks>,
+	/// Extended attributes of the file
+	pub xattrs: Xattrs,
+	inode: MemInode,
 }
 
 impl RomFileInner {
-	pub fn new(data: &'static [u8], attr: FileAttr) -> Self {
-		Self { data, attr }
+	pub fn new(data: &'static [u8], attr: FileAttr, inode: MemInode) -> Self {
+		Self {
+			data,
+			attr,
+			locks: Arc::new(FileLocks::new()),
+			xattrs: Xattrs::default(),
+			inode,
+		}
 	}
 }
 
@@ -47,6 +280,8 @@ struct RomFileInterface {
 	pos: Arc<Mutex<usize>>,
 	/// File content
 	inner: Arc<RwLock<RomFileInner>>,
+	/// `flock` locks of the open file description
+	flocks: Arc<DescriptionLocks>,
 }
 
 #[async_trait]
@@ -106,6 +341,18 @@ impl ObjectInterface for RomFileInterface {
 			guard.data.len() as isize + offset
 		} else if whence == SeekWhence::Cur {
 			(*pos_guard as isize) + offset
+		} else if whence == SeekWhence::Data || whence == SeekWhence::Hole {
+			// The file contains no holes.
+			let len = isize::try_from(guard.data.len()).unwrap();
+			if !(0..len).contains(&offset) {
+				return Err(Errno::Nxio);
+			}
+
+			if whence == SeekWhence::Data {
+				offset
+			} else {
+				len
+			}
 		} else {
 			return Err(Errno::Inval);
 		};
@@ -122,6 +369,54 @@ impl ObjectInterface for RomFileInterface {
 		let guard = self.inner.read().await;
 		Ok(guard.attr)
 	}
+
+	async fn fstatfs(&self) -> io::Result<StatFs> {
+		Ok(self.inner.read().await.inode.usage.statfs())
+	}
+
+	async fn utimens(&self, atime: Option<timespec>, mtime: Option<timespec>) -> io::Result<()> {
+		set_times(&mut self.inner.write().await.attr, atime, mtime);
+		Ok(())
+	}
+
+	async fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+		set_owner(&mut self.inner.write().await.attr, uid, gid);
+		Ok(())
+	}
+
+	async fn getxattr(&self, name: &str) -> io::Result<Vec<u8>> {
+		self.inner.read().await.xattrs.get(name)
+	}
+
+	async fn setxattr(&self, name: &str, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+		self.inner.write().await.xattrs.set(name, value, flags)
+	}
+
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		Ok(self.inner.read().await.xattrs.list())
+	}
+
+	async fn removexattr(&self, name: &str) -> io::Result<()> {
+		self.inner.write().await.xattrs.remove(name)
+	}
+
+	async fn getlk(&self, lock: RecordLock) -> io::Result<Option<RecordLock>> {
+		let locks = self.inner.read().await.locks.clone();
+		Ok(locks.conflict(&lock).await)
+	}
+
+	async fn setlk(&self, lock: RecordLock, wait: bool) -> io::Result<()> {
+		// Do not hold the file while waiting for the lock.
+		let locks = self.inner.read().await.locks.clone();
+		locks.lock(lock, wait).await?;
+		self.flocks.placed(&locks, &lock).await;
+		Ok(())
+	}
+
+	async fn release_locks(&self, owner: LockOwner) {
+		let locks = self.inner.read().await.locks.clone();
+		locks.release(owner).await;
+	}
 }
 
 impl RomFileInterface {
@@ -129,6 +424,7 @@ impl RomFileInterface {
 		Self {
 			pos: Arc::new(Mutex::new(0)),
 			inner,
+			flocks: Arc::new(DescriptionLocks::default()),
 		}
 	}
 
@@ -137,19 +433,229 @@ impl RomFileInterface {
 	}
 }
 
+/// Content of a RAM file, which is stored in chunks of [`CHUNK_SIZE`] bytes.
+///
+/// Chunks, which have never been written or allocated, are holes and occupy
+/// no memory. All bytes beyond the end of the file are zero.
+#[derive(Debug, Default)]
+struct SparseData {
+	/// Allocated chunks by their index
+	chunks: BTreeMap<usize, Box<[u8]>>,
+	len: usize,
+}
+
+impl SparseData {
+	fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Reads at `offset`, which is limited by the length of `buf` and the end of the file.
+	fn read(&self, offset: usize, buf: &mut [u8]) -> usize {
+		if offset >= self.len {
+			return 0;
+		}
+		let len = buf.len().min(self.len - offset);
+
+		let mut done = 0;
+		while done < len {
+			let pos = offset + done;
+			let within = pos % CHUNK_SIZE;
+			let count = (len - done).min(CHUNK_SIZE - within);
+			let buf = &mut buf[done..done + count];
+			match self.chunks.get(&(pos / CHUNK_SIZE)) {
+				Some(chunk) => buf.copy_from_slice(&chunk[within..within + count]),
+				None => buf.fill(0),
+			}
+			done += count;
+		}
+
+		len
+	}
+
+	/// Allocates the chunks covering `offset..end`, which are charged in `usage`.
+	fn allocate(&mut self, offset: usize, end: usize, usage: &MemUsage) -> io::Result<()> {
+		if offset >= end {
+			return Ok(());
+		}
+
+		for index in offset / CHUNK_SIZE..end.div_ceil(CHUNK_SIZE) {
+			if self.chunks.contains_key(&index) {
+				continue;
+			}
+
+			usage.alloc_blocks(1)?;
+			let mut chunk = Vec::new();
+			if chunk.try_reserve_exact(CHUNK_SIZE).is_err() {
+				usage.free_blocks(1);
+				return Err(Errno::Nospc);
+			}
+			chunk.resize(CHUNK_SIZE, 0);
+			self.chunks.insert(index, chunk.into_boxed_slice());
+		}
+
+		Ok(())
+	}
+
+	/// Writes `buf` at `offset` and extends the file if necessary.
+	fn write(&mut self, offset: usize, buf: &[u8], usage: &MemUsage) -> io::Result<()> {
+		let end = offset.checked_add(buf.len()).ok_or(Errno::Fbig)?;
+		self.allocate(offset, end, usage)?;
+
+		let mut done = 0;
+		while done < buf.len() {
+			let pos = offset + done;
+			let within = pos % CHUNK_SIZE;
+			let count = (buf.len() - done).min(CHUNK_SIZE - within);
+			let chunk = self.chunks.get_mut(&(pos / CHUNK_SIZE)).unwrap();
+			chunk[within..within + count].copy_from_slice(&buf[done..done + count]);
+			done += count;
+		}
+
+		self.len = self.len.max(end);
+		Ok(())
+	}
+
+	/// Deallocates the chunks within `offset..end` and zeroes the remaining bytes.
+	fn punch_hole(&mut self, offset: usize, end: usize, usage: &MemUsage) {
+		if offset >= end {
+			return;
+		}
+
+		let first = offset.div_ceil(CHUNK_SIZE);
+		let last = end / CHUNK_SIZE;
+		if first < last {
+			let mut tail = self.chunks.split_off(&first);
+			let mut rest = tail.split_off(&last);
+			self.chunks.append(&mut rest);
+			usage.free_blocks(tail.len().try_into().unwrap());
+		}
+
+		// Zero the partially covered chunks at both ends of the range.
+		for index in [offset / CHUNK_SIZE, end / CHUNK_SIZE] {
+			if let Some(chunk) = self.chunks.get_mut(&index) {
+				let start = offset.clamp(index * CHUNK_SIZE, (index + 1) * CHUNK_SIZE);
+				let stop = end.clamp(index * CHUNK_SIZE, (index + 1) * CHUNK_SIZE);
+				chunk[start - index * CHUNK_SIZE..stop - index * CHUNK_SIZE].fill(0);
+			}
+		}
+	}
+
+	/// Changes the length of the file without allocating memory for new bytes.
+	fn set_len(&mut self, len: usize, usage: &MemUsage) {
+		if len < self.len {
+			self.punch_hole(len, self.len.max(self.allocated_end()), usage);
+		}
+		self.len = len;
+	}
+
+	/// Returns the end of the last allocated chunk.
+	fn allocated_end(&self) -> usize {
+		self.chunks
+			.last_key_value()
+			.map_or(0, |(index, _)| (index + 1) * CHUNK_SIZE)
+	}
+
+	/// Returns the first position at or after `offset`, which contains data.
+	fn seek_data(&self, offset: usize) -> Option<usize> {
+		let (index, _) = self.chunks.range(offset / CHUNK_SIZE..).next()?;
+		let pos = offset.max(index * CHUNK_SIZE);
+		(pos < self.len).then_some(pos)
+	}
+
+	/// Returns the first position at or after `offset`, which is within a hole.
+	/// The end of the file is an implicit hole.
+	fn seek_hole(&self, offset: usize) -> usize {
+		let mut index = offset / CHUNK_SIZE;
+		for (&allocated, _) in self.chunks.range(index..) {
+			if allocated != index {
+				break;
+			}
+			index += 1;
+		}
+		offset.max(index * CHUNK_SIZE).min(self.len)
+	}
+
+	fn blocks(&self) -> usize {
+		self.chunks.len()
+	}
+}
+
 #[derive(Debug)]
 pub(crate) struct RamFileInner {
-	pub data: Vec<u8>,
+	data: SparseData,
 	pub attr: FileAttr,
+	/// Advisory locks of the file
+	pub locks: Arc<FileLocks>,
+	/// Extended attributes of the file
+	pub xattrs: Xattrs,
+	/// Inode, which is charged for the content of the file
+	inode: MemInode,
 }
 
 impl RamFileInner {
-	pub fn new(attr: FileAttr) -> Self {
+	pub fn new(attr: FileAttr, inode: MemInode) -> Self {
 		Self {
-			data: Vec::new(),
+			data: SparseData::default(),
 			attr,
+			locks: Arc::new(FileLocks::new()),
+			xattrs: Xattrs::default(),
+			inode,
 		}
 	}
+
+	/// Updates size and allocated blocks in the attributes.
+	fn update_size(&mut self) {
+		self.attr.st_size = self.data.len().try_into().unwrap();
+		self.attr.st_blocks = (self.data.blocks() * (CHUNK_SIZE / 512))
+			.try_into()
+			.unwrap();
+	}
+
+	/// Writes `buf` at `offset`, which fails with `ENOSPC` if the file system
+	/// or the kernel heap is full.
+	fn write(&mut self, offset: usize, buf: &[u8]) -> io::Result<()> {
+		let result = self.data.write(offset, buf, &self.inode.usage);
+		self.update_size();
+		result
+	}
+
+	/// Changes the size of the file. New bytes are a hole.
+	fn set_len(&mut self, len: usize) {
+		self.data.set_len(len, &self.inode.usage);
+		self.update_size();
+	}
+
+	fn fallocate(&mut self, mode: FallocateFlags, offset: usize, len: usize) -> io::Result<()> {
+		let end = offset.checked_add(len).ok_or(Errno::Fbig)?;
+
+		let result = if mode.contains(FallocateFlags::FALLOC_FL_PUNCH_HOLE) {
+			self.data.punch_hole(offset, end, &self.inode.usage);
+			Ok(())
+		} else {
+			if mode.contains(FallocateFlags::FALLOC_FL_ZERO_RANGE) {
+				self.data.punch_hole(offset, end, &self.inode.usage);
+			}
+			self.data
+				.allocate(offset, end, &self.inode.usage)
+				.map(|()| {
+					if !mode.contains(FallocateFlags::FALLOC_FL_KEEP_SIZE) && end > self.data.len()
+					{
+						self.data.len = end;
+					}
+				})
+		};
+
+		self.update_size();
+		result
+	}
+}
+
+impl Drop for RamFileInner {
+	fn drop(&mut self) {
+		self.inode
+			.usage
+			.free_blocks(self.data.blocks().try_into().unwrap());
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -158,6 +664,8 @@ pub struct RamFileInterface {
 	pos: Arc<Mutex<usize>>,
 	/// File content
 	inner: Arc<RwLock<RamFileInner>>,
+	/// `flock` locks of the open file description
+	flocks: Arc<DescriptionLocks>,
 }
 
 #[async_trait]
@@ -185,20 +693,9 @@ impl ObjectInterface for RamFileInterface {
 
 		let guard = self.inner.read().await;
 		let mut pos_guard = self.pos.lock().await;
-		let pos = *pos_guard;
-
-		if pos >= guard.data.len() {
-			return Ok(0);
-		}
 
-		let len = if guard.data.len() - pos < buf.len() {
-			guard.data.len() - pos
-		} else {
-			buf.len()
-		};
-
-		buf[..len].copy_from_slice(&guard.data[pos..pos + len]);
-		*pos_guard = pos + len;
+		let len = guard.data.read(*pos_guard, buf);
+		*pos_guard += len;
 
 		Ok(len)
 	}
@@ -210,43 +707,43 @@ impl ObjectInterface for RamFileInterface {
 		let mut pos_guard = self.pos.lock().await;
 		let pos = *pos_guard;
 
-		if pos + buf.len() > guard.data.len() {
-			guard.data.resize(pos + buf.len(), 0);
-			guard.attr.st_size = guard.data.len().try_into().unwrap();
-		}
+		guard.write(pos, buf)?;
 
 		guard.attr.st_atim = t;
 		guard.attr.st_mtim = t;
 		guard.attr.st_ctim = t;
 
-		guard.data[pos..pos + buf.len()].copy_from_slice(buf);
 		*pos_guard = pos + buf.len();
 
 		Ok(buf.len())
 	}
 
+	/// Seeking beyond the end of the file does not change its size. A
+	/// subsequent write creates a hole between the old end and the position.
 	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
-		let mut guard = self.inner.write().await;
+		let guard = self.inner.read().await;
 		let mut pos_guard = self.pos.lock().await;
-
-		let new_pos: isize = if whence == SeekWhence::Set {
-			if offset < 0 {
-				return Err(Errno::Inval);
+		let len = guard.data.len();
+
+		let new_pos = match whence {
+			SeekWhence::Set => Some(offset),
+			SeekWhence::Cur => isize::try_from(*pos_guard).unwrap().checked_add(offset),
+			SeekWhence::End => isize::try_from(len).unwrap().checked_add(offset),
+			SeekWhence::Data | SeekWhence::Hole => {
+				let offset = usize::try_from(offset)
+					.ok()
+					.filter(|&offset| offset < len)
+					.ok_or(Errno::Nxio)?;
+				let pos = if whence == SeekWhence::Data {
+					guard.data.seek_data(offset).ok_or(Errno::Nxio)?
+				} else {
+					guard.data.seek_hole(offset)
+				};
+				Some(pos.try_into().unwrap())
 			}
-
-			offset
-		} else if whence == SeekWhence::End {
-			guard.data.len() as isize + offset
-		} else if whence == SeekWhence::Cur {
-			(*pos_guard as isize) + offset
-		} else {
-			return Err(Errno::Inval);
 		};
 
-		if new_pos > isize::try_from(guard.data.len()).unwrap() {
-			guard.data.resize(new_pos.try_into().unwrap(), 0);
-			guard.attr.st_size = guard.data.len().try_into().unwrap();
-		}
+		let new_pos = new_pos.filter(|&pos| pos >= 0).ok_or(Errno::Inval)?;
 		*pos_guard = new_pos.try_into().unwrap();
 
 		Ok(new_pos)
@@ -257,18 +754,68 @@ impl ObjectInterface for RamFileInterface {
 		Ok(guard.attr)
 	}
 
+	async fn fstatfs(&self) -> io::Result<StatFs> {
+		Ok(self.inner.read().await.inode.usage.statfs())
+	}
+
 	async fn truncate(&self, size: usize) -> io::Result<()> {
-		let mut guard = self.inner.write().await;
-		guard.data.resize(size, 0);
-		guard.attr.st_size = size as i64;
+		self.inner.write().await.set_len(size);
 		Ok(())
 	}
 
+	async fn fallocate(&self, mode: FallocateFlags, offset: usize, len: usize) -> io::Result<()> {
+		self.inner.write().await.fallocate(mode, offset, len)
+	}
+
 	async fn chmod(&self, access_permission: AccessPermission) -> io::Result<()> {
 		let mut guard = self.inner.write().await;
 		guard.attr.st_mode = access_permission;
 		Ok(())
 	}
+
+	async fn utimens(&self, atime: Option<timespec>, mtime: Option<timespec>) -> io::Result<()> {
+		set_times(&mut self.inner.write().await.attr, atime, mtime);
+		Ok(())
+	}
+
+	async fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+		set_owner(&mut self.inner.write().await.attr, uid, gid);
+		Ok(())
+	}
+
+	async fn getxattr(&self, name: &str) -> io::Result<Vec<u8>> {
+		self.inner.read().await.xattrs.get(name)
+	}
+
+	async fn setxattr(&self, name: &str, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+		self.inner.write().await.xattrs.set(name, value, flags)
+	}
+
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		Ok(self.inner.read().await.xattrs.list())
+	}
+
+	async fn removexattr(&self, name: &str) -> io::Result<()> {
+		self.inner.write().await.xattrs.remove(name)
+	}
+
+	async fn getlk(&self, lock: RecordLock) -> io::Result<Option<RecordLock>> {
+		let locks = self.inner.read().await.locks.clone();
+		Ok(locks.conflict(&lock).await)
+	}
+
+	async fn setlk(&self, lock: RecordLock, wait: bool) -> io::Result<()> {
+		// Do not hold the file while waiting for the lock.
+		let locks = self.inner.read().await.locks.clone();
+		locks.lock(lock, wait).await?;
+		self.flocks.placed(&locks, &lock).await;
+		Ok(())
+	}
+
+	async fn release_locks(&self, owner: LockOwner) {
+		let locks = self.inner.read().await.locks.clone();
+		locks.release(owner).await;
+	}
 }
 
 impl RamFileInterface {
@@ -276,6 +823,7 @@ impl RamFileInterface {
 		Self {
 			pos: Arc::new(Mutex::new(0)),
 			inner,
+			flocks: Arc::new(DescriptionLocks::default()),
 		}
 	}
 
@@ -319,24 +867,143 @@ impl VfsNode for RomFile {
 			Err(Errno::Badf)
 		}
 	}
+
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		if components.is_empty() {
+			block_on(
+				async { Ok(self.data.read().await.inode.usage.statfs()) },
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		if components.is_empty() {
+			Err(Errno::Inval)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_utimens(
+		&self,
+		components: &mut Vec<&str>,
+		atime: Option<timespec>,
+		mtime: Option<timespec>,
+	) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					set_times(&mut self.data.write().await.attr, atime, mtime);
+					Ok(())
+				},
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_chown(
+		&self,
+		components: &mut Vec<&str>,
+		uid: Option<u32>,
+		gid: Option<u32>,
+	) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					set_owner(&mut self.data.write().await.attr, uid, gid);
+					Ok(())
+				},
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		if components.is_empty() {
+			block_on(async { self.data.read().await.xattrs.get(name) }, None)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async { self.data.write().await.xattrs.set(name, value, flags) },
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		if components.is_empty() {
+			block_on(async { Ok(self.data.read().await.xattrs.list()) }, None)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(async { self.data.write().await.xattrs.remove(name) }, None)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+	fn traverse_mem_usage(&self, components: &mut Vec<&str>) -> io::Result<Arc<MemUsage>> {
+		if components.is_empty() {
+			block_on(
+				async { Ok(self.data.read().await.inode.usage.clone()) },
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
 }
 
 impl RomFile {
-	pub fn new(data: &'static [u8], mode: AccessPermission) -> Self {
+	/// Creates a file, which is accounted in `usage`. As the content is not
+	/// allocated on the heap, it does not occupy any blocks.
+	pub fn new(
+		data: &'static [u8],
+		mode: AccessPermission,
+		usage: &Arc<MemUsage>,
+	) -> io::Result<Self> {
 		let microseconds = arch::kernel::systemtime::now_micros();
 		let t = timespec::from_usec(microseconds as i64);
 		let attr = FileAttr {
 			st_size: data.len().try_into().unwrap(),
 			st_mode: mode | AccessPermission::S_IFREG,
+			st_blksize: BLOCK_SIZE.try_into().unwrap(),
 			st_atim: t,
 			st_mtim: t,
 			st_ctim: t,
 			..Default::default()
 		};
 
-		Self {
-			data: Arc::new(RwLock::new(RomFileInner::new(data, attr))),
-		}
+		Ok(Self {
+			data: Arc::new(RwLock::new(RomFileInner::new(
+				data,
+				attr,
+				usage.alloc_inode()?,
+			))),
+		})
 	}
 }
 
@@ -375,22 +1042,282 @@ impl VfsNode for RamFile {
 			Err(Errno::Badf)
 		}
 	}
+
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		if components.is_empty() {
+			block_on(
+				async { Ok(self.data.read().await.inode.usage.statfs()) },
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		if components.is_empty() {
+			Err(Errno::Inval)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_utimens(
+		&self,
+		components: &mut Vec<&str>,
+		atime: Option<timespec>,
+		mtime: Option<timespec>,
+	) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					set_times(&mut self.data.write().await.attr, atime, mtime);
+					Ok(())
+				},
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_chown(
+		&self,
+		components: &mut Vec<&str>,
+		uid: Option<u32>,
+		gid: Option<u32>,
+	) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					set_owner(&mut self.data.write().await.attr, uid, gid);
+					Ok(())
+				},
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		if components.is_empty() {
+			block_on(async { self.data.read().await.xattrs.get(name) }, None)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async { self.data.write().await.xattrs.set(name, value, flags) },
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		if components.is_empty() {
+			block_on(async { Ok(self.data.read().await.xattrs.list()) }, None)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(async { self.data.write().await.xattrs.remove(name) }, None)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+	fn traverse_mem_usage(&self, components: &mut Vec<&str>) -> io::Result<Arc<MemUsage>> {
+		if components.is_empty() {
+			block_on(
+				async { Ok(self.data.read().await.inode.usage.clone()) },
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
 }
 
 impl RamFile {
-	pub fn new(mode: AccessPermission) -> Self {
+	/// Creates an empty file, which is accounted in `usage`.
+	pub fn new(mode: AccessPermission, usage: &Arc<MemUsage>) -> io::Result<Self> {
 		let microseconds = arch::kernel::systemtime::now_micros();
 		let t = timespec::from_usec(microseconds as i64);
 		let attr = FileAttr {
 			st_mode: mode | AccessPermission::S_IFREG,
+			st_blksize: BLOCK_SIZE.try_into().unwrap(),
+			st_atim: t,
+			st_mtim: t,
+			st_ctim: t,
+			..Default::default()
+		};
+
+		Ok(Self {
+			data: Arc::new(RwLock::new(RamFileInner::new(attr, usage.alloc_inode()?))),
+		})
+	}
+}
+
+/// Symbolic link, whose target is stored on the heap
+#[derive(Debug)]
+pub(crate) struct MemSymlink {
+	target: String,
+	attr: RwLock<FileAttr>,
+	inode: MemInode,
+}
+
+impl VfsNode for MemSymlink {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Symlink
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		block_on(async { Ok(*self.attr.read().await) }, None)
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		if components.is_empty() {
+			self.get_file_attributes()
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		if components.is_empty() {
+			Ok(self.inode.usage.statfs())
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		if components.is_empty() {
+			Ok(self.target.clone())
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_mem_usage(&self, components: &mut Vec<&str>) -> io::Result<Arc<MemUsage>> {
+		if components.is_empty() {
+			Ok(self.inode.usage.clone())
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_utimens(
+		&self,
+		components: &mut Vec<&str>,
+		atime: Option<timespec>,
+		mtime: Option<timespec>,
+	) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					set_times(&mut *self.attr.write().await, atime, mtime);
+					Ok(())
+				},
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+
+	fn traverse_chown(
+		&self,
+		components: &mut Vec<&str>,
+		uid: Option<u32>,
+		gid: Option<u32>,
+	) -> io::Result<()> {
+		if components.is_empty() {
+			block_on(
+				async {
+					set_owner(&mut *self.attr.write().await, uid, gid);
+					Ok(())
+				},
+				None,
+			)
+		} else {
+			Err(Errno::Badf)
+		}
+	}
+}
+
+impl MemSymlink {
+	/// Creates a symbolic link to `target`, which is accounted in `usage`.
+	pub fn new(target: &str, usage: &Arc<MemUsage>) -> io::Result<Self> {
+		let microseconds = arch::kernel::systemtime::now_micros();
+		let t = timespec::from_usec(microseconds as i64);
+		let attr = FileAttr {
+			st_size: target.len().try_into().unwrap(),
+			st_mode: AccessPermission::S_IFLNK | AccessPermission::from_bits_retain(0o777),
+			st_blksize: BLOCK_SIZE.try_into().unwrap(),
 			st_atim: t,
 			st_mtim: t,
 			st_ctim: t,
 			..Default::default()
 		};
 
-		Self {
-			data: Arc::new(RwLock::new(RamFileInner::new(attr))),
+		Ok(Self {
+			target: target.to_string(),
+			attr: RwLock::new(attr),
+			inode: usage.alloc_inode()?,
+		})
+	}
+}
+
+/// Whiteout, which hides an entry of the lower layer of an overlay.
+///
+/// Like on Linux, a whiteout appears as character device with the device
+/// number 0.
+#[derive(Debug)]
+pub(crate) struct MemWhiteout {
+	inode: MemInode,
+}
+
+impl VfsNode for MemWhiteout {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Whiteout
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		Ok(FileAttr {
+			st_mode: AccessPermission::S_IFCHR,
+			st_rdev: 0,
+			..Default::default()
+		})
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		if components.is_empty() {
+			self.get_file_attributes()
+		} else {
+			Err(Errno::Notdir)
+		}
+	}
+
+	fn traverse_mem_usage(&self, components: &mut Vec<&str>) -> io::Result<Arc<MemUsage>> {
+		if components.is_empty() {
+			Ok(self.inode.usage.clone())
+		} else {
+			Err(Errno::Notdir)
 		}
 	}
 }
@@ -401,6 +1328,14 @@ pub struct MemDirectoryInterface {
 	inner:
 		Arc<RwLock<BTreeMap<String, Box<dyn VfsNode + core::marker::Send + core::marker::Sync>>>>,
 	read_idx: Mutex<usize>,
+	/// Attributes of the directory
+	attr: Arc<RwLock<FileAttr>>,
+	/// Extended attributes of the directory
+	xattrs: Arc<RwLock<Xattrs>>,
+	/// Accounting of the file system
+	usage: Arc<MemUsage>,
+	/// Absolute path of the directory
+	path: OnceCell<String>,
 }
 
 impl MemDirectoryInterface {
@@ -408,72 +1343,80 @@ impl MemDirectoryInterface {
 		inner: Arc<
 			RwLock<BTreeMap<String, Box<dyn VfsNode + core::marker::Send + core::marker::Sync>>>,
 		>,
+		attr: Arc<RwLock<FileAttr>>,
+		xattrs: Arc<RwLock<Xattrs>>,
+		usage: Arc<MemUsage>,
 	) -> Self {
 		Self {
 			inner,
 			read_idx: Mutex::new(0),
+			attr,
+			xattrs,
+			usage,
+			path: OnceCell::new(),
 		}
 	}
 }
 
 #[async_trait]
 impl ObjectInterface for MemDirectoryInterface {
-	async fn getdents(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
-		let mut buf_offset: usize = 0;
-		let mut ret = 0;
-		let mut read_idx = self.read_idx.lock().await;
-		for name in self.inner.read().await.keys().skip(*read_idx) {
-			let namelen = name.len();
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(*self.attr.read().await)
+	}
 
-			let dirent_len = offset_of!(Dirent64, d_name) + namelen + 1;
-			let next_dirent = (buf_offset + dirent_len).align_up(align_of::<Dirent64>());
+	async fn fstatfs(&self) -> io::Result<StatFs> {
+		Ok(self.usage.statfs())
+	}
 
-			if next_dirent > buf.len() {
-				// target buffer full -> we return the nr. of bytes written (like linux does)
-				break;
-			}
+	async fn utimens(&self, atime: Option<timespec>, mtime: Option<timespec>) -> io::Result<()> {
+		set_times(&mut *self.attr.write().await, atime, mtime);
+		Ok(())
+	}
 
-			*read_idx += 1;
-
-			// could be replaced with slice_as_ptr once maybe_uninit_slice is stabilized.
-			let target_dirent = buf[buf_offset].as_mut_ptr().cast::<Dirent64>();
-
-			unsafe {
-				target_dirent.write(Dirent64 {
-					d_ino: 1, // TODO: we don't have inodes in the mem filesystem. Maybe this could lead to problems
-					d_off: 0,
-					d_reclen: (dirent_len.align_up(align_of::<Dirent64>()))
-						.try_into()
-						.unwrap(),
-					d_type: FileType::Unknown, // TODO: Proper filetype
-					d_name: PhantomData {},
-				});
-				let nameptr = core::ptr::from_mut(&mut (*(target_dirent)).d_name).cast::<u8>();
-				core::ptr::copy_nonoverlapping(
-					name.as_bytes().as_ptr().cast::<u8>(),
-					nameptr,
-					namelen,
-				);
-				nameptr.add(namelen).write(0); // zero termination
-			}
+	async fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+		set_owner(&mut *self.attr.write().await, uid, gid);
+		Ok(())
+	}
 
-			buf_offset = next_dirent;
-			ret = buf_offset;
-		}
-		Ok(ret)
+	async fn getxattr(&self, name: &str) -> io::Result<Vec<u8>> {
+		self.xattrs.read().await.get(name)
+	}
+
+	async fn setxattr(&self, name: &str, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+		self.xattrs.write().await.set(name, value, flags)
+	}
+
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		Ok(self.xattrs.read().await.list())
+	}
+
+	async fn removexattr(&self, name: &str) -> io::Result<()> {
+		self.xattrs.write().await.remove(name)
+	}
+
+	async fn getdents(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		let mut read_idx = self.read_idx.lock().await;
+		Ok(write_dirents(
+			self.inner
+				.read()
+				.await
+				.iter()
+				.map(|(name, node)| (name.as_str(), FileType::from(node.get_kind()))),
+			&mut read_idx,
+			buf,
+		))
 	}
 
-	/// lseek for a directory entry is the equivalent for seekdir on linux. But on Hermit this is
-	/// logically the same operation, so we can just use the same fn in the backend.
-	/// Any other offset than 0 is not supported. (Mostly because it doesn't make any sense, as
-	/// userspace applications have no way of knowing valid offsets)
 	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
-		if whence != SeekWhence::Set && offset != 0 {
-			error!("Invalid offset for directory lseek ({offset})");
-			return Err(Errno::Inval);
-		}
-		*self.read_idx.lock().await = offset as usize;
-		Ok(offset)
+		seek_dirents(&mut *self.read_idx.lock().await, offset, whence)
+	}
+
+	async fn dirpath(&self) -> io::Result<String> {
+		self.path.get().cloned().ok_or(Errno::Notdir)
+	}
+
+	async fn set_dirpath(&self, path: &str) {
+		let _ = self.path.set(path.to_string());
 	}
 }
 
@@ -481,24 +1424,35 @@ impl ObjectInterface for MemDirectoryInterface {
 pub(crate) struct MemDirectory {
 	inner:
 		Arc<RwLock<BTreeMap<String, Box<dyn VfsNode + core::marker::Send + core::marker::Sync>>>>,
-	attr: FileAttr,
+	attr: Arc<RwLock<FileAttr>>,
+	xattrs: Arc<RwLock<Xattrs>>,
+	inode: MemInode,
 }
 
 impl MemDirectory {
+	/// Creates the root directory of a new in-memory file system.
 	pub fn new(mode: AccessPermission) -> Self {
+		Self::with_usage(mode, &MemUsage::new()).expect("Unable to allocate root inode")
+	}
+
+	/// Creates a directory, which is accounted in `usage`.
+	pub fn with_usage(mode: AccessPermission, usage: &Arc<MemUsage>) -> io::Result<Self> {
 		let microseconds = arch::kernel::systemtime::now_micros();
 		let t = timespec::from_usec(microseconds as i64);
 
-		Self {
+		Ok(Self {
 			inner: Arc::new(RwLock::new(BTreeMap::new())),
-			attr: FileAttr {
+			attr: Arc::new(RwLock::new(FileAttr {
 				st_mode: mode | AccessPermission::S_IFDIR,
+				st_blksize: BLOCK_SIZE.try_into().unwrap(),
 				st_atim: t,
 				st_mtim: t,
 				st_ctim: t,
 				..Default::default()
-			},
-		}
+			})),
+			xattrs: Arc::new(RwLock::new(Xattrs::default())),
+			inode: usage.alloc_inode()?,
+		})
 	}
 
 	async fn async_traverse_open(
@@ -525,7 +1479,7 @@ impl MemDirectory {
 						return Err(Errno::Noent);
 					}
 				} else if opt.contains(OpenOption::O_CREAT) {
-					let file = Box::new(RamFile::new(mode));
+					let file = Box::new(RamFile::new(mode, &self.inode.usage)?);
 					guard.insert(node_name, file.clone());
 					return Ok(Arc::new(async_lock::RwLock::new(RamFileInterface::new(
 						file.data.clone(),
@@ -551,12 +1505,17 @@ impl VfsNode for MemDirectory {
 
 	fn get_object(&self) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
 		Ok(Arc::new(async_lock::RwLock::new(
-			MemDirectoryInterface::new(self.inner.clone()),
+			MemDirectoryInterface::new(
+				self.inner.clone(),
+				self.attr.clone(),
+				self.xattrs.clone(),
+				self.inode.usage.clone(),
+			),
 		)))
 	}
 
 	fn get_file_attributes(&self) -> io::Result<FileAttr> {
-		Ok(self.attr)
+		block_on(async { Ok(*self.attr.read().await) }, None)
 	}
 
 	fn traverse_mkdir(&self, components: &mut Vec<&str>, mode: AccessPermission) -> io::Result<()> {
@@ -570,10 +1529,11 @@ impl VfsNode for MemDirectory {
 					}
 
 					if components.is_empty() {
+						let directory = MemDirectory::with_usage(mode, &self.inode.usage)?;
 						self.inner
 							.write()
 							.await
-							.insert(node_name, Box::new(MemDirectory::new(mode)));
+							.insert(node_name, Box::new(directory));
 						return Ok(());
 					}
 				}
@@ -621,7 +1581,7 @@ impl VfsNode for MemDirectory {
 						let mut guard = self.inner.write().await;
 
 						let obj = guard.remove(&node_name).ok_or(Errno::Noent)?;
-						if obj.get_kind() == NodeKind::File {
+						if obj.get_kind() != NodeKind::Directory {
 							return Ok(());
 						} else {
 							guard.insert(node_name, obj);
@@ -651,8 +1611,11 @@ impl VfsNode for MemDirectory {
 					}
 				} else {
 					let mut entries: Vec<DirectoryEntry> = Vec::new();
-					for name in self.inner.read().await.keys() {
-						entries.push(DirectoryEntry::new(name.clone()));
+					for (name, node) in self.inner.read().await.iter() {
+						entries.push(DirectoryEntry::with_type(
+							name.clone(),
+							node.get_kind().into(),
+						));
 					}
 
 					Ok(entries)
@@ -712,6 +1675,337 @@ impl VfsNode for MemDirectory {
 		)
 	}
 
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					if let Some(node) = self.inner.read().await.get(component) {
+						node.traverse_statfs(components)
+					} else {
+						Err(Errno::Noent)
+					}
+				} else {
+					Ok(self.inode.usage.statfs())
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_utimens(
+		&self,
+		components: &mut Vec<&str>,
+		atime: Option<timespec>,
+		mtime: Option<timespec>,
+	) -> io::Result<()> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					if let Some(node) = self.inner.read().await.get(component) {
+						node.traverse_utimens(components, atime, mtime)
+					} else {
+						Err(Errno::Noent)
+					}
+				} else {
+					set_times(&mut *self.attr.write().await, atime, mtime);
+					Ok(())
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_chown(
+		&self,
+		components: &mut Vec<&str>,
+		uid: Option<u32>,
+		gid: Option<u32>,
+	) -> io::Result<()> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					if let Some(node) = self.inner.read().await.get(component) {
+						node.traverse_chown(components, uid, gid)
+					} else {
+						Err(Errno::Noent)
+					}
+				} else {
+					set_owner(&mut *self.attr.write().await, uid, gid);
+					Ok(())
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					if let Some(node) = self.inner.read().await.get(component) {
+						node.traverse_getxattr(components, name)
+					} else {
+						Err(Errno::Noent)
+					}
+				} else {
+					self.xattrs.read().await.get(name)
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					if let Some(node) = self.inner.read().await.get(component) {
+						node.traverse_setxattr(components, name, value, flags)
+					} else {
+						Err(Errno::Noent)
+					}
+				} else {
+					self.xattrs.write().await.set(name, value, flags)
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					if let Some(node) = self.inner.read().await.get(component) {
+						node.traverse_listxattr(components)
+					} else {
+						Err(Errno::Noent)
+					}
+				} else {
+					Ok(self.xattrs.read().await.list())
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<()> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					if let Some(node) = self.inner.read().await.get(component) {
+						node.traverse_removexattr(components, name)
+					} else {
+						Err(Errno::Noent)
+					}
+				} else {
+					self.xattrs.write().await.remove(name)
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					if let Some(node) = self.inner.read().await.get(component) {
+						node.traverse_readlink(components)
+					} else {
+						Err(Errno::Noent)
+					}
+				} else {
+					Err(Errno::Inval)
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_rename(&self, from: &mut Vec<&str>, to: &mut Vec<&str>) -> io::Result<()> {
+		// Both paths continue through the same child, which decides on its own.
+		if from.len() > 1 && to.len() > 1 && from.last() == to.last() {
+			let component = from.pop().unwrap();
+			to.pop();
+
+			return block_on(
+				async {
+					if let Some(node) = self.inner.read().await.get(component) {
+						node.traverse_rename(from, to)
+					} else {
+						Err(Errno::Noent)
+					}
+				},
+				None,
+			);
+		}
+
+		if from.is_empty() || to.is_empty() {
+			return Err(Errno::Busy);
+		}
+
+		let source = self.traverse_lstat(&mut from.clone())?;
+		if from == to {
+			return Ok(());
+		}
+		// A directory cannot become a subdirectory of itself.
+		if to.len() > from.len() && to.ends_with(from) {
+			return Err(Errno::Inval);
+		}
+
+		if to.len() > 1 {
+			let parent = self.traverse_lstat(&mut to[1..].to_vec())?;
+			if !parent.st_mode.contains(AccessPermission::S_IFDIR) {
+				return Err(Errno::Notdir);
+			}
+		}
+		// Nodes can only be moved within the same in-memory file system.
+		let usage = &self.inode.usage;
+		if !Arc::ptr_eq(&self.traverse_mem_usage(&mut from.clone())?, usage)
+			|| !Arc::ptr_eq(&self.traverse_mem_usage(&mut to[1..].to_vec())?, usage)
+		{
+			return Err(Errno::Xdev);
+		}
+
+		// The target is checked before anything is modified.
+		let target = self.traverse_lstat(&mut to.clone()).ok();
+		if let Some(target) = target {
+			// The target must not be the mount point of another file system.
+			if !Arc::ptr_eq(&self.traverse_mem_usage(&mut to.clone())?, usage) {
+				return Err(Errno::Busy);
+			}
+
+			let source_is_dir = source.st_mode.contains(AccessPermission::S_IFDIR);
+			let target_is_dir = target.st_mode.contains(AccessPermission::S_IFDIR);
+			match (source_is_dir, target_is_dir) {
+				(true, true) => {
+					if !self.traverse_readdir(&mut to.clone())?.is_empty() {
+						return Err(Errno::Notempty);
+					}
+				}
+				(true, false) => return Err(Errno::Notdir),
+				(false, true) => return Err(Errno::Isdir),
+				(false, false) => {}
+			}
+		}
+
+		// Detached nodes are put back if the rename fails.
+		let restore =
+			|components: &[&str],
+			 node: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>| {
+				if let Err((e, _)) = self.traverse_attach(&mut components.to_vec(), node) {
+					error!(
+						"Unable to restore {} after a failed rename: {e:?}",
+						relative_path(components)
+					);
+				}
+			};
+
+		let node = self.traverse_detach(&mut from.clone())?;
+		let replaced = match target {
+			Some(_) => match self.traverse_detach(&mut to.clone()) {
+				Ok(replaced) => Some(replaced),
+				Err(e) => {
+					restore(from.as_slice(), node);
+					return Err(e);
+				}
+			},
+			None => None,
+		};
+
+		if let Err((e, node)) = self.traverse_attach(&mut to.clone(), node) {
+			if let Some(replaced) = replaced {
+				restore(to.as_slice(), replaced);
+			}
+			restore(from.as_slice(), node);
+			return Err(e);
+		}
+
+		Ok(())
+	}
+
+	fn traverse_detach(
+		&self,
+		components: &mut Vec<&str>,
+	) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		block_on(
+			async {
+				let component = components.pop().ok_or(Errno::Busy)?;
+
+				if components.is_empty() {
+					self.inner
+						.write()
+						.await
+						.remove(component)
+						.ok_or(Errno::Noent)
+				} else if let Some(directory) = self.inner.read().await.get(component) {
+					directory.traverse_detach(components)
+				} else {
+					Err(Errno::Noent)
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_attach(
+		&self,
+		components: &mut Vec<&str>,
+		node: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	) -> Result<
+		(),
+		(
+			Errno,
+			Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+		),
+	> {
+		block_on(
+			async {
+				let Some(component) = components.pop() else {
+					return Err((Errno::Busy, node));
+				};
+
+				if components.is_empty() {
+					let mut guard = self.inner.write().await;
+					if guard.contains_key(component) {
+						return Err((Errno::Exist, node));
+					}
+					guard.insert(String::from(component), node);
+					Ok(())
+				} else if let Some(directory) = self.inner.read().await.get(component) {
+					directory.traverse_attach(components, node)
+				} else {
+					Err((Errno::Noent, node))
+				}
+			},
+			None,
+		)
+	}
+
+	fn traverse_mem_usage(&self, components: &mut Vec<&str>) -> io::Result<Arc<MemUsage>> {
+		block_on(
+			async {
+				if let Some(component) = components.pop() {
+					if let Some(node) = self.inner.read().await.get(component) {
+						node.traverse_mem_usage(components)
+					} else {
+						Err(Errno::Noent)
+					}
+				} else {
+					Ok(self.inode.usage.clone())
+				}
+			},
+			None,
+		)
+	}
+
 	fn traverse_mount(
 		&self,
 		components: &mut Vec<&str>,
@@ -759,7 +2053,7 @@ impl VfsNode for MemDirectory {
 					let name = String::from(component);
 
 					if components.is_empty() {
-						let file = RomFile::new(data, mode);
+						let file = RomFile::new(data, mode, &self.inode.usage)?;
 						self.inner.write().await.insert(name, Box::new(file));
 						return Ok(());
 					}
@@ -774,4 +2068,207 @@ impl VfsNode for MemDirectory {
 			None,
 		)
 	}
+
+	fn traverse_symlink(&self, components: &mut Vec<&str>, target: &str) -> io::Result<()> {
+		block_on(
+			async {
+				let component = components.pop().ok_or(Errno::Exist)?;
+
+				if components.is_empty() {
+					let mut guard = self.inner.write().await;
+					if guard.contains_key(component) {
+						return Err(Errno::Exist);
+					}
+					let symlink = MemSymlink::new(target, &self.inode.usage)?;
+					guard.insert(String::from(component), Box::new(symlink));
+					Ok(())
+				} else if let Some(directory) = self.inner.read().await.get(component) {
+					directory.traverse_symlink(components, target)
+				} else {
+					Err(Errno::Noent)
+				}
+			},
+			None,
+		)
+	}
+
+	/// Replaces the entry, if any, by a whiteout.
+	fn traverse_whiteout(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		block_on(
+			async {
+				let component = components.pop().ok_or(Errno::Busy)?;
+
+				if components.is_empty() {
+					let whiteout = MemWhiteout {
+						inode: self.inode.usage.alloc_inode()?,
+					};
+					self.inner
+						.write()
+						.await
+						.insert(String::from(component), Box::new(whiteout));
+					Ok(())
+				} else if let Some(directory) = self.inner.read().await.get(component) {
+					directory.traverse_whiteout(components)
+				} else {
+					Err(Errno::Noent)
+				}
+			},
+			None,
+		)
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_size() {
+		assert_eq!(parse_size("4096", 0), Some(4096));
+		assert_eq!(parse_size("64k", 0), Some(64 << 10));
+		assert_eq!(parse_size("16M", 0), Some(16 << 20));
+		assert_eq!(parse_size("2g", 0), Some(2 << 30));
+		assert_eq!(parse_size("25%", 1000), Some(250));
+		assert_eq!(parse_size("150%", 1000), None);
+		assert_eq!(parse_size("12x", 0), None);
+		assert_eq!(parse_size("", 0), None);
+	}
+
+	#[test]
+	fn test_limits() {
+		let size = (2 * BLOCK_SIZE).to_string();
+		let usage = MemUsage::with_limits(Some(&size), Some("2")).unwrap();
+		assert_eq!(usage.max_blocks, Some(2));
+		assert_eq!(usage.max_inodes, Some(2));
+
+		assert!(MemUsage::with_limits(None, Some("1k")).is_err());
+		assert!(MemUsage::with_limits(None, Some("50%")).is_err());
+		assert!(MemUsage::with_limits(Some("1x"), None).is_err());
+	}
+
+	#[test]
+	fn test_inode_limit() {
+		let usage = MemUsage::with_limits(None, Some("2")).unwrap();
+		let first = usage.alloc_inode().unwrap();
+		let second = usage.alloc_inode().unwrap();
+		assert_eq!(usage.alloc_inode().unwrap_err(), Errno::Nospc);
+
+		drop(first);
+		let _third = usage.alloc_inode().unwrap();
+		assert_eq!(usage.statfs().f_ffree, 0);
+		drop(second);
+		assert_eq!(usage.statfs().f_ffree, 1);
+	}
+
+	#[test]
+	fn test_block_limit() {
+		let usage = usage(Some(2));
+		let mut data = SparseData::default();
+		assert_eq!(
+			data.write(0, &[1; 3 * CHUNK_SIZE], &usage).unwrap_err(),
+			Errno::Nospc
+		);
+
+		data.write(0, &[1; 2 * CHUNK_SIZE], &usage).unwrap();
+		assert_eq!(
+			data.write(2 * CHUNK_SIZE, &[1], &usage).unwrap_err(),
+			Errno::Nospc
+		);
+		// Overwriting allocated blocks needs no further space.
+		data.write(CHUNK_SIZE, &[2; 10], &usage).unwrap();
+
+		data.set_len(CHUNK_SIZE, &usage);
+		assert_eq!(usage.blocks.load(Ordering::Relaxed), 1);
+		data.write(2 * CHUNK_SIZE, &[1], &usage).unwrap();
+	}
+
+	fn usage(max_blocks: Option<u64>) -> MemUsage {
+		MemUsage {
+			max_blocks,
+			max_inodes: None,
+			blocks: AtomicU64::new(0),
+			inodes: AtomicU64::new(0),
+		}
+	}
+
+	#[test]
+	fn test_sparse_write_read() {
+		let usage = usage(None);
+		let mut data = SparseData::default();
+		data.write(3 * CHUNK_SIZE + 10, b"hello", &usage).unwrap();
+		assert_eq!(data.len(), 3 * CHUNK_SIZE + 15);
+		assert_eq!(data.blocks(), 1);
+		assert_eq!(usage.blocks.load(Ordering::Relaxed), 1);
+
+		let mut buf = [0xffu8; 20];
+		assert_eq!(data.read(3 * CHUNK_SIZE, &mut buf), 15);
+		assert_eq!(&buf[..10], &[0; 10]);
+		assert_eq!(&buf[10..15], b"hello");
+		assert_eq!(data.read(100, &mut buf), 20);
+		assert_eq!(buf, [0; 20]);
+	}
+
+	#[test]
+	fn test_sparse_seek() {
+		let usage = usage(None);
+		let mut data = SparseData::default();
+		data.write(CHUNK_SIZE, &[1; 10], &usage).unwrap();
+		data.write(2 * CHUNK_SIZE, &[1; 10], &usage).unwrap();
+		data.set_len(5 * CHUNK_SIZE, &usage);
+
+		assert_eq!(data.seek_data(0), Some(CHUNK_SIZE));
+		assert_eq!(data.seek_data(CHUNK_SIZE + 5), Some(CHUNK_SIZE + 5));
+		assert_eq!(data.seek_data(3 * CHUNK_SIZE), None);
+		assert_eq!(data.seek_hole(0), 0);
+		assert_eq!(data.seek_hole(CHUNK_SIZE + 5), 3 * CHUNK_SIZE);
+		assert_eq!(data.seek_hole(4 * CHUNK_SIZE), 4 * CHUNK_SIZE);
+	}
+
+	#[test]
+	fn test_sparse_punch_hole() {
+		let usage = usage(None);
+		let mut data = SparseData::default();
+		data.write(0, &[1; 3 * CHUNK_SIZE], &usage).unwrap();
+		data.punch_hole(10, 2 * CHUNK_SIZE + 10, &usage);
+		assert_eq!(data.len(), 3 * CHUNK_SIZE);
+		assert_eq!(data.blocks(), 2);
+		assert_eq!(usage.blocks.load(Ordering::Relaxed), 2);
+
+		let mut buf = vec![0u8; 3 * CHUNK_SIZE];
+		data.read(0, &mut buf);
+		assert!(buf[..10].iter().all(|&b| b == 1));
+		assert!(buf[10..2 * CHUNK_SIZE + 10].iter().all(|&b| b == 0));
+		assert!(buf[2 * CHUNK_SIZE + 10..].iter().all(|&b| b == 1));
+	}
+
+	#[test]
+	fn test_sparse_truncate() {
+		let usage = usage(None);
+		let mut data = SparseData::default();
+		data.write(0, &[1; 2 * CHUNK_SIZE], &usage).unwrap();
+		data.set_len(100, &usage);
+		assert_eq!(data.blocks(), 1);
+		data.set_len(2 * CHUNK_SIZE, &usage);
+		assert_eq!(data.blocks(), 1);
+
+		let mut buf = vec![0xffu8; 2 * CHUNK_SIZE];
+		data.read(0, &mut buf);
+		assert!(buf[..100].iter().all(|&b| b == 1));
+		assert!(buf[100..].iter().all(|&b| b == 0));
+	}
+
+	#[test]
+	fn test_sparse_limit() {
+		let usage = usage(Some(2));
+		let mut data = SparseData::default();
+		data.write(0, &[1; 10], &usage).unwrap();
+		data.write(10 * CHUNK_SIZE, &[1; 10], &usage).unwrap();
+		assert_eq!(
+			data.write(5 * CHUNK_SIZE, &[1; 10], &usage),
+			Err(Errno::Nospc)
+		);
+		data.set_len(0, &usage);
+		assert_eq!(usage.blocks.load(Ordering::Relaxed), 0);
+		data.write(5 * CHUNK_SIZE, &[1; 10], &usage).unwrap();
+	}
 }
@@ -1,24 +1,41 @@
+mod dev;
+#[cfg(feature = "ext4")]
+mod ext4;
+#[cfg(feature = "fat")]
+mod fat;
+mod fstab;
 #[cfg(all(feature = "fuse", feature = "pci"))]
 pub(crate) mod fuse;
 mod mem;
+mod overlay;
+mod proc;
+mod readonly;
 mod uhyve;
 
 use alloc::boxed::Box;
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem::{MaybeUninit, offset_of};
 use core::ops::BitAnd;
 
+use align_address::Align;
+use async_lock::Mutex;
 use async_trait::async_trait;
+use dev::DevDirectory;
 use embedded_io::{Read, Write};
 use hermit_sync::{InterruptSpinMutex, OnceCell};
-use mem::MemDirectory;
+use mem::{MemDirectory, MemUsage};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
+use proc::ProcDirectory;
+use readonly::ReadOnlyDirectory;
 
 use crate::errno::Errno;
 use crate::executor::block_on;
 use crate::fd::{AccessPermission, ObjectInterface, OpenOption, insert_object, remove_object};
 use crate::io;
+use crate::syscalls::Dirent64;
 use crate::time::{SystemTime, timespec};
 
 static FILESYSTEM: OnceCell<Filesystem> = OnceCell::new();
@@ -28,14 +45,22 @@ static WORKING_DIRECTORY: InterruptSpinMutex<Option<String>> = InterruptSpinMute
 static UMASK: InterruptSpinMutex<AccessPermission> =
 	InterruptSpinMutex::new(AccessPermission::from_bits_retain(0o777));
 
+/// Maximum number of symbolic links, which are followed during a lookup
+const MAX_SYMLINKS: usize = 40;
+
 #[derive(Debug, Clone)]
 pub struct DirectoryEntry {
 	pub name: String,
+	pub file_type: FileType,
 }
 
 impl DirectoryEntry {
 	pub fn new(name: String) -> Self {
-		Self { name }
+		Self::with_type(name, FileType::Unknown)
+	}
+
+	pub fn with_type(name: String, file_type: FileType) -> Self {
+		Self { name, file_type }
 	}
 }
 
@@ -46,6 +71,21 @@ pub(crate) enum NodeKind {
 	File,
 	/// Node represent a directory
 	Directory,
+	/// Node represent a symbolic link
+	Symlink,
+	/// Node hides an entry of a lower layer
+	Whiteout,
+}
+
+impl From<NodeKind> for FileType {
+	fn from(kind: NodeKind) -> Self {
+		match kind {
+			NodeKind::File => FileType::RegularFile,
+			NodeKind::Directory => FileType::Directory,
+			NodeKind::Symlink => FileType::SymbolicLink,
+			NodeKind::Whiteout => FileType::Whiteout,
+		}
+	}
 }
 
 /// VfsNode represents an internal node of the ramdisk.
@@ -125,35 +165,249 @@ pub(crate) trait VfsNode: core::fmt::Debug {
 	) -> io::Result<()> {
 		Err(Errno::Nosys)
 	}
+
+	/// Helper function to get file system statistics
+	fn traverse_statfs(&self, _components: &mut Vec<&str>) -> io::Result<StatFs> {
+		Err(Errno::Nosys)
+	}
+
+	/// Helper function to change the access and modification time
+	fn traverse_utimens(
+		&self,
+		_components: &mut Vec<&str>,
+		_atime: Option<timespec>,
+		_mtime: Option<timespec>,
+	) -> io::Result<()> {
+		Err(Errno::Nosys)
+	}
+
+	/// Helper function to change the owner and the group
+	fn traverse_chown(
+		&self,
+		_components: &mut Vec<&str>,
+		_uid: Option<u32>,
+		_gid: Option<u32>,
+	) -> io::Result<()> {
+		Err(Errno::Nosys)
+	}
+
+	/// Helper function to read an extended attribute
+	fn traverse_getxattr(&self, _components: &mut Vec<&str>, _name: &str) -> io::Result<Vec<u8>> {
+		Err(Errno::Opnotsupp)
+	}
+
+	/// Helper function to create or replace an extended attribute
+	fn traverse_setxattr(
+		&self,
+		_components: &mut Vec<&str>,
+		_name: &str,
+		_value: &[u8],
+		_flags: XattrFlags,
+	) -> io::Result<()> {
+		Err(Errno::Opnotsupp)
+	}
+
+	/// Helper function to list the names of all extended attributes
+	fn traverse_listxattr(&self, _components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		Err(Errno::Opnotsupp)
+	}
+
+	/// Helper function to remove an extended attribute
+	fn traverse_removexattr(&self, _components: &mut Vec<&str>, _name: &str) -> io::Result<()> {
+		Err(Errno::Opnotsupp)
+	}
+
+	/// Helper function to read the target of a symbolic link
+	fn traverse_readlink(&self, _components: &mut Vec<&str>) -> io::Result<String> {
+		Err(Errno::Nosys)
+	}
+
+	/// Helper function to create a symbolic link
+	fn traverse_symlink(&self, _components: &mut Vec<&str>, _target: &str) -> io::Result<()> {
+		Err(Errno::Nosys)
+	}
+
+	/// Helper function to create a whiteout, which hides an entry of a lower layer
+	fn traverse_whiteout(&self, _components: &mut Vec<&str>) -> io::Result<()> {
+		Err(Errno::Nosys)
+	}
+
+	/// Helper function to rename a node within the file system
+	fn traverse_rename(&self, _from: &mut Vec<&str>, _to: &mut Vec<&str>) -> io::Result<()> {
+		Err(Errno::Nosys)
+	}
+
+	/// Helper function to remove a node from the tree without destroying it
+	fn traverse_detach(
+		&self,
+		_components: &mut Vec<&str>,
+	) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		Err(Errno::Nosys)
+	}
+
+	/// Helper function to insert a node, which has been removed by `traverse_detach`.
+	/// The node is handed back if it cannot be inserted.
+	#[allow(clippy::type_complexity)]
+	fn traverse_attach(
+		&self,
+		_components: &mut Vec<&str>,
+		node: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	) -> Result<
+		(),
+		(
+			Errno,
+			Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+		),
+	> {
+		Err((Errno::Nosys, node))
+	}
+
+	/// Helper function to get the accounting of the in-memory file system, to
+	/// which a node belongs
+	fn traverse_mem_usage(&self, _components: &mut Vec<&str>) -> io::Result<Arc<MemUsage>> {
+		Err(Errno::Xdev)
+	}
 }
 
-#[derive(Debug, Clone)]
-struct DirectoryReader(Vec<DirectoryEntry>);
+#[derive(Debug)]
+pub(crate) struct DirectoryReader {
+	/// Directory entries
+	entries: Vec<DirectoryEntry>,
+	/// Attributes of the directory
+	attr: FileAttr,
+	read_idx: Mutex<usize>,
+	/// Absolute path of the directory
+	path: OnceCell<String>,
+}
 
 impl DirectoryReader {
-	pub fn new(data: Vec<DirectoryEntry>) -> Self {
-		Self(data)
+	pub fn new(entries: Vec<DirectoryEntry>, attr: FileAttr) -> Self {
+		Self {
+			entries,
+			attr,
+			read_idx: Mutex::new(0),
+			path: OnceCell::new(),
+		}
 	}
 }
 
+/// Writes the entries of a directory stream, starting at the entry
+/// `read_idx`, to `buf` and advances `read_idx` past the written entries.
+///
+/// Returns the number of written bytes.
+fn write_dirents<'a>(
+	entries: impl Iterator<Item = (&'a str, FileType)>,
+	read_idx: &mut usize,
+	buf: &mut [MaybeUninit<u8>],
+) -> usize {
+	let mut buf_offset: usize = 0;
+	let mut ret = 0;
+	for (name, file_type) in entries.skip(*read_idx) {
+		let namelen = name.len();
+
+		let dirent_len = offset_of!(Dirent64, d_name) + namelen + 1;
+		let next_dirent = (buf_offset + dirent_len).align_up(align_of::<Dirent64>());
+
+		if next_dirent > buf.len() {
+			// target buffer full -> we return the nr. of bytes written (like linux does)
+			break;
+		}
+
+		*read_idx += 1;
+
+		// could be replaced with slice_as_ptr once maybe_uninit_slice is stabilized.
+		let target_dirent = buf[buf_offset].as_mut_ptr().cast::<Dirent64>();
+
+		unsafe {
+			target_dirent.write(Dirent64 {
+				d_ino: 1,
+				d_off: 0,
+				d_reclen: (dirent_len.align_up(align_of::<Dirent64>()))
+					.try_into()
+					.unwrap(),
+				d_type: file_type,
+				d_name: PhantomData {},
+			});
+			let nameptr = core::ptr::from_mut(&mut (*(target_dirent)).d_name).cast::<u8>();
+			core::ptr::copy_nonoverlapping(name.as_bytes().as_ptr().cast::<u8>(), nameptr, namelen);
+			nameptr.add(namelen).write(0); // zero termination
+		}
+
+		buf_offset = next_dirent;
+		ret = buf_offset;
+	}
+	ret
+}
+
+/// lseek for a directory stream is the equivalent for seekdir on linux.
+/// Any other offset than 0 is not supported. (Mostly because it doesn't make any sense, as
+/// userspace applications have no way of knowing valid offsets)
+fn seek_dirents(read_idx: &mut usize, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+	if whence != SeekWhence::Set || offset != 0 {
+		error!("Invalid offset for directory lseek ({offset})");
+		return Err(Errno::Inval);
+	}
+	*read_idx = 0;
+	Ok(0)
+}
+
+/// Converts the reversed path components to a path relative to the mount point.
+fn relative_path(components: &[&str]) -> String {
+	let mut path = String::new();
+	for component in components.iter().rev() {
+		if !path.is_empty() {
+			path.push('/');
+		}
+		path.push_str(component);
+	}
+	path
+}
+
 #[async_trait]
 impl ObjectInterface for DirectoryReader {
-	async fn getdents(&self, _buf: &mut [core::mem::MaybeUninit<u8>]) -> io::Result<usize> {
-		let _ = &self.0; // Dummy statement to avoid warning for the moment
-		unimplemented!()
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(self.attr)
+	}
+
+	async fn getdents(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		let mut read_idx = self.read_idx.lock().await;
+		Ok(write_dirents(
+			self.entries
+				.iter()
+				.map(|entry| (entry.name.as_str(), entry.file_type)),
+			&mut read_idx,
+			buf,
+		))
+	}
+
+	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+		seek_dirents(&mut *self.read_idx.lock().await, offset, whence)
+	}
+
+	async fn dirpath(&self) -> io::Result<String> {
+		self.path.get().cloned().ok_or(Errno::Notdir)
+	}
+
+	async fn set_dirpath(&self, path: &str) {
+		let _ = self.path.set(path.to_string());
 	}
 }
 
 #[derive(Debug)]
 pub(crate) struct Filesystem {
-	root: MemDirectory,
+	root: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
 }
 
 impl Filesystem {
 	pub fn new() -> Self {
-		Self {
-			root: MemDirectory::new(AccessPermission::from_bits(0o777).unwrap()),
-		}
+		Self::with_root(Box::new(MemDirectory::new(
+			AccessPermission::from_bits(0o777).unwrap(),
+		)))
+	}
+
+	/// Creates a file system, whose root directory is `root`.
+	pub fn with_root(root: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>) -> Self {
+		Self { root }
 	}
 
 	/// Tries to open file at given path.
@@ -169,7 +423,16 @@ impl Filesystem {
 		components.reverse();
 		components.pop();
 
-		self.root.traverse_open(&mut components, opt, mode)
+		let obj = self.root.traverse_open(&mut components, opt, mode)?;
+		block_on(
+			async {
+				obj.read().await.set_dirpath(path).await;
+				Ok(())
+			},
+			None,
+		)?;
+
+		Ok(obj)
 	}
 
 	/// Unlinks a file given by path
@@ -207,9 +470,9 @@ impl Filesystem {
 
 	pub fn opendir(&self, path: &str) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
 		debug!("Open directory {path}");
-		Ok(Arc::new(async_lock::RwLock::new(DirectoryReader::new(
-			self.readdir(path)?,
-		))))
+		let reader = DirectoryReader::new(self.readdir(path)?, self.stat(path)?);
+		let _ = reader.path.set(path.to_string());
+		Ok(Arc::new(async_lock::RwLock::new(reader)))
 	}
 
 	/// List given directory
@@ -249,6 +512,152 @@ impl Filesystem {
 		self.root.traverse_lstat(&mut components)
 	}
 
+	/// statfs
+	pub fn statfs(&self, path: &str) -> io::Result<StatFs> {
+		debug!("Getting file system statistics {path}");
+
+		if path.trim() == "/" {
+			let mut components: Vec<&str> = Vec::new();
+			return self.root.traverse_statfs(&mut components);
+		}
+
+		let mut components: Vec<&str> = path.split('/').collect();
+		components.reverse();
+		components.pop();
+
+		self.root.traverse_statfs(&mut components)
+	}
+
+	/// Changes the timestamps of the node given by path
+	pub fn utimens(
+		&self,
+		path: &str,
+		atime: Option<timespec>,
+		mtime: Option<timespec>,
+	) -> io::Result<()> {
+		debug!("Changing timestamps of {path}");
+
+		let mut components: Vec<&str> = path.split('/').collect();
+		components.reverse();
+		components.pop();
+
+		self.root.traverse_utimens(&mut components, atime, mtime)
+	}
+
+	/// Changes owner and group of the node given by path
+	pub fn chown(&self, path: &str, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+		debug!("Changing owner of {path}");
+
+		let mut components: Vec<&str> = path.split('/').collect();
+		components.reverse();
+		components.pop();
+
+		self.root.traverse_chown(&mut components, uid, gid)
+	}
+
+	/// Returns the value of the extended attribute `name` of the node given by path
+	pub fn getxattr(&self, path: &str, name: &str) -> io::Result<Vec<u8>> {
+		debug!("Getting extended attribute {name} of {path}");
+
+		let mut components: Vec<&str> = path.split('/').collect();
+		components.reverse();
+		components.pop();
+
+		self.root.traverse_getxattr(&mut components, name)
+	}
+
+	/// Sets the extended attribute `name` of the node given by path
+	pub fn setxattr(
+		&self,
+		path: &str,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		debug!("Setting extended attribute {name} of {path}");
+
+		let mut components: Vec<&str> = path.split('/').collect();
+		components.reverse();
+		components.pop();
+
+		self.root
+			.traverse_setxattr(&mut components, name, value, flags)
+	}
+
+	/// Lists the extended attributes of the node given by path
+	pub fn listxattr(&self, path: &str) -> io::Result<Vec<String>> {
+		debug!("Listing extended attributes of {path}");
+
+		let mut components: Vec<&str> = path.split('/').collect();
+		components.reverse();
+		components.pop();
+
+		self.root.traverse_listxattr(&mut components)
+	}
+
+	/// Removes the extended attribute `name` of the node given by path
+	pub fn removexattr(&self, path: &str, name: &str) -> io::Result<()> {
+		debug!("Removing extended attribute {name} of {path}");
+
+		let mut components: Vec<&str> = path.split('/').collect();
+		components.reverse();
+		components.pop();
+
+		self.root.traverse_removexattr(&mut components, name)
+	}
+
+	/// Reads the target of the symbolic link given by path
+	pub fn readlink(&self, path: &str) -> io::Result<String> {
+		debug!("Reading link {path}");
+
+		let mut components: Vec<&str> = path.split('/').collect();
+		components.reverse();
+		components.pop();
+
+		self.root.traverse_readlink(&mut components)
+	}
+
+	/// Follows symbolic links in the last component of path
+	///
+	/// Returns the path of the first node that is not a symbolic link.
+	pub fn follow(&self, path: &str) -> io::Result<String> {
+		let mut path = String::from(path);
+
+		for _ in 0..MAX_SYMLINKS {
+			let attr = self.lstat(&path)?;
+			if (attr.st_mode & AccessPermission::S_IFMT).bits() != AccessPermission::S_IFLNK.bits()
+			{
+				return Ok(path);
+			}
+
+			let target = self.readlink(&path)?;
+			path = if target.starts_with('/') {
+				target
+			} else {
+				let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+				format!("{parent}/{target}")
+			};
+		}
+
+		Err(Errno::Loop)
+	}
+
+	/// Renames the node at path `from` to `to`
+	pub fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+		debug!("Renaming {from} to {to}");
+
+		let mut from_components: Vec<&str> = from.split('/').collect();
+		from_components.reverse();
+		from_components.pop();
+
+		let mut to_components: Vec<&str> = to.split('/').collect();
+		to_components.reverse();
+		to_components.pop();
+
+		self.root
+			.traverse_rename(&mut from_components, &mut to_components)
+	}
+
 	/// Create new backing-fs at mountpoint mntpath
 	pub fn mount(
 		&self,
@@ -311,6 +720,35 @@ pub struct FileAttr {
 	pub st_ctim: timespec,
 }
 
+/// File system statistics, laid out like Linux' `struct statfs`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct StatFs {
+	/// type of the file system
+	pub f_type: i64,
+	/// optimal transfer block size
+	pub f_bsize: i64,
+	/// total data blocks in the file system
+	pub f_blocks: u64,
+	/// free blocks in the file system
+	pub f_bfree: u64,
+	/// free blocks available to unprivileged users
+	pub f_bavail: u64,
+	/// total file nodes in the file system
+	pub f_files: u64,
+	/// free file nodes in the file system
+	pub f_ffree: u64,
+	/// file system id
+	pub f_fsid: [i32; 2],
+	/// maximum length of file names
+	pub f_namelen: i64,
+	/// fragment size
+	pub f_frsize: i64,
+	/// mount flags of the file system
+	pub f_flags: i64,
+	pub f_spare: [i64; 4],
+}
+
 #[derive(TryFromPrimitive, IntoPrimitive, PartialEq, Eq, Clone, Copy, Debug)]
 #[repr(u8)]
 pub enum FileType {
@@ -335,39 +773,218 @@ pub enum SeekWhence {
 	Hole = 4,
 }
 
-pub(crate) fn init() {
-	const VERSION: &str = env!("CARGO_PKG_VERSION");
-	const UTC_BUILT_TIME: &str = build_time::build_time_utc!();
+/// Special value of `tv_nsec`, which sets the timestamp to the current time
+pub const UTIME_NOW: i32 = 0x3fff_ffff;
+/// Special value of `tv_nsec`, which leaves the timestamp unchanged
+pub const UTIME_OMIT: i32 = 0x3fff_fffe;
+
+/// Converts the timestamps passed to `utimensat` and `futimens` to the new
+/// access and modification time, where `None` leaves the time unchanged.
+fn resolve_times(
+	times: Option<&[timespec; 2]>,
+) -> io::Result<(Option<timespec>, Option<timespec>)> {
+	let now = timespec::from_usec(crate::arch::kernel::systemtime::now_micros() as i64);
+	let Some(times) = times else {
+		return Ok((Some(now), Some(now)));
+	};
+
+	let resolve = |time: timespec| match time.tv_nsec {
+		UTIME_NOW => Ok(Some(now)),
+		UTIME_OMIT => Ok(None),
+		0..1_000_000_000 => Ok(Some(time)),
+		_ => Err(Errno::Inval),
+	};
+
+	Ok((resolve(times[0])?, resolve(times[1])?))
+}
 
-	FILESYSTEM.set(Filesystem::new()).unwrap();
-	FILESYSTEM
-		.get()
-		.unwrap()
-		.mkdir("/tmp", AccessPermission::from_bits(0o777).unwrap())
-		.expect("Unable to create /tmp");
-	FILESYSTEM
-		.get()
-		.unwrap()
-		.mkdir("/proc", AccessPermission::from_bits(0o777).unwrap())
-		.expect("Unable to create /proc");
+/// Maximum length of the name of an extended attribute
+pub const XATTR_NAME_MAX: usize = 255;
+/// Maximum size of the value of an extended attribute
+pub const XATTR_SIZE_MAX: usize = 65536;
+/// Maximum size of the list of extended attribute names
+pub const XATTR_LIST_MAX: usize = 65536;
+
+bitflags! {
+	/// Flags of `setxattr`
+	#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+	pub struct XattrFlags: i32 {
+		/// Fail if the attribute already exists
+		const XATTR_CREATE = 1;
+		/// Fail if the attribute does not exist
+		const XATTR_REPLACE = 2;
+	}
+}
+
+bitflags! {
+	/// Modes of `fallocate`
+	#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+	pub struct FallocateFlags: i32 {
+		/// Do not change the size of the file
+		const FALLOC_FL_KEEP_SIZE = 0x01;
+		/// Deallocate the range, which must be combined with `FALLOC_FL_KEEP_SIZE`
+		const FALLOC_FL_PUNCH_HOLE = 0x02;
+		/// Zero the range
+		const FALLOC_FL_ZERO_RANGE = 0x10;
+	}
+}
 
-	if let Ok(mut file) = File::create("/proc/version") {
-		if write!(file, "HermitOS version {VERSION} # UTC {UTC_BUILT_TIME}").is_err() {
-			error!("Unable to write in /proc/version");
+/// Checks the arguments of `fallocate` like Linux does.
+pub(crate) fn check_fallocate(mode: i32, offset: i64, len: i64) -> io::Result<FallocateFlags> {
+	if offset < 0 || len <= 0 {
+		return Err(Errno::Inval);
+	}
+	if offset.checked_add(len).is_none() {
+		return Err(Errno::Fbig);
+	}
+
+	let mode = FallocateFlags::from_bits(mode).ok_or(Errno::Opnotsupp)?;
+	if mode.contains(FallocateFlags::FALLOC_FL_PUNCH_HOLE) {
+		if !mode.contains(FallocateFlags::FALLOC_FL_KEEP_SIZE) {
+			return Err(Errno::Opnotsupp);
+		}
+		if mode.contains(FallocateFlags::FALLOC_FL_ZERO_RANGE) {
+			return Err(Errno::Inval);
 		}
+	}
+
+	Ok(mode)
+}
+
+/// Checks that `name` is a valid name of an extended attribute.
+///
+/// Like on Linux, the name must start with one of the known namespaces.
+fn check_xattr_name(name: &str) -> io::Result<()> {
+	const NAMESPACES: [&str; 4] = ["security.", "system.", "trusted.", "user."];
+
+	if name.is_empty() || name.len() > XATTR_NAME_MAX {
+		return Err(Errno::Range);
+	}
+
+	if NAMESPACES
+		.iter()
+		.any(|ns| name.len() > ns.len() && name.starts_with(ns))
+	{
+		Ok(())
 	} else {
-		error!("Unable to create /proc/version");
+		Err(Errno::Opnotsupp)
 	}
+}
 
-	let mut cwd = WORKING_DIRECTORY.lock();
-	*cwd = Some("/tmp".to_string());
-	drop(cwd);
+/// Checks the arguments of `setxattr`.
+fn check_setxattr(name: &str, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+	check_xattr_name(name)?;
+	if value.len() > XATTR_SIZE_MAX {
+		return Err(Errno::Toobig);
+	}
+	if flags.contains(XattrFlags::XATTR_CREATE | XattrFlags::XATTR_REPLACE) {
+		return Err(Errno::Inval);
+	}
+	Ok(())
+}
+
+/// Special value for the directory file descriptor of the `*at` functions,
+/// which resolves relative paths against the current working directory
+pub const AT_FDCWD: FileDescriptor = -100;
+
+bitflags! {
+	/// Flags of the `*at` functions
+	#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+	pub struct AtFlags: i32 {
+		/// Do not follow a symbolic link at the end of the path
+		const AT_SYMLINK_NOFOLLOW = 0x100;
+		/// Remove a directory instead of a file
+		const AT_REMOVEDIR = 0x200;
+		/// Operate on the directory file descriptor itself if the path is empty
+		const AT_EMPTY_PATH = 0x1000;
+	}
+}
+
+bitflags! {
+	/// Flags of a mounted file system
+	#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+	pub struct MountFlags: u64 {
+		/// Mount read-only
+		const MS_RDONLY = 1;
+		/// Ignore suid and sgid bits
+		const MS_NOSUID = 2;
+		/// Disallow access to device special files
+		const MS_NODEV = 4;
+		/// Disallow program execution
+		const MS_NOEXEC = 8;
+	}
+}
+
+/// Entry of the mount table
+#[derive(Debug, Clone)]
+pub(crate) struct MountEntry {
+	/// Device or host directory, which provides the file system
+	pub source: String,
+	/// Absolute path of the mount point
+	pub target: String,
+	/// Type of the file system, e.g. `tmpfs`
+	pub fs_type: &'static str,
+	pub flags: MountFlags,
+}
+
+/// All mounted file systems in the order they were mounted
+static MOUNT_TABLE: InterruptSpinMutex<Vec<MountEntry>> = InterruptSpinMutex::new(Vec::new());
+
+pub(crate) fn init() {
+	let specs = fstab::specs();
+	let (filesystem, entry) = match fstab::root(&specs) {
+		Some((entry, node)) => (Filesystem::with_root(node), entry),
+		None => (
+			Filesystem::new(),
+			MountEntry {
+				source: "rootfs".to_string(),
+				target: "/".to_string(),
+				fs_type: "tmpfs",
+				flags: MountFlags::empty(),
+			},
+		),
+	};
+	FILESYSTEM.set(filesystem).unwrap();
+	MOUNT_TABLE.lock().push(entry);
+	let cwd = match FILESYSTEM
+		.get()
+		.unwrap()
+		.mkdir("/tmp", AccessPermission::from_bits(0o777).unwrap())
+	{
+		Ok(()) | Err(Errno::Exist) => "/tmp",
+		// A root file system mounted with `ro` has no place for /tmp.
+		Err(Errno::Rofs) => "/",
+		Err(e) => panic!("Unable to create /tmp: {e:?}"),
+	};
+	mount(
+		"proc",
+		"/proc",
+		"proc",
+		MountFlags::MS_NOSUID | MountFlags::MS_NODEV | MountFlags::MS_NOEXEC,
+		Box::new(ProcDirectory::new()),
+	)
+	.expect("Unable to mount /proc");
+	mount(
+		"devfs",
+		"/dev",
+		"devfs",
+		MountFlags::MS_NOSUID | MountFlags::MS_NOEXEC,
+		Box::new(DevDirectory::new()),
+	)
+	.expect("Unable to mount /dev");
+
+	*WORKING_DIRECTORY.lock() = Some(cwd.to_string());
 
 	#[cfg(all(feature = "fuse", feature = "pci"))]
 	fuse::init();
-	if crate::env::is_uhyve() {
-		uhyve::init();
+	if crate::env::mounts().is_empty() {
+		#[cfg(all(feature = "fuse", feature = "pci"))]
+		fuse::mount_default();
+		if crate::env::is_uhyve() {
+			uhyve::init();
+		}
 	}
+	fstab::init(&specs);
 }
 
 pub fn create_file(name: &str, data: &'static [u8], mode: AccessPermission) -> io::Result<()> {
@@ -379,6 +996,35 @@ pub fn create_file(name: &str, data: &'static [u8], mode: AccessPermission) -> i
 	})
 }
 
+/// Mounts the file system `node` at `target` and records it in the mount table.
+///
+/// With `MS_RDONLY`, all modifications of the file system fail with `EROFS`.
+pub(crate) fn mount(
+	source: &str,
+	target: &str,
+	fs_type: &'static str,
+	flags: MountFlags,
+	mut node: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+) -> io::Result<()> {
+	if flags.contains(MountFlags::MS_RDONLY) {
+		node = Box::new(ReadOnlyDirectory::new(node));
+	}
+	FILESYSTEM.get().ok_or(Errno::Inval)?.mount(target, node)?;
+	MOUNT_TABLE.lock().push(MountEntry {
+		source: source.to_string(),
+		target: target.to_string(),
+		fs_type,
+		flags,
+	});
+
+	Ok(())
+}
+
+/// Returns a snapshot of the mount table.
+pub(crate) fn mounts() -> Vec<MountEntry> {
+	MOUNT_TABLE.lock().clone()
+}
+
 /// Removes an empty directory.
 pub fn remove_dir(path: &str) -> io::Result<()> {
 	with_relative_filename(path, |path| {
@@ -425,6 +1071,218 @@ pub fn read_lstat(name: &str) -> io::Result<FileAttr> {
 	})
 }
 
+/// Returns statistics about the file system containing `name`.
+pub fn statfs(name: &str) -> io::Result<StatFs> {
+	with_relative_filename(name, |name| {
+		FILESYSTEM.get().ok_or(Errno::Inval)?.statfs(name)
+	})
+}
+
+/// Creates a new, empty directory relative to the directory `dirfd`.
+pub fn mkdirat(dirfd: FileDescriptor, path: &str, mode: AccessPermission) -> io::Result<()> {
+	let mask = *UMASK.lock();
+
+	with_relative_filename_at(dirfd, path, |path| {
+		FILESYSTEM
+			.get()
+			.ok_or(Errno::Inval)?
+			.mkdir(path, mode.bitand(mask))
+	})
+}
+
+/// Removes a file or, with `AT_REMOVEDIR`, an empty directory relative to `dirfd`.
+pub fn unlinkat(dirfd: FileDescriptor, path: &str, flags: AtFlags) -> io::Result<()> {
+	with_relative_filename_at(dirfd, path, |path| {
+		let fs = FILESYSTEM.get().ok_or(Errno::Inval)?;
+		if flags.contains(AtFlags::AT_REMOVEDIR) {
+			fs.rmdir(path)
+		} else {
+			fs.unlink(path)
+		}
+	})
+}
+
+/// Returns the attributes of `name` relative to the directory `dirfd`.
+pub fn fstatat(dirfd: FileDescriptor, name: &str, flags: AtFlags) -> io::Result<FileAttr> {
+	if name.is_empty() {
+		return if flags.contains(AtFlags::AT_EMPTY_PATH) {
+			fd::fstat(dirfd)
+		} else {
+			Err(Errno::Noent)
+		};
+	}
+
+	with_relative_filename_at(dirfd, name, |name| {
+		let fs = FILESYSTEM.get().ok_or(Errno::Inval)?;
+		if flags.contains(AtFlags::AT_SYMLINK_NOFOLLOW) {
+			fs.lstat(name)
+		} else {
+			fs.stat(name)
+		}
+	})
+}
+
+/// Changes the access and modification time of `name` relative to `dirfd`.
+///
+/// If `times` is `None`, both timestamps are set to the current time.
+/// A symbolic link in the last component of `name` is followed unless
+/// `flags` contains `AT_SYMLINK_NOFOLLOW`.
+pub fn utimensat(
+	dirfd: FileDescriptor,
+	name: &str,
+	times: Option<&[timespec; 2]>,
+	flags: AtFlags,
+) -> io::Result<()> {
+	let (atime, mtime) = resolve_times(times)?;
+
+	if name.is_empty() && flags.contains(AtFlags::AT_EMPTY_PATH) {
+		return fd::utimens(dirfd, atime, mtime);
+	}
+
+	with_relative_filename_at(dirfd, name, |name| {
+		let fs = FILESYSTEM.get().ok_or(Errno::Inval)?;
+		if flags.contains(AtFlags::AT_SYMLINK_NOFOLLOW) {
+			fs.utimens(name, atime, mtime)
+		} else {
+			fs.utimens(&fs.follow(name)?, atime, mtime)
+		}
+	})
+}
+
+/// Changes the access and modification time of the open file `fd`.
+pub fn futimens(fd: FileDescriptor, times: Option<&[timespec; 2]>) -> io::Result<()> {
+	let (atime, mtime) = resolve_times(times)?;
+	fd::utimens(fd, atime, mtime)
+}
+
+/// Changes owner and group of `name` relative to `dirfd`, where `None`
+/// leaves the id unchanged.
+///
+/// A symbolic link in the last component of `name` is followed unless
+/// `flags` contains `AT_SYMLINK_NOFOLLOW`.
+pub fn fchownat(
+	dirfd: FileDescriptor,
+	name: &str,
+	uid: Option<u32>,
+	gid: Option<u32>,
+	flags: AtFlags,
+) -> io::Result<()> {
+	if name.is_empty() && flags.contains(AtFlags::AT_EMPTY_PATH) {
+		return fd::chown(dirfd, uid, gid);
+	}
+
+	with_relative_filename_at(dirfd, name, |name| {
+		let fs = FILESYSTEM.get().ok_or(Errno::Inval)?;
+		if flags.contains(AtFlags::AT_SYMLINK_NOFOLLOW) {
+			fs.chown(name, uid, gid)
+		} else {
+			fs.chown(&fs.follow(name)?, uid, gid)
+		}
+	})
+}
+
+/// Returns the value of the extended attribute `attr` of `name`.
+///
+/// A symbolic link in the last component of `name` is only followed if
+/// `follow` is set.
+pub fn getxattr(name: &str, attr: &str, follow: bool) -> io::Result<Vec<u8>> {
+	check_xattr_name(attr)?;
+	with_followed_filename(name, follow, |fs, name| fs.getxattr(name, attr))
+}
+
+/// Creates or replaces the extended attribute `attr` of `name`.
+///
+/// A symbolic link in the last component of `name` is only followed if
+/// `follow` is set.
+pub fn setxattr(
+	name: &str,
+	attr: &str,
+	value: &[u8],
+	flags: XattrFlags,
+	follow: bool,
+) -> io::Result<()> {
+	check_setxattr(attr, value, flags)?;
+	with_followed_filename(name, follow, |fs, name| {
+		fs.setxattr(name, attr, value, flags)
+	})
+}
+
+/// Returns the names of all extended attributes of `name`.
+///
+/// A symbolic link in the last component of `name` is only followed if
+/// `follow` is set.
+pub fn listxattr(name: &str, follow: bool) -> io::Result<Vec<String>> {
+	with_followed_filename(name, follow, |fs, name| fs.listxattr(name))
+}
+
+/// Removes the extended attribute `attr` of `name`.
+///
+/// A symbolic link in the last component of `name` is only followed if
+/// `follow` is set.
+pub fn removexattr(name: &str, attr: &str, follow: bool) -> io::Result<()> {
+	check_xattr_name(attr)?;
+	with_followed_filename(name, follow, |fs, name| fs.removexattr(name, attr))
+}
+
+/// Returns the value of the extended attribute `attr` of the open file `fd`.
+pub fn fgetxattr(fd: FileDescriptor, attr: &str) -> io::Result<Vec<u8>> {
+	check_xattr_name(attr)?;
+	fd::getxattr(fd, attr)
+}
+
+/// Creates or replaces the extended attribute `attr` of the open file `fd`.
+pub fn fsetxattr(
+	fd: FileDescriptor,
+	attr: &str,
+	value: &[u8],
+	flags: XattrFlags,
+) -> io::Result<()> {
+	check_setxattr(attr, value, flags)?;
+	fd::setxattr(fd, attr, value, flags)
+}
+
+/// Returns the names of all extended attributes of the open file `fd`.
+pub fn flistxattr(fd: FileDescriptor) -> io::Result<Vec<String>> {
+	fd::listxattr(fd)
+}
+
+/// Removes the extended attribute `attr` of the open file `fd`.
+pub fn fremovexattr(fd: FileDescriptor, attr: &str) -> io::Result<()> {
+	check_xattr_name(attr)?;
+	fd::removexattr(fd, attr)
+}
+
+/// Returns the target of the symbolic link `name`.
+pub fn readlink(name: &str) -> io::Result<String> {
+	readlinkat(AT_FDCWD, name)
+}
+
+/// Returns the target of the symbolic link `name` relative to the directory `dirfd`.
+pub fn readlinkat(dirfd: FileDescriptor, name: &str) -> io::Result<String> {
+	with_relative_filename_at(dirfd, name, |name| {
+		FILESYSTEM.get().ok_or(Errno::Inval)?.readlink(name)
+	})
+}
+
+/// Renames a file or directory.
+pub fn rename(from: &str, to: &str) -> io::Result<()> {
+	renameat(AT_FDCWD, from, AT_FDCWD, to)
+}
+
+/// Renames `from` relative to `olddirfd` to `to` relative to `newdirfd`.
+pub fn renameat(
+	olddirfd: FileDescriptor,
+	from: &str,
+	newdirfd: FileDescriptor,
+	to: &str,
+) -> io::Result<()> {
+	// Resolve both paths first, because resolving holds the lock of the working directory.
+	let from = with_relative_filename_at(olddirfd, from, |from| Ok(from.to_string()))?;
+	let to = with_relative_filename_at(newdirfd, to, |to| Ok(to.to_string()))?;
+
+	FILESYSTEM.get().ok_or(Errno::Inval)?.rename(&from, &to)
+}
+
 fn with_relative_filename<F, T>(name: &str, callback: F) -> io::Result<T>
 where
 	F: FnOnce(&str) -> io::Result<T>,
@@ -447,6 +1305,46 @@ where
 	}
 }
 
+/// Resolves `name` relative to the current working directory and, if
+/// `follow` is set, follows a symbolic link in its last component.
+fn with_followed_filename<F, T>(name: &str, follow: bool, callback: F) -> io::Result<T>
+where
+	F: FnOnce(&Filesystem, &str) -> io::Result<T>,
+{
+	with_relative_filename(name, |name| {
+		let fs = FILESYSTEM.get().ok_or(Errno::Inval)?;
+		if follow {
+			callback(fs, &fs.follow(name)?)
+		} else {
+			callback(fs, name)
+		}
+	})
+}
+
+/// Resolves `name` relative to the directory referred to by `dirfd`.
+///
+/// Absolute paths ignore `dirfd` and `AT_FDCWD` refers to the current
+/// working directory.
+fn with_relative_filename_at<F, T>(dirfd: FileDescriptor, name: &str, callback: F) -> io::Result<T>
+where
+	F: FnOnce(&str) -> io::Result<T>,
+{
+	if name.starts_with("/") || dirfd == AT_FDCWD {
+		return with_relative_filename(name, callback);
+	}
+
+	let obj = fd::get_object(dirfd)?;
+	let mut path = block_on(async { obj.read().await.dirpath().await }, None)?;
+	if !name.is_empty() {
+		if !path.ends_with('/') {
+			path.push('/');
+		}
+		path.push_str(name);
+	}
+
+	callback(&path)
+}
+
 pub fn truncate(name: &str, size: usize) -> io::Result<()> {
 	with_relative_filename(name, |name| {
 		let fs = FILESYSTEM.get().ok_or(Errno::Inval)?;
@@ -459,17 +1357,31 @@ pub fn truncate(name: &str, size: usize) -> io::Result<()> {
 }
 
 pub fn open(name: &str, flags: OpenOption, mode: AccessPermission) -> io::Result<FileDescriptor> {
+	openat(AT_FDCWD, name, flags, mode)
+}
+
+/// Opens the file `name` relative to the directory `dirfd`.
+pub fn openat(
+	dirfd: FileDescriptor,
+	name: &str,
+	flags: OpenOption,
+	mode: AccessPermission,
+) -> io::Result<FileDescriptor> {
 	// mode is 0x777 (0b0111_0111_0111), when flags | O_CREAT, else 0
 	// flags is bitmask of O_DEC_* defined above.
 	// (taken from rust stdlib/sys hermit target )
 	let mask = *UMASK.lock();
 
-	with_relative_filename(name, |name| {
+	with_relative_filename_at(dirfd, name, |name| {
 		debug!("Open {name}, {flags:?}, {mode:?}");
 
 		let fs = FILESYSTEM.get().ok_or(Errno::Inval)?;
 		let file = fs.open(name, flags, mode.bitand(mask))?;
 		let fd = insert_object(file)?;
+		fd::set_descriptor_path(fd, name)?;
+		if flags.contains(OpenOption::O_CLOEXEC) {
+			fd::set_descriptor_flags(fd, fd::DescriptorFlags::FD_CLOEXEC)?;
+		}
 		Ok(fd)
 	})
 }
@@ -500,6 +1412,15 @@ pub fn set_cwd(cwd: &str) -> io::Result<()> {
 	Ok(())
 }
 
+/// Changes the working directory to the directory referred to by `fd`.
+pub fn fchdir(fd: FileDescriptor) -> io::Result<()> {
+	let obj = fd::get_object(fd)?;
+	let path = block_on(async { obj.read().await.dirpath().await }, None)?;
+	*WORKING_DIRECTORY.lock() = Some(path);
+
+	Ok(())
+}
+
 pub fn umask(new_mask: AccessPermission) -> AccessPermission {
 	let mut lock = UMASK.lock();
 	let old = *lock;
@@ -510,7 +1431,9 @@ pub fn umask(new_mask: AccessPermission) -> AccessPermission {
 /// Open a directory to read the directory entries
 pub(crate) fn opendir(name: &str) -> io::Result<FileDescriptor> {
 	let obj = FILESYSTEM.get().ok_or(Errno::Inval)?.opendir(name)?;
-	insert_object(obj)
+	let fd = insert_object(obj)?;
+	with_relative_filename(name, |name| fd::set_descriptor_path(fd, name))?;
+	Ok(fd)
 }
 
 use crate::fd::{self, FileDescriptor};
@@ -597,6 +1520,20 @@ impl File {
 	pub fn metadata(&self) -> io::Result<Metadata> {
 		metadata(&self.path)
 	}
+
+	/// Changes the timestamps of the file, where `None` leaves the time unchanged.
+	pub fn set_times(
+		&self,
+		accessed: Option<SystemTime>,
+		modified: Option<SystemTime>,
+	) -> io::Result<()> {
+		fd::utimens(self.fd, accessed.map(Into::into), modified.map(Into::into))
+	}
+
+	/// Changes the modification time of the file.
+	pub fn set_modified(&self, time: SystemTime) -> io::Result<()> {
+		self.set_times(None, Some(time))
+	}
 }
 
 impl embedded_io::ErrorType for File {
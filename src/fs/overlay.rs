@@ -0,0 +1,869 @@
+//! Implements an overlay file system, which combines a read-only lower layer
+//! with a writable in-memory upper layer.
+//!
+//! Lookups prefer the upper layer. Files of the lower layer are copied up
+//! before they are opened for writing. Deleting an entry of the lower layer
+//! creates a whiteout in the upper layer, which hides the entry and, for
+//! directories, everything below it. Directory listings merge both layers.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::errno::Errno;
+use crate::executor::block_on;
+use crate::fd::{AccessPermission, ObjectInterface, OpenOption};
+use crate::fs::mem::MemDirectory;
+use crate::fs::{
+	DirectoryEntry, DirectoryReader, FileAttr, FileType, MAX_SYMLINKS, NodeKind, StatFs, VfsNode,
+	XattrFlags, relative_path,
+};
+use crate::io;
+use crate::time::timespec;
+
+/// Magic number of overlay file systems
+const OVERLAYFS_SUPER_MAGIC: i64 = 0x794c_7630;
+
+fn is_type(attr: &FileAttr, file_type: AccessPermission) -> bool {
+	(attr.st_mode & AccessPermission::S_IFMT).bits() == file_type.bits()
+}
+
+fn is_dir(attr: &FileAttr) -> bool {
+	is_type(attr, AccessPermission::S_IFDIR)
+}
+
+fn is_symlink(attr: &FileAttr) -> bool {
+	is_type(attr, AccessPermission::S_IFLNK)
+}
+
+/// Returns `true` if `attr` belongs to a whiteout of the upper layer.
+fn is_whiteout(attr: &FileAttr) -> bool {
+	is_type(attr, AccessPermission::S_IFCHR) && attr.st_rdev == 0
+}
+
+/// Returns the reversed path components of the target of the symbolic link
+/// `components`. Absolute targets are relative to the root of the overlay.
+fn link_target(components: &[&str], target: &str) -> Vec<String> {
+	let mut path: Vec<String> = if target.starts_with('/') {
+		Vec::new()
+	} else {
+		components[1..]
+			.iter()
+			.rev()
+			.map(ToString::to_string)
+			.collect()
+	};
+
+	for component in target.split('/') {
+		match component {
+			"" | "." => {}
+			".." => {
+				path.pop();
+			}
+			_ => path.push(component.to_string()),
+		}
+	}
+
+	path.reverse();
+	path
+}
+
+#[derive(Debug)]
+pub(crate) struct OverlayDirectory {
+	/// Read-only layer, which is never modified
+	lower: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	/// Writable layer, which receives all modifications and the whiteouts of
+	/// deleted entries of the lower layer
+	upper: MemDirectory,
+}
+
+impl OverlayDirectory {
+	pub fn new(lower: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>) -> Self {
+		Self {
+			lower,
+			upper: MemDirectory::new(AccessPermission::from_bits(0o777).unwrap()),
+		}
+	}
+
+	/// Returns `true` if the path or one of its parents has been deleted from the lower layer.
+	fn is_whiteout(&self, components: &[&str]) -> bool {
+		(1..=components.len()).any(|len| {
+			let prefix = &components[components.len() - len..];
+			self.upper
+				.traverse_lstat(&mut prefix.to_vec())
+				.is_ok_and(|attr| is_whiteout(&attr))
+		})
+	}
+
+	/// Hides the entry of the lower layer by a whiteout in the upper layer.
+	fn add_whiteout(&self, components: &[&str]) -> io::Result<()> {
+		self.copy_up_parents(components)?;
+		self.upper.traverse_whiteout(&mut components.to_vec())
+	}
+
+	/// Removes the whiteout of the path, if any, before an entry is created in
+	/// the upper layer.
+	///
+	/// Returns `true` if a whiteout has been removed.
+	fn remove_whiteout(&self, components: &[&str]) -> io::Result<bool> {
+		match self.upper.traverse_lstat(&mut components.to_vec()) {
+			Ok(attr) if is_whiteout(&attr) => {
+				self.upper.traverse_unlink(&mut components.to_vec())?;
+				Ok(true)
+			}
+			_ => Ok(false),
+		}
+	}
+
+	/// Hides all entries of the lower directory, which are not present in the
+	/// upper directory. Used for directories, which replace a deleted directory
+	/// of the lower layer.
+	fn make_opaque(&self, components: &[&str]) -> io::Result<()> {
+		let Ok(entries) = self.lower.traverse_readdir(&mut components.to_vec()) else {
+			return Ok(());
+		};
+
+		for entry in entries {
+			if entry.name == "." || entry.name == ".." {
+				continue;
+			}
+			let mut path = components.to_vec();
+			path.insert(0, &entry.name);
+			if self.upper.traverse_lstat(&mut path.clone()).is_err() {
+				self.upper.traverse_whiteout(&mut path)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Removes the whiteout at the path or the whiteouts within an upper
+	/// directory at the path, which a renamed directory replaces.
+	///
+	/// Returns the removed whiteouts, so that they can be restored if the
+	/// rename fails.
+	fn take_whiteouts(&self, components: &[&str]) -> io::Result<Vec<Vec<String>>> {
+		let owned = |components: &[&str]| -> Vec<String> {
+			components.iter().map(ToString::to_string).collect()
+		};
+
+		let paths = match self.upper.traverse_lstat(&mut components.to_vec()) {
+			Ok(attr) if is_whiteout(&attr) => vec![owned(components)],
+			Ok(attr) if is_dir(&attr) => self
+				.upper
+				.traverse_readdir(&mut components.to_vec())?
+				.into_iter()
+				.filter(|entry| entry.file_type == FileType::Whiteout)
+				.map(|entry| {
+					let mut path = vec![entry.name];
+					path.extend(owned(components));
+					path
+				})
+				.collect(),
+			_ => Vec::new(),
+		};
+
+		for path in &paths {
+			self.upper
+				.traverse_unlink(&mut path.iter().map(String::as_str).collect())?;
+		}
+		Ok(paths)
+	}
+
+	/// Puts back the whiteouts, which [`take_whiteouts`](Self::take_whiteouts) has removed.
+	fn restore_whiteouts(&self, paths: &[Vec<String>]) {
+		for path in paths {
+			let mut components: Vec<&str> = path.iter().map(String::as_str).collect();
+			if let Err(e) = self.upper.traverse_whiteout(&mut components) {
+				error!(
+					"Unable to restore the whiteout {}: {e:?}",
+					relative_path(&components)
+				);
+			}
+		}
+	}
+
+	fn upper_lstat(&self, components: &[&str]) -> io::Result<FileAttr> {
+		if components.is_empty() {
+			return self.upper.get_file_attributes();
+		}
+		match self.upper.traverse_lstat(&mut components.to_vec()) {
+			Ok(attr) if is_whiteout(&attr) => Err(Errno::Noent),
+			result => result,
+		}
+	}
+
+	fn lower_lstat(&self, components: &[&str]) -> io::Result<FileAttr> {
+		if self.is_whiteout(components) {
+			return Err(Errno::Noent);
+		}
+		if components.is_empty() {
+			return self.lower.get_file_attributes();
+		}
+		self.lower.traverse_lstat(&mut components.to_vec())
+	}
+
+	/// Returns the attributes of the visible entry, preferring the upper layer.
+	fn lstat(&self, components: &[&str]) -> io::Result<FileAttr> {
+		self.upper_lstat(components)
+			.or_else(|_| self.lower_lstat(components))
+	}
+
+	fn readlink(&self, components: &[&str]) -> io::Result<String> {
+		if self.upper_lstat(components).is_ok() {
+			return self.upper.traverse_readlink(&mut components.to_vec());
+		}
+		if self.is_whiteout(components) {
+			return Err(Errno::Noent);
+		}
+		self.lower.traverse_readlink(&mut components.to_vec())
+	}
+
+	/// Follows symbolic links in the last component of the path.
+	///
+	/// The links are resolved within the overlay, as the in-memory copy of a
+	/// link cannot be followed by the upper layer.
+	fn follow(&self, components: &[&str]) -> io::Result<Vec<String>> {
+		let mut path: Vec<String> = components.iter().map(ToString::to_string).collect();
+
+		for _ in 0..MAX_SYMLINKS {
+			let components: Vec<&str> = path.iter().map(String::as_str).collect();
+			if !self.lstat(&components).is_ok_and(|attr| is_symlink(&attr)) {
+				return Ok(path);
+			}
+			let target = self.readlink(&components)?;
+			path = link_target(&components, &target);
+		}
+
+		Err(Errno::Loop)
+	}
+
+	/// Lists the directory by merging the entries of both layers.
+	fn merged_readdir(&self, components: &[&str]) -> io::Result<Vec<DirectoryEntry>> {
+		let upper = self.upper.traverse_readdir(&mut components.to_vec());
+		let lower = if self.is_whiteout(components) {
+			Err(Errno::Noent)
+		} else {
+			self.lower.traverse_readdir(&mut components.to_vec())
+		};
+
+		if let (Err(_), Err(e)) = (&upper, &lower) {
+			return Err(*e);
+		}
+
+		let mut entries = BTreeMap::new();
+		for entry in lower.unwrap_or_default() {
+			if entry.name != "." && entry.name != ".." {
+				entries.insert(entry.name, entry.file_type);
+			}
+		}
+		for entry in upper.unwrap_or_default() {
+			if entry.file_type == FileType::Whiteout {
+				entries.remove(&entry.name);
+			} else {
+				entries.insert(entry.name, entry.file_type);
+			}
+		}
+
+		Ok(entries
+			.into_iter()
+			.map(|(name, file_type)| DirectoryEntry::with_type(name, file_type))
+			.collect())
+	}
+
+	/// Creates all parent directories of the path in the upper layer.
+	fn copy_up_parents(&self, components: &[&str]) -> io::Result<()> {
+		for len in 1..components.len() {
+			let prefix = &components[components.len() - len..];
+			if self.upper_lstat(prefix).is_ok() {
+				continue;
+			}
+
+			let attr = self.lower_lstat(prefix)?;
+			if !is_dir(&attr) {
+				return Err(Errno::Notdir);
+			}
+			let mode = attr.st_mode & AccessPermission::from_bits_retain(0o777);
+			self.upper.traverse_mkdir(&mut prefix.to_vec(), mode)?;
+		}
+
+		Ok(())
+	}
+
+	/// Copies a file of the lower layer into the upper layer.
+	///
+	/// If `truncate` is set, only an empty file is created.
+	fn copy_up(&self, components: &[&str], attr: &FileAttr, truncate: bool) -> io::Result<()> {
+		debug!("Copy up {}", relative_path(components));
+
+		self.copy_up_parents(components)?;
+
+		let mode = attr.st_mode & AccessPermission::from_bits_retain(0o777);
+		let dst = self.upper.traverse_open(
+			&mut components.to_vec(),
+			OpenOption::O_CREAT | OpenOption::O_RDWR,
+			mode,
+		)?;
+		self.copy_up_xattrs(components)?;
+		if truncate {
+			return Ok(());
+		}
+
+		let src = self
+			.lower
+			.traverse_open(&mut components.to_vec(), OpenOption::O_RDONLY, mode)?;
+
+		block_on(
+			async {
+				let src = src.read().await;
+				let dst = dst.read().await;
+				let mut buf = [0u8; 4096];
+				loop {
+					let len = src.read(&mut buf).await?;
+					if len == 0 {
+						return Ok(());
+					}
+					let mut written = 0;
+					while written < len {
+						written += dst.write(&buf[written..len]).await?;
+					}
+				}
+			},
+			None,
+		)
+	}
+
+	/// Copies a file, directory or symbolic link of the lower layer into the
+	/// upper layer, unless it is already there. Directories are copied
+	/// without their entries and symbolic links are not followed.
+	fn copy_up_node(&self, components: &[&str]) -> io::Result<()> {
+		if self.upper_lstat(components).is_ok() {
+			return Ok(());
+		}
+
+		let attr = self.lower_lstat(components)?;
+		if is_dir(&attr) {
+			self.copy_up_parents(components)?;
+			let mode = attr.st_mode & AccessPermission::from_bits_retain(0o777);
+			self.upper.traverse_mkdir(&mut components.to_vec(), mode)?;
+			self.copy_up_xattrs(components)
+		} else if is_symlink(&attr) {
+			debug!("Copy up {}", relative_path(components));
+			let target = self.lower.traverse_readlink(&mut components.to_vec())?;
+			self.copy_up_parents(components)?;
+			self.upper
+				.traverse_symlink(&mut components.to_vec(), &target)
+		} else {
+			self.copy_up(components, &attr, false)
+		}
+	}
+	/// Copies the extended attributes of a lower entry to its copy in the upper layer.
+	/// Lower layers without support for extended attributes have nothing to copy.
+	fn copy_up_xattrs(&self, components: &[&str]) -> io::Result<()> {
+		let Ok(names) = self.lower.traverse_listxattr(&mut components.to_vec()) else {
+			return Ok(());
+		};
+
+		for name in names {
+			let value = self
+				.lower
+				.traverse_getxattr(&mut components.to_vec(), &name)?;
+			self.upper.traverse_setxattr(
+				&mut components.to_vec(),
+				&name,
+				&value,
+				XattrFlags::empty(),
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl VfsNode for OverlayDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		self.lstat(&[])
+	}
+
+	fn get_object(&self) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		Ok(Arc::new(async_lock::RwLock::new(DirectoryReader::new(
+			self.merged_readdir(&[])?,
+			self.lstat(&[])?,
+		))))
+	}
+
+	fn traverse_mkdir(&self, components: &mut Vec<&str>, mode: AccessPermission) -> io::Result<()> {
+		if components.is_empty() || self.lstat(components).is_ok() {
+			return Err(Errno::Exist);
+		}
+
+		self.copy_up_parents(components)?;
+		let replaced = self.remove_whiteout(components)?;
+		self.upper.traverse_mkdir(&mut components.clone(), mode)?;
+		if replaced {
+			self.make_opaque(components)?;
+		}
+
+		Ok(())
+	}
+
+	fn traverse_rmdir(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		let attr = self.lstat(components)?;
+		if !is_dir(&attr) {
+			return Err(Errno::Notdir);
+		}
+		if !self.merged_readdir(components)?.is_empty() {
+			return Err(Errno::Notempty);
+		}
+
+		if self.upper_lstat(components).is_ok() {
+			self.upper.traverse_rmdir(&mut components.clone())?;
+		}
+		if self.lower_lstat(components).is_ok() {
+			self.add_whiteout(components)?;
+		}
+
+		Ok(())
+	}
+
+	fn traverse_unlink(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		let attr = self.lstat(components)?;
+		if is_dir(&attr) {
+			return Err(Errno::Isdir);
+		}
+
+		if self.upper_lstat(components).is_ok() {
+			self.upper.traverse_unlink(&mut components.clone())?;
+		}
+		if self.lower_lstat(components).is_ok() {
+			self.add_whiteout(components)?;
+		}
+
+		Ok(())
+	}
+
+	fn traverse_readdir(&self, components: &mut Vec<&str>) -> io::Result<Vec<DirectoryEntry>> {
+		self.merged_readdir(components)
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		self.lstat(components)
+	}
+
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		let path = self.follow(components)?;
+		let mut components: Vec<&str> = path.iter().map(String::as_str).collect();
+
+		if components.is_empty() {
+			return self.get_file_attributes();
+		}
+		if self.upper_lstat(&components).is_ok() {
+			return self.upper.traverse_stat(&mut components);
+		}
+		if self.is_whiteout(&components) {
+			return Err(Errno::Noent);
+		}
+		self.lower.traverse_stat(&mut components)
+	}
+
+	/// Reports the capacity of the upper layer, which receives all writes.
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		self.lstat(components)?;
+		Ok(StatFs {
+			f_type: OVERLAYFS_SUPER_MAGIC,
+			..self.upper.traverse_statfs(&mut Vec::new())?
+		})
+	}
+
+	fn traverse_utimens(
+		&self,
+		components: &mut Vec<&str>,
+		atime: Option<timespec>,
+		mtime: Option<timespec>,
+	) -> io::Result<()> {
+		self.copy_up_node(components)?;
+		self.upper.traverse_utimens(components, atime, mtime)
+	}
+
+	fn traverse_chown(
+		&self,
+		components: &mut Vec<&str>,
+		uid: Option<u32>,
+		gid: Option<u32>,
+	) -> io::Result<()> {
+		self.copy_up_node(components)?;
+		self.upper.traverse_chown(components, uid, gid)
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		if self.upper_lstat(components).is_ok() {
+			return self.upper.traverse_getxattr(components, name);
+		}
+		if self.is_whiteout(components) {
+			return Err(Errno::Noent);
+		}
+		self.lower.traverse_getxattr(components, name)
+	}
+
+	fn traverse_setxattr(
+		&self,
+		components: &mut Vec<&str>,
+		name: &str,
+		value: &[u8],
+		flags: XattrFlags,
+	) -> io::Result<()> {
+		self.copy_up_node(components)?;
+		self.upper.traverse_setxattr(components, name, value, flags)
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		if self.upper_lstat(components).is_ok() {
+			return self.upper.traverse_listxattr(components);
+		}
+		if self.is_whiteout(components) {
+			return Err(Errno::Noent);
+		}
+		self.lower.traverse_listxattr(components)
+	}
+
+	fn traverse_removexattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<()> {
+		self.copy_up_node(components)?;
+		self.upper.traverse_removexattr(components, name)
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		self.readlink(components)
+	}
+
+	fn traverse_symlink(&self, components: &mut Vec<&str>, target: &str) -> io::Result<()> {
+		if components.is_empty() || self.lstat(components).is_ok() {
+			return Err(Errno::Exist);
+		}
+
+		self.copy_up_parents(components)?;
+		self.remove_whiteout(components)?;
+		self.upper.traverse_symlink(components, target)
+	}
+
+	/// Renames an entry by moving it within the upper layer.
+	///
+	/// Directories of the lower layer cannot be moved, because this would
+	/// require copying up the whole tree. The target is validated before
+	/// anything is modified and replaced atomically by the upper layer.
+	fn traverse_rename(&self, from: &mut Vec<&str>, to: &mut Vec<&str>) -> io::Result<()> {
+		let source = self.lstat(from)?;
+		if from == to {
+			return Ok(());
+		}
+		if to.len() > from.len() && to.ends_with(from) {
+			return Err(Errno::Inval);
+		}
+
+		let in_lower = self.lower_lstat(from).is_ok();
+		if in_lower && is_dir(&source) {
+			return Err(Errno::Xdev);
+		}
+
+		if let Ok(target) = self.lstat(to) {
+			match (is_dir(&source), is_dir(&target)) {
+				(true, true) => {
+					if !self.merged_readdir(to)?.is_empty() {
+						return Err(Errno::Notempty);
+					}
+				}
+				(true, false) => return Err(Errno::Notdir),
+				(false, true) => return Err(Errno::Isdir),
+				(false, false) => {}
+			}
+		}
+
+		self.copy_up_node(from)?;
+		self.copy_up_parents(to)?;
+		// A file replaces a whiteout like any other file, while a directory
+		// needs the whiteouts out of its way.
+		let whiteouts = if is_dir(&source) {
+			self.take_whiteouts(to)?
+		} else {
+			Vec::new()
+		};
+		if let Err(e) = self
+			.upper
+			.traverse_rename(&mut from.clone(), &mut to.clone())
+		{
+			self.restore_whiteouts(&whiteouts);
+			return Err(e);
+		}
+
+		if in_lower {
+			self.add_whiteout(from)?;
+		}
+		if is_dir(&source) {
+			self.make_opaque(to)?;
+		}
+
+		Ok(())
+	}
+
+	fn traverse_mount(
+		&self,
+		components: &mut Vec<&str>,
+		obj: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	) -> io::Result<()> {
+		self.copy_up_parents(components)?;
+		self.remove_whiteout(components)?;
+		self.upper.traverse_mount(components, obj)
+	}
+
+	fn traverse_open(
+		&self,
+		components: &mut Vec<&str>,
+		opt: OpenOption,
+		mode: AccessPermission,
+	) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		let writable = opt.intersects(
+			OpenOption::O_WRONLY | OpenOption::O_RDWR | OpenOption::O_CREAT | OpenOption::O_TRUNC,
+		);
+
+		// Like on Linux, `O_EXCL` does not follow a symbolic link.
+		if opt.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) && self.lstat(components).is_ok()
+		{
+			return Err(Errno::Exist);
+		}
+
+		let path = self.follow(components)?;
+		let components = &mut path.iter().map(String::as_str).collect::<Vec<_>>();
+
+		let Ok(attr) = self.lstat(components) else {
+			if !opt.contains(OpenOption::O_CREAT) || opt.contains(OpenOption::O_DIRECTORY) {
+				return Err(Errno::Noent);
+			}
+			self.copy_up_parents(components)?;
+			self.remove_whiteout(components)?;
+			return self.upper.traverse_open(components, opt, mode);
+		};
+
+		if opt.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) {
+			return Err(Errno::Exist);
+		}
+
+		if is_dir(&attr) {
+			if opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR) {
+				return Err(Errno::Isdir);
+			}
+			return Ok(Arc::new(async_lock::RwLock::new(DirectoryReader::new(
+				self.merged_readdir(components)?,
+				attr,
+			))));
+		}
+		if opt.contains(OpenOption::O_DIRECTORY) {
+			return Err(Errno::Notdir);
+		}
+
+		if self.upper_lstat(components).is_err() {
+			if !writable {
+				return self.lower.traverse_open(components, opt, mode);
+			}
+			self.copy_up(components, &attr, opt.contains(OpenOption::O_TRUNC))?;
+		}
+
+		self.upper.traverse_open(components, opt, mode)
+	}
+
+	fn traverse_create_file(
+		&self,
+		components: &mut Vec<&str>,
+		data: &'static [u8],
+		mode: AccessPermission,
+	) -> io::Result<()> {
+		self.copy_up_parents(components)?;
+		self.remove_whiteout(components)?;
+		self.upper.traverse_create_file(components, data, mode)
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+	use crate::fs::mem::MemUsage;
+
+	/// Creates an in-memory directory without reading the limits from the
+	/// environment, which does not exist on the host.
+	fn mem_directory(mode: AccessPermission) -> MemDirectory {
+		MemDirectory::with_usage(mode, &MemUsage::with_limits(None, None).unwrap()).unwrap()
+	}
+
+	/// Creates an overlay, whose lower layer contains the files `a` and `b`
+	/// and the directory `dir` with the file `dir/c`.
+	fn overlay() -> OverlayDirectory {
+		let mode = AccessPermission::from_bits(0o755).unwrap();
+		let lower = mem_directory(mode);
+		lower
+			.traverse_create_file(&mut vec!["a"], b"lower a", mode)
+			.unwrap();
+		lower
+			.traverse_create_file(&mut vec!["b"], b"lower b", mode)
+			.unwrap();
+		lower.traverse_mkdir(&mut vec!["dir"], mode).unwrap();
+		lower
+			.traverse_create_file(&mut vec!["c", "dir"], b"lower c", mode)
+			.unwrap();
+		OverlayDirectory {
+			lower: Box::new(lower),
+			upper: mem_directory(AccessPermission::from_bits(0o777).unwrap()),
+		}
+	}
+
+	fn names(overlay: &OverlayDirectory, components: &[&str]) -> Vec<String> {
+		overlay
+			.traverse_readdir(&mut components.to_vec())
+			.unwrap()
+			.into_iter()
+			.map(|entry| entry.name)
+			.collect()
+	}
+
+	fn read_file(overlay: &OverlayDirectory, components: &[&str]) -> Vec<u8> {
+		let obj = overlay
+			.traverse_open(
+				&mut components.to_vec(),
+				OpenOption::O_RDONLY,
+				AccessPermission::empty(),
+			)
+			.unwrap();
+		block_on(
+			async {
+				let mut buf = vec![0; 64];
+				let len = obj.read().await.read(&mut buf).await?;
+				buf.truncate(len);
+				Ok(buf)
+			},
+			None,
+		)
+		.unwrap()
+	}
+
+	#[test]
+	fn test_readdir_after_unlink() {
+		let overlay = overlay();
+		assert_eq!(names(&overlay, &[]), ["a", "b", "dir"]);
+
+		overlay.traverse_unlink(&mut vec!["a"]).unwrap();
+		assert_eq!(names(&overlay, &[]), ["b", "dir"]);
+		assert_eq!(
+			overlay.traverse_lstat(&mut vec!["a"]).unwrap_err(),
+			Errno::Noent
+		);
+
+		overlay.traverse_unlink(&mut vec!["c", "dir"]).unwrap();
+		assert!(names(&overlay, &["dir"]).is_empty());
+
+		// Creating the file again removes its whiteout.
+		overlay
+			.traverse_open(
+				&mut vec!["a"],
+				OpenOption::O_CREAT | OpenOption::O_RDWR,
+				AccessPermission::from_bits(0o644).unwrap(),
+			)
+			.unwrap();
+		assert_eq!(names(&overlay, &[]), ["a", "b", "dir"]);
+		assert!(read_file(&overlay, &["a"]).is_empty());
+	}
+
+	#[test]
+	fn test_recreate_lower_directory() {
+		let overlay = overlay();
+		let mode = AccessPermission::from_bits(0o755).unwrap();
+
+		assert_eq!(
+			overlay.traverse_rmdir(&mut vec!["dir"]).unwrap_err(),
+			Errno::Notempty
+		);
+		overlay.traverse_unlink(&mut vec!["c", "dir"]).unwrap();
+		overlay.traverse_rmdir(&mut vec!["dir"]).unwrap();
+		assert_eq!(names(&overlay, &[]), ["a", "b"]);
+
+		// The new directory is opaque and hides the entries of the lower one.
+		overlay.traverse_mkdir(&mut vec!["dir"], mode).unwrap();
+		assert!(names(&overlay, &["dir"]).is_empty());
+		assert_eq!(
+			overlay.traverse_lstat(&mut vec!["c", "dir"]).unwrap_err(),
+			Errno::Noent
+		);
+
+		overlay
+			.traverse_create_file(&mut vec!["d", "dir"], b"upper d", mode)
+			.unwrap();
+		assert_eq!(names(&overlay, &["dir"]), ["d"]);
+	}
+
+	#[test]
+	fn test_rename_over_lower_file() {
+		let overlay = overlay();
+
+		overlay
+			.traverse_rename(&mut vec!["a"], &mut vec!["b"])
+			.unwrap();
+		assert_eq!(names(&overlay, &[]), ["b", "dir"]);
+		assert_eq!(read_file(&overlay, &["b"]), b"lower a");
+		assert_eq!(
+			overlay.traverse_lstat(&mut vec!["a"]).unwrap_err(),
+			Errno::Noent
+		);
+
+		// A failing rename leaves the target in place.
+		assert_eq!(
+			overlay
+				.traverse_rename(&mut vec!["b"], &mut vec!["dir"])
+				.unwrap_err(),
+			Errno::Isdir
+		);
+		assert_eq!(names(&overlay, &["dir"]), ["c"]);
+		assert_eq!(
+			overlay
+				.traverse_rename(&mut vec!["dir"], &mut vec!["moved"])
+				.unwrap_err(),
+			Errno::Xdev
+		);
+		assert_eq!(names(&overlay, &[]), ["b", "dir"]);
+	}
+
+	#[test]
+	fn test_rename_directory_over_whiteout() {
+		let overlay = overlay();
+		let mode = AccessPermission::from_bits(0o755).unwrap();
+
+		overlay.traverse_unlink(&mut vec!["c", "dir"]).unwrap();
+		overlay.traverse_rmdir(&mut vec!["dir"]).unwrap();
+		overlay.traverse_mkdir(&mut vec!["new"], mode).unwrap();
+		overlay
+			.traverse_rename(&mut vec!["new"], &mut vec!["dir"])
+			.unwrap();
+
+		assert_eq!(names(&overlay, &[]), ["a", "b", "dir"]);
+		assert!(names(&overlay, &["dir"]).is_empty());
+	}
+
+	#[test]
+	fn test_symlink() {
+		let overlay = overlay();
+
+		overlay
+			.traverse_symlink(&mut vec!["link"], "dir/c")
+			.unwrap();
+		assert_eq!(read_file(&overlay, &["link"]), b"lower c");
+		assert_eq!(
+			overlay.traverse_readlink(&mut vec!["link"]).unwrap(),
+			"dir/c"
+		);
+		assert!(is_symlink(
+			&overlay.traverse_lstat(&mut vec!["link"]).unwrap()
+		));
+		assert_eq!(overlay.traverse_stat(&mut vec!["link"]).unwrap().st_size, 7);
+	}
+}
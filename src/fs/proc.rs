@@ -0,0 +1,461 @@
+//! Implements a process file system, which exposes the state of the kernel
+//! below `/proc`.
+//!
+//! The content of a file is generated when the file is opened. Each open file
+//! therefore reads a consistent snapshot, which never changes while reading.
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use async_lock::Mutex;
+use async_trait::async_trait;
+
+use crate::errno::Errno;
+use crate::executor::block_on;
+use crate::fd::{
+	AccessPermission, Descriptor, FileDescriptor, ObjectInterface, OpenOption, PollEvent,
+};
+use crate::fs::{
+	self, DirectoryEntry, DirectoryReader, FileAttr, NodeKind, SeekWhence, StatFs, VfsNode,
+	relative_path,
+};
+use crate::mm::physicalmem::{self, PHYSICAL_FREE_LIST};
+use crate::{arch, io};
+
+/// Magic number of the process file system
+const PROC_SUPER_MAGIC: i64 = 0x9fa0;
+
+/// Generates the content of a file.
+type Generator = fn() -> String;
+
+/// All directories below the mount point
+const DIRECTORIES: &[&str] = &["net", "self", "self/fd"];
+
+/// All regular files below the mount point
+const FILES: &[(&str, Generator)] = &[
+	("version", version),
+	("meminfo", meminfo),
+	("cpuinfo", cpuinfo),
+	("interrupts", interrupts),
+	("uptime", uptime),
+	("mounts", mounts),
+	#[cfg(feature = "tcp")]
+	("net/tcp", crate::executor::network::tcp_socket_table),
+];
+
+fn version() -> String {
+	const VERSION: &str = env!("CARGO_PKG_VERSION");
+	const UTC_BUILT_TIME: &str = build_time::build_time_utc!();
+
+	format!("HermitOS version {VERSION} # UTC {UTC_BUILT_TIME}\n")
+}
+
+fn meminfo() -> String {
+	let total = physicalmem::total_memory_size();
+	let free = PHYSICAL_FREE_LIST.lock().free_space();
+
+	let mut content = String::new();
+	writeln!(content, "MemTotal:       {:8} kB", total / 1024).unwrap();
+	writeln!(content, "MemFree:        {:8} kB", free / 1024).unwrap();
+
+	let (heap_used, heap_total) = crate::mm::heap_usage();
+	writeln!(content, "HeapTotal:      {:8} kB", heap_total / 1024).unwrap();
+	writeln!(content, "HeapUsed:       {:8} kB", heap_used / 1024).unwrap();
+
+	content
+}
+
+fn cpuinfo() -> String {
+	let model_name = arch::processor::model_name().unwrap_or_else(|| "unknown".to_string());
+	let frequency = arch::processor::get_frequency();
+	let features = arch::processor::features();
+
+	let mut content = String::new();
+	for processor in 0..arch::get_processor_count() {
+		writeln!(content, "processor\t: {processor}").unwrap();
+		writeln!(content, "model name\t: {model_name}").unwrap();
+		writeln!(content, "cpu MHz\t\t: {frequency}").unwrap();
+		writeln!(content, "flags\t\t: {features}").unwrap();
+		writeln!(content).unwrap();
+	}
+
+	content
+}
+
+fn interrupts() -> String {
+	let cpus = usize::try_from(arch::get_processor_count()).unwrap();
+
+	let mut content = String::from("    ");
+	for cpu in 0..cpus {
+		write!(content, " {:>10}", format!("CPU{cpu}")).unwrap();
+	}
+	writeln!(content).unwrap();
+
+	for (irq, name, counters) in arch::interrupts::irq_statistics() {
+		write!(content, "{irq:3}:").unwrap();
+		for cpu in 0..cpus {
+			write!(
+				content,
+				" {:10}",
+				counters.get(cpu).copied().unwrap_or_default()
+			)
+			.unwrap();
+		}
+		writeln!(content, "   {}", name.unwrap_or_default()).unwrap();
+	}
+
+	content
+}
+
+fn uptime() -> String {
+	let ticks = arch::processor::get_timer_ticks();
+	format!(
+		"{}.{:02} 0.00\n",
+		ticks / 1_000_000,
+		ticks % 1_000_000 / 10_000
+	)
+}
+
+fn mounts() -> String {
+	let mut content = String::new();
+	for entry in fs::mounts() {
+		let mode = if entry.flags.contains(fs::MountFlags::MS_RDONLY) {
+			"ro"
+		} else {
+			"rw"
+		};
+		writeln!(
+			content,
+			"{} {} {} {mode} 0 0",
+			entry.source, entry.target, entry.fs_type
+		)
+		.unwrap();
+	}
+
+	content
+}
+
+fn statfs() -> StatFs {
+	StatFs {
+		f_type: PROC_SUPER_MAGIC,
+		f_bsize: 4096,
+		f_namelen: 255,
+		f_frsize: 4096,
+		..Default::default()
+	}
+}
+
+/// Returns the file descriptors of the current task.
+fn file_descriptors() -> Vec<FileDescriptor> {
+	let mut fds = crate::core_scheduler()
+		.get_current_task_object_map()
+		.read()
+		.keys()
+		.copied()
+		.collect::<Vec<_>>();
+	fds.sort_unstable();
+	fds
+}
+
+fn current_descriptor(fd: FileDescriptor) -> io::Result<Descriptor> {
+	crate::core_scheduler()
+		.get_current_task_object_map()
+		.read()
+		.get(&fd)
+		.cloned()
+		.ok_or(Errno::Noent)
+}
+
+fn current_object(fd: FileDescriptor) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+	Ok(current_descriptor(fd)?.object)
+}
+
+/// Returns the target of the link `/proc/self/fd/N` of an object, which has
+/// not been opened by a path. Like on Linux, the name consists of the type
+/// and the inode number of the object.
+async fn object_name(obj: &dyn ObjectInterface) -> String {
+	let Ok(attr) = obj.fstat().await else {
+		return String::from("anon_inode:[unknown]");
+	};
+
+	let file_type = attr.st_mode & AccessPermission::S_IFMT;
+	let kind = if file_type.bits() == AccessPermission::S_IFSOCK.bits() {
+		"socket"
+	} else if file_type.bits() == AccessPermission::S_IFIFO.bits() {
+		"pipe"
+	} else {
+		"anon_inode"
+	};
+	format!("{kind}:[{}]", attr.st_ino)
+}
+
+enum Node {
+	Directory,
+	File(Generator),
+	FileDescriptor(FileDescriptor),
+}
+
+impl Node {
+	fn lookup(path: &str) -> io::Result<Self> {
+		if path.is_empty() || DIRECTORIES.contains(&path) {
+			return Ok(Self::Directory);
+		}
+
+		if let Some((_, generator)) = FILES.iter().find(|(name, _)| *name == path) {
+			return Ok(Self::File(*generator));
+		}
+
+		let fd = path
+			.strip_prefix("self/fd/")
+			.and_then(|fd| fd.parse::<FileDescriptor>().ok())
+			.ok_or(Errno::Noent)?;
+		if file_descriptors().contains(&fd) {
+			Ok(Self::FileDescriptor(fd))
+		} else {
+			Err(Errno::Noent)
+		}
+	}
+
+	fn attributes(&self) -> FileAttr {
+		let st_mode = match self {
+			Self::Directory => {
+				AccessPermission::S_IFDIR | AccessPermission::from_bits_retain(0o555)
+			}
+			Self::File(_) => AccessPermission::S_IFREG | AccessPermission::from_bits_retain(0o444),
+			Self::FileDescriptor(_) => {
+				AccessPermission::S_IFLNK | AccessPermission::from_bits_retain(0o700)
+			}
+		};
+
+		FileAttr {
+			st_mode,
+			st_nlink: 1,
+			..Default::default()
+		}
+	}
+}
+
+/// Lists the entries of the directory `dir`.
+fn readdir(dir: &str) -> Vec<DirectoryEntry> {
+	if dir == "self/fd" {
+		return file_descriptors()
+			.into_iter()
+			.map(|fd| DirectoryEntry::new(fd.to_string()))
+			.collect();
+	}
+
+	let names = DIRECTORIES
+		.iter()
+		.copied()
+		.chain(FILES.iter().map(|(name, _)| *name));
+	names
+		.filter_map(|name| match name.rsplit_once('/') {
+			Some((parent, name)) if parent == dir => Some(name),
+			None if dir.is_empty() => Some(name),
+			_ => None,
+		})
+		.map(|name| DirectoryEntry::new(name.to_string()))
+		.collect()
+}
+
+#[derive(Debug)]
+struct ProcFileInterface {
+	/// Position within the file
+	pos: Mutex<usize>,
+	/// Snapshot of the file content
+	data: Vec<u8>,
+	attr: FileAttr,
+}
+
+impl ProcFileInterface {
+	fn new(data: String, attr: FileAttr) -> Self {
+		Self {
+			pos: Mutex::new(0),
+			data: data.into_bytes(),
+			attr,
+		}
+	}
+}
+
+#[async_trait]
+impl ObjectInterface for ProcFileInterface {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		let ret = if *self.pos.lock().await < self.data.len() {
+			event.intersection(PollEvent::POLLIN | PollEvent::POLLRDNORM | PollEvent::POLLRDBAND)
+		} else {
+			PollEvent::empty()
+		};
+
+		Ok(ret)
+	}
+
+	async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		let mut pos_guard = self.pos.lock().await;
+		let pos = *pos_guard;
+
+		if pos >= self.data.len() {
+			return Ok(0);
+		}
+
+		let len = buf.len().min(self.data.len() - pos);
+		buf[..len].copy_from_slice(&self.data[pos..pos + len]);
+		*pos_guard = pos + len;
+
+		Ok(len)
+	}
+
+	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+		let mut pos_guard = self.pos.lock().await;
+
+		let new_pos = match whence {
+			SeekWhence::Set => offset,
+			SeekWhence::Cur => isize::try_from(*pos_guard).unwrap() + offset,
+			SeekWhence::End => isize::try_from(self.data.len()).unwrap() + offset,
+			_ => return Err(Errno::Inval),
+		};
+
+		*pos_guard = usize::try_from(new_pos).map_err(|_| Errno::Inval)?;
+		Ok(new_pos)
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		Ok(self.attr)
+	}
+
+	async fn fstatfs(&self) -> io::Result<StatFs> {
+		Ok(statfs())
+	}
+}
+
+#[derive(Debug)]
+pub(crate) struct ProcDirectory;
+
+impl ProcDirectory {
+	pub fn new() -> Self {
+		Self
+	}
+}
+
+impl VfsNode for ProcDirectory {
+	fn get_kind(&self) -> NodeKind {
+		NodeKind::Directory
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		Ok(Node::Directory.attributes())
+	}
+
+	fn get_object(&self) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		Ok(Arc::new(async_lock::RwLock::new(DirectoryReader::new(
+			readdir(""),
+			Node::Directory.attributes(),
+		))))
+	}
+
+	fn traverse_mkdir(
+		&self,
+		components: &mut Vec<&str>,
+		_mode: AccessPermission,
+	) -> io::Result<()> {
+		match Node::lookup(&relative_path(components)) {
+			Ok(_) => Err(Errno::Exist),
+			Err(_) => Err(Errno::Acces),
+		}
+	}
+
+	fn traverse_rmdir(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		Node::lookup(&relative_path(components))?;
+		Err(Errno::Acces)
+	}
+
+	fn traverse_unlink(&self, components: &mut Vec<&str>) -> io::Result<()> {
+		Node::lookup(&relative_path(components))?;
+		Err(Errno::Acces)
+	}
+
+	fn traverse_readdir(&self, components: &mut Vec<&str>) -> io::Result<Vec<DirectoryEntry>> {
+		let path = relative_path(components);
+		match Node::lookup(&path)? {
+			Node::Directory => Ok(readdir(&path)),
+			_ => Err(Errno::Notdir),
+		}
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		Ok(Node::lookup(&relative_path(components))?.attributes())
+	}
+
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		match Node::lookup(&relative_path(components))? {
+			Node::FileDescriptor(fd) => {
+				let obj = current_object(fd)?;
+				block_on(async { obj.read().await.fstat().await }, None)
+			}
+			node => Ok(node.attributes()),
+		}
+	}
+
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		Node::lookup(&relative_path(components))?;
+		Ok(statfs())
+	}
+
+	/// Resolves file descriptor links to the path, by which the object has
+	/// been opened, or to the name of an object without a path.
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		match Node::lookup(&relative_path(components))? {
+			Node::FileDescriptor(fd) => {
+				let descriptor = current_descriptor(fd)?;
+				if let Some(path) = descriptor.path {
+					return Ok(path);
+				}
+				block_on(
+					async { Ok(object_name(&*descriptor.object.read().await).await) },
+					None,
+				)
+			}
+			_ => Err(Errno::Inval),
+		}
+	}
+
+	fn traverse_open(
+		&self,
+		components: &mut Vec<&str>,
+		opt: OpenOption,
+		_mode: AccessPermission,
+	) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		let path = relative_path(components);
+		let node = Node::lookup(&path)?;
+		let attr = node.attributes();
+
+		if opt.contains(OpenOption::O_CREAT | OpenOption::O_EXCL) {
+			return Err(Errno::Exist);
+		}
+
+		match node {
+			Node::Directory => {
+				if opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR) {
+					return Err(Errno::Isdir);
+				}
+				Ok(Arc::new(async_lock::RwLock::new(DirectoryReader::new(
+					readdir(&path),
+					Node::Directory.attributes(),
+				))))
+			}
+			_ if opt.contains(OpenOption::O_DIRECTORY) => Err(Errno::Notdir),
+			Node::File(generator) => {
+				if opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR | OpenOption::O_TRUNC) {
+					return Err(Errno::Acces);
+				}
+				Ok(Arc::new(async_lock::RwLock::new(ProcFileInterface::new(
+					generator(),
+					attr,
+				))))
+			}
+			// Opening a file descriptor link refers to the already open object.
+			Node::FileDescriptor(fd) => current_object(fd),
+		}
+	}
+}
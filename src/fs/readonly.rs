@@ -0,0 +1,313 @@
+//! Read-only view of a file system, which enforces `MS_RDONLY` mounts
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+
+use async_trait::async_trait;
+
+use crate::errno::Errno;
+use crate::fd::lock::{LockOwner, RecordLock};
+use crate::fd::{AccessPermission, ObjectInterface, OpenOption, PollEvent, StatusFlags};
+use crate::fs::{
+	DirectoryEntry, FallocateFlags, FileAttr, MountFlags, NodeKind, SeekWhence, StatFs, VfsNode,
+	XattrFlags,
+};
+use crate::io;
+use crate::time::timespec;
+
+/// Marks the statistics of a file system as read-only.
+fn read_only_statfs(mut statfs: StatFs) -> StatFs {
+	statfs.f_flags |= i64::try_from(MountFlags::MS_RDONLY.bits()).unwrap();
+	statfs
+}
+
+/// Object of a read-only file system, which rejects all modifications through
+/// its file descriptors with `EROFS`.
+#[derive(Debug)]
+struct ReadOnlyObject {
+	inner: Arc<async_lock::RwLock<dyn ObjectInterface>>,
+}
+
+impl ReadOnlyObject {
+	fn wrap(
+		inner: Arc<async_lock::RwLock<dyn ObjectInterface>>,
+	) -> Arc<async_lock::RwLock<dyn ObjectInterface>> {
+		Arc::new(async_lock::RwLock::new(Self { inner }))
+	}
+}
+
+#[async_trait]
+impl ObjectInterface for ReadOnlyObject {
+	async fn poll(&self, event: PollEvent) -> io::Result<PollEvent> {
+		self.inner.read().await.poll(event).await
+	}
+
+	async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+		self.inner.read().await.read(buf).await
+	}
+
+	async fn write(&self, _buf: &[u8]) -> io::Result<usize> {
+		Err(Errno::Rofs)
+	}
+
+	async fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
+		self.inner.read().await.lseek(offset, whence).await
+	}
+
+	async fn fstat(&self) -> io::Result<FileAttr> {
+		self.inner.read().await.fstat().await
+	}
+
+	async fn fstatfs(&self) -> io::Result<StatFs> {
+		self.inner
+			.read()
+			.await
+			.fstatfs()
+			.await
+			.map(read_only_statfs)
+	}
+
+	async fn getdents(&self, buf: &mut [MaybeUninit<u8>]) -> io::Result<usize> {
+		self.inner.read().await.getdents(buf).await
+	}
+
+	async fn status_flags(&self) -> io::Result<StatusFlags> {
+		self.inner.read().await.status_flags().await
+	}
+
+	async fn set_status_flags(&mut self, status_flags: StatusFlags) -> io::Result<()> {
+		self.inner
+			.write()
+			.await
+			.set_status_flags(status_flags)
+			.await
+	}
+
+	async fn truncate(&self, _size: usize) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	async fn fallocate(
+		&self,
+		_mode: FallocateFlags,
+		_offset: usize,
+		_len: usize,
+	) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	async fn chmod(&self, _access_permission: AccessPermission) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	async fn utimens(&self, _atime: Option<timespec>, _mtime: Option<timespec>) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	async fn chown(&self, _uid: Option<u32>, _gid: Option<u32>) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	async fn getxattr(&self, name: &str) -> io::Result<Vec<u8>> {
+		self.inner.read().await.getxattr(name).await
+	}
+
+	async fn setxattr(&self, _name: &str, _value: &[u8], _flags: XattrFlags) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	async fn listxattr(&self) -> io::Result<Vec<String>> {
+		self.inner.read().await.listxattr().await
+	}
+
+	async fn removexattr(&self, _name: &str) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	async fn getlk(&self, lock: RecordLock) -> io::Result<Option<RecordLock>> {
+		self.inner.read().await.getlk(lock).await
+	}
+
+	async fn setlk(&self, lock: RecordLock, wait: bool) -> io::Result<()> {
+		self.inner.read().await.setlk(lock, wait).await
+	}
+
+	async fn release_locks(&self, owner: LockOwner) {
+		self.inner.read().await.release_locks(owner).await;
+	}
+
+	async fn isatty(&self) -> io::Result<bool> {
+		self.inner.read().await.isatty().await
+	}
+
+	async fn dirpath(&self) -> io::Result<String> {
+		self.inner.read().await.dirpath().await
+	}
+
+	async fn set_dirpath(&self, path: &str) {
+		self.inner.read().await.set_dirpath(path).await;
+	}
+}
+
+/// Read-only view of a file system, which rejects all modifications through
+/// paths and opened objects with `EROFS`.
+#[derive(Debug)]
+pub(crate) struct ReadOnlyDirectory {
+	inner: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+}
+
+impl ReadOnlyDirectory {
+	pub fn new(inner: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>) -> Self {
+		Self { inner }
+	}
+}
+
+impl VfsNode for ReadOnlyDirectory {
+	fn get_kind(&self) -> NodeKind {
+		self.inner.get_kind()
+	}
+
+	fn get_file_attributes(&self) -> io::Result<FileAttr> {
+		self.inner.get_file_attributes()
+	}
+
+	fn get_object(&self) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		self.inner.get_object().map(ReadOnlyObject::wrap)
+	}
+
+	fn traverse_mkdir(
+		&self,
+		_components: &mut Vec<&str>,
+		_mode: AccessPermission,
+	) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_rmdir(&self, _components: &mut Vec<&str>) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_unlink(&self, _components: &mut Vec<&str>) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_readdir(&self, components: &mut Vec<&str>) -> io::Result<Vec<DirectoryEntry>> {
+		self.inner.traverse_readdir(components)
+	}
+
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		self.inner.traverse_lstat(components)
+	}
+
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		self.inner.traverse_stat(components)
+	}
+
+	fn traverse_mount(
+		&self,
+		components: &mut Vec<&str>,
+		obj: Box<dyn VfsNode + core::marker::Send + core::marker::Sync>,
+	) -> io::Result<()> {
+		self.inner.traverse_mount(components, obj)
+	}
+
+	fn traverse_open(
+		&self,
+		components: &mut Vec<&str>,
+		mut opt: OpenOption,
+		mode: AccessPermission,
+	) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
+		if opt.intersects(OpenOption::O_WRONLY | OpenOption::O_RDWR | OpenOption::O_TRUNC) {
+			return Err(Errno::Rofs);
+		}
+
+		// Like on Linux, `O_CREAT` only fails if the file would be created.
+		if opt.contains(OpenOption::O_CREAT) {
+			match self.inner.traverse_lstat(&mut components.clone()) {
+				Ok(_) if opt.contains(OpenOption::O_EXCL) => return Err(Errno::Exist),
+				Ok(_) => opt.remove(OpenOption::O_CREAT),
+				Err(Errno::Noent) => return Err(Errno::Rofs),
+				Err(e) => return Err(e),
+			}
+		}
+
+		self.inner
+			.traverse_open(components, opt, mode)
+			.map(ReadOnlyObject::wrap)
+	}
+
+	fn traverse_create_file(
+		&self,
+		_components: &mut Vec<&str>,
+		_data: &'static [u8],
+		_mode: AccessPermission,
+	) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_statfs(&self, components: &mut Vec<&str>) -> io::Result<StatFs> {
+		self.inner.traverse_statfs(components).map(read_only_statfs)
+	}
+
+	fn traverse_utimens(
+		&self,
+		_components: &mut Vec<&str>,
+		_atime: Option<timespec>,
+		_mtime: Option<timespec>,
+	) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_chown(
+		&self,
+		_components: &mut Vec<&str>,
+		_uid: Option<u32>,
+		_gid: Option<u32>,
+	) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_getxattr(&self, components: &mut Vec<&str>, name: &str) -> io::Result<Vec<u8>> {
+		self.inner.traverse_getxattr(components, name)
+	}
+
+	fn traverse_setxattr(
+		&self,
+		_components: &mut Vec<&str>,
+		_name: &str,
+		_value: &[u8],
+		_flags: XattrFlags,
+	) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_listxattr(&self, components: &mut Vec<&str>) -> io::Result<Vec<String>> {
+		self.inner.traverse_listxattr(components)
+	}
+
+	fn traverse_removexattr(&self, _components: &mut Vec<&str>, _name: &str) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_readlink(&self, components: &mut Vec<&str>) -> io::Result<String> {
+		self.inner.traverse_readlink(components)
+	}
+
+	fn traverse_symlink(&self, _components: &mut Vec<&str>, _target: &str) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_rename(&self, _from: &mut Vec<&str>, _to: &mut Vec<&str>) -> io::Result<()> {
+		Err(Errno::Rofs)
+	}
+
+	fn traverse_detach(
+		&self,
+		_components: &mut Vec<&str>,
+	) -> io::Result<Box<dyn VfsNode + core::marker::Send + core::marker::Sync>> {
+		Err(Errno::Rofs)
+	}
+}
@@ -16,32 +16,91 @@ use uhyve_interface::{GuestPhysAddr, GuestVirtAddr, Hypercall};
 use crate::arch::mm::paging;
 use crate::errno::Errno;
 use crate::fs::{
-	self, AccessPermission, FileAttr, NodeKind, ObjectInterface, OpenOption, SeekWhence, VfsNode,
+	self, AccessPermission, FileAttr, MountFlags, NodeKind, ObjectInterface, OpenOption,
+	SeekWhence, VfsNode,
 };
 use crate::io;
 use crate::syscalls::interfaces::uhyve::uhyve_hypercall;
 
+/// File system hypercalls of uhyve
 #[derive(Debug)]
-struct UhyveFileHandleInner(i32);
+pub(crate) enum FsHypercall<'a> {
+	Open(&'a mut OpenParams),
+	Close(&'a mut CloseParams),
+	Read(&'a mut ReadParams),
+	Write(&'a WriteParams),
+	Lseek(&'a mut LseekParams),
+	Unlink(&'a mut UnlinkParams),
+}
+
+/// Performs the file system hypercalls, which allows replacing uhyve by a mock in tests.
+pub(crate) trait HypercallHandler: Send + Sync + core::fmt::Debug {
+	/// Returns the address of `ptr`, which is passed to the host.
+	fn guest_phys_addr(&self, ptr: *const u8) -> GuestPhysAddr;
+
+	fn hypercall(&self, hypercall: FsHypercall<'_>);
+}
+
+/// Forwards the file system hypercalls to uhyve
+#[derive(Debug)]
+struct UhyveHandler;
+
+impl HypercallHandler for UhyveHandler {
+	fn guest_phys_addr(&self, ptr: *const u8) -> GuestPhysAddr {
+		GuestPhysAddr::new(
+			paging::virtual_to_physical(VirtAddr::from_ptr(ptr))
+				.unwrap()
+				.as_u64(),
+		)
+	}
+
+	fn hypercall(&self, hypercall: FsHypercall<'_>) {
+		match hypercall {
+			FsHypercall::Open(params) => uhyve_hypercall(Hypercall::FileOpen(params)),
+			FsHypercall::Close(params) => uhyve_hypercall(Hypercall::FileClose(params)),
+			FsHypercall::Read(params) => uhyve_hypercall(Hypercall::FileRead(params)),
+			FsHypercall::Write(params) => uhyve_hypercall(Hypercall::FileWrite(params)),
+			FsHypercall::Lseek(params) => uhyve_hypercall(Hypercall::FileLseek(params)),
+			FsHypercall::Unlink(params) => uhyve_hypercall(Hypercall::FileUnlink(params)),
+		}
+	}
+}
+
+/// Converts the return value of a hypercall, where negative values are the
+/// negated errno of the host.
+fn host_result(ret: i64) -> io::Result<i64> {
+	if ret >= 0 {
+		Ok(ret)
+	} else {
+		Err(i32::try_from(-ret)
+			.ok()
+			.and_then(|errno| Errno::try_from(errno).ok())
+			.unwrap_or(Errno::Io))
+	}
+}
+
+#[derive(Debug)]
+struct UhyveFileHandleInner {
+	fd: i32,
+	handler: &'static dyn HypercallHandler,
+}
 
 impl UhyveFileHandleInner {
-	pub fn new(fd: i32) -> Self {
-		Self(fd)
+	pub fn new(fd: i32, handler: &'static dyn HypercallHandler) -> Self {
+		Self { fd, handler }
 	}
 
 	fn lseek(&self, offset: isize, whence: SeekWhence) -> io::Result<isize> {
 		let mut lseek_params = LseekParams {
-			fd: self.0,
+			fd: self.fd,
 			offset,
 			whence: u8::from(whence).into(),
 		};
-		uhyve_hypercall(Hypercall::FileLseek(&mut lseek_params));
+		self.handler
+			.hypercall(FsHypercall::Lseek(&mut lseek_params));
 
-		if lseek_params.offset >= 0 {
-			Ok(lseek_params.offset)
-		} else {
-			Err(Errno::Inval)
-		}
+		let offset = lseek_params.offset; // circumvent packed field access
+		host_result(offset as i64).map(|offset| offset as isize)
 	}
 }
 
@@ -52,29 +111,28 @@ impl ErrorType for UhyveFileHandleInner {
 impl Read for UhyveFileHandleInner {
 	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
 		let mut read_params = ReadParams {
-			fd: self.0,
+			fd: self.fd,
 			buf: GuestVirtAddr::new(buf.as_mut_ptr() as u64),
 			len: buf.len(),
 			ret: 0,
 		};
-		uhyve_hypercall(Hypercall::FileRead(&mut read_params));
+		self.handler.hypercall(FsHypercall::Read(&mut read_params));
 
-		if read_params.ret >= 0 {
-			Ok(read_params.ret.try_into().unwrap())
-		} else {
-			Err(Errno::Io)
-		}
+		let ret = read_params.ret; // circumvent packed field access
+		host_result(ret as i64).map(|len| len.try_into().unwrap())
 	}
 }
 
 impl Write for UhyveFileHandleInner {
+	/// Writes `buf` to the host file. uhyve writes the whole buffer or stops
+	/// the virtual machine, so the hypercall has no result.
 	fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
 		let write_params = WriteParams {
-			fd: self.0,
+			fd: self.fd,
 			buf: GuestVirtAddr::new(buf.as_ptr() as u64),
 			len: buf.len(),
 		};
-		uhyve_hypercall(Hypercall::FileWrite(&write_params));
+		self.handler.hypercall(FsHypercall::Write(&write_params));
 
 		Ok(write_params.len)
 	}
@@ -86,11 +144,15 @@ impl Write for UhyveFileHandleInner {
 
 impl Drop for UhyveFileHandleInner {
 	fn drop(&mut self) {
-		let mut close_params = CloseParams { fd: self.0, ret: 0 };
-		uhyve_hypercall(Hypercall::FileClose(&mut close_params));
-		if close_params.ret != 0 {
-			let ret = close_params.ret; // circumvent packed field access
-			panic!("Can't close fd {} - return value {ret}", self.0);
+		let mut close_params = CloseParams {
+			fd: self.fd,
+			ret: 0,
+		};
+		self.handler
+			.hypercall(FsHypercall::Close(&mut close_params));
+		let ret = close_params.ret; // circumvent packed field access
+		if let Err(err) = host_result(ret.into()) {
+			error!("Unable to close host fd {}: {err:?}", self.fd);
 		}
 	}
 }
@@ -99,8 +161,8 @@ impl Drop for UhyveFileHandleInner {
 struct UhyveFileHandle(pub Arc<Mutex<UhyveFileHandleInner>>);
 
 impl UhyveFileHandle {
-	pub fn new(fd: i32) -> Self {
-		Self(Arc::new(Mutex::new(UhyveFileHandleInner::new(fd))))
+	fn new(inner: UhyveFileHandleInner) -> Self {
+		Self(Arc::new(Mutex::new(inner)))
 	}
 }
 
@@ -128,11 +190,19 @@ impl Clone for UhyveFileHandle {
 #[derive(Debug)]
 pub(crate) struct UhyveDirectory {
 	prefix: Option<String>,
+	handler: &'static dyn HypercallHandler,
 }
 
 impl UhyveDirectory {
 	pub const fn new(prefix: Option<String>) -> Self {
-		UhyveDirectory { prefix }
+		Self::with_handler(prefix, &UhyveHandler)
+	}
+
+	pub const fn with_handler(
+		prefix: Option<String>,
+		handler: &'static dyn HypercallHandler,
+	) -> Self {
+		UhyveDirectory { prefix, handler }
 	}
 
 	fn traversal_path(&self, components: &[&str]) -> CString {
@@ -149,6 +219,58 @@ impl UhyveDirectory {
 			CString::new(path).unwrap()
 		}
 	}
+
+	fn open_path(
+		&self,
+		path: &CString,
+		opt: OpenOption,
+		mode: AccessPermission,
+	) -> io::Result<UhyveFileHandleInner> {
+		let mut open_params = OpenParams {
+			name: self.handler.guest_phys_addr(path.as_ptr().cast()),
+			flags: opt.bits(),
+			mode: mode.bits() as i32,
+			ret: -1,
+		};
+		self.handler.hypercall(FsHypercall::Open(&mut open_params));
+
+		let fd = host_result(open_params.ret.into())?;
+		Ok(UhyveFileHandleInner::new(
+			fd.try_into().unwrap(),
+			self.handler,
+		))
+	}
+
+	/// Determines the attributes of the host file `path`.
+	///
+	/// uhyve has no hypercall for `stat`. Instead, the file is opened for
+	/// reading: the host refuses to read a directory with `EISDIR`, and the
+	/// size of a file is the offset of its end. Owners and timestamps are
+	/// unknown, and files, which the host does not allow to read, cannot be
+	/// examined.
+	fn stat_path(&self, path: &CString) -> io::Result<FileAttr> {
+		let mut file = self.open_path(path, OpenOption::O_RDONLY, AccessPermission::empty())?;
+
+		let mut byte = [0; 1];
+		match file.read(&mut byte) {
+			Err(Errno::Isdir) => Ok(FileAttr {
+				st_nlink: 1,
+				st_mode: AccessPermission::S_IFDIR | AccessPermission::from_bits_truncate(0o555),
+				..Default::default()
+			}),
+			Err(err) => Err(err),
+			Ok(_) => {
+				let size = file.lseek(0, SeekWhence::End)?;
+				Ok(FileAttr {
+					st_nlink: 1,
+					st_mode: AccessPermission::S_IFREG
+						| AccessPermission::from_bits_truncate(0o444),
+					st_size: size.try_into().unwrap(),
+					..Default::default()
+				})
+			}
+		}
+	}
 }
 
 impl VfsNode for UhyveDirectory {
@@ -157,12 +279,14 @@ impl VfsNode for UhyveDirectory {
 		NodeKind::Directory
 	}
 
-	fn traverse_stat(&self, _components: &mut Vec<&str>) -> io::Result<FileAttr> {
-		Err(Errno::Nosys)
+	fn traverse_stat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		self.stat_path(&self.traversal_path(components))
 	}
 
-	fn traverse_lstat(&self, _components: &mut Vec<&str>) -> io::Result<FileAttr> {
-		Err(Errno::Nosys)
+	/// Like [`traverse_stat`](Self::traverse_stat), because the host follows
+	/// symbolic links, when it opens a file.
+	fn traverse_lstat(&self, components: &mut Vec<&str>) -> io::Result<FileAttr> {
+		self.stat_path(&self.traversal_path(components))
 	}
 
 	fn traverse_open(
@@ -172,52 +296,31 @@ impl VfsNode for UhyveDirectory {
 		mode: AccessPermission,
 	) -> io::Result<Arc<async_lock::RwLock<dyn ObjectInterface>>> {
 		let path = self.traversal_path(components);
-
-		let mut open_params = OpenParams {
-			name: GuestPhysAddr::new(
-				paging::virtual_to_physical(VirtAddr::from_ptr(path.as_ptr()))
-					.unwrap()
-					.as_u64(),
-			),
-			flags: opt.bits(),
-			mode: mode.bits() as i32,
-			ret: -1,
-		};
-		uhyve_hypercall(Hypercall::FileOpen(&mut open_params));
-
-		if open_params.ret > 0 {
-			Ok(Arc::new(async_lock::RwLock::new(UhyveFileHandle::new(
-				open_params.ret,
-			))))
-		} else {
-			Err(Errno::Io)
-		}
+		let file = self.open_path(&path, opt, mode)?;
+		Ok(Arc::new(async_lock::RwLock::new(UhyveFileHandle::new(
+			file,
+		))))
 	}
 
 	fn traverse_unlink(&self, components: &mut Vec<&str>) -> io::Result<()> {
 		let path = self.traversal_path(components);
 
 		let mut unlink_params = UnlinkParams {
-			name: GuestPhysAddr::new(
-				paging::virtual_to_physical(VirtAddr::from_ptr(path.as_ptr()))
-					.unwrap()
-					.as_u64(),
-			),
+			name: self.handler.guest_phys_addr(path.as_ptr().cast()),
 			ret: -1,
 		};
-		uhyve_hypercall(Hypercall::FileUnlink(&mut unlink_params));
+		self.handler
+			.hypercall(FsHypercall::Unlink(&mut unlink_params));
 
-		if unlink_params.ret == 0 {
-			Ok(())
-		} else {
-			Err(Errno::Io)
-		}
+		host_result(unlink_params.ret.into()).map(|_| ())
 	}
 
+	/// uhyve has no hypercall for removing directories.
 	fn traverse_rmdir(&self, _components: &mut Vec<&str>) -> io::Result<()> {
 		Err(Errno::Nosys)
 	}
 
+	/// uhyve has no hypercall for creating directories.
 	fn traverse_mkdir(
 		&self,
 		_components: &mut Vec<&str>,
@@ -231,12 +334,140 @@ pub(crate) fn init() {
 	info!("Try to initialize uhyve filesystem");
 	let mount_point = hermit_var_or!("UHYVE_MOUNT", "/root").to_string();
 	info!("Mounting uhyve filesystem at {mount_point}");
-	fs::FILESYSTEM
-		.get()
-		.unwrap()
-		.mount(
-			&mount_point,
-			Box::new(UhyveDirectory::new(Some(mount_point.clone()))),
-		)
-		.expect("Mount failed. Duplicate mount_point?");
+	fs::mount(
+		"uhyve",
+		&mount_point,
+		"uhyvefs",
+		MountFlags::empty(),
+		Box::new(UhyveDirectory::new(Some(mount_point.clone()))),
+	)
+	.expect("Mount failed. Duplicate mount_point?");
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use core::ffi::{CStr, c_char};
+
+	use super::*;
+
+	/// Simulates a host, which contains the file `/root/file` with
+	/// `FILE_SIZE` bytes and the directory `/root/dir`.
+	#[derive(Debug)]
+	struct MockHandler;
+
+	const FILE_FD: i32 = 3;
+	const DIR_FD: i32 = 4;
+	const FILE_SIZE: isize = 5;
+
+	fn path<'a>(name: GuestPhysAddr) -> &'a str {
+		unsafe { CStr::from_ptr(name.as_u64() as *const c_char) }
+			.to_str()
+			.unwrap()
+	}
+
+	impl HypercallHandler for MockHandler {
+		fn guest_phys_addr(&self, ptr: *const u8) -> GuestPhysAddr {
+			GuestPhysAddr::new(ptr as u64)
+		}
+
+		fn hypercall(&self, hypercall: FsHypercall<'_>) {
+			match hypercall {
+				FsHypercall::Unlink(params) => {
+					let ret = match path(params.name) {
+						"/root/file" => 0,
+						"/root/dir" => -i32::from(Errno::Isdir),
+						_ => -i32::from(Errno::Noent),
+					};
+					params.ret = ret;
+				}
+				FsHypercall::Open(params) => {
+					let ret = match path(params.name) {
+						"/root/file" => FILE_FD,
+						"/root/dir" => DIR_FD,
+						_ => -i32::from(Errno::Noent),
+					};
+					params.ret = ret;
+				}
+				FsHypercall::Read(params) => {
+					let ret = match params.fd {
+						FILE_FD => params.len.min(FILE_SIZE as usize) as isize,
+						_ => -i32::from(Errno::Isdir) as isize,
+					};
+					params.ret = ret;
+				}
+				FsHypercall::Lseek(params) => {
+					let whence = params.whence; // circumvent packed field access
+					assert_eq!(whence, i32::from(u8::from(SeekWhence::End)));
+					params.offset = FILE_SIZE + params.offset;
+				}
+				FsHypercall::Close(params) => params.ret = 0,
+				h => panic!("unexpected hypercall {h:?}"),
+			}
+		}
+	}
+
+	static MOCK: MockHandler = MockHandler;
+
+	fn root() -> UhyveDirectory {
+		UhyveDirectory::with_handler(Some("/root".to_string()), &MOCK)
+	}
+
+	#[test]
+	fn test_stat() {
+		let attr = root().traverse_stat(&mut vec!["file"]).unwrap();
+		assert!(attr.st_mode.contains(AccessPermission::S_IFREG));
+		assert_eq!(attr.st_size, FILE_SIZE as i64);
+
+		let attr = root().traverse_lstat(&mut vec!["dir"]).unwrap();
+		assert!(attr.st_mode.contains(AccessPermission::S_IFDIR));
+
+		assert_eq!(
+			root().traverse_stat(&mut vec!["missing"]).unwrap_err(),
+			Errno::Noent
+		);
+	}
+
+	#[test]
+	fn test_unsupported() {
+		let mode = AccessPermission::from_bits(0o755).unwrap();
+		assert_eq!(
+			root().traverse_readdir(&mut vec!["dir"]).unwrap_err(),
+			Errno::Nosys
+		);
+		assert_eq!(
+			root().traverse_mkdir(&mut vec!["new"], mode).unwrap_err(),
+			Errno::Nosys
+		);
+		assert_eq!(
+			root().traverse_rmdir(&mut vec!["dir"]).unwrap_err(),
+			Errno::Nosys
+		);
+	}
+
+	#[test]
+	fn test_host_errors() {
+		assert_eq!(
+			root().traverse_unlink(&mut vec!["dir"]).unwrap_err(),
+			Errno::Isdir
+		);
+		assert_eq!(
+			root()
+				.traverse_open(
+					&mut vec!["missing"],
+					OpenOption::O_RDONLY,
+					AccessPermission::empty()
+				)
+				.unwrap_err(),
+			Errno::Noent
+		);
+		assert!(
+			root()
+				.traverse_open(
+					&mut vec!["file"],
+					OpenOption::O_RDONLY,
+					AccessPermission::empty()
+				)
+				.is_ok()
+		);
+	}
 }
@@ -63,6 +63,7 @@ mod macros;
 mod logging;
 
 pub mod arch;
+mod backtrace;
 mod config;
 pub mod console;
 mod drivers;
@@ -278,6 +279,7 @@ fn application_processor_main() -> ! {
 fn panic(info: &core::panic::PanicInfo<'_>) -> ! {
 	let core_id = crate::arch::core_local::core_id();
 	panic_println!("[{core_id}][PANIC] {info}\n");
+	backtrace::print();
 
 	crate::scheduler::shutdown(1);
 }
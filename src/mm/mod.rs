@@ -43,6 +43,8 @@
 pub(crate) mod device_alloc;
 pub(crate) mod physicalmem;
 pub(crate) mod virtualmem;
+#[cfg(feature = "mman")]
+pub(crate) mod vma;
 
 use core::mem;
 use core::ops::Range;
@@ -288,6 +290,22 @@ pub(crate) fn print_information() {
 	info!("Virtual memory free list:\n{}", KERNEL_FREE_LIST.lock());
 }
 
+/// Returns the number of allocated bytes and the total size of the kernel heap.
+pub(crate) fn heap_usage() -> (usize, usize) {
+	cfg_if::cfg_if! {
+		if #[cfg(target_os = "none")] {
+			let allocator = ALLOCATOR.lock();
+			let counters = allocator.get_counters();
+			(
+				counters.allocated_bytes,
+				counters.allocated_bytes + counters.available_bytes,
+			)
+		} else {
+			(0, 0)
+		}
+	}
+}
+
 /// Maps a given physical address and size in virtual space and returns address.
 #[cfg(feature = "pci")]
 pub(crate) fn map(
@@ -0,0 +1,335 @@
+//! Virtual memory areas created by `mmap`.
+//!
+//! Each [`Vma`] describes a page-aligned range of virtual memory with uniform
+//! protection. If a VMA is backed, its pages are backed by a physically
+//! contiguous range of frames, which is kept even while the VMA is
+//! inaccessible (`PROT_NONE`). Inaccessible pages are not mapped at all, so
+//! that any access to them faults.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use free_list::{PageLayout, PageRange};
+use hermit_sync::InterruptTicketMutex;
+use memory_addresses::{PhysAddr, VirtAddr};
+
+#[cfg(target_arch = "x86_64")]
+use crate::arch::mm::paging::PageTableEntryFlagsExt;
+use crate::arch::mm::paging::{BasePageSize, PageSize, PageTableEntryFlags};
+use crate::errno::Errno;
+use crate::mm::physicalmem::PHYSICAL_FREE_LIST;
+use crate::mm::virtualmem::KERNEL_FREE_LIST;
+use crate::syscalls::mman::MemoryProtection;
+use crate::{arch, io};
+
+const PAGE_SIZE: usize = BasePageSize::SIZE as usize;
+
+/// All VMAs created by `mmap`, keyed by their start address.
+pub(crate) static VMA_LIST: InterruptTicketMutex<VmaList> =
+	InterruptTicketMutex::new(VmaList::new());
+
+#[derive(Debug, Clone, Copy)]
+struct Vma {
+	/// First virtual address of the area.
+	start: usize,
+	/// First virtual address after the area.
+	end: usize,
+	/// Access rights of all pages in this area.
+	prot: MemoryProtection,
+	/// Start of the physical frames backing this area, if any.
+	frames: Option<usize>,
+}
+
+impl Vma {
+	fn len(&self) -> usize {
+		self.end - self.start
+	}
+
+	fn count(&self) -> usize {
+		self.len() / PAGE_SIZE
+	}
+
+	fn is_mapped(&self) -> bool {
+		self.frames.is_some() && !self.prot.is_empty()
+	}
+
+	/// Splits the area at `at` and returns the upper half.
+	fn split_off(&mut self, at: usize) -> Self {
+		debug_assert!(self.start < at && at < self.end);
+
+		let upper = Self {
+			start: at,
+			end: self.end,
+			prot: self.prot,
+			frames: self.frames.map(|frames| frames + (at - self.start)),
+		};
+		self.end = at;
+		upper
+	}
+
+	/// Returns `true` if `next` directly follows `self` and both can be described by a single area.
+	fn can_merge(&self, next: &Self) -> bool {
+		if self.end != next.start || self.prot != next.prot {
+			return false;
+		}
+
+		match (self.frames, next.frames) {
+			(None, None) => true,
+			(Some(frames), Some(next_frames)) => frames + self.len() == next_frames,
+			_ => false,
+		}
+	}
+
+	fn map(&self) {
+		let frames = self.frames.unwrap();
+		debug!(
+			"Mapping {:#x}..{:#x} -> {frames:#x} ({:?})",
+			self.start, self.end, self.prot
+		);
+		arch::mm::paging::map::<BasePageSize>(
+			VirtAddr::new(self.start as u64),
+			PhysAddr::new(frames as u64),
+			self.count(),
+			page_table_flags(self.prot),
+		);
+	}
+
+	fn unmap(&self) {
+		debug!("Unmapping {:#x}..{:#x}", self.start, self.end);
+		arch::mm::paging::unmap::<BasePageSize>(VirtAddr::new(self.start as u64), self.count());
+	}
+
+	/// Allocates physical frames for this area if it is not backed yet.
+	fn back(&mut self) -> io::Result<()> {
+		if self.frames.is_none() {
+			let layout = PageLayout::from_size(self.len()).map_err(|_| Errno::Inval)?;
+			let frame_range = PHYSICAL_FREE_LIST
+				.lock()
+				.allocate(layout)
+				.map_err(|_| Errno::Nomem)?;
+			self.frames = Some(frame_range.start());
+		}
+
+		Ok(())
+	}
+
+	/// Releases the physical frames and the virtual address range of this area.
+	fn release(self) {
+		if self.is_mapped() {
+			self.unmap();
+		}
+
+		if let Some(frames) = self.frames {
+			let range = PageRange::from_start_len(frames, self.len()).unwrap();
+			if let Err(_err) = unsafe { PHYSICAL_FREE_LIST.lock().deallocate(range) } {
+				error!("Unable to deallocate {range:?}");
+			}
+		}
+
+		let range = PageRange::from_start_len(self.start, self.len()).unwrap();
+		if let Err(_err) = unsafe { KERNEL_FREE_LIST.lock().deallocate(range) } {
+			error!("Unable to deallocate {range:?}");
+		}
+	}
+}
+
+fn page_table_flags(prot: MemoryProtection) -> PageTableEntryFlags {
+	let mut flags = PageTableEntryFlags::empty();
+	flags.normal();
+	if prot.contains(MemoryProtection::Write) {
+		flags.writable();
+	} else {
+		flags.read_only();
+	}
+	if !prot.contains(MemoryProtection::Exec) {
+		flags.execute_disable();
+	}
+	flags
+}
+
+/// Validates `addr` and `size` and returns the page-aligned range `start..end`.
+fn page_range(addr: VirtAddr, size: usize) -> io::Result<(usize, usize)> {
+	let start = addr.as_usize();
+	if size == 0 || !start.is_multiple_of(PAGE_SIZE) {
+		return Err(Errno::Inval);
+	}
+
+	let end = start
+		.checked_add(size)
+		.and_then(|end| end.checked_next_multiple_of(PAGE_SIZE))
+		.ok_or(Errno::Inval)?;
+
+	Ok((start, end))
+}
+
+pub(crate) struct VmaList {
+	areas: BTreeMap<usize, Vma>,
+}
+
+impl VmaList {
+	pub const fn new() -> Self {
+		Self {
+			areas: BTreeMap::new(),
+		}
+	}
+
+	/// Creates a new area of at least `size` bytes with the protection `prot`.
+	pub fn map(&mut self, size: usize, prot: MemoryProtection) -> io::Result<VirtAddr> {
+		let size = size
+			.checked_next_multiple_of(PAGE_SIZE)
+			.ok_or(Errno::Nomem)?;
+		let layout = PageLayout::from_size(size).map_err(|_| Errno::Inval)?;
+		let page_range = KERNEL_FREE_LIST
+			.lock()
+			.allocate(layout)
+			.map_err(|_| Errno::Nomem)?;
+
+		let mut vma = Vma {
+			start: page_range.start(),
+			end: page_range.end(),
+			prot,
+			frames: None,
+		};
+
+		// Inaccessible areas are only reserved and backed once they become accessible.
+		if !prot.is_empty() {
+			if let Err(err) = vma.back() {
+				vma.release();
+				return Err(err);
+			}
+			vma.map();
+		}
+
+		self.insert(vma);
+
+		Ok(VirtAddr::new(page_range.start() as u64))
+	}
+
+	/// Removes all pages in `addr..addr + size` from the list.
+	///
+	/// Pages that are not part of any area are ignored.
+	pub fn unmap(&mut self, addr: VirtAddr, size: usize) -> io::Result<()> {
+		let (start, end) = page_range(addr, size)?;
+
+		self.split_at(start);
+		self.split_at(end);
+
+		let keys = self
+			.areas
+			.range(start..end)
+			.map(|(key, _)| *key)
+			.collect::<Vec<_>>();
+		for key in keys {
+			let vma = self.areas.remove(&key).unwrap();
+			vma.release();
+		}
+
+		Ok(())
+	}
+
+	/// Changes the protection of all pages in `addr..addr + size`.
+	///
+	/// Fails with [`Errno::Nomem`] if the range contains pages that are not part of any area.
+	pub fn protect(
+		&mut self,
+		addr: VirtAddr,
+		size: usize,
+		prot: MemoryProtection,
+	) -> io::Result<()> {
+		let (start, end) = page_range(addr, size)?;
+
+		if !self.is_covered(start, end) {
+			return Err(Errno::Nomem);
+		}
+
+		self.split_at(start);
+		self.split_at(end);
+
+		if !prot.is_empty() {
+			for vma in self.areas.range_mut(start..end).map(|(_, vma)| vma) {
+				vma.back()?;
+			}
+		}
+
+		for vma in self.areas.range_mut(start..end).map(|(_, vma)| vma) {
+			if vma.is_mapped() && prot.is_empty() {
+				vma.unmap();
+			}
+			vma.prot = prot;
+			if vma.is_mapped() {
+				vma.map();
+			}
+		}
+
+		self.merge_range(start, end);
+
+		Ok(())
+	}
+
+	/// Returns `true` if every page in `start..end` is part of an area.
+	fn is_covered(&self, start: usize, end: usize) -> bool {
+		let mut pos = start;
+		let first = self.areas.range(..=start).next_back().map(|(_, vma)| vma);
+		let rest = self.areas.range(start + 1..end).map(|(_, vma)| vma);
+		for vma in first.into_iter().chain(rest) {
+			if vma.end <= pos {
+				continue;
+			}
+			if vma.start > pos {
+				return false;
+			}
+			pos = vma.end;
+			if pos >= end {
+				return true;
+			}
+		}
+		false
+	}
+
+	/// Ensures that no area crosses `at`.
+	fn split_at(&mut self, at: usize) {
+		let Some((_, vma)) = self.areas.range_mut(..at).next_back() else {
+			return;
+		};
+
+		if vma.end > at {
+			let upper = vma.split_off(at);
+			self.areas.insert(upper.start, upper);
+		}
+	}
+
+	fn insert(&mut self, vma: Vma) {
+		let (start, end) = (vma.start, vma.end);
+		self.areas.insert(vma.start, vma);
+		self.merge_range(start, end);
+	}
+
+	/// Merges compatible neighboring areas in and around `start..end`.
+	fn merge_range(&mut self, start: usize, end: usize) {
+		let first = self
+			.areas
+			.range(..start)
+			.next_back()
+			.map_or(start, |(key, _)| *key);
+		let mut keys = self
+			.areas
+			.range(first..=end)
+			.map(|(key, _)| *key)
+			.collect::<Vec<_>>()
+			.into_iter();
+
+		let Some(mut current) = keys.next() else {
+			return;
+		};
+		for key in keys {
+			let next = self.areas[&key];
+			let vma = self.areas.get_mut(&current).unwrap();
+			if vma.can_merge(&next) {
+				vma.end = next.end;
+				self.areas.remove(&key);
+			} else {
+				current = key;
+			}
+		}
+	}
+}
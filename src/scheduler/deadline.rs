@@ -0,0 +1,362 @@
+//! Deadline scheduling class
+//!
+//! A deadline task reserves `runtime` microseconds of every `period` and has
+//! to receive them within `deadline` microseconds after the start of the
+//! period. Ready deadline tasks are ordered by their absolute deadline
+//! (earliest deadline first), and each task is throttled by a constant
+//! bandwidth server as soon as it has consumed its runtime. A task, which
+//! wakes up with more runtime than its bandwidth allows until its deadline,
+//! gets a new deadline and a new runtime (wakeup rule of the constant
+//! bandwidth server). Consequently, a misbehaving task cannot steal time from
+//! the other deadline tasks, as long as the admission control of [`admit`]
+//! accepted all of them.
+
+use core::cmp;
+
+use crate::errno::Errno;
+use crate::io;
+
+/// Fixed-point representation of the whole processing time of a core
+pub const BW_UNIT: u64 = 1 << 20;
+
+/// Maximal bandwidth of all deadline tasks on a core. The rest is left to
+/// the other scheduling classes.
+pub const MAX_BANDWIDTH: u64 = BW_UNIT * 95 / 100;
+
+/// Parameters of a deadline task in microseconds
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DeadlineParams {
+	/// Processing time, which is guaranteed in each period
+	pub runtime: u64,
+	/// Relative deadline, until the runtime has to be provided
+	pub deadline: u64,
+	/// Distance between the starts of two periods
+	pub period: u64,
+}
+
+impl DeadlineParams {
+	/// Checks that `0 < runtime <= deadline <= period`. A `period` of zero is
+	/// replaced by `deadline`.
+	pub fn new(runtime: u64, deadline: u64, period: u64) -> io::Result<Self> {
+		let period = if period == 0 { deadline } else { period };
+		if runtime == 0 || runtime > deadline || deadline > period {
+			return Err(Errno::Inval);
+		}
+
+		Ok(Self {
+			runtime,
+			deadline,
+			period,
+		})
+	}
+
+	/// Returns the share of the processing time in units of [`BW_UNIT`].
+	pub fn bandwidth(&self) -> u64 {
+		let bandwidth = u128::from(self.runtime) * u128::from(BW_UNIT) / u128::from(self.period);
+		bandwidth.try_into().unwrap()
+	}
+}
+
+/// Replaces the bandwidth `old` of a task by `new` in the bandwidth `total`
+/// of a core and returns the new total bandwidth. Fails with `EBUSY`, if the
+/// core would be overloaded.
+pub fn admit(total: u64, old: u64, new: u64) -> io::Result<u64> {
+	let total = total - old + new;
+	if total > MAX_BANDWIDTH {
+		Err(Errno::Busy)
+	} else {
+		Ok(total)
+	}
+}
+
+/// Budget of a deadline task in the current period
+#[derive(Debug)]
+pub struct DeadlineServer {
+	params: DeadlineParams,
+	/// Start of the current period
+	period_start: u64,
+	/// Absolute deadline of the current period
+	abs_deadline: u64,
+	/// Runtime, which is left in the current period
+	remaining: u64,
+	/// Time, since which the task runs without being charged
+	since: u64,
+	/// Number of missed deadlines
+	misses: u64,
+	/// The deadline of the current period is already missed
+	missed: bool,
+	/// The job of the current period is finished
+	finished: bool,
+}
+
+impl DeadlineServer {
+	/// Creates a server, whose first period starts at `now`.
+	pub fn new(params: DeadlineParams, now: u64) -> Self {
+		Self {
+			params,
+			period_start: now,
+			abs_deadline: now + params.deadline,
+			remaining: params.runtime,
+			since: now,
+			misses: 0,
+			missed: false,
+			finished: false,
+		}
+	}
+
+	pub fn params(&self) -> DeadlineParams {
+		self.params
+	}
+
+	pub fn abs_deadline(&self) -> u64 {
+		self.abs_deadline
+	}
+
+	/// Returns the number of jobs, which did not finish until their deadline.
+	pub fn misses(&self) -> u64 {
+		self.misses
+	}
+
+	/// Returns `true` if the runtime of the current period is used up.
+	pub fn is_throttled(&self) -> bool {
+		self.remaining == 0
+	}
+
+	/// Returns the start of the next period.
+	pub fn next_release(&self) -> u64 {
+		self.period_start + self.params.period
+	}
+
+	/// Returns the time, at which the runtime is used up, if the task runs
+	/// from now on.
+	pub fn budget_end(&self) -> u64 {
+		self.since + self.remaining
+	}
+
+	/// The task starts running at `now`.
+	pub fn start(&mut self, now: u64) {
+		self.since = now;
+	}
+
+	/// Charges the time, which the task has run until `now`. A job, which
+	/// still runs after its deadline, misses the deadline.
+	pub fn charge(&mut self, now: u64) {
+		let elapsed = now.saturating_sub(self.since);
+		self.since = now;
+		self.remaining = self.remaining.saturating_sub(elapsed);
+
+		if now > self.abs_deadline && !self.missed {
+			self.misses += 1;
+			self.missed = true;
+		}
+	}
+
+	/// The task finished its job and gives up the rest of its runtime until
+	/// the next period.
+	pub fn finish_job(&mut self) {
+		self.remaining = 0;
+		self.finished = true;
+	}
+
+	/// The task becomes ready at `now`. A new period starts, if the runtime
+	/// is used up or the deadline has passed. A job, which is not finished
+	/// at this point, has exceeded its runtime and misses its deadline.
+	///
+	/// If the remaining runtime would exceed the bandwidth of the task until
+	/// the current deadline, the job continues with a new deadline and a new
+	/// runtime starting at `now`.
+	pub fn wakeup(&mut self, now: u64) {
+		if self.remaining == 0 || now >= self.abs_deadline {
+			if !self.finished && !self.missed {
+				self.misses += 1;
+			}
+
+			self.period_start = cmp::max(self.next_release(), now);
+			self.abs_deadline = self.period_start + self.params.deadline;
+			self.remaining = self.params.runtime;
+			self.since = now;
+			self.missed = false;
+			self.finished = false;
+		} else if self.overflows(now) {
+			self.period_start = now;
+			self.abs_deadline = now + self.params.deadline;
+			self.remaining = self.params.runtime;
+			self.since = now;
+		}
+	}
+
+	/// Returns `true` if `remaining / (abs_deadline - now)` is larger than
+	/// `runtime / period`.
+	fn overflows(&self, now: u64) -> bool {
+		let left = u128::from(self.remaining) * u128::from(self.params.period);
+		let right = u128::from(self.abs_deadline - now) * u128::from(self.params.runtime);
+		left > right
+	}
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+
+	/// Simulated deadline task, whose jobs need `work` microseconds
+	struct SimTask {
+		server: DeadlineServer,
+		work: u64,
+		left: u64,
+		executed: u64,
+		sleeping: bool,
+	}
+
+	impl SimTask {
+		fn new(runtime: u64, period: u64, work: u64) -> Self {
+			let params = DeadlineParams::new(runtime, period, period).unwrap();
+			Self {
+				server: DeadlineServer::new(params, 0),
+				work,
+				left: work,
+				executed: 0,
+				sleeping: false,
+			}
+		}
+	}
+
+	/// Runs the tasks on a single core with earliest deadline first for
+	/// `duration` microseconds.
+	fn simulate(tasks: &mut [SimTask], duration: u64) {
+		const STEP: u64 = 10;
+
+		for now in (0..duration).step_by(STEP as usize) {
+			for task in tasks.iter_mut() {
+				if task.sleeping && now >= task.server.next_release() {
+					task.server.wakeup(now);
+					task.sleeping = false;
+					if task.left == 0 {
+						task.left = task.work;
+					}
+				}
+			}
+
+			let Some(task) = tasks
+				.iter_mut()
+				.filter(|task| !task.sleeping)
+				.min_by_key(|task| task.server.abs_deadline())
+			else {
+				continue;
+			};
+
+			task.server.start(now);
+			task.server.charge(now + STEP);
+			task.left = task.left.saturating_sub(STEP);
+			task.executed += STEP;
+			if task.left == 0 {
+				task.server.finish_job();
+				task.sleeping = true;
+			} else if task.server.is_throttled() {
+				task.sleeping = true;
+			}
+		}
+	}
+
+	#[test]
+	fn test_params() {
+		assert_eq!(
+			DeadlineParams::new(1_000, 5_000, 0),
+			Ok(DeadlineParams {
+				runtime: 1_000,
+				deadline: 5_000,
+				period: 5_000,
+			})
+		);
+		assert_eq!(DeadlineParams::new(0, 5_000, 10_000), Err(Errno::Inval));
+		assert_eq!(DeadlineParams::new(6_000, 5_000, 10_000), Err(Errno::Inval));
+		assert_eq!(DeadlineParams::new(1_000, 5_000, 4_000), Err(Errno::Inval));
+		assert_eq!(
+			DeadlineParams::new(2_500, 10_000, 10_000)
+				.unwrap()
+				.bandwidth(),
+			BW_UNIT / 4
+		);
+	}
+
+	#[test]
+	fn test_admission() {
+		let half = BW_UNIT / 2;
+		let total = admit(0, 0, half).unwrap();
+		let total = admit(total, 0, BW_UNIT * 2 / 5).unwrap();
+		assert_eq!(admit(total, 0, BW_UNIT / 10), Err(Errno::Busy));
+		assert_eq!(
+			admit(total, half, BW_UNIT / 10),
+			Ok(BW_UNIT * 2 / 5 + BW_UNIT / 10)
+		);
+		assert_eq!(admit(0, 0, BW_UNIT), Err(Errno::Busy));
+	}
+
+	#[test]
+	fn test_wakeup_rule() {
+		let params = DeadlineParams::new(2_000, 10_000, 10_000).unwrap();
+		let mut server = DeadlineServer::new(params, 0);
+		server.charge(500);
+
+		// The remaining runtime fits into the bandwidth until the deadline.
+		server.wakeup(1_000);
+		server.start(1_000);
+		assert_eq!(server.abs_deadline(), 10_000);
+		assert_eq!(server.budget_end(), 2_500);
+
+		// The remaining runtime would exceed the bandwidth until the deadline.
+		server.wakeup(9_000);
+		assert_eq!(server.abs_deadline(), 19_000);
+		assert_eq!(server.budget_end(), 11_000);
+		assert_eq!(server.misses(), 0);
+	}
+
+	#[test]
+	fn test_no_misses_when_admitted() {
+		let mut tasks = [
+			SimTask::new(2_000, 10_000, 2_000),
+			SimTask::new(2_500, 5_000, 2_500),
+			SimTask::new(4_000, 20_000, 4_000),
+		];
+		let total = tasks.iter().try_fold(0, |total, task| {
+			admit(total, 0, task.server.params().bandwidth())
+		});
+		assert!(total.is_ok());
+
+		simulate(&mut tasks, 1_000_000);
+		for task in &tasks {
+			assert_eq!(task.server.misses(), 0);
+		}
+		assert_eq!(tasks[1].executed, 500_000);
+	}
+
+	#[test]
+	fn test_misses_under_overload() {
+		let mut tasks = [
+			SimTask::new(5_000, 10_000, 5_000),
+			SimTask::new(2_500, 5_000, 2_500),
+			SimTask::new(8_000, 20_000, 8_000),
+		];
+		let total = tasks.iter().try_fold(0, |total, task| {
+			admit(total, 0, task.server.params().bandwidth())
+		});
+		assert_eq!(total, Err(Errno::Busy));
+
+		simulate(&mut tasks, 1_000_000);
+		assert!(tasks.iter().map(|task| task.server.misses()).sum::<u64>() > 0);
+	}
+
+	#[test]
+	fn test_overrun_is_isolated() {
+		// The first task never finishes its job and is throttled in every period.
+		let mut tasks = [
+			SimTask::new(3_000, 10_000, u64::MAX),
+			SimTask::new(2_000, 5_000, 2_000),
+		];
+
+		simulate(&mut tasks, 1_000_000);
+		assert_eq!(tasks[0].server.misses(), 99);
+		assert_eq!(tasks[0].executed, 300_000);
+		assert_eq!(tasks[1].server.misses(), 0);
+		assert_eq!(tasks[1].executed, 400_000);
+	}
+}
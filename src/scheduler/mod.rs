@@ -3,14 +3,15 @@
 use alloc::boxed::Box;
 use alloc::collections::{BTreeMap, VecDeque};
 use alloc::rc::Rc;
+use alloc::string::String;
 use alloc::sync::Arc;
 #[cfg(feature = "smp")]
 use alloc::vec::Vec;
 use core::cell::RefCell;
-use core::ptr;
 #[cfg(all(target_arch = "x86_64", feature = "smp"))]
 use core::sync::atomic::AtomicBool;
 use core::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use core::{fmt, ptr};
 
 use ahash::RandomState;
 use crossbeam_utils::Backoff;
@@ -26,11 +27,14 @@ use crate::arch::switch::switch_to_task;
 use crate::arch::switch::{switch_to_fpu_owner, switch_to_task};
 use crate::arch::{get_processor_count, interrupts};
 use crate::errno::Errno;
-use crate::fd::{FileDescriptor, ObjectInterface};
+use crate::fd::{Descriptor, DescriptorFlags, FileDescriptor, ObjectInterface};
 use crate::kernel::scheduler::TaskStacks;
+use crate::scheduler::deadline::{DeadlineParams, DeadlineServer};
 use crate::scheduler::task::*;
+use crate::synch::futex;
 use crate::{arch, io};
 
+pub mod deadline;
 pub mod task;
 
 static NO_TASKS: AtomicU32 = AtomicU32::new(0);
@@ -40,22 +44,148 @@ static SCHEDULER_INPUTS: SpinMutex<Vec<&InterruptTicketMutex<SchedulerInput>>> =
 	SpinMutex::new(Vec::new());
 #[cfg(all(target_arch = "x86_64", feature = "smp"))]
 static CORE_HLT_STATE: SpinMutex<Vec<&AtomicBool>> = SpinMutex::new(Vec::new());
+/// Map between Core ID and the number of runnable tasks on this core
+#[cfg(feature = "smp")]
+static CORE_LOADS: SpinMutex<Vec<&AtomicU32>> = SpinMutex::new(Vec::new());
 /// Map between Task ID and Queue of waiting tasks
 static WAITING_TASKS: InterruptTicketMutex<BTreeMap<TaskId, VecDeque<TaskHandle>>> =
 	InterruptTicketMutex::new(BTreeMap::new());
-/// Map between Task ID and TaskHandle
-static TASKS: InterruptTicketMutex<BTreeMap<TaskId, TaskHandle>> =
+/// Map between Task ID and TaskEntry
+static TASKS: InterruptTicketMutex<BTreeMap<TaskId, TaskEntry>> =
+	InterruptTicketMutex::new(BTreeMap::new());
+/// Map between Task ID and the cores, on which the task is allowed to run
+static AFFINITIES: InterruptTicketMutex<BTreeMap<TaskId, CpuSet>> =
+	InterruptTicketMutex::new(BTreeMap::new());
+/// Map between Task ID and the scheduling attributes of tasks, which do not
+/// use [`SchedPolicy::Other`]
+static SCHED_ATTRS: InterruptTicketMutex<BTreeMap<TaskId, SchedAttr>> =
+	InterruptTicketMutex::new(BTreeMap::new());
+/// Map between Core ID and the bandwidth, which is reserved by its deadline tasks
+static DEADLINE_BANDWIDTHS: InterruptTicketMutex<BTreeMap<CoreId, u64>> =
 	InterruptTicketMutex::new(BTreeMap::new());
 
 /// Unique identifier for a core.
 pub type CoreId = u32;
 
+/// A task in [`TASKS`]. Finished tasks stay in the map, until they are joined.
+struct TaskEntry {
+	handle: TaskHandle,
+	stats: Arc<TaskStats>,
+	/// Name of the task, which is used for debugging
+	name: Option<String>,
+	/// Size of the user stack in bytes
+	stack_size: usize,
+	/// Exit status of a finished task
+	exit_status: Option<i32>,
+	/// The task is removed from the map as soon as it finishes.
+	detached: bool,
+}
+
+impl TaskEntry {
+	fn new(handle: TaskHandle, stats: Arc<TaskStats>, stack_size: usize) -> Self {
+		Self {
+			handle,
+			stats,
+			name: None,
+			stack_size,
+			exit_status: None,
+			detached: false,
+		}
+	}
+}
+
+/// Information about a task, see [`task_infos`]
+#[derive(Clone, Debug)]
+pub(crate) struct TaskInfo {
+	pub id: TaskId,
+	pub name: Option<String>,
+	pub core_id: CoreId,
+	pub status: TaskStatus,
+	pub prio: Priority,
+	/// Consumed processing time in microseconds
+	pub cpu_time: u64,
+	/// Size of the user stack in bytes
+	pub stack_size: usize,
+}
+
+/// Default length of a time slice in microseconds
+const DEFAULT_TIME_SLICE: u64 = 10_000;
+
+/// Length of the time slice in microseconds, after which a task is preempted
+/// in favor of another ready task with the same priority. It can be changed
+/// by `HERMIT_TIME_SLICE`, where `0` disables preemption.
+static TIME_SLICE: Lazy<Option<u64>> = Lazy::new(|| {
+	let time_slice = match hermit_var!("HERMIT_TIME_SLICE") {
+		Some(time_slice) => time_slice.parse().unwrap_or_else(|_| {
+			warn!("Invalid HERMIT_TIME_SLICE {time_slice}, using the default");
+			DEFAULT_TIME_SLICE
+		}),
+		None => DEFAULT_TIME_SLICE,
+	};
+	(time_slice > 0).then_some(time_slice)
+});
+
+/// Interval in microseconds, in which a core compares its load with the other cores
+#[cfg(feature = "smp")]
+const BALANCE_INTERVAL: u64 = 20_000;
+
+/// Scheduling policy and parameters of a task
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SchedAttr {
+	pub policy: SchedPolicy,
+	/// Priority of the task, which is [`DEADLINE_PRIO`] for deadline tasks
+	pub prio: Priority,
+	/// Runtime, deadline and period of a deadline task
+	pub deadline: Option<DeadlineParams>,
+}
+
+/// Attribute of a task, which may be changed by another core
+#[derive(Copy, Clone, Debug)]
+enum TaskAttr {
+	Affinity(CpuSet),
+	Priority(Priority),
+	Sched(SchedAttr),
+}
+
+impl TaskAttr {
+	fn apply(self, task: &mut Task) {
+		match self {
+			TaskAttr::Affinity(affinity) => task.affinity = affinity,
+			TaskAttr::Priority(prio) => task.prio = prio,
+			TaskAttr::Sched(attr) => {
+				let now = arch::processor::get_timer_ticks();
+				task.policy = attr.policy;
+				task.prio = attr.prio;
+				task.deadline = attr.deadline.map(|params| DeadlineServer::new(params, now));
+			}
+		}
+	}
+}
+
+/// Ready task, which is moved to another core
+#[cfg(feature = "smp")]
+struct MigratedTask(Task);
+
+// SAFETY: Only the TLS block of a task, which is referenced by raw pointers,
+// is not `Send`. The block is owned by the task and is only accessed through
+// the thread pointer, while the task is running. A migrated task does not run
+// and is exclusively owned by the scheduler input of its new core, see
+// `PerCoreScheduler::pop_movable_task`.
+#[cfg(feature = "smp")]
+unsafe impl Send for MigratedTask {}
+
 #[cfg(feature = "smp")]
 pub(crate) struct SchedulerInput {
 	/// Queue of new tasks
 	new_tasks: VecDeque<NewTask>,
 	/// Queue of task, which are wakeup by another core
 	wakeup_tasks: VecDeque<TaskHandle>,
+	/// Queue of ready tasks, which are moved from another core
+	migrated_tasks: VecDeque<MigratedTask>,
+	/// Idle cores, which want to take over a ready task of this core
+	steal_requests: VecDeque<CoreId>,
+	/// New attributes of tasks, which are set by another core
+	attr_requests: VecDeque<(TaskId, TaskAttr)>,
 }
 
 #[cfg(feature = "smp")]
@@ -64,6 +194,9 @@ impl SchedulerInput {
 		Self {
 			new_tasks: VecDeque::new(),
 			wakeup_tasks: VecDeque::new(),
+			migrated_tasks: VecDeque::new(),
+			steal_requests: VecDeque::new(),
+			attr_requests: VecDeque::new(),
 		}
 	}
 }
@@ -90,6 +223,19 @@ pub(crate) struct PerCoreScheduler {
 	finished_tasks: VecDeque<Rc<RefCell<Task>>>,
 	/// Queue of blocked tasks, sorted by wakeup time.
 	blocked_tasks: BlockedTaskQueue,
+	/// The current task gives up the core voluntarily
+	yielding: bool,
+	/// The current task may continue on another core after giving up this core
+	#[cfg(feature = "smp")]
+	migratable: bool,
+	/// Time, until which the processing time of the current task is accounted
+	accounted: u64,
+	/// Number of runnable tasks, which is published to the other cores
+	#[cfg(feature = "smp")]
+	load: &'static AtomicU32,
+	/// Time of the next comparison with the load of the other cores
+	#[cfg(feature = "smp")]
+	next_balance: u64,
 }
 
 pub(crate) trait PerCoreSchedulerExt {
@@ -97,6 +243,16 @@ pub(crate) trait PerCoreSchedulerExt {
 	/// Interrupt flag will be cleared during the reschedule
 	fn reschedule(self);
 
+	/// Like [`reschedule`](Self::reschedule), but the current task may be
+	/// moved to another core, before it continues. Afterwards, the caller
+	/// must not use any reference to the scheduler of the previous core.
+	fn reschedule_migratable(self);
+
+	/// Gives up the core in favor of another ready task with the same
+	/// priority. A deadline task finishes its current job and waits for its
+	/// next period. The task may continue on another core.
+	fn yield_now(self);
+
 	#[cfg(feature = "net")]
 	fn add_network_timer(self, wakeup_time: Option<u64>);
 
@@ -172,6 +328,35 @@ impl PerCoreSchedulerExt for &mut PerCoreScheduler {
 		without_interrupts(|| self.scheduler());
 	}
 
+	fn reschedule_migratable(self) {
+		#[cfg(feature = "smp")]
+		{
+			// The flag has to be set together with the request, otherwise an
+			// interrupt could move the task, while it still uses `self`.
+			without_interrupts(|| {
+				self.migratable = true;
+				self.reschedule();
+			});
+
+			// The flag expires, if no other task has been scheduled.
+			without_interrupts(|| core_scheduler().migratable = false);
+		}
+
+		#[cfg(not(feature = "smp"))]
+		self.reschedule();
+	}
+
+	fn yield_now(self) {
+		without_interrupts(|| {
+			if let Some(server) = self.current_task.borrow_mut().deadline.as_mut() {
+				server.finish_job();
+			}
+			self.yielding = true;
+		});
+
+		self.reschedule_migratable();
+	}
+
 	#[cfg(feature = "net")]
 	fn add_network_timer(self, wakeup_time: Option<u64>) {
 		without_interrupts(|| {
@@ -194,13 +379,36 @@ impl PerCoreSchedulerExt for &mut PerCoreScheduler {
 				"Finishing task {} with exit code {}",
 				current_task_borrowed.id, exit_code
 			);
-			current_task_borrowed.status = TaskStatus::Finished;
+			current_task_borrowed.set_status(TaskStatus::Finished);
 			NO_TASKS.fetch_sub(1, Ordering::SeqCst);
 
 			let current_id = current_task_borrowed.id;
+			let core_id = current_task_borrowed.core_id;
 			drop(current_task_borrowed);
 
+			// Release the bandwidth of a deadline task.
+			if let Some(params) = SCHED_ATTRS
+				.lock()
+				.remove(&current_id)
+				.and_then(|attr| attr.deadline)
+				&& let Some(total) = DEADLINE_BANDWIDTHS.lock().get_mut(&core_id)
+			{
+				*total -= params.bandwidth();
+			}
+			AFFINITIES.lock().remove(&current_id);
+
+			// Keep the exit status until the task is joined.
+			let mut tasks = TASKS.lock();
+			if tasks.get(&current_id).is_some_and(|entry| entry.detached) {
+				tasks.remove(&current_id);
+			} else if let Some(entry) = tasks.get_mut(&current_id) {
+				entry.exit_status = Some(exit_code);
+			}
+			drop(tasks);
+
 			// wakeup tasks, which are waiting for task with the identifier id
+			// A task, whose timeout has elapsed in the meantime, is not blocked
+			// anymore and ignores the wakeup.
 			if let Some(mut queue) = WAITING_TASKS.lock().remove(&current_id) {
 				while let Some(task) = queue.pop_front() {
 					self.custom_wakeup(task);
@@ -219,12 +427,10 @@ struct NewTask {
 	arg: usize,
 	prio: Priority,
 	core_id: CoreId,
+	affinity: CpuSet,
 	stacks: TaskStacks,
-	object_map: Arc<
-		RwSpinLock<
-			HashMap<FileDescriptor, Arc<async_lock::RwLock<dyn ObjectInterface>>, RandomState>,
-		>,
-	>,
+	stats: Arc<TaskStats>,
+	object_map: Arc<RwSpinLock<HashMap<FileDescriptor, Descriptor, RandomState>>>,
 }
 
 impl From<NewTask> for Task {
@@ -235,10 +441,14 @@ impl From<NewTask> for Task {
 			arg,
 			prio,
 			core_id,
+			affinity,
 			stacks,
+			stats,
 			object_map,
 		} = value;
 		let mut task = Self::new(tid, core_id, TaskStatus::Ready, prio, stacks, object_map);
+		task.affinity = affinity;
+		task.stats = stats;
 		task.create_stack_frame(func, arg);
 		task
 	}
@@ -256,13 +466,18 @@ impl PerCoreScheduler {
 		// Create the new task.
 		let tid = get_tid();
 		let stacks = TaskStacks::new(stack_size);
+		let stack_size = stacks.get_user_stack_size();
+		let affinity = core_scheduler().get_current_task_affinity();
+		let stats = Arc::new(TaskStats::new(TaskStatus::Ready));
 		let new_task = NewTask {
 			tid,
 			func,
 			arg,
 			prio,
 			core_id,
+			affinity,
 			stacks,
+			stats: stats.clone(),
 			object_map: core_scheduler().get_current_task_object_map(),
 		};
 
@@ -271,13 +486,18 @@ impl PerCoreScheduler {
 			#[cfg(feature = "smp")]
 			let mut input_locked = get_scheduler_input(core_id).lock();
 			WAITING_TASKS.lock().insert(tid, VecDeque::with_capacity(1));
+			AFFINITIES.lock().insert(tid, affinity);
 			TASKS.lock().insert(
 				tid,
-				TaskHandle::new(
-					tid,
-					prio,
-					#[cfg(feature = "smp")]
-					core_id,
+				TaskEntry::new(
+					TaskHandle::new(
+						tid,
+						prio,
+						#[cfg(feature = "smp")]
+						core_id,
+					),
+					stats,
+					stack_size,
 				),
 			);
 			NO_TASKS.fetch_add(1, Ordering::SeqCst);
@@ -286,6 +506,7 @@ impl PerCoreScheduler {
 			if core_id == core_scheduler().core_id {
 				let task = Rc::new(RefCell::new(Task::from(new_task)));
 				core_scheduler().ready_queue.push(task);
+				core_scheduler().update_time_slice(false);
 				false
 			} else {
 				input_locked.new_tasks.push_back(new_task);
@@ -295,6 +516,7 @@ impl PerCoreScheduler {
 			if core_id == 0 {
 				let task = Rc::new(RefCell::new(Task::from(new_task)));
 				core_scheduler().ready_queue.push(task);
+				core_scheduler().update_time_slice(false);
 				false
 			} else {
 				panic!("Invalid core_id {}!", core_id)
@@ -314,32 +536,40 @@ impl PerCoreScheduler {
 	fn clone_impl(&self, func: extern "C" fn(usize), arg: usize) -> TaskId {
 		static NEXT_CORE_ID: AtomicU32 = AtomicU32::new(1);
 
+		// Get the current task.
+		let current_task_borrowed = self.current_task.borrow();
+		let affinity = current_task_borrowed.affinity;
+
 		// Get the Core ID of the next CPU.
 		let core_id: CoreId = {
 			// Increase the CPU number by 1.
 			let id = NEXT_CORE_ID.fetch_add(1, Ordering::SeqCst);
 
 			// Check for overflow.
-			if id == arch::get_processor_count() {
+			let id = if id == arch::get_processor_count() {
 				NEXT_CORE_ID.store(0, Ordering::SeqCst);
 				0
 			} else {
 				id
-			}
-		};
+			};
 
-		// Get the current task.
-		let current_task_borrowed = self.current_task.borrow();
+			// Skip the cores, on which the task is not allowed to run.
+			affinity.next(id).unwrap_or(id)
+		};
 
 		// Clone the current task.
 		let tid = get_tid();
+		let stack_size = current_task_borrowed.stacks.get_user_stack_size();
+		let stats = Arc::new(TaskStats::new(TaskStatus::Ready));
 		let clone_task = NewTask {
 			tid,
 			func,
 			arg,
 			prio: current_task_borrowed.prio,
 			core_id,
-			stacks: TaskStacks::new(current_task_borrowed.stacks.get_user_stack_size()),
+			affinity,
+			stacks: TaskStacks::new(stack_size),
+			stats: stats.clone(),
 			object_map: current_task_borrowed.object_map.clone(),
 		};
 
@@ -348,13 +578,18 @@ impl PerCoreScheduler {
 			#[cfg(feature = "smp")]
 			let mut input_locked = get_scheduler_input(core_id).lock();
 			WAITING_TASKS.lock().insert(tid, VecDeque::with_capacity(1));
+			AFFINITIES.lock().insert(tid, affinity);
 			TASKS.lock().insert(
 				tid,
-				TaskHandle::new(
-					tid,
-					current_task_borrowed.prio,
-					#[cfg(feature = "smp")]
-					core_id,
+				TaskEntry::new(
+					TaskHandle::new(
+						tid,
+						current_task_borrowed.prio,
+						#[cfg(feature = "smp")]
+						core_id,
+					),
+					stats,
+					stack_size,
 				),
 			);
 			NO_TASKS.fetch_add(1, Ordering::SeqCst);
@@ -362,6 +597,7 @@ impl PerCoreScheduler {
 			if core_id == core_scheduler().core_id {
 				let clone_task = Rc::new(RefCell::new(Task::from(clone_task)));
 				core_scheduler().ready_queue.push(clone_task);
+				core_scheduler().update_time_slice(false);
 				false
 			} else {
 				input_locked.new_tasks.push_back(clone_task);
@@ -371,6 +607,7 @@ impl PerCoreScheduler {
 			if core_id == 0 {
 				let clone_task = Rc::new(RefCell::new(Task::from(clone_task)));
 				core_scheduler().ready_queue.push(clone_task);
+				core_scheduler().update_time_slice(false);
 				false
 			} else {
 				panic!("Invalid core_id {}!", core_id);
@@ -403,14 +640,17 @@ impl PerCoreScheduler {
 			crate::executor::run();
 			self.blocked_tasks
 				.handle_waiting_tasks(&mut self.ready_queue);
+			self.update_time_slice(false);
 		});
 	}
 
 	#[cfg(not(feature = "smp"))]
 	pub fn custom_wakeup(&mut self, task: TaskHandle) {
 		without_interrupts(|| {
-			let task = self.blocked_tasks.custom_wakeup(task);
-			self.ready_queue.push(task);
+			if let Some(task) = self.blocked_tasks.custom_wakeup(task) {
+				self.ready_queue.push(task);
+				self.update_time_slice(false);
+			}
 		});
 	}
 
@@ -418,8 +658,10 @@ impl PerCoreScheduler {
 	pub fn custom_wakeup(&mut self, task: TaskHandle) {
 		if task.get_core_id() == self.core_id {
 			without_interrupts(|| {
-				let task = self.blocked_tasks.custom_wakeup(task);
-				self.ready_queue.push(task);
+				if let Some(task) = self.blocked_tasks.custom_wakeup(task) {
+					self.ready_queue.push(task);
+					self.update_time_slice(false);
+				}
 			});
 		} else {
 			get_scheduler_input(task.get_core_id())
@@ -458,14 +700,26 @@ impl PerCoreScheduler {
 		without_interrupts(|| self.current_task.borrow().id)
 	}
 
+	/// Returns the id of the current task and the name of its stack, if `addr`
+	/// lies in the guard page below one of its stacks.
+	pub fn get_current_task_guard_page(
+		&self,
+		addr: memory_addresses::VirtAddr,
+	) -> Option<(TaskId, &'static str)> {
+		let current_task = self.current_task.try_borrow().ok()?;
+		let stack = current_task.stacks.guard_page(addr)?;
+		Some((current_task.id, stack))
+	}
+
+	#[inline]
+	pub fn get_current_task_affinity(&self) -> CpuSet {
+		without_interrupts(|| self.current_task.borrow().affinity)
+	}
+
 	#[inline]
 	pub fn get_current_task_object_map(
 		&self,
-	) -> Arc<
-		RwSpinLock<
-			HashMap<FileDescriptor, Arc<async_lock::RwLock<dyn ObjectInterface>>, RandomState>,
-		>,
-	> {
+	) -> Arc<RwSpinLock<HashMap<FileDescriptor, Descriptor, RandomState>>> {
 		without_interrupts(|| self.current_task.borrow().object_map.clone())
 	}
 
@@ -479,7 +733,49 @@ impl PerCoreScheduler {
 		without_interrupts(|| {
 			let current_task = self.current_task.borrow();
 			let object_map = current_task.object_map.read();
-			object_map.get(&fd).cloned().ok_or(Errno::Badf)
+			object_map
+				.get(&fd)
+				.map(|descriptor| descriptor.object.clone())
+				.ok_or(Errno::Badf)
+		})
+	}
+
+	/// Returns the flags of the file descriptor `fd`
+	pub fn get_descriptor_flags(&self, fd: FileDescriptor) -> io::Result<DescriptorFlags> {
+		without_interrupts(|| {
+			let current_task = self.current_task.borrow();
+			let object_map = current_task.object_map.read();
+			object_map
+				.get(&fd)
+				.map(|descriptor| descriptor.flags)
+				.ok_or(Errno::Badf)
+		})
+	}
+
+	/// Sets the flags of the file descriptor `fd`, which are not shared
+	/// with its duplicates
+	pub fn set_descriptor_flags(
+		&self,
+		fd: FileDescriptor,
+		flags: DescriptorFlags,
+	) -> io::Result<()> {
+		without_interrupts(|| {
+			let current_task = self.current_task.borrow();
+			let mut object_map = current_task.object_map.write();
+			let descriptor = object_map.get_mut(&fd).ok_or(Errno::Badf)?;
+			descriptor.flags = flags;
+			Ok(())
+		})
+	}
+
+	/// Remembers the absolute path, by which the object of `fd` has been opened
+	pub fn set_descriptor_path(&self, fd: FileDescriptor, path: &str) -> io::Result<()> {
+		without_interrupts(|| {
+			let current_task = self.current_task.borrow();
+			let mut object_map = current_task.object_map.write();
+			let descriptor = object_map.get_mut(&fd).ok_or(Errno::Badf)?;
+			descriptor.path = Some(String::from(path));
+			Ok(())
 		})
 	}
 
@@ -488,11 +784,9 @@ impl PerCoreScheduler {
 	#[cfg(feature = "common-os")]
 	#[cfg_attr(not(target_arch = "x86_64"), expect(dead_code))]
 	pub fn recreate_objmap(&self) -> io::Result<()> {
-		let mut map = HashMap::<
-			FileDescriptor,
-			Arc<async_lock::RwLock<dyn ObjectInterface>>,
-			RandomState,
-		>::with_hasher(RandomState::with_seeds(0, 0, 0, 0));
+		let mut map = HashMap::<FileDescriptor, Descriptor, RandomState>::with_hasher(
+			RandomState::with_seeds(0, 0, 0, 0),
+		);
 
 		without_interrupts(|| {
 			let mut current_task = self.current_task.borrow_mut();
@@ -536,7 +830,7 @@ impl PerCoreScheduler {
 			};
 
 			let fd = new_fd()?;
-			let _ = object_map.insert(fd, obj.clone());
+			let _ = object_map.insert(fd, Descriptor::new(obj.clone()));
 			Ok(fd)
 		})
 	}
@@ -544,14 +838,24 @@ impl PerCoreScheduler {
 	/// Duplicate a IO interface and returns a new file descriptor as
 	/// identifier to the new copy
 	pub fn dup_object(&self, fd: FileDescriptor) -> io::Result<FileDescriptor> {
+		self.dup_object_from(fd, 0)
+	}
+
+	/// Duplicate a IO interface and returns the lowest unused file
+	/// descriptor, which is greater than or equal to `min_fd`
+	pub fn dup_object_from(
+		&self,
+		fd: FileDescriptor,
+		min_fd: FileDescriptor,
+	) -> io::Result<FileDescriptor> {
 		without_interrupts(|| {
 			let current_task = self.current_task.borrow();
 			let mut object_map = current_task.object_map.write();
 
-			let obj = (*(object_map.get(&fd).ok_or(Errno::Inval)?)).clone();
+			let descriptor = object_map.get(&fd).ok_or(Errno::Inval)?.duplicate();
 
 			let new_fd = || -> io::Result<FileDescriptor> {
-				let mut fd: FileDescriptor = 0;
+				let mut fd: FileDescriptor = min_fd;
 				loop {
 					if !object_map.contains_key(&fd) {
 						break Ok(fd);
@@ -567,7 +871,7 @@ impl PerCoreScheduler {
 			match object_map.entry(fd) {
 				hash_map::Entry::Occupied(_occupied_entry) => Err(Errno::Mfile),
 				hash_map::Entry::Vacant(vacant_entry) => {
-					vacant_entry.insert(obj);
+					vacant_entry.insert(descriptor);
 					Ok(fd)
 				}
 			}
@@ -583,12 +887,12 @@ impl PerCoreScheduler {
 			let current_task = self.current_task.borrow();
 			let mut object_map = current_task.object_map.write();
 
-			let obj = object_map.get(&fd1).cloned().ok_or(Errno::Badf)?;
+			let descriptor = object_map.get(&fd1).ok_or(Errno::Badf)?.duplicate();
 
 			match object_map.entry(fd2) {
 				hash_map::Entry::Occupied(_occupied_entry) => Err(Errno::Mfile),
 				hash_map::Entry::Vacant(vacant_entry) => {
-					vacant_entry.insert(obj);
+					vacant_entry.insert(descriptor);
 					Ok(fd2)
 				}
 			}
@@ -604,7 +908,10 @@ impl PerCoreScheduler {
 			let current_task = self.current_task.borrow();
 			let mut object_map = current_task.object_map.write();
 
-			object_map.remove(&fd).ok_or(Errno::Badf)
+			object_map
+				.remove(&fd)
+				.map(|descriptor| descriptor.object)
+				.ok_or(Errno::Badf)
 		})
 	}
 
@@ -636,32 +943,20 @@ impl PerCoreScheduler {
 		tss.interrupt_stack_table[0] = ist_start.into();
 	}
 
-	pub fn set_current_task_priority(&mut self, prio: Priority) {
-		without_interrupts(|| {
-			trace!("Change priority of the current task");
-			self.current_task.borrow_mut().prio = prio;
-		});
-	}
-
+	/// Changes the priority of the task `id`, which may run on another core.
 	pub fn set_priority(&mut self, id: TaskId, prio: Priority) -> Result<(), ()> {
 		trace!("Change priority of task {id} to priority {prio}");
 
 		without_interrupts(|| {
-			let task = get_task_handle(id).ok_or(())?;
-			#[cfg(feature = "smp")]
-			let other_core = task.get_core_id() != self.core_id;
-			#[cfg(not(feature = "smp"))]
-			let other_core = false;
+			let mut tasks = TASKS.lock();
+			let entry = tasks
+				.get_mut(&id)
+				.filter(|entry| entry.exit_status.is_none())
+				.ok_or(())?;
+			entry.handle = entry.handle.with_priority(prio);
+			drop(tasks);
 
-			if other_core {
-				warn!("Have to change the priority on another core");
-			} else if self.current_task.borrow().id == task.get_id() {
-				self.current_task.borrow_mut().prio = prio;
-			} else {
-				self.ready_queue
-					.set_priority(task, prio)
-					.expect("Do not find valid task in ready queue");
-			}
+			self.set_attr(id, TaskAttr::Priority(prio));
 
 			Ok(())
 		})
@@ -695,6 +990,48 @@ impl PerCoreScheduler {
 		}
 	}
 
+	/// Arms the timer to preempt the current task at the end of its time
+	/// slice, if another task with the same priority is ready. Otherwise, the
+	/// core stays tickless, as long as no task waits for the next load
+	/// balancing. A new time slice starts if `restart` is set or no time slice
+	/// was running. A deadline task is preempted, as soon as its runtime is
+	/// used up.
+	fn update_time_slice(&mut self, restart: bool) {
+		let (status, prio, policy, budget_end) = {
+			let borrowed = self.current_task.borrow();
+			(
+				borrowed.status,
+				borrowed.prio,
+				borrowed.policy,
+				borrowed.deadline.as_ref().map(DeadlineServer::budget_end),
+			)
+		};
+		// FIFO tasks keep the core, until they give it up.
+		let contended = status == TaskStatus::Running
+			&& matches!(policy, SchedPolicy::Other | SchedPolicy::RoundRobin)
+			&& self.ready_queue.contains_prio(prio);
+		let now = arch::processor::get_timer_ticks();
+
+		let slice_end = match *TIME_SLICE {
+			Some(time_slice) if contended => match self.blocked_tasks.slice_end() {
+				Some(slice_end) if !restart && slice_end <= now + time_slice => Some(slice_end),
+				_ => Some(now + time_slice),
+			},
+			_ => None,
+		};
+		let slice_end = budget_end
+			.filter(|_| status == TaskStatus::Running)
+			.or(slice_end);
+
+		// Waiting tasks have to be offered to the other cores from time to time.
+		#[cfg(feature = "smp")]
+		let slice_end = slice_end.or_else(|| {
+			(!self.ready_queue.is_empty() && get_processor_count() > 1).then_some(self.next_balance)
+		});
+
+		self.blocked_tasks.set_slice_end(slice_end);
+	}
+
 	/// Check if a finished task could be deleted.
 	fn cleanup_tasks(&mut self) {
 		// Pop the first finished task and remove it from the TASKS list, which implicitly deallocates all associated memory.
@@ -708,14 +1045,235 @@ impl PerCoreScheduler {
 		let mut input_locked = CoreLocal::get().scheduler_input.lock();
 
 		while let Some(task) = input_locked.wakeup_tasks.pop_front() {
-			let task = self.blocked_tasks.custom_wakeup(task);
-			self.ready_queue.push(task);
+			if let Some(task) = self.blocked_tasks.custom_wakeup(task) {
+				self.ready_queue.push(task);
+			}
 		}
 
 		while let Some(new_task) = input_locked.new_tasks.pop_front() {
 			let task = Rc::new(RefCell::new(Task::from(new_task)));
 			self.ready_queue.push(task.clone());
 		}
+
+		while let Some(MigratedTask(task)) = input_locked.migrated_tasks.pop_front() {
+			debug!("Task {} migrated to core {}", task.id, self.core_id);
+			self.ready_queue.push(Rc::new(RefCell::new(task)));
+		}
+
+		let attr_requests = core::mem::take(&mut input_locked.attr_requests);
+		drop(input_locked);
+
+		for (id, attr) in attr_requests {
+			// The task may have been moved to another core in the meantime.
+			if !self.set_local_attr(id, attr) {
+				forward_attr(id, attr);
+			}
+		}
+
+		self.balance_load();
+		self.update_time_slice(false);
+	}
+
+	/// Removes the last ready task with the lowest priority, which satisfies
+	/// `pred`, from this core.
+	///
+	/// Only tasks, which have left this core through
+	/// [`reschedule_migratable`](PerCoreSchedulerExt::reschedule_migratable)
+	/// and are exclusively referenced by the ready queue, can be removed. A
+	/// preempted task may still reference the scheduler of this core. If the
+	/// task still owns the FPU, its FPU state is saved first.
+	/// Deadline tasks stay on this core, which reserved their bandwidth.
+	#[cfg(feature = "smp")]
+	fn pop_movable_task(&mut self, mut pred: impl FnMut(&Task) -> bool) -> Option<Task> {
+		#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+		let fpu_owner = &self.fpu_owner;
+		let task = self.ready_queue.pop_migratable(|task| {
+			#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+			let references = if Rc::ptr_eq(task, fpu_owner) { 2 } else { 1 };
+			#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+			let references = 1;
+
+			Rc::strong_count(task) == references && {
+				let borrowed = task.borrow();
+				borrowed.migratable && borrowed.deadline.is_none() && pred(&borrowed)
+			}
+		})?;
+
+		#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+		if Rc::ptr_eq(&task, &self.fpu_owner) {
+			task.borrow_mut().last_fpu_state.save_and_release();
+			self.fpu_owner = self.idle_task.clone();
+		}
+
+		Some(Rc::into_inner(task).unwrap().into_inner())
+	}
+
+	/// Moves the task `task` to the core `core_id`. The kernel stacks of the
+	/// task are installed by the context switch on the new core.
+	#[cfg(feature = "smp")]
+	fn send_task(&self, mut task: Task, core_id: CoreId) {
+		debug!(
+			"Migrating task {} from core {} to core {core_id}",
+			task.id, self.core_id
+		);
+		task.core_id = core_id;
+		if let Some(entry) = TASKS.lock().get_mut(&task.id) {
+			entry.handle = TaskHandle::new(task.id, task.prio, core_id);
+		}
+		CORE_LOADS.lock()[usize::try_from(core_id).unwrap()].fetch_add(1, Ordering::Relaxed);
+
+		get_scheduler_input(core_id)
+			.lock()
+			.migrated_tasks
+			.push_back(MigratedTask(task));
+		arch::wakeup_core(core_id);
+	}
+
+	/// Moves the last ready task with the lowest priority, which is allowed
+	/// to run on the core `core_id`, to that core. Returns `false` if no such
+	/// task can be moved.
+	#[cfg(feature = "smp")]
+	fn migrate_task(&mut self, core_id: CoreId) -> bool {
+		match self.pop_movable_task(|task| task.affinity.contains(core_id)) {
+			Some(task) => {
+				self.send_task(task, core_id);
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Moves all ready tasks, which are not allowed to run on this core, to
+	/// the least loaded core of their affinity.
+	#[cfg(feature = "smp")]
+	fn evict_tasks(&mut self) {
+		let core_id = self.core_id;
+		while let Some(task) = self.pop_movable_task(|task| !task.affinity.contains(core_id)) {
+			let target = CORE_LOADS
+				.lock()
+				.iter()
+				.map(|load| load.load(Ordering::Relaxed))
+				.enumerate()
+				.filter(|(core_id, _)| task.affinity.contains((*core_id).try_into().unwrap()))
+				.min_by_key(|(_, load)| *load)
+				.map(|(core_id, _)| core_id.try_into().unwrap())
+				.expect("The affinity does not contain an online core");
+			self.send_task(task, target);
+		}
+	}
+
+	/// Changes an attribute of the task `id` on the core, which manages the task.
+	fn set_attr(&mut self, id: TaskId, attr: TaskAttr) {
+		without_interrupts(|| {
+			#[cfg(feature = "smp")]
+			if !self.set_local_attr(id, attr) {
+				forward_attr(id, attr);
+			}
+			#[cfg(not(feature = "smp"))]
+			self.set_local_attr(id, attr);
+		});
+	}
+
+	/// Changes an attribute of the task `id`, if it is managed by this core,
+	/// and returns `false` otherwise. The current task gives up this core at
+	/// its next voluntary scheduling point, if it is no longer allowed to run
+	/// on it.
+	fn set_local_attr(&mut self, id: TaskId, attr: TaskAttr) -> bool {
+		if self.current_task.borrow().id == id {
+			attr.apply(&mut self.current_task.borrow_mut());
+		} else if let Some(task) = self.ready_queue.remove_task(id) {
+			// The position in the ready queue depends on the priority.
+			attr.apply(&mut task.borrow_mut());
+			self.ready_queue.push(task);
+		} else if let Some(task) = self.blocked_tasks.get_task(id) {
+			attr.apply(&mut task.borrow_mut());
+		} else {
+			return false;
+		}
+
+		self.update_time_slice(false);
+		true
+	}
+
+	/// Publishes the number of runnable tasks on this core and returns it.
+	#[cfg(feature = "smp")]
+	fn publish_load(&self) -> u32 {
+		let running = self.current_task.borrow().status == TaskStatus::Running;
+		let load = u32::try_from(self.ready_queue.len()).unwrap() + u32::from(running);
+		self.load.store(load, Ordering::Relaxed);
+		load
+	}
+
+	/// Publishes the load of this core and moves ready tasks to other cores.
+	///
+	/// Idle cores, which asked for a task, are served immediately. In addition,
+	/// the load of all cores is compared every [`BALANCE_INTERVAL`] and tasks
+	/// are moved to the least loaded core until the load is even.
+	#[cfg(feature = "smp")]
+	fn balance_load(&mut self) {
+		self.publish_load();
+		if self.ready_queue.is_empty() {
+			return;
+		}
+
+		self.evict_tasks();
+
+		let steal_requests =
+			core::mem::take(&mut CoreLocal::get().scheduler_input.lock().steal_requests);
+		for core_id in steal_requests {
+			// The idle core may have found some work in the meantime.
+			if get_core_load(core_id) == 0 && !self.migrate_task(core_id) {
+				break;
+			}
+		}
+
+		let now = arch::processor::get_timer_ticks();
+		if now >= self.next_balance {
+			self.next_balance = now + BALANCE_INTERVAL;
+
+			loop {
+				let own_load = self.publish_load();
+				let Some((core_id, load)) = CORE_LOADS
+					.lock()
+					.iter()
+					.map(|load| load.load(Ordering::Relaxed))
+					.enumerate()
+					.min_by_key(|(_, load)| *load)
+				else {
+					break;
+				};
+
+				if load + 1 >= own_load || !self.migrate_task(core_id.try_into().unwrap()) {
+					break;
+				}
+			}
+		}
+
+		self.publish_load();
+	}
+
+	/// Asks the busiest core to move one of its waiting tasks to this idle core.
+	#[cfg(feature = "smp")]
+	fn steal_task(&self) {
+		let busiest = CORE_LOADS
+			.lock()
+			.iter()
+			.map(|load| load.load(Ordering::Relaxed))
+			.enumerate()
+			.max_by_key(|(_, load)| *load);
+
+		// A single task is running and cannot be moved.
+		if let Some((core_id, load)) = busiest
+			&& load > 1
+		{
+			let core_id = CoreId::try_from(core_id).unwrap();
+			let mut input_locked = get_scheduler_input(core_id).lock();
+			if !input_locked.steal_requests.contains(&self.core_id) {
+				input_locked.steal_requests.push_back(self.core_id);
+			}
+			drop(input_locked);
+			arch::wakeup_core(core_id);
+		}
 	}
 
 	/// Only the idle task should call this function.
@@ -738,6 +1296,8 @@ impl PerCoreScheduler {
 
 			if core_scheduler.ready_queue.is_empty() {
 				if backoff.is_completed() {
+					#[cfg(feature = "smp")]
+					core_scheduler.steal_task();
 					interrupts::enable_and_wait();
 					backoff.reset();
 				} else {
@@ -768,29 +1328,99 @@ impl PerCoreScheduler {
 		// => we have time to cleanup the system
 		self.cleanup_tasks();
 
+		#[cfg(feature = "smp")]
+		self.balance_load();
+
+		// Check if the current task has overflowed its kernel stack.
+		#[cfg(feature = "kernel-stack")]
+		{
+			let borrowed = self.current_task.borrow();
+			if !borrowed.stacks.check_canary() {
+				let id = borrowed.id;
+				drop(borrowed);
+				panic!("stack overflow in task {id} ({})", DisplayName::of(id));
+			}
+		}
+
+		let now = arch::processor::get_timer_ticks();
+		let yielding = core::mem::take(&mut self.yielding);
+		#[cfg(feature = "smp")]
+		let migratable = core::mem::take(&mut self.migratable);
+
+		// Account the processing time of the current task.
+		self.current_task
+			.borrow()
+			.stats
+			.add_cpu_time(now.saturating_sub(self.accounted));
+		self.accounted = now;
+
+		// Charge the runtime of a deadline task and throttle it until its next
+		// period, if the runtime is used up.
+		let mut release = None;
+		{
+			let mut borrowed = self.current_task.borrow_mut();
+			if borrowed.status == TaskStatus::Running
+				&& let Some(server) = borrowed.deadline.as_mut()
+			{
+				server.charge(now);
+				if server.is_throttled() {
+					release = Some(server.next_release());
+				}
+			}
+		}
+		if let Some(release) = release {
+			debug!("Throttling deadline task until {release}");
+			self.blocked_tasks
+				.add(self.current_task.clone(), Some(release));
+		}
+
 		// Get information about the current task.
-		let (id, last_stack_pointer, prio, status) = {
+		let (id, last_stack_pointer, prio, status, allowed, policy, abs_deadline) = {
 			let mut borrowed = self.current_task.borrow_mut();
+			// A task can only leave this core, if it may be moved to another core.
+			#[cfg(feature = "smp")]
+			let allowed = borrowed.affinity.contains(self.core_id) || !migratable;
+			#[cfg(not(feature = "smp"))]
+			let allowed = true;
 			(
 				borrowed.id,
 				ptr::from_mut(&mut borrowed.last_stack_pointer).cast::<usize>(),
 				borrowed.prio,
 				borrowed.status,
+				allowed,
+				borrowed.policy,
+				borrowed.deadline.as_ref().map(DeadlineServer::abs_deadline),
 			)
 		};
 
 		let mut new_task = None;
 
-		if status == TaskStatus::Running {
+		if status == TaskStatus::Running && !allowed {
+			// The task is not allowed to run on this core anymore.
+			// Switch to any other task, so that the task can be moved to another core.
+			new_task = Some(
+				self.ready_queue
+					.pop()
+					.unwrap_or_else(|| self.idle_task.clone()),
+			);
+		} else if status == TaskStatus::Running {
 			// A task is currently running.
-			// Check if a task with a equal or higher priority is available.
-			if let Some(task) = self.ready_queue.pop_with_prio(prio) {
-				new_task = Some(task);
-			}
+			new_task = match (policy, abs_deadline) {
+				// Check if a deadline task with an earlier deadline is available.
+				(_, Some(abs_deadline)) => self.ready_queue.pop_earlier_deadline(abs_deadline),
+				// Check if a task with a higher priority is available.
+				(SchedPolicy::Fifo, None) if !yielding => self
+					.ready_queue
+					.pop_with_prio(Priority::from(prio.into() + 1)),
+				// Check if a task with a equal or higher priority is available.
+				_ => self.ready_queue.pop_with_prio(prio),
+			};
 		} else {
 			if status == TaskStatus::Finished {
 				// Mark the finished task as invalid and add it to the finished tasks for a later cleanup.
-				self.current_task.borrow_mut().status = TaskStatus::Invalid;
+				self.current_task
+					.borrow_mut()
+					.set_status(TaskStatus::Invalid);
 				self.finished_tasks.push_back(self.current_task.clone());
 			}
 
@@ -811,10 +1441,19 @@ impl PerCoreScheduler {
 			// There is a new task we want to switch to.
 
 			// Handle the current task.
+			#[cfg(feature = "smp")]
+			{
+				self.current_task.borrow_mut().migratable = migratable;
+			}
 			if status == TaskStatus::Running {
 				// Mark the running task as ready again and add it back to the queue.
-				self.current_task.borrow_mut().status = TaskStatus::Ready;
-				self.ready_queue.push(self.current_task.clone());
+				// A preempted FIFO task stays in front of its priority.
+				self.current_task.borrow_mut().set_status(TaskStatus::Ready);
+				if policy == SchedPolicy::Fifo && allowed && !yielding {
+					self.ready_queue.push_front(self.current_task.clone());
+				} else {
+					self.ready_queue.push(self.current_task.clone());
+				}
 			}
 
 			// Handle the new task and get information about it.
@@ -822,7 +1461,10 @@ impl PerCoreScheduler {
 				let mut borrowed = task.borrow_mut();
 				if borrowed.status != TaskStatus::Idle {
 					// Mark the new task as running.
-					borrowed.status = TaskStatus::Running;
+					borrowed.set_status(TaskStatus::Running);
+				}
+				if let Some(server) = borrowed.deadline.as_mut() {
+					server.start(now);
 				}
 
 				(borrowed.id, borrowed.last_stack_pointer)
@@ -840,6 +1482,7 @@ impl PerCoreScheduler {
 				#[cfg(not(target_arch = "riscv64"))]
 				{
 					self.current_task = task;
+					self.update_time_slice(true);
 				}
 
 				// Finally return the context of the new task.
@@ -853,13 +1496,18 @@ impl PerCoreScheduler {
 					}
 					task.borrow().last_fpu_state.restore();
 					self.current_task = task;
+					self.update_time_slice(true);
 					unsafe {
 						switch_to_task(last_stack_pointer, new_stack_pointer.as_usize());
 					}
+
+					// The task may continue on another core, so `self` is stale.
+					return None;
 				}
 			}
 		}
 
+		self.update_time_slice(false);
 		None
 	}
 }
@@ -881,6 +1529,67 @@ pub(crate) fn abort() -> ! {
 	core_scheduler().exit(-1)
 }
 
+/// Reports a stack overflow of the current task, if `addr` lies in the guard
+/// page below one of its stacks.
+///
+/// The guard pages are not mapped, so that a stack overflow triggers a page
+/// fault instead of corrupting the adjacent memory.
+///
+/// Returns `true` if a stack overflow has been detected.
+pub(crate) fn report_stack_overflow(addr: memory_addresses::VirtAddr) -> bool {
+	let Some((id, stack)) = core_scheduler().get_current_task_guard_page(addr) else {
+		return false;
+	};
+
+	error!("stack overflow in task {id} ({})", DisplayName::of(id));
+	error!("Access to {addr:p} hit the guard page of the {stack} stack");
+	true
+}
+
+/// Name of a task for diagnostic messages after a fault
+///
+/// The name is copied into a fixed buffer, so that neither the allocator nor
+/// a blocking lock is needed, which may be held by the faulting task.
+struct DisplayName {
+	buf: [u8; 32],
+	/// Length of the name, or `None` if the task list is locked
+	len: Option<usize>,
+}
+
+impl DisplayName {
+	fn of(id: TaskId) -> Self {
+		let mut name = Self {
+			buf: [0; 32],
+			len: None,
+		};
+		let Some(tasks) = TASKS.try_lock() else {
+			return name;
+		};
+
+		let task_name = tasks
+			.get(&id)
+			.and_then(|entry| entry.name.as_deref())
+			.unwrap_or("unnamed");
+		let mut len = task_name.len().min(name.buf.len());
+		while !task_name.is_char_boundary(len) {
+			len -= 1;
+		}
+		name.buf[..len].copy_from_slice(&task_name.as_bytes()[..len]);
+		name.len = Some(len);
+		name
+	}
+}
+
+impl fmt::Display for DisplayName {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.len {
+			// The buffer only contains complete characters.
+			Some(len) => f.write_str(core::str::from_utf8(&self.buf[..len]).unwrap()),
+			None => f.write_str("name unavailable"),
+		}
+	}
+}
+
 /// Add a per-core scheduler for the current core.
 pub(crate) fn add_current_core() {
 	// Create an idle task for this core.
@@ -892,13 +1601,20 @@ pub(crate) fn add_current_core() {
 	WAITING_TASKS.lock().insert(tid, VecDeque::with_capacity(1));
 	TASKS.lock().insert(
 		tid,
-		TaskHandle::new(
-			tid,
-			IDLE_PRIO,
-			#[cfg(feature = "smp")]
-			core_id,
+		TaskEntry::new(
+			TaskHandle::new(
+				tid,
+				IDLE_PRIO,
+				#[cfg(feature = "smp")]
+				core_id,
+			),
+			idle_task.borrow().stats.clone(),
+			idle_task.borrow().stacks.get_user_stack_size(),
 		),
 	);
+	#[cfg(feature = "smp")]
+	let load: &AtomicU32 = Box::leak(Box::new(AtomicU32::new(0)));
+
 	// Initialize a scheduler for this core.
 	debug!("Initializing scheduler for core {core_id} with idle task {tid}");
 	let boxed_scheduler = Box::new(PerCoreScheduler {
@@ -911,6 +1627,14 @@ pub(crate) fn add_current_core() {
 		ready_queue: PriorityTaskQueue::new(),
 		finished_tasks: VecDeque::new(),
 		blocked_tasks: BlockedTaskQueue::new(),
+		yielding: false,
+		#[cfg(feature = "smp")]
+		migratable: false,
+		accounted: arch::processor::get_timer_ticks(),
+		#[cfg(feature = "smp")]
+		load,
+		#[cfg(feature = "smp")]
+		next_balance: 0,
 	});
 
 	let scheduler = Box::into_raw(boxed_scheduler);
@@ -921,6 +1645,7 @@ pub(crate) fn add_current_core() {
 			core_id.try_into().unwrap(),
 			&CoreLocal::get().scheduler_input,
 		);
+		CORE_LOADS.lock().insert(core_id.try_into().unwrap(), load);
 		#[cfg(target_arch = "x86_64")]
 		CORE_HLT_STATE
 			.lock()
@@ -934,63 +1659,328 @@ pub(crate) fn take_core_hlt_state(core_id: CoreId) -> bool {
 	CORE_HLT_STATE.lock()[usize::try_from(core_id).unwrap()].swap(false, Ordering::Acquire)
 }
 
+#[inline]
+#[cfg(feature = "smp")]
+fn get_core_load(core_id: CoreId) -> u32 {
+	CORE_LOADS.lock()[usize::try_from(core_id).unwrap()].load(Ordering::Relaxed)
+}
+
 #[inline]
 #[cfg(feature = "smp")]
 fn get_scheduler_input(core_id: CoreId) -> &'static InterruptTicketMutex<SchedulerInput> {
 	SCHEDULER_INPUTS.lock()[usize::try_from(core_id).unwrap()]
 }
 
+/// Spawns a new task on the core `selector` or, if `selector` is negative, on
+/// the next core in a round robin manner. The new task inherits the affinity
+/// of the current task.
+///
+/// Fails with `EINVAL` if the core `selector` does not exist or is outside of
+/// the affinity of the current task.
 pub unsafe fn spawn(
 	func: unsafe extern "C" fn(usize),
 	arg: usize,
 	prio: Priority,
 	stack_size: usize,
 	selector: isize,
-) -> TaskId {
+) -> io::Result<TaskId> {
 	static CORE_COUNTER: AtomicU32 = AtomicU32::new(1);
 
+	let affinity = core_scheduler().get_current_task_affinity();
 	let core_id = if selector < 0 {
-		// use Round Robin to schedule the cores
-		CORE_COUNTER.fetch_add(1, Ordering::SeqCst) % get_processor_count()
+		// use Round Robin to schedule the cores, on which the task is allowed to run
+		let core_id = CORE_COUNTER.fetch_add(1, Ordering::SeqCst) % get_processor_count();
+		affinity.next(core_id).unwrap_or(core_id)
 	} else {
-		selector as u32
+		let core_id = CoreId::try_from(selector).map_err(|_| Errno::Inval)?;
+		if core_id >= get_processor_count() || !affinity.contains(core_id) {
+			return Err(Errno::Inval);
+		}
+		core_id
 	};
 
-	unsafe { PerCoreScheduler::spawn(func, arg, prio, core_id, stack_size) }
+	Ok(unsafe { PerCoreScheduler::spawn(func, arg, prio, core_id, stack_size) })
 }
 
 #[allow(clippy::result_unit_err)]
 pub fn join(id: TaskId) -> Result<(), ()> {
-	let core_scheduler = core_scheduler();
+	join_timeout(id, None).map(|_| ()).map_err(|_| ())
+}
 
-	debug!(
-		"Task {} is waiting for task {}",
-		core_scheduler.get_current_task_id(),
-		id
-	);
+/// Waits until the task `id` has finished and returns its exit status. The
+/// timeout is given in microseconds and is relative to the current time.
+///
+/// Fails with `ESRCH` if the task does not exist or has already been joined,
+/// with `EINVAL` if it is detached, with `EDEADLK` if the current task joins
+/// itself and with `ETIMEDOUT` if the timeout elapses.
+pub fn join_timeout(id: TaskId, timeout: Option<u64>) -> io::Result<i32> {
+	let current_id = core_scheduler().get_current_task_id();
+	debug!("Task {current_id} is waiting for task {id}");
+
+	if id == current_id {
+		return Err(Errno::Deadlk);
+	}
+
+	let wakeup_time = timeout.map(|t| arch::processor::get_timer_ticks() + t);
 
 	loop {
+		let mut tasks = TASKS.lock();
+		match tasks.get(&id) {
+			None => return Err(Errno::Srch),
+			Some(entry) if entry.detached => return Err(Errno::Inval),
+			Some(entry) => {
+				if let Some(exit_status) = entry.exit_status {
+					tasks.remove(&id);
+					return Ok(exit_status);
+				}
+			}
+		}
+		drop(tasks);
+
+		// The task may be moved to another core while it is blocked.
+		let core_scheduler = core_scheduler();
 		let mut waiting_tasks_guard = WAITING_TASKS.lock();
 
-		if let Some(queue) = waiting_tasks_guard.get_mut(&id) {
-			queue.push_back(core_scheduler.get_current_task_handle());
-			core_scheduler.block_current_task(None);
+		// The task has finished in the meantime.
+		let Some(queue) = waiting_tasks_guard.get_mut(&id) else {
+			continue;
+		};
 
-			// Switch to the next task.
-			drop(waiting_tasks_guard);
-			core_scheduler.reschedule();
-		} else {
-			return Ok(());
+		// Remove the handle of a previous wakeup, which may belong to another core.
+		queue.retain(|handle| handle.get_id() != current_id);
+		if matches!(wakeup_time, Some(t) if t <= arch::processor::get_timer_ticks()) {
+			return Err(Errno::Timedout);
 		}
+
+		queue.push_back(core_scheduler.get_current_task_handle());
+		core_scheduler.block_current_task(wakeup_time);
+
+		// Switch to the next task.
+		drop(waiting_tasks_guard);
+		core_scheduler.reschedule_migratable();
 	}
 }
 
+/// Detaches the task `id`, so that it is cleaned up as soon as it has
+/// finished. The task cannot be joined afterwards.
+///
+/// Fails with `ESRCH` if the task does not exist and with `EINVAL` if it is
+/// already detached.
+pub fn detach(id: TaskId) -> io::Result<()> {
+	let mut tasks = TASKS.lock();
+	let entry = tasks.get_mut(&id).ok_or(Errno::Srch)?;
+	if entry.detached {
+		return Err(Errno::Inval);
+	}
+
+	if entry.exit_status.is_some() {
+		tasks.remove(&id);
+	} else {
+		entry.detached = true;
+	}
+
+	Ok(())
+}
+
+/// Sets the name of the task `id`, which is used for debugging.
+pub fn set_task_name(id: TaskId, name: String) -> io::Result<()> {
+	let mut tasks = TASKS.lock();
+	let entry = tasks.get_mut(&id).ok_or(Errno::Srch)?;
+	entry.name = Some(name);
+
+	Ok(())
+}
+
+/// Returns the name of the task `id`, if it has one.
+pub fn task_name(id: TaskId) -> io::Result<Option<String>> {
+	let tasks = TASKS.lock();
+	let entry = tasks.get(&id).ok_or(Errno::Srch)?;
+
+	Ok(entry.name.clone())
+}
+
+/// Returns information about all tasks, including finished tasks, which have
+/// not been joined yet.
+pub(crate) fn task_infos() -> Vec<TaskInfo> {
+	TASKS
+		.lock()
+		.iter()
+		.map(|(id, entry)| TaskInfo {
+			id: *id,
+			name: entry.name.clone(),
+			#[cfg(feature = "smp")]
+			core_id: entry.handle.get_core_id(),
+			#[cfg(not(feature = "smp"))]
+			core_id: 0,
+			status: if entry.exit_status.is_some() {
+				TaskStatus::Finished
+			} else {
+				entry.stats.status()
+			},
+			prio: entry.handle.get_priority(),
+			cpu_time: entry.stats.cpu_time(),
+			stack_size: entry.stack_size,
+		})
+		.collect()
+}
+
 pub fn shutdown(arg: i32) -> ! {
 	crate::syscalls::shutdown(arg)
 }
 
-fn get_task_handle(id: TaskId) -> Option<TaskHandle> {
-	TASKS.lock().get(&id).copied()
+/// Returns the handle of the task `id`, if the task has not finished yet.
+pub(crate) fn get_task_handle(id: TaskId) -> Option<TaskHandle> {
+	TASKS
+		.lock()
+		.get(&id)
+		.filter(|entry| entry.exit_status.is_none())
+		.map(|entry| entry.handle)
+}
+
+/// Passes the new attribute of the task `id` to the core, which manages the task.
+#[cfg(feature = "smp")]
+fn forward_attr(id: TaskId, attr: TaskAttr) {
+	let Some(handle) = get_task_handle(id) else {
+		return;
+	};
+
+	let core_id = handle.get_core_id();
+	get_scheduler_input(core_id)
+		.lock()
+		.attr_requests
+		.push_back((id, attr));
+	arch::wakeup_core(core_id);
+}
+
+/// Restricts the task `id` to the cores in `affinity`.
+///
+/// A task, which runs on a core outside of `affinity`, is moved, as soon as
+/// it gives up its core voluntarily. The current task is moved immediately.
+pub fn set_affinity(id: TaskId, affinity: CpuSet) -> io::Result<()> {
+	if affinity.next(0).is_none() {
+		return Err(Errno::Inval);
+	}
+
+	let handle = get_task_handle(id).ok_or(Errno::Srch)?;
+	if handle.get_priority() == IDLE_PRIO {
+		return Err(Errno::Inval);
+	}
+
+	// Deadline tasks cannot leave the core, which reserved their bandwidth.
+	#[cfg(feature = "smp")]
+	if !affinity.contains(handle.get_core_id())
+		&& SCHED_ATTRS
+			.lock()
+			.get(&id)
+			.is_some_and(|attr| attr.deadline.is_some())
+	{
+		return Err(Errno::Busy);
+	}
+
+	AFFINITIES.lock().insert(id, affinity);
+
+	let core_scheduler = core_scheduler();
+	core_scheduler.set_attr(id, TaskAttr::Affinity(affinity));
+	if id == core_scheduler.get_current_task_id() && !affinity.contains(core_id()) {
+		core_scheduler.reschedule_migratable();
+	}
+
+	Ok(())
+}
+
+/// Returns the cores, on which the task `id` is allowed to run.
+pub fn get_affinity(id: TaskId) -> io::Result<CpuSet> {
+	get_task_handle(id).ok_or(Errno::Srch)?;
+	Ok(AFFINITIES
+		.lock()
+		.get(&id)
+		.copied()
+		.unwrap_or_else(CpuSet::all))
+}
+
+/// Sets the scheduling policy and the parameters of the task `id`.
+///
+/// A deadline task is only admitted, if the bandwidth of all deadline tasks
+/// on its core stays below [`deadline::MAX_BANDWIDTH`]. Afterwards, the task
+/// is not moved to another core anymore.
+pub fn set_scheduler(id: TaskId, attr: SchedAttr) -> io::Result<()> {
+	let handle = get_task_handle(id).ok_or(Errno::Srch)?;
+	if handle.get_priority() == IDLE_PRIO {
+		return Err(Errno::Inval);
+	}
+
+	#[cfg(feature = "smp")]
+	let core_id = handle.get_core_id();
+	#[cfg(not(feature = "smp"))]
+	let core_id = 0;
+
+	{
+		let mut sched_attrs = SCHED_ATTRS.lock();
+		let bandwidth = |attr: Option<&SchedAttr>| {
+			attr.and_then(|attr| attr.deadline)
+				.map_or(0, |params| params.bandwidth())
+		};
+		let old = bandwidth(sched_attrs.get(&id));
+		let new = bandwidth(Some(&attr));
+		if old != new {
+			let mut bandwidths = DEADLINE_BANDWIDTHS.lock();
+			let total = bandwidths.entry(core_id).or_default();
+			*total = deadline::admit(*total, old, new)?;
+		}
+
+		if attr.policy == SchedPolicy::Other {
+			sched_attrs.remove(&id);
+		} else {
+			sched_attrs.insert(id, attr);
+		}
+	}
+
+	if let Some(entry) = TASKS.lock().get_mut(&id) {
+		entry.handle = entry.handle.with_priority(attr.prio);
+	}
+
+	let core_scheduler = core_scheduler();
+	core_scheduler.set_attr(id, TaskAttr::Sched(attr));
+
+	// The priorities have changed.
+	core_scheduler.reschedule_migratable();
+
+	Ok(())
+}
+
+/// Changes the priority of the task `id`, which uses [`SchedPolicy::Other`].
+///
+/// Fails with `ESRCH` if the task does not exist or has finished and with
+/// `EPERM` if it uses another scheduling policy, whose priority is only
+/// changed by [`set_scheduler`].
+///
+/// A task, which has inherited a higher priority through a PI futex, keeps it
+/// and returns to `prio` after the futex has been released.
+pub fn set_priority(id: TaskId, prio: Priority) -> io::Result<()> {
+	let sched_attrs = SCHED_ATTRS.lock();
+	if sched_attrs.contains_key(&id) {
+		return Err(Errno::Perm);
+	}
+
+	get_task_handle(id).ok_or(Errno::Srch)?;
+	futex::set_base_priority(id, prio);
+	Ok(())
+}
+
+/// Returns the scheduling policy and the parameters of the task `id`.
+pub fn get_scheduler(id: TaskId) -> io::Result<SchedAttr> {
+	let handle = get_task_handle(id).ok_or(Errno::Srch)?;
+	Ok(SCHED_ATTRS.lock().get(&id).copied().unwrap_or(SchedAttr {
+		policy: SchedPolicy::Other,
+		prio: handle.get_priority(),
+		deadline: None,
+	}))
+}
+
+/// Returns the length of a time slice in microseconds, if tasks with the same
+/// priority share a core.
+pub fn time_slice() -> Option<u64> {
+	*TIME_SLICE
 }
 
 #[cfg(all(target_arch = "x86_64", feature = "common-os"))]
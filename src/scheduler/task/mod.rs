@@ -8,6 +8,7 @@ use alloc::rc::Rc;
 use alloc::sync::Arc;
 use core::cell::RefCell;
 use core::num::NonZeroU64;
+use core::sync::atomic::{AtomicU8, AtomicU64, Ordering};
 use core::{cmp, fmt};
 
 use ahash::RandomState;
@@ -19,10 +20,12 @@ use memory_addresses::VirtAddr;
 #[cfg(not(feature = "common-os"))]
 use self::tls::Tls;
 use crate::arch::core_local::*;
+use crate::arch::get_processor_count;
 use crate::arch::scheduler::TaskStacks;
 use crate::fd::stdio::*;
-use crate::fd::{FileDescriptor, ObjectInterface, STDERR_FILENO, STDIN_FILENO, STDOUT_FILENO};
+use crate::fd::{Descriptor, FileDescriptor, STDERR_FILENO, STDIN_FILENO, STDOUT_FILENO};
 use crate::scheduler::CoreId;
+use crate::scheduler::deadline::DeadlineServer;
 use crate::{arch, env};
 
 /// Returns the most significant bit.
@@ -41,13 +44,70 @@ fn msb(n: u64) -> Option<u32> {
 
 /// The status of the task - used for scheduling
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
 pub(crate) enum TaskStatus {
-	Invalid,
-	Ready,
-	Running,
-	Blocked,
-	Finished,
-	Idle,
+	Invalid = 0,
+	Ready = 1,
+	Running = 2,
+	Blocked = 3,
+	Finished = 4,
+	Idle = 5,
+}
+
+impl TaskStatus {
+	fn from_u8(value: u8) -> Self {
+		match value {
+			1 => Self::Ready,
+			2 => Self::Running,
+			3 => Self::Blocked,
+			4 => Self::Finished,
+			5 => Self::Idle,
+			_ => Self::Invalid,
+		}
+	}
+}
+
+impl fmt::Display for TaskStatus {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let status = match self {
+			Self::Invalid => "invalid",
+			Self::Ready => "ready",
+			Self::Running => "running",
+			Self::Blocked => "blocked",
+			Self::Finished => "finished",
+			Self::Idle => "idle",
+		};
+		f.pad(status)
+	}
+}
+
+/// Statistics of a task, which can be read by other cores
+#[derive(Debug)]
+pub(crate) struct TaskStats {
+	status: AtomicU8,
+	/// Processing time, which the task has consumed, in microseconds
+	cpu_time: AtomicU64,
+}
+
+impl TaskStats {
+	pub fn new(status: TaskStatus) -> Self {
+		Self {
+			status: AtomicU8::new(status as u8),
+			cpu_time: AtomicU64::new(0),
+		}
+	}
+
+	pub fn status(&self) -> TaskStatus {
+		TaskStatus::from_u8(self.status.load(Ordering::Relaxed))
+	}
+
+	pub fn cpu_time(&self) -> u64 {
+		self.cpu_time.load(Ordering::Relaxed)
+	}
+
+	pub fn add_cpu_time(&self, usecs: u64) {
+		self.cpu_time.fetch_add(usecs, Ordering::Relaxed);
+	}
 }
 
 /// Unique identifier for a task (i.e. `pid`).
@@ -100,6 +160,80 @@ pub const IDLE_PRIO: Priority = Priority::from(0);
 /// Maximum number of priorities
 pub const NO_PRIORITIES: usize = 31;
 
+/// Highest priority, which is reserved for tasks of [`SchedPolicy::Deadline`]
+pub const DEADLINE_PRIO: Priority = Priority::from(30);
+
+/// Canary at the bottom of each kernel stack, which is checked on every context switch
+#[cfg(feature = "kernel-stack")]
+pub const STACK_CANARY: u64 = 0x5741_4c4c_4e55_5453;
+
+/// Scheduling policy of a task
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SchedPolicy {
+	/// Tasks with the same priority share the core in time slices.
+	#[default]
+	Other,
+	/// Tasks run until they block, yield or are preempted by a task with a
+	/// higher priority.
+	Fifo,
+	/// Like [`SchedPolicy::Fifo`], but tasks with the same priority share the
+	/// core in time slices.
+	RoundRobin,
+	/// Tasks run with [`DEADLINE_PRIO`] and are ordered by their deadlines,
+	/// see [`crate::scheduler::deadline`].
+	Deadline,
+}
+
+/// Maximum number of cores, which can be described by a [`CpuSet`]
+pub const MAX_CORES: usize = 256;
+
+/// Set of cores, on which a task is allowed to run
+///
+/// Bit `i` of byte `j` stands for the core `8 * j + i`, which matches the
+/// layout of `cpu_set_t`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CpuSet([u8; MAX_CORES / 8]);
+
+impl CpuSet {
+	/// Creates a set, which contains all cores
+	pub const fn all() -> Self {
+		CpuSet([u8::MAX; MAX_CORES / 8])
+	}
+
+	/// Creates a set from the bit mask `mask`. Cores above [`MAX_CORES`] are ignored.
+	pub fn from_bytes(mask: &[u8]) -> Self {
+		let mut set = CpuSet([0; MAX_CORES / 8]);
+		let len = mask.len().min(set.0.len());
+		set.0[..len].copy_from_slice(&mask[..len]);
+		set
+	}
+
+	/// Writes the cores of this set, which are online, as bit mask into `mask`.
+	pub fn to_bytes(&self, mask: &mut [u8]) {
+		mask.fill(0);
+		for core_id in (0..get_processor_count()).filter(|core_id| self.contains(*core_id)) {
+			let i = usize::try_from(core_id).unwrap();
+			mask[i / 8] |= 1 << (i % 8);
+		}
+	}
+
+	/// Returns `true` if the set contains the core `core_id`.
+	pub fn contains(&self, core_id: CoreId) -> bool {
+		let i = usize::try_from(core_id).unwrap();
+		i < MAX_CORES && self.0[i / 8] & (1 << (i % 8)) != 0
+	}
+
+	/// Returns the first online core of the set, starting the search at
+	/// `core_id` and wrapping around. Returns `None` if the set contains no
+	/// online core.
+	pub fn next(&self, core_id: CoreId) -> Option<CoreId> {
+		let count = get_processor_count();
+		(0..count)
+			.map(|i| (core_id + i) % count)
+			.find(|core_id| self.contains(*core_id))
+	}
+}
+
 #[derive(Copy, Clone, Debug)]
 pub(crate) struct TaskHandle {
 	id: TaskId,
@@ -130,6 +264,12 @@ impl TaskHandle {
 	pub fn get_priority(&self) -> Priority {
 		self.priority
 	}
+
+	/// Returns a handle of the same task with the priority `priority`
+	pub fn with_priority(mut self, priority: Priority) -> Self {
+		self.priority = priority;
+		self
+	}
 }
 
 impl Ord for TaskHandle {
@@ -173,6 +313,11 @@ impl TaskHandlePriorityQueue {
 		self.prio_bitmap.into_inner() == 0
 	}
 
+	/// Returns the highest priority of all task handles in the queue
+	pub fn highest_priority(&self) -> Option<Priority> {
+		msb(self.prio_bitmap.into_inner()).map(|i| Priority::from(i.try_into().unwrap()))
+	}
+
 	/// Checks if the given task is in the queue. Returns `true` if the task
 	/// was found.
 	pub fn contains(&self, task: TaskHandle) -> bool {
@@ -218,6 +363,27 @@ impl TaskHandlePriorityQueue {
 		None
 	}
 
+	/// Pop the task handle with the highest priority, which satisfies
+	/// `predicate`, from the queue
+	pub fn pop_if(&mut self, mut predicate: impl FnMut(&TaskHandle) -> bool) -> Option<TaskHandle> {
+		let mut bitmap = self.prio_bitmap.into_inner();
+		while let Some(i) = msb(bitmap) {
+			let queue_index = i as usize;
+			let queue = self.queues[queue_index].as_mut().unwrap();
+			if let Some(pos) = queue.iter().position(&mut predicate) {
+				let task = queue.remove(pos);
+				if queue.is_empty() {
+					*self.prio_bitmap &= !(1 << queue_index as u64);
+				}
+				return task;
+			}
+
+			bitmap &= !(1 << i);
+		}
+
+		None
+	}
+
 	/// Remove a specific task handle from the priority queue. Returns `true` if
 	/// the handle was in the queue.
 	pub fn remove(&mut self, task: TaskHandle) -> bool {
@@ -263,12 +429,47 @@ impl PriorityTaskQueue {
 
 	/// Add a task by its priority to the queue
 	pub fn push(&mut self, task: Rc<RefCell<Task>>) {
-		let i = task.borrow().prio.into() as usize;
+		let (i, abs_deadline) = {
+			let borrowed = task.borrow();
+			(
+				borrowed.prio.into() as usize,
+				borrowed.deadline.as_ref().map(DeadlineServer::abs_deadline),
+			)
+		};
 		//assert!(i < NO_PRIORITIES, "Priority {} is too high", i);
 
 		self.prio_bitmap |= (1 << i) as u64;
 		let queue = &mut self.queues[i];
-		queue.push_back(task);
+
+		// Deadline tasks are sorted by their absolute deadlines.
+		if let Some(abs_deadline) = abs_deadline {
+			let mut cursor = queue.cursor_front_mut();
+			while let Some(current) = cursor.current() {
+				if current
+					.borrow()
+					.deadline
+					.as_ref()
+					.is_some_and(|server| server.abs_deadline() > abs_deadline)
+				{
+					break;
+				}
+				cursor.move_next();
+			}
+			cursor.insert_before(task);
+		} else {
+			queue.push_back(task);
+		}
+	}
+
+	/// Add a task in front of all tasks with the same priority
+	pub fn push_front(&mut self, task: Rc<RefCell<Task>>) {
+		if task.borrow().deadline.is_some() {
+			self.push(task);
+		} else {
+			let i = task.borrow().prio.into() as usize;
+			self.prio_bitmap |= (1 << i) as u64;
+			self.queues[i].push_front(task);
+		}
 	}
 
 	fn pop_from_queue(&mut self, queue_index: usize) -> Option<Rc<RefCell<Task>>> {
@@ -309,6 +510,12 @@ impl PriorityTaskQueue {
 		self.prio_bitmap == 0
 	}
 
+	/// Returns the number of tasks in the queue.
+	#[cfg(feature = "smp")]
+	pub fn len(&self) -> usize {
+		self.queues.iter().map(LinkedList::len).sum()
+	}
+
 	/// Returns reference to prio_bitmap
 	#[allow(dead_code)]
 	#[inline]
@@ -325,6 +532,11 @@ impl PriorityTaskQueue {
 		None
 	}
 
+	/// Returns `true` if a task with the priority `prio` is in the queue.
+	pub fn contains_prio(&self, prio: Priority) -> bool {
+		self.prio_bitmap & (1 << prio.into()) != 0
+	}
+
 	/// Pop the next task, which has a higher or the same priority as `prio`
 	pub fn pop_with_prio(&mut self, prio: Priority) -> Option<Rc<RefCell<Task>>> {
 		if let Some(i) = msb(self.prio_bitmap)
@@ -336,6 +548,45 @@ impl PriorityTaskQueue {
 		None
 	}
 
+	/// Pop the deadline task with the earliest deadline, if it is earlier than
+	/// `abs_deadline`
+	pub fn pop_earlier_deadline(&mut self, abs_deadline: u64) -> Option<Rc<RefCell<Task>>> {
+		let i = DEADLINE_PRIO.into() as usize;
+		let earlier = self.queues[i].front().is_some_and(|task| {
+			task.borrow()
+				.deadline
+				.as_ref()
+				.is_some_and(|server| server.abs_deadline() < abs_deadline)
+		});
+
+		if earlier {
+			self.pop_from_queue(i)
+		} else {
+			None
+		}
+	}
+
+	/// Remove the last task with the lowest priority, which satisfies `pred`.
+	/// This task would wait the longest on this core and is the best candidate
+	/// to be moved to another core.
+	#[cfg(feature = "smp")]
+	pub fn pop_migratable(
+		&mut self,
+		mut pred: impl FnMut(&Rc<RefCell<Task>>) -> bool,
+	) -> Option<Rc<RefCell<Task>>> {
+		for i in 0..NO_PRIORITIES {
+			if self.prio_bitmap & (1 << i) == 0 {
+				continue;
+			}
+
+			if let Some(index) = self.queues[i].iter().rposition(&mut pred) {
+				return self.remove_from_queue(index, i);
+			}
+		}
+
+		None
+	}
+
 	/// Returns the highest priority of all available task
 	#[cfg(all(any(target_arch = "x86_64", target_arch = "riscv64"), feature = "smp"))]
 	pub fn get_highest_priority(&self) -> Priority {
@@ -346,22 +597,26 @@ impl PriorityTaskQueue {
 		}
 	}
 
-	/// Change priority of specific task
-	pub fn set_priority(&mut self, handle: TaskHandle, prio: Priority) -> Result<(), ()> {
-		let old_priority = handle.get_priority().into() as usize;
-		if let Some(index) = self.queues[old_priority]
+	/// Returns the task with the identifier `id`, if it is in the queue.
+	pub fn get_task(&self, id: TaskId) -> Option<&Rc<RefCell<Task>>> {
+		self.queues
 			.iter()
-			.position(|current_task| current_task.borrow().id == handle.id)
-		{
-			let Some(task) = self.remove_from_queue(index, old_priority) else {
-				return Err(());
-			};
-			task.borrow_mut().prio = prio;
-			self.push(task);
-			return Ok(());
+			.flatten()
+			.find(|task| task.borrow().id == id)
+	}
+
+	/// Removes the task with the identifier `id` from the queue.
+	pub fn remove_task(&mut self, id: TaskId) -> Option<Rc<RefCell<Task>>> {
+		for i in 0..NO_PRIORITIES {
+			if let Some(index) = self.queues[i]
+				.iter()
+				.position(|task| task.borrow().id == id)
+			{
+				return self.remove_from_queue(index, i);
+			}
 		}
 
-		Err(())
+		None
 	}
 }
 
@@ -386,14 +641,22 @@ pub(crate) struct Task {
 	pub last_fpu_state: arch::processor::FPUState,
 	/// ID of the core this task is running on
 	pub core_id: CoreId,
+	/// Cores, on which this task is allowed to run
+	pub affinity: CpuSet,
+	/// The task has left its core at a point, at which it does not reference
+	/// the scheduler of the core. Only such tasks may be moved to another core.
+	#[cfg(feature = "smp")]
+	pub migratable: bool,
+	/// Scheduling policy of the task
+	pub policy: SchedPolicy,
+	/// Budget of a task with [`SchedPolicy::Deadline`]
+	pub deadline: Option<DeadlineServer>,
 	/// Stack of the task
 	pub stacks: TaskStacks,
+	/// Statistics, which are shared with the task list of the scheduler
+	pub stats: Arc<TaskStats>,
 	/// Mapping between file descriptor and the referenced IO interface
-	pub object_map: Arc<
-		RwSpinLock<
-			HashMap<FileDescriptor, Arc<async_lock::RwLock<dyn ObjectInterface>>, RandomState>,
-		>,
-	>,
+	pub object_map: Arc<RwSpinLock<HashMap<FileDescriptor, Descriptor, RandomState>>>,
 	/// Task Thread-Local-Storage (TLS)
 	#[cfg(not(feature = "common-os"))]
 	pub tls: Option<Tls>,
@@ -414,11 +677,7 @@ impl Task {
 		task_status: TaskStatus,
 		task_prio: Priority,
 		stacks: TaskStacks,
-		object_map: Arc<
-			RwSpinLock<
-				HashMap<FileDescriptor, Arc<async_lock::RwLock<dyn ObjectInterface>>, RandomState>,
-			>,
-		>,
+		object_map: Arc<RwSpinLock<HashMap<FileDescriptor, Descriptor, RandomState>>>,
 	) -> Task {
 		debug!("Creating new task {tid} on core {core_id}");
 
@@ -430,7 +689,14 @@ impl Task {
 			user_stack_pointer: VirtAddr::zero(),
 			last_fpu_state: arch::processor::FPUState::new(),
 			core_id,
+			affinity: CpuSet::all(),
+			// A new task starts at its entry point.
+			#[cfg(feature = "smp")]
+			migratable: true,
+			policy: SchedPolicy::Other,
+			deadline: None,
 			stacks,
+			stats: Arc::new(TaskStats::new(task_status)),
 			object_map,
 			#[cfg(not(feature = "common-os"))]
 			tls: None,
@@ -444,22 +710,14 @@ impl Task {
 
 		/// All cores use the same mapping between file descriptor and the referenced object
 		static OBJECT_MAP: OnceCell<
-			Arc<
-				RwSpinLock<
-					HashMap<
-						FileDescriptor,
-						Arc<async_lock::RwLock<dyn ObjectInterface>>,
-						RandomState,
-					>,
-				>,
-			>,
+			Arc<RwSpinLock<HashMap<FileDescriptor, Descriptor, RandomState>>>,
 		> = OnceCell::new();
 
 		if core_id == 0 {
 			OBJECT_MAP
 				.set(Arc::new(RwSpinLock::new(HashMap::<
 					FileDescriptor,
-					Arc<async_lock::RwLock<dyn ObjectInterface>>,
+					Descriptor,
 					RandomState,
 				>::with_hasher(
 					RandomState::with_seeds(0, 0, 0, 0),
@@ -471,16 +729,16 @@ impl Task {
 				let stdin = Arc::new(async_lock::RwLock::new(UhyveStdin::new()));
 				let stdout = Arc::new(async_lock::RwLock::new(UhyveStdout::new()));
 				let stderr = Arc::new(async_lock::RwLock::new(UhyveStderr::new()));
-				guard.insert(STDIN_FILENO, stdin);
-				guard.insert(STDOUT_FILENO, stdout);
-				guard.insert(STDERR_FILENO, stderr);
+				guard.insert(STDIN_FILENO, Descriptor::new(stdin));
+				guard.insert(STDOUT_FILENO, Descriptor::new(stdout));
+				guard.insert(STDERR_FILENO, Descriptor::new(stderr));
 			} else {
 				let stdin = Arc::new(async_lock::RwLock::new(GenericStdin::new()));
 				let stdout = Arc::new(async_lock::RwLock::new(GenericStdout::new()));
 				let stderr = Arc::new(async_lock::RwLock::new(GenericStderr::new()));
-				guard.insert(STDIN_FILENO, stdin);
-				guard.insert(STDOUT_FILENO, stdout);
-				guard.insert(STDERR_FILENO, stderr);
+				guard.insert(STDIN_FILENO, Descriptor::new(stdin));
+				guard.insert(STDOUT_FILENO, Descriptor::new(stdout));
+				guard.insert(STDERR_FILENO, Descriptor::new(stderr));
 			}
 		}
 
@@ -492,7 +750,13 @@ impl Task {
 			user_stack_pointer: VirtAddr::zero(),
 			last_fpu_state: arch::processor::FPUState::new(),
 			core_id,
+			affinity: CpuSet::all(),
+			#[cfg(feature = "smp")]
+			migratable: false,
+			policy: SchedPolicy::Other,
+			deadline: None,
 			stacks: TaskStacks::from_boot_stacks(),
+			stats: Arc::new(TaskStats::new(TaskStatus::Idle)),
 			object_map: OBJECT_MAP.get().unwrap().clone(),
 			#[cfg(not(feature = "common-os"))]
 			tls: None,
@@ -500,6 +764,12 @@ impl Task {
 			root_page_table: *crate::scheduler::BOOT_ROOT_PAGE_TABLE.get().unwrap(),
 		}
 	}
+
+	/// Changes the status of the task and publishes it in [`TaskStats`].
+	pub fn set_status(&mut self, status: TaskStatus) {
+		self.status = status;
+		self.stats.status.store(status as u8, Ordering::Relaxed);
+	}
 }
 
 /*impl Drop for Task {
@@ -508,6 +778,16 @@ impl Task {
 	}
 }*/
 
+/// Programs the one-shot timer to fire at the earlier of `wakeup_time` and
+/// the end of the current time slice.
+fn set_timer(wakeup_time: Option<u64>, slice_end: Option<u64>) {
+	let time = match (wakeup_time, slice_end) {
+		(Some(a), Some(b)) => Some(a.min(b)),
+		(a, b) => a.or(b),
+	};
+	arch::set_oneshot_timer(time);
+}
+
 struct BlockedTask {
 	task: Rc<RefCell<Task>>,
 	wakeup_time: Option<u64>,
@@ -523,6 +803,8 @@ pub(crate) struct BlockedTaskQueue {
 	list: LinkedList<BlockedTask>,
 	#[cfg(feature = "net")]
 	network_wakeup_time: Option<u64>,
+	/// End of the time slice of the current task
+	slice_end: Option<u64>,
 }
 
 impl BlockedTaskQueue {
@@ -531,6 +813,7 @@ impl BlockedTaskQueue {
 			list: LinkedList::new(),
 			#[cfg(feature = "net")]
 			network_wakeup_time: None,
+			slice_end: None,
 		}
 	}
 
@@ -554,7 +837,11 @@ impl BlockedTaskQueue {
 			"Trying to wake up task {} which is not blocked",
 			borrowed.id
 		);
-		borrowed.status = TaskStatus::Ready;
+		borrowed.set_status(TaskStatus::Ready);
+
+		if let Some(server) = borrowed.deadline.as_mut() {
+			server.wakeup(arch::processor::get_timer_ticks());
+		}
 	}
 
 	#[cfg(feature = "net")]
@@ -568,7 +855,7 @@ impl BlockedTaskQueue {
 			(a, b) => a.or(b),
 		};
 
-		arch::set_oneshot_timer(time);
+		set_timer(time, self.slice_end);
 	}
 
 	/// Blocks the given task for `wakeup_time` ticks, or indefinitely if None is given.
@@ -584,7 +871,7 @@ impl BlockedTaskQueue {
 				"Trying to block task {} which is not running",
 				borrowed.id
 			);
-			borrowed.status = TaskStatus::Blocked;
+			borrowed.set_status(TaskStatus::Blocked);
 		}
 
 		let new_node = BlockedTask::new(task, wakeup_time);
@@ -594,17 +881,17 @@ impl BlockedTaskQueue {
 			let mut cursor = self.list.cursor_front_mut();
 			let set_oneshot_timer = || {
 				#[cfg(not(feature = "net"))]
-				arch::set_oneshot_timer(wakeup_time);
+				set_timer(wakeup_time, self.slice_end);
 				#[cfg(feature = "net")]
 				match self.network_wakeup_time {
 					Some(time) => {
 						if time > wt {
-							arch::set_oneshot_timer(wakeup_time);
+							set_timer(wakeup_time, self.slice_end);
 						} else {
-							arch::set_oneshot_timer(self.network_wakeup_time);
+							set_timer(self.network_wakeup_time, self.slice_end);
 						}
 					}
-					_ => arch::set_oneshot_timer(wakeup_time),
+					_ => set_timer(wakeup_time, self.slice_end),
 				}
 			};
 
@@ -626,8 +913,19 @@ impl BlockedTaskQueue {
 		self.list.push_back(new_node);
 	}
 
+	/// Returns the blocked task with the identifier `id`.
+	pub fn get_task(&self, id: TaskId) -> Option<&Rc<RefCell<Task>>> {
+		self.list
+			.iter()
+			.map(|blocked_task| &blocked_task.task)
+			.find(|task| task.borrow().id == id)
+	}
+
 	/// Manually wake up a blocked task.
-	pub fn custom_wakeup(&mut self, task: TaskHandle) -> Rc<RefCell<Task>> {
+	///
+	/// Returns `None` if the task is not blocked anymore, for example because
+	/// its wakeup time has elapsed before the wakeup was delivered.
+	pub fn custom_wakeup(&mut self, task: TaskHandle) -> Option<Rc<RefCell<Task>>> {
 		let mut first_task = true;
 		let mut cursor = self.list.cursor_front_mut();
 
@@ -649,46 +947,51 @@ impl BlockedTaskQueue {
 				// next task's wakeup time (if any).
 				#[cfg(feature = "net")]
 				if first_task {
-					arch::set_oneshot_timer(cursor.current().map_or_else(
-						|| self.network_wakeup_time,
-						|node| match node.wakeup_time {
-							Some(wt) => {
-								if let Some(timer) = self.network_wakeup_time {
-									if wt < timer { Some(wt) } else { Some(timer) }
-								} else {
-									Some(wt)
+					set_timer(
+						cursor.current().map_or_else(
+							|| self.network_wakeup_time,
+							|node| match node.wakeup_time {
+								Some(wt) => {
+									if let Some(timer) = self.network_wakeup_time {
+										if wt < timer { Some(wt) } else { Some(timer) }
+									} else {
+										Some(wt)
+									}
 								}
-							}
-							None => self.network_wakeup_time,
-						},
-					));
+								None => self.network_wakeup_time,
+							},
+						),
+						self.slice_end,
+					);
 				}
 				#[cfg(not(feature = "net"))]
 				if first_task {
-					arch::set_oneshot_timer(
+					set_timer(
 						cursor
 							.current()
 							.map_or_else(|| None, |node| node.wakeup_time),
+						self.slice_end,
 					);
 				}
 
 				// Wake it up.
 				Self::mark_ready(&task_ref);
 
-				return task_ref;
+				return Some(task_ref);
 			}
 
 			first_task = false;
 			cursor.move_next();
 		}
 
-		unreachable!();
+		None
 	}
 
 	/// Wakes up all tasks whose wakeup time has elapsed.
 	///
 	/// Should be called by the One-Shot Timer interrupt handler when the wakeup time for
-	/// at least one task has elapsed.
+	/// at least one task has elapsed. Afterwards, the timer is armed for the next
+	/// wakeup or the end of the time slice, whichever comes first.
 	pub fn handle_waiting_tasks(&mut self, ready_queue: &mut PriorityTaskQueue) {
 		// Get the current time.
 		let time = arch::processor::get_timer_ticks();
@@ -716,6 +1019,12 @@ impl BlockedTaskQueue {
 			ready_queue.push(task.task);
 		}
 
+		set_timer(self.next_wakeup_time(), self.slice_end);
+	}
+
+	/// Returns the time, at which the first blocked task or the network has
+	/// to be woken up.
+	fn next_wakeup_time(&self) -> Option<u64> {
 		let new_task_wakeup_time = self.list.front().and_then(|task| task.wakeup_time);
 		cfg_if::cfg_if! {
 			if 	#[cfg(feature = "net")] {
@@ -724,13 +1033,26 @@ impl BlockedTaskQueue {
 				let network_wakeup_time = None;
 			}
 		};
-		let timer_wakeup_time = match (new_task_wakeup_time, network_wakeup_time) {
+		match (new_task_wakeup_time, network_wakeup_time) {
 			(None, None) => None,
 			(None, Some(network_wt)) => Some(network_wt),
 			(Some(task_wt), None) => Some(task_wt),
 			(Some(task_wt), Some(network_wt)) => Some(u64::min(task_wt, network_wt)),
-		};
+		}
+	}
 
-		arch::set_oneshot_timer(timer_wakeup_time);
+	/// Returns the end of the time slice of the current task, if it has to
+	/// be preempted.
+	pub fn slice_end(&self) -> Option<u64> {
+		self.slice_end
+	}
+
+	/// Sets the end of the time slice of the current task and reprograms the
+	/// timer if it has changed.
+	pub fn set_slice_end(&mut self, slice_end: Option<u64>) {
+		if self.slice_end != slice_end {
+			self.slice_end = slice_end;
+			set_timer(self.next_wakeup_time(), self.slice_end);
+		}
 	}
 }
@@ -9,6 +9,25 @@ fn read() -> Option<u8> {
 	if len > 0 { Some(buf[0]) } else { None }
 }
 
+fn print_tasks() {
+	println!(
+		"{:>5} {:>4} {:<8} {:>4} {:>12} {:>10} NAME",
+		"TID", "CORE", "STATUS", "PRIO", "CPU TIME(ms)", "STACK"
+	);
+	for info in crate::scheduler::task_infos() {
+		println!(
+			"{:>5} {:>4} {:<8} {:>4} {:>12} {:>10} {}",
+			info.id.into(),
+			info.core_id,
+			info.status,
+			info.prio.into(),
+			info.cpu_time / 1000,
+			info.stack_size,
+			info.name.as_deref().unwrap_or("-")
+		);
+	}
+}
+
 pub(crate) fn init() {
 	let (print, read) = (
 		|s: &str| {
@@ -42,6 +61,17 @@ pub(crate) fn init() {
 			aliases: &["i"],
 		},
 	);
+	shell.commands.insert(
+		"tasks",
+		ShellCommand {
+			help: "Lists all tasks with their state and CPU time",
+			func: |_, _| {
+				print_tasks();
+				Ok(())
+			},
+			aliases: &["ps"],
+		},
+	);
 	shell.commands.insert(
 		"shutdown",
 		ShellCommand {
@@ -1,5 +1,8 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
 use core::sync::atomic::AtomicU32;
 use core::sync::atomic::Ordering::SeqCst;
+use core::{cmp, mem};
 
 use ahash::RandomState;
 use hashbrown::HashMap;
@@ -9,12 +12,29 @@ use hermit_sync::InterruptTicketMutex;
 use crate::arch::kernel::core_local::core_scheduler;
 use crate::arch::kernel::processor::get_timer_ticks;
 use crate::errno::Errno;
-use crate::scheduler::PerCoreSchedulerExt;
-use crate::scheduler::task::TaskHandlePriorityQueue;
+use crate::io;
+use crate::scheduler::task::{
+	DEADLINE_PRIO, Priority, TaskHandle, TaskHandlePriorityQueue, TaskId,
+};
+use crate::scheduler::{self, PerCoreSchedulerExt};
 
 // TODO: Replace with a concurrent hashmap.
-static PARKING_LOT: InterruptTicketMutex<HashMap<usize, TaskHandlePriorityQueue, RandomState>> =
-	InterruptTicketMutex::new(HashMap::with_hasher(RandomState::with_seeds(0, 0, 0, 0)));
+static PARKING_LOT: InterruptTicketMutex<ParkingLot> = InterruptTicketMutex::new(ParkingLot::new());
+
+/// Kernel state of all PI futexes, on which tasks are waiting
+static PI_STATE: InterruptTicketMutex<PiState> = InterruptTicketMutex::new(PiState::new());
+
+/// A PI futex has waiters in the kernel, so that it has to be unlocked by
+/// [`futex_unlock_pi`].
+pub(crate) const FUTEX_WAITERS: u32 = 0x8000_0000;
+/// Bits of a PI futex, which contain the thread ID of the owner
+pub(crate) const FUTEX_TID_MASK: u32 = 0x3fff_ffff;
+
+/// Bitset, which matches every waiting thread
+pub(crate) const FUTEX_BITSET_MATCH_ANY: u32 = u32::MAX;
+
+/// Maximal number of futexes, on which [`futex_waitv`] waits
+pub(crate) const FUTEX_WAITV_MAX: usize = 128;
 
 bitflags! {
 	pub struct Flags: u32 {
@@ -28,6 +48,198 @@ fn addr(addr: &AtomicU32) -> usize {
 	ptr.addr()
 }
 
+fn wakeup_time(timeout: Option<u64>, flags: Flags) -> Option<u64> {
+	if flags.contains(Flags::RELATIVE) {
+		timeout.and_then(|t| get_timer_ticks().checked_add(t))
+	} else {
+		timeout
+	}
+}
+
+/// A task, which waits on one or more futexes
+struct Waiter {
+	handle: TaskHandle,
+	/// Addresses of the futexes, on which the task waits, together with the
+	/// bitsets of the wakeups it waits for
+	futexes: Vec<(usize, u32)>,
+	/// Index of the futex, whose wakeup woke up the task
+	woken: Option<usize>,
+}
+
+/// Waiting tasks of all futexes
+struct ParkingLot {
+	queues: HashMap<usize, TaskHandlePriorityQueue, RandomState>,
+	waiters: BTreeMap<TaskId, Waiter>,
+}
+
+impl ParkingLot {
+	const fn new() -> Self {
+		Self {
+			queues: HashMap::with_hasher(RandomState::with_seeds(0, 0, 0, 0)),
+			waiters: BTreeMap::new(),
+		}
+	}
+
+	/// Removes `handle` from the waiting queue of `address`.
+	fn remove_from_queue(&mut self, address: usize, handle: TaskHandle) {
+		if let Entry::Occupied(mut queue) = self.queues.entry(address) {
+			queue.get_mut().remove(handle);
+			if queue.get().is_empty() {
+				queue.remove();
+			}
+		}
+	}
+
+	/// Blocks the current task and adds it to the waiting queues of `futexes`.
+	/// The task is woken up by a matching wakeup on one of them or at
+	/// `wakeup_time`.
+	fn park(&mut self, futexes: Vec<(usize, u32)>, wakeup_time: Option<u64>) {
+		let scheduler = core_scheduler();
+		scheduler.block_current_task(wakeup_time);
+		let handle = scheduler.get_current_task_handle();
+		for &(address, _) in &futexes {
+			self.queues.entry(address).or_default().push(handle);
+		}
+		self.waiters.insert(
+			handle.get_id(),
+			Waiter {
+				handle,
+				futexes,
+				woken: None,
+			},
+		);
+	}
+
+	/// Removes the waiting task `id` from all waiting queues.
+	fn unpark(&mut self, id: TaskId) {
+		let Some(waiter) = self.waiters.remove(&id) else {
+			return;
+		};
+
+		for &(address, _) in &waiter.futexes {
+			self.remove_from_queue(address, waiter.handle);
+		}
+	}
+
+	/// Replaces the handle of the waiting task `id` by `handle`.
+	fn update_handle(&mut self, id: TaskId, handle: TaskHandle) {
+		let waiter = self.waiters.get_mut(&id).unwrap();
+		let old = mem::replace(&mut waiter.handle, handle);
+		for &(address, _) in &waiter.futexes {
+			let queue = self.queues.get_mut(&address).unwrap();
+			queue.remove(old);
+			queue.push(handle);
+		}
+	}
+
+	/// Pops the waiting task with the highest priority from the queue of
+	/// `address`, which waits for a wakeup with the given bitset.
+	fn pop(&mut self, address: usize, bitset: u32) -> Option<TaskHandle> {
+		let waiters = &self.waiters;
+		let Entry::Occupied(mut queue) = self.queues.entry(address) else {
+			return None;
+		};
+
+		let handle = queue.get_mut().pop_if(|handle| {
+			waiters[&handle.get_id()]
+				.futexes
+				.iter()
+				.any(|&(waiting, mask)| waiting == address && mask & bitset != 0)
+		});
+		if queue.get().is_empty() {
+			queue.remove();
+		}
+
+		handle
+	}
+
+	/// Wakes up `count` tasks waiting on the futex at `address` for a wakeup
+	/// with the given bitset. If `count` is `i32::MAX`, all matching tasks are
+	/// woken up. Returns the number of woken tasks.
+	fn wake(&mut self, address: usize, count: i32, bitset: u32) -> i32 {
+		let scheduler = core_scheduler();
+		let mut woken = 0;
+		while woken != count || count == i32::MAX {
+			let Some(handle) = self.pop(address, bitset) else {
+				break;
+			};
+
+			// Tasks, which wait on several futexes, are woken up only once.
+			let waiter = self.waiters.get_mut(&handle.get_id()).unwrap();
+			waiter.woken = waiter
+				.futexes
+				.iter()
+				.position(|&(waiting, mask)| waiting == address && mask & bitset != 0);
+			let futexes = mem::take(&mut waiter.futexes);
+			for &(waiting, _) in &futexes {
+				self.remove_from_queue(waiting, handle);
+			}
+
+			scheduler.custom_wakeup(handle);
+			woken = woken.saturating_add(1);
+		}
+
+		woken
+	}
+
+	/// Moves `count` tasks from the queue of `from` to the queue of `to`. If
+	/// `count` is `i32::MAX`, all tasks are moved. Returns the number of moved
+	/// tasks.
+	fn requeue(&mut self, from: usize, to: usize, count: i32) -> i32 {
+		if from == to {
+			return 0;
+		}
+
+		let mut requeued = 0;
+		while requeued != count || count == i32::MAX {
+			let Some(handle) = self.pop(from, FUTEX_BITSET_MATCH_ANY) else {
+				break;
+			};
+			self.remove_from_queue(from, handle);
+
+			let waiter = self.waiters.get_mut(&handle.get_id()).unwrap();
+			for (address, _) in &mut waiter.futexes {
+				if *address == from {
+					*address = to;
+				}
+			}
+			self.queues.entry(to).or_default().push(handle);
+			requeued = requeued.saturating_add(1);
+		}
+
+		requeued
+	}
+}
+
+/// Waits until the current task is woken up after [`ParkingLot::park`].
+/// Returns the index of the futex, which woke up the task, or ETIMEDOUT if
+/// `wakeup_time` has passed.
+fn wait_unparked(wakeup_time: Option<u64>) -> io::Result<usize> {
+	let id = core_scheduler().get_current_task_id();
+
+	loop {
+		core_scheduler().reschedule_migratable();
+
+		let mut parking_lot = PARKING_LOT.lock();
+		if let Some(index) = parking_lot.waiters[&id].woken {
+			parking_lot.waiters.remove(&id);
+			return Ok(index);
+		}
+
+		if matches!(wakeup_time, Some(t) if t <= get_timer_ticks()) {
+			parking_lot.unpark(id);
+			return Err(Errno::Timedout);
+		}
+
+		// A spurious wakeup occurred, sleep again.
+		// The task may have been moved to another core in the meantime,
+		// so the handle in the parking lot has to be updated.
+		let scheduler = core_scheduler();
+		scheduler.block_current_task(wakeup_time);
+		parking_lot.update_handle(id, scheduler.get_current_task_handle());
+	}
+}
+
 /// If the value at address matches the expected value, park the current thread until it is either
 /// woken up with `futex_wake` (returns 0) or the specified timeout elapses (returns -ETIMEDOUT).
 ///
@@ -40,58 +252,36 @@ pub(crate) fn futex_wait(
 	timeout: Option<u64>,
 	flags: Flags,
 ) -> i32 {
+	futex_wait_bitset(address, expected, timeout, flags, FUTEX_BITSET_MATCH_ANY)
+}
+
+/// Like [`futex_wait`], but the thread is only woken up by wakeups, whose
+/// bitset shares at least one bit with `bitset` (see [`futex_wake_bitset`]).
+/// Returns -EINVAL if `bitset` is zero.
+pub(crate) fn futex_wait_bitset(
+	address: &AtomicU32,
+	expected: u32,
+	timeout: Option<u64>,
+	flags: Flags,
+	bitset: u32,
+) -> i32 {
+	if bitset == 0 {
+		return -i32::from(Errno::Inval);
+	}
+
 	let mut parking_lot = PARKING_LOT.lock();
 	// Check the futex value after locking the parking lot so that all changes are observed.
 	if address.load(SeqCst) != expected {
 		return -i32::from(Errno::Again);
 	}
 
-	let wakeup_time = if flags.contains(Flags::RELATIVE) {
-		timeout.and_then(|t| get_timer_ticks().checked_add(t))
-	} else {
-		timeout
-	};
-
-	let scheduler = core_scheduler();
-	scheduler.block_current_task(wakeup_time);
-	let handle = scheduler.get_current_task_handle();
-	parking_lot.entry(addr(address)).or_default().push(handle);
+	let wakeup_time = wakeup_time(timeout, flags);
+	parking_lot.park(vec![(addr(address), bitset)], wakeup_time);
 	drop(parking_lot);
 
-	loop {
-		scheduler.reschedule();
-
-		let mut parking_lot = PARKING_LOT.lock();
-		if matches!(wakeup_time, Some(t) if t <= get_timer_ticks()) {
-			let mut wakeup = true;
-			// Timeout occurred, try to remove ourselves from the waiting queue.
-			if let Entry::Occupied(mut queue) = parking_lot.entry(addr(address)) {
-				// If we are not in the waking queue, this must have been a wakeup.
-				wakeup = !queue.get_mut().remove(handle);
-				if queue.get().is_empty() {
-					queue.remove();
-				}
-			}
-
-			if wakeup {
-				return 0;
-			} else {
-				return -i32::from(Errno::Timedout);
-			}
-		} else {
-			// If we are not in the waking queue, this must have been a wakeup.
-			let wakeup = !matches!(parking_lot
-				.get(&addr(address)), Some(queue) if queue.contains(handle));
-
-			if wakeup {
-				return 0;
-			} else {
-				// A spurious wakeup occurred, sleep again.
-				// Tasks do not change core, so the handle in the parking lot is still current.
-				scheduler.block_current_task(wakeup_time);
-			}
-		}
-		drop(parking_lot);
+	match wait_unparked(wakeup_time) {
+		Ok(_) => 0,
+		Err(err) => -i32::from(err),
 	}
 }
 
@@ -115,52 +305,55 @@ pub(crate) fn futex_wait_and_set(
 		return -i32::from(Errno::Again);
 	}
 
-	let wakeup_time = if flags.contains(Flags::RELATIVE) {
-		timeout.and_then(|t| get_timer_ticks().checked_add(t))
-	} else {
-		timeout
-	};
-
-	let scheduler = core_scheduler();
-	scheduler.block_current_task(wakeup_time);
-	let handle = scheduler.get_current_task_handle();
-	parking_lot.entry(addr(address)).or_default().push(handle);
+	let wakeup_time = wakeup_time(timeout, flags);
+	parking_lot.park(vec![(addr(address), FUTEX_BITSET_MATCH_ANY)], wakeup_time);
 	drop(parking_lot);
 
-	loop {
-		scheduler.reschedule();
+	match wait_unparked(wakeup_time) {
+		Ok(_) => 0,
+		Err(err) => -i32::from(err),
+	}
+}
 
-		let mut parking_lot = PARKING_LOT.lock();
-		if matches!(wakeup_time, Some(t) if t <= get_timer_ticks()) {
-			let mut wakeup = true;
-			// Timeout occurred, try to remove ourselves from the waiting queue.
-			if let Entry::Occupied(mut queue) = parking_lot.entry(addr(address)) {
-				// If we are not in the waking queue, this must have been a wakeup.
-				wakeup = !queue.get_mut().remove(handle);
-				if queue.get().is_empty() {
-					queue.remove();
-				}
-			}
+/// A futex of [`futex_waitv`] together with the value it is expected to have
+pub(crate) struct WaitvFutex<'a> {
+	pub address: &'a AtomicU32,
+	pub expected: u32,
+}
 
-			if wakeup {
-				return 0;
-			} else {
-				return -i32::from(Errno::Timedout);
-			}
-		} else {
-			// If we are not in the waking queue, this must have been a wakeup.
-			let wakeup = !matches!(parking_lot
-				.get(&addr(address)), Some(queue) if queue.contains(handle));
+/// If the values of all futexes match their expected values, park the current thread until one
+/// of the futexes is woken up (returns its index) or the specified timeout elapses (returns
+/// -ETIMEDOUT). Returns -EAGAIN if one of the values differs and -EINVAL if no or more than
+/// [`FUTEX_WAITV_MAX`] futexes are given.
+///
+/// The timeout is given in microseconds. If [`Flags::RELATIVE`] is given, it is interpreted as
+/// relative to the current time. Otherwise it is understood to be an absolute time
+/// (see `get_timer_ticks`).
+pub(crate) fn futex_waitv(futexes: &[WaitvFutex<'_>], timeout: Option<u64>, flags: Flags) -> i32 {
+	if futexes.is_empty() || futexes.len() > FUTEX_WAITV_MAX {
+		return -i32::from(Errno::Inval);
+	}
 
-			if wakeup {
-				return 0;
-			} else {
-				// A spurious wakeup occurred, sleep again.
-				// Tasks do not change core, so the handle in the parking lot is still current.
-				scheduler.block_current_task(wakeup_time);
-			}
-		}
-		drop(parking_lot);
+	let mut parking_lot = PARKING_LOT.lock();
+	// Check the futex values after locking the parking lot so that all changes are observed.
+	if futexes
+		.iter()
+		.any(|futex| futex.address.load(SeqCst) != futex.expected)
+	{
+		return -i32::from(Errno::Again);
+	}
+
+	let wakeup_time = wakeup_time(timeout, flags);
+	let addresses = futexes
+		.iter()
+		.map(|futex| (addr(futex.address), FUTEX_BITSET_MATCH_ANY))
+		.collect();
+	parking_lot.park(addresses, wakeup_time);
+	drop(parking_lot);
+
+	match wait_unparked(wakeup_time) {
+		Ok(index) => index.try_into().unwrap(),
+		Err(err) => -i32::from(err),
 	}
 }
 
@@ -170,68 +363,556 @@ pub(crate) fn futex_wait_and_set(
 /// `address` is used only for its address.
 /// It is safe to pass a dangling pointer.
 pub(crate) fn futex_wake(address: *const AtomicU32, count: i32) -> i32 {
+	futex_wake_bitset(address, count, FUTEX_BITSET_MATCH_ANY)
+}
+
+/// Like [`futex_wake`], but only wakes up threads, whose bitset shares at least one bit with
+/// `bitset` (see [`futex_wait_bitset`]). Returns -EINVAL if `bitset` is zero.
+pub(crate) fn futex_wake_bitset(address: *const AtomicU32, count: i32, bitset: u32) -> i32 {
+	if count < 0 || bitset == 0 {
+		return -i32::from(Errno::Inval);
+	}
+
+	PARKING_LOT.lock().wake(address.addr(), count, bitset)
+}
+
+/// Wake `count` threads waiting on the futex at address. Returns the number of threads
+/// woken up (saturates to `i32::MAX`). If `count` is `i32::MAX`, wake up all matching
+/// waiting threads. If `count` is negative, returns -EINVAL. If no thread is available,
+/// the futex at address will set to `new_value`.
+pub(crate) fn futex_wake_or_set(address: &AtomicU32, count: i32, new_value: u32) -> i32 {
 	if count < 0 {
 		return -i32::from(Errno::Inval);
 	}
 
 	let mut parking_lot = PARKING_LOT.lock();
-	let mut queue = match parking_lot.entry(address.addr()) {
-		Entry::Occupied(entry) => entry,
-		Entry::Vacant(_) => return 0,
-	};
+	let woken = parking_lot.wake(addr(address), count, FUTEX_BITSET_MATCH_ANY);
+	if woken == 0 {
+		address.store(new_value, SeqCst);
+	}
 
-	let scheduler = core_scheduler();
-	let mut woken = 0;
-	while woken != count || count == i32::MAX {
-		match queue.get_mut().pop() {
-			Some(handle) => scheduler.custom_wakeup(handle),
-			None => break,
+	woken
+}
+
+/// Wake `count` threads waiting on the futex at address and move up to `requeue_count` of the
+/// remaining threads to the futex at `address2`, so that they are woken up by wakeups of
+/// `address2`. Returns the number of woken and moved threads (saturates to `i32::MAX`).
+/// If `count` or `requeue_count` is negative, returns -EINVAL.
+/// Both addresses are used only for their address.
+/// It is safe to pass dangling pointers.
+pub(crate) fn futex_requeue(
+	address: *const AtomicU32,
+	count: i32,
+	address2: *const AtomicU32,
+	requeue_count: i32,
+) -> i32 {
+	if count < 0 || requeue_count < 0 {
+		return -i32::from(Errno::Inval);
+	}
+
+	let mut parking_lot = PARKING_LOT.lock();
+	let woken = parking_lot.wake(address.addr(), count, FUTEX_BITSET_MATCH_ANY);
+	let requeued = parking_lot.requeue(address.addr(), address2.addr(), requeue_count);
+
+	woken.saturating_add(requeued)
+}
+
+/// Like [`futex_requeue`], but returns -EAGAIN if the value at address does not match the
+/// expected value.
+pub(crate) fn futex_cmp_requeue(
+	address: &AtomicU32,
+	expected: u32,
+	count: i32,
+	address2: *const AtomicU32,
+	requeue_count: i32,
+) -> i32 {
+	if count < 0 || requeue_count < 0 {
+		return -i32::from(Errno::Inval);
+	}
+
+	let mut parking_lot = PARKING_LOT.lock();
+	// Check the futex value after locking the parking lot so that all changes are observed.
+	if address.load(SeqCst) != expected {
+		return -i32::from(Errno::Again);
+	}
+
+	let woken = parking_lot.wake(addr(address), count, FUTEX_BITSET_MATCH_ANY);
+	let requeued = parking_lot.requeue(addr(address), address2.addr(), requeue_count);
+
+	woken.saturating_add(requeued)
+}
+
+/// Set the value of the futex
+const FUTEX_OP_SET: u32 = 0;
+/// Add the argument to the value of the futex
+const FUTEX_OP_ADD: u32 = 1;
+/// Set the bits of the argument in the value of the futex
+const FUTEX_OP_OR: u32 = 2;
+/// Clear the bits of the argument in the value of the futex
+const FUTEX_OP_ANDN: u32 = 3;
+/// Toggle the bits of the argument in the value of the futex
+const FUTEX_OP_XOR: u32 = 4;
+/// The argument is `1 << oparg`
+const FUTEX_OP_OPARG_SHIFT: u32 = 8;
+
+const FUTEX_OP_CMP_EQ: u32 = 0;
+const FUTEX_OP_CMP_NE: u32 = 1;
+const FUTEX_OP_CMP_LT: u32 = 2;
+const FUTEX_OP_CMP_LE: u32 = 3;
+const FUTEX_OP_CMP_GT: u32 = 4;
+const FUTEX_OP_CMP_GE: u32 = 5;
+
+/// Operation of [`futex_wake_op`], which is encoded like the `FUTEX_OP`
+/// macro of Linux:
+/// `(op << 28) | (cmp << 24) | (oparg << 12) | cmparg`
+#[derive(Debug, PartialEq, Eq)]
+struct WakeOp {
+	op: u32,
+	oparg: u32,
+	cmp: u32,
+	cmparg: i32,
+}
+
+impl WakeOp {
+	fn decode(encoded: u32) -> io::Result<Self> {
+		// Both arguments are signed 12-bit numbers.
+		let sign_extend = |arg: u32| ((arg << 20) as i32) >> 20;
+
+		let op = encoded >> 28;
+		let cmp = (encoded >> 24) & 0xf;
+		let mut oparg = sign_extend((encoded >> 12) & 0xfff) as u32;
+		let cmparg = sign_extend(encoded & 0xfff);
+
+		if op & FUTEX_OP_OPARG_SHIFT != 0 {
+			if oparg >= u32::BITS {
+				return Err(Errno::Inval);
+			}
+			oparg = 1 << oparg;
 		}
-		woken = woken.saturating_add(1);
+
+		let op = op & !FUTEX_OP_OPARG_SHIFT;
+		if op > FUTEX_OP_XOR || cmp > FUTEX_OP_CMP_GE {
+			return Err(Errno::Nosys);
+		}
+
+		Ok(Self {
+			op,
+			oparg,
+			cmp,
+			cmparg,
+		})
 	}
 
-	if queue.get().is_empty() {
-		queue.remove();
+	/// Returns the new value of the futex.
+	fn apply(&self, value: u32) -> u32 {
+		match self.op {
+			FUTEX_OP_SET => self.oparg,
+			FUTEX_OP_ADD => value.wrapping_add(self.oparg),
+			FUTEX_OP_OR => value | self.oparg,
+			FUTEX_OP_ANDN => value & !self.oparg,
+			FUTEX_OP_XOR => value ^ self.oparg,
+			_ => unreachable!(),
+		}
 	}
 
-	woken
+	/// Compares the old value of the futex with the argument.
+	fn compare(&self, value: u32) -> bool {
+		let value = value as i32;
+		match self.cmp {
+			FUTEX_OP_CMP_EQ => value == self.cmparg,
+			FUTEX_OP_CMP_NE => value != self.cmparg,
+			FUTEX_OP_CMP_LT => value < self.cmparg,
+			FUTEX_OP_CMP_LE => value <= self.cmparg,
+			FUTEX_OP_CMP_GT => value > self.cmparg,
+			FUTEX_OP_CMP_GE => value >= self.cmparg,
+			_ => unreachable!(),
+		}
+	}
 }
 
-/// Wake `count` threads waiting on the futex at address. Returns the number of threads
-/// woken up (saturates to `i32::MAX`). If `count` is `i32::MAX`, wake up all matching
-/// waiting threads. If `count` is negative, returns -EINVAL. If no thread is available,
-/// the futex at address will set to `new_value`.
-pub(crate) fn futex_wake_or_set(address: &AtomicU32, count: i32, new_value: u32) -> i32 {
-	if count < 0 {
+/// Atomically modify the futex at `address2` as encoded in `op` and wake `count` threads waiting
+/// on the futex at address. If the old value of `address2` passes the comparison of `op`, wake
+/// `count2` threads waiting on `address2` as well. Returns the number of woken threads
+/// (saturates to `i32::MAX`).
+///
+/// Returns -EINVAL if a count is negative and -ENOSYS if `op` contains an unknown operation
+/// or comparison. `address` is used only for its address.
+pub(crate) fn futex_wake_op(
+	address: *const AtomicU32,
+	count: i32,
+	address2: &AtomicU32,
+	count2: i32,
+	op: u32,
+) -> i32 {
+	if count < 0 || count2 < 0 {
 		return -i32::from(Errno::Inval);
 	}
+	let op = match WakeOp::decode(op) {
+		Ok(op) => op,
+		Err(err) => return -i32::from(err),
+	};
 
 	let mut parking_lot = PARKING_LOT.lock();
-	let mut queue = match parking_lot.entry(addr(address)) {
-		Entry::Occupied(entry) => entry,
-		Entry::Vacant(_) => {
-			address.store(new_value, SeqCst);
-			return 0;
+	let old = address2
+		.fetch_update(SeqCst, SeqCst, |value| Some(op.apply(value)))
+		.unwrap();
+
+	let mut woken = parking_lot.wake(address.addr(), count, FUTEX_BITSET_MATCH_ANY);
+	if op.compare(old) {
+		woken =
+			woken.saturating_add(parking_lot.wake(addr(address2), count2, FUTEX_BITSET_MATCH_ANY));
+	}
+
+	woken
+}
+
+/// Highest priority, which a task inherits from the tasks waiting for its PI
+/// futexes. Only tasks with a deadline server run with [`DEADLINE_PRIO`].
+const MAX_INHERITED_PRIO: Priority = Priority::from(DEADLINE_PRIO.into() - 1);
+
+/// A PI futex, on which tasks are waiting
+struct PiFutex {
+	owner: TaskId,
+	waiters: TaskHandlePriorityQueue,
+}
+
+/// Owners and waiting tasks of the PI futexes
+struct PiState {
+	futexes: HashMap<usize, PiFutex, RandomState>,
+	/// Map between a waiting task and the address of the futex, on which it
+	/// waits, together with its handle in the waiting queue
+	waiting: BTreeMap<TaskId, (usize, TaskHandle)>,
+	/// Priorities of boosted tasks before they were boosted
+	base_prios: BTreeMap<TaskId, Priority>,
+}
+
+impl PiState {
+	const fn new() -> Self {
+		Self {
+			futexes: HashMap::with_hasher(RandomState::with_seeds(0, 0, 0, 0)),
+			waiting: BTreeMap::new(),
+			base_prios: BTreeMap::new(),
+		}
+	}
+
+	/// Returns `true` if the task `id` would wait for itself, if it waits for
+	/// a futex owned by `owner`.
+	fn would_deadlock(&self, id: TaskId, mut owner: TaskId) -> bool {
+		for _ in 0..=self.waiting.len() {
+			if owner == id {
+				return true;
+			}
+
+			let Some((address, _)) = self.waiting.get(&owner) else {
+				return false;
+			};
+			owner = self.futexes[address].owner;
+		}
+
+		false
+	}
+
+	/// Boosts the task `id` to the highest priority of the tasks, which wait
+	/// for one of its futexes, or restores its original priority. The change
+	/// is passed along the chain of owners, if `id` waits for a futex itself.
+	///
+	/// Waiting deadline tasks boost the owner only to [`MAX_INHERITED_PRIO`].
+	fn propagate(&mut self, mut id: TaskId) {
+		// A chain contains every waiting task at most once.
+		for _ in 0..=self.waiting.len() {
+			let Some(handle) = scheduler::get_task_handle(id) else {
+				self.base_prios.remove(&id);
+				break;
+			};
+			let current = handle.get_priority();
+			let base = self.base_prios.get(&id).copied().unwrap_or(current);
+			let prio = self
+				.futexes
+				.values()
+				.filter(|futex| futex.owner == id)
+				.filter_map(|futex| futex.waiters.highest_priority())
+				.map(|prio| cmp::min(prio, MAX_INHERITED_PRIO))
+				.fold(base, cmp::max);
+
+			if prio == base {
+				self.base_prios.remove(&id);
+			} else {
+				self.base_prios.insert(id, base);
+			}
+
+			if prio == current {
+				break;
+			}
+
+			debug!("Change priority of task {id} from {current} to {prio}");
+			let _ = core_scheduler().set_priority(id, prio);
+
+			// The waiting queue is sorted by the priorities.
+			let Some((address, queued)) = self.waiting.get_mut(&id) else {
+				break;
+			};
+			let futex = self.futexes.get_mut(address).unwrap();
+			futex.waiters.remove(*queued);
+			*queued = queued.with_priority(prio);
+			futex.waiters.push(*queued);
+			id = futex.owner;
+		}
+	}
+
+	/// Removes the waiting task `id` from the futex at `address`, if it is
+	/// still waiting.
+	fn remove_waiter(&mut self, id: TaskId, address: &AtomicU32) {
+		let Some((_, queued)) = self.waiting.remove(&id) else {
+			return;
+		};
+
+		let Entry::Occupied(mut futex) = self.futexes.entry(addr(address)) else {
+			return;
+		};
+		futex.get_mut().waiters.remove(queued);
+		let owner = futex.get().owner;
+		if futex.get().waiters.is_empty() {
+			futex.remove();
+			address.fetch_and(!FUTEX_WAITERS, SeqCst);
+		}
+
+		self.propagate(owner);
+	}
+}
+
+/// Changes the priority of the task `id`, to which it returns, as soon as it
+/// does not inherit a higher priority from the tasks waiting for its futexes.
+pub(crate) fn set_base_priority(id: TaskId, prio: Priority) {
+	let mut state = PI_STATE.lock();
+	state.base_prios.insert(id, prio);
+	state.propagate(id);
+}
+
+fn tid(id: TaskId) -> u32 {
+	id.into().try_into().unwrap()
+}
+
+/// Locks the PI futex at `address` for the thread `tid`, if it is unlocked.
+/// Otherwise, the value of the futex is returned.
+fn try_lock_pi(address: &AtomicU32, tid: u32) -> Result<(), u32> {
+	let mut value = address.load(SeqCst);
+	while value & FUTEX_TID_MASK == 0 {
+		match address.compare_exchange_weak(value, tid | (value & FUTEX_WAITERS), SeqCst, SeqCst) {
+			Ok(_) => return Ok(()),
+			Err(current) => value = current,
+		}
+	}
+
+	Err(value)
+}
+
+/// Locks the priority-inheritance futex at address, which contains the thread
+/// ID of its owner or 0 if it is unlocked. Returns 0 after the current thread
+/// became the owner or -ETIMEDOUT if the specified timeout elapses.
+///
+/// As long as the current thread waits, the owner inherits its priority. If the
+/// owner waits for another PI futex itself, the priority is passed along the
+/// chain of owners. Returns -EDEADLK if the chain ends at the current thread
+/// and -ESRCH if the owner does not exist.
+///
+/// Threads can lock and unlock an uncontended futex in user space by
+/// exchanging 0 and their thread ID atomically. Waiting threads set
+/// [`FUTEX_WAITERS`], so that the owner has to call [`futex_unlock_pi`].
+///
+/// The timeout is given in microseconds. If [`Flags::RELATIVE`] is given, it is interpreted as
+/// relative to the current time. Otherwise it is understood to be an absolute time
+/// (see `get_timer_ticks`).
+pub(crate) fn futex_lock_pi(address: &AtomicU32, timeout: Option<u64>, flags: Flags) -> i32 {
+	let id = core_scheduler().get_current_task_id();
+	let tid = tid(id);
+
+	let wakeup_time = if flags.contains(Flags::RELATIVE) {
+		timeout.and_then(|t| get_timer_ticks().checked_add(t))
+	} else {
+		timeout
+	};
+
+	let mut state = PI_STATE.lock();
+	let owner = loop {
+		let value = match try_lock_pi(address, tid) {
+			Ok(()) => return 0,
+			Err(value) => value,
+		};
+
+		let owner = value & FUTEX_TID_MASK;
+		if owner == tid {
+			return -i32::from(Errno::Deadlk);
+		}
+		let owner = TaskId::from(owner.try_into().unwrap());
+		if scheduler::get_task_handle(owner).is_none() {
+			return -i32::from(Errno::Srch);
+		}
+		if state.would_deadlock(id, owner) {
+			return -i32::from(Errno::Deadlk);
+		}
+
+		// The owner has to unlock the futex in the kernel.
+		if value & FUTEX_WAITERS != 0
+			|| address
+				.compare_exchange(value, value | FUTEX_WAITERS, SeqCst, SeqCst)
+				.is_ok()
+		{
+			break owner;
 		}
 	};
 
 	let scheduler = core_scheduler();
-	let mut woken = 0;
-	while woken != count || count == i32::MAX {
-		match queue.get_mut().pop() {
-			Some(handle) => scheduler.custom_wakeup(handle),
-			None => break,
+	scheduler.block_current_task(wakeup_time);
+	let handle = scheduler.get_current_task_handle();
+	let futex = state.futexes.entry(addr(address)).or_insert(PiFutex {
+		owner,
+		waiters: TaskHandlePriorityQueue::new(),
+	});
+	futex.owner = owner;
+	futex.waiters.push(handle);
+	state.waiting.insert(id, (addr(address), handle));
+	state.propagate(owner);
+	drop(state);
+
+	loop {
+		core_scheduler().reschedule_migratable();
+
+		let mut state = PI_STATE.lock();
+		// The previous owner removes us from the waiting tasks, when it passes the futex to us.
+		let Some(&(_, queued)) = state.waiting.get(&id) else {
+			return 0;
+		};
+
+		if matches!(wakeup_time, Some(t) if t <= get_timer_ticks()) {
+			state.remove_waiter(id, address);
+			return -i32::from(Errno::Timedout);
 		}
-		woken = woken.saturating_add(1);
+
+		// A spurious wakeup occurred, sleep again.
+		// The task may have been moved to another core in the meantime,
+		// so the handle in the waiting queue has to be updated.
+		let scheduler = core_scheduler();
+		scheduler.block_current_task(wakeup_time);
+		let handle = scheduler.get_current_task_handle();
+		let futex = state.futexes.get_mut(&addr(address)).unwrap();
+		futex.waiters.remove(queued);
+		futex.waiters.push(handle);
+		state.waiting.insert(id, (addr(address), handle));
+		drop(state);
 	}
+}
+
+/// Like [`futex_lock_pi`], but returns -EAGAIN instead of waiting, if the
+/// futex is locked by another thread.
+pub(crate) fn futex_trylock_pi(address: &AtomicU32) -> i32 {
+	let tid = tid(core_scheduler().get_current_task_id());
 
-	if queue.get().is_empty() {
-		queue.remove();
+	match try_lock_pi(address, tid) {
+		Ok(()) => 0,
+		Err(value) if value & FUTEX_TID_MASK == tid => -i32::from(Errno::Deadlk),
+		Err(_) => -i32::from(Errno::Again),
 	}
+}
 
-	if woken == 0 {
-		address.store(new_value, SeqCst);
+/// Unlocks the priority-inheritance futex at address, which has to be owned by
+/// the current thread (otherwise returns -EPERM). The waiting thread with the
+/// highest priority becomes the new owner and the current thread loses the
+/// priority, which it has inherited from the waiting threads.
+pub(crate) fn futex_unlock_pi(address: &AtomicU32) -> i32 {
+	let id = core_scheduler().get_current_task_id();
+
+	let mut state = PI_STATE.lock();
+	if address.load(SeqCst) & FUTEX_TID_MASK != tid(id) {
+		return -i32::from(Errno::Perm);
 	}
 
-	woken
+	let Entry::Occupied(mut futex) = state.futexes.entry(addr(address)) else {
+		// No thread waits in the kernel.
+		address.store(0, SeqCst);
+		return 0;
+	};
+
+	let handle = futex.get_mut().waiters.pop().unwrap();
+	let new_owner = handle.get_id();
+	let waiters = if futex.get().waiters.is_empty() {
+		futex.remove();
+		0
+	} else {
+		futex.get_mut().owner = new_owner;
+		FUTEX_WAITERS
+	};
+	address.store(tid(new_owner) | waiters, SeqCst);
+	state.waiting.remove(&new_owner);
+
+	// The new owner inherits the priorities of the remaining waiting threads.
+	state.propagate(new_owner);
+	state.propagate(id);
+
+	let scheduler = core_scheduler();
+	scheduler.custom_wakeup(handle);
+	drop(state);
+
+	// The new owner may have a higher priority than the current thread.
+	scheduler.reschedule_migratable();
+
+	0
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod tests {
+	use super::*;
+
+	const fn futex_op(op: u32, oparg: u32, cmp: u32, cmparg: u32) -> u32 {
+		(op << 28) | (cmp << 24) | ((oparg & 0xfff) << 12) | (cmparg & 0xfff)
+	}
+
+	#[test]
+	fn test_wake_op() {
+		let op = WakeOp::decode(futex_op(FUTEX_OP_ADD, 1, FUTEX_OP_CMP_GT, 0)).unwrap();
+		assert_eq!(op.apply(5), 6);
+		assert!(op.compare(5));
+		assert!(!op.compare(0));
+
+		// The arguments are sign-extended.
+		let op = WakeOp::decode(futex_op(
+			FUTEX_OP_SET,
+			(-1i32) as u32,
+			FUTEX_OP_CMP_LT,
+			(-2i32) as u32,
+		))
+		.unwrap();
+		assert_eq!(op.apply(5), u32::MAX);
+		assert!(op.compare((-3i32) as u32));
+		assert!(!op.compare(u32::MAX));
+
+		let op = WakeOp::decode(futex_op(
+			FUTEX_OP_OR | FUTEX_OP_OPARG_SHIFT,
+			4,
+			FUTEX_OP_CMP_EQ,
+			0,
+		))
+		.unwrap();
+		assert_eq!(op.apply(1), 0x11);
+
+		let op = WakeOp::decode(futex_op(FUTEX_OP_ANDN, 0b110, FUTEX_OP_CMP_NE, 0)).unwrap();
+		assert_eq!(op.apply(0b111), 0b001);
+
+		assert_eq!(
+			WakeOp::decode(futex_op(FUTEX_OP_XOR + 1, 0, FUTEX_OP_CMP_EQ, 0)),
+			Err(Errno::Nosys)
+		);
+		assert_eq!(
+			WakeOp::decode(futex_op(FUTEX_OP_SET, 0, FUTEX_OP_CMP_GE + 1, 0)),
+			Err(Errno::Nosys)
+		);
+		assert_eq!(
+			WakeOp::decode(futex_op(
+				FUTEX_OP_SET | FUTEX_OP_OPARG_SHIFT,
+				32,
+				FUTEX_OP_CMP_EQ,
+				0
+			)),
+			Err(Errno::Inval)
+		);
+	}
 }
@@ -27,10 +27,11 @@ impl RecursiveMutex {
 
 	pub fn acquire(&self) {
 		// Get information about the current task.
-		let core_scheduler = core_scheduler();
-		let tid = core_scheduler.get_current_task_id();
+		let tid = core_scheduler().get_current_task_id();
 
 		loop {
+			// The task may be moved to another core while it is blocked.
+			let core_scheduler = core_scheduler();
 			{
 				let mut locked_state = self.state.lock();
 
@@ -58,7 +59,7 @@ impl RecursiveMutex {
 			}
 
 			// Switch to the next task.
-			core_scheduler.reschedule();
+			core_scheduler.reschedule_migratable();
 		}
 	}
 
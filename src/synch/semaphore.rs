@@ -68,12 +68,13 @@ impl Semaphore {
 	pub fn acquire(&self, time: Option<u64>) -> bool {
 		#[cfg(feature = "smp")]
 		let backoff = Backoff::new();
-		let core_scheduler = core_scheduler();
 
 		let wakeup_time = time.map(|ms| crate::arch::processor::get_timer_ticks() + ms * 1000);
 
 		// Loop until we have acquired the semaphore.
 		loop {
+			// The task may be moved to another core while it is blocked.
+			let core_scheduler = core_scheduler();
 			let mut locked_state = self.state.lock();
 
 			if locked_state.count > 0 {
@@ -101,7 +102,7 @@ impl Semaphore {
 					.push(core_scheduler.get_current_task_handle());
 				drop(locked_state);
 				// Switch to the next task.
-				core_scheduler.reschedule();
+				core_scheduler.reschedule_migratable();
 			} else {
 				drop(locked_state);
 				backoff.snooze();
@@ -117,7 +118,7 @@ impl Semaphore {
 					.push(core_scheduler.get_current_task_handle());
 				drop(locked_state);
 				// Switch to the next task.
-				core_scheduler.reschedule();
+				core_scheduler.reschedule_migratable();
 			}
 		}
 	}
@@ -1,9 +1,41 @@
+use alloc::vec::Vec;
+use core::ptr;
 use core::sync::atomic::AtomicU32;
 
 use crate::errno::Errno;
-use crate::synch::futex::{self as synch, Flags};
+use crate::synch::futex::{self as synch, Flags, WaitvFutex};
 use crate::time::timespec;
 
+/// The futex has a size of 32 bits.
+pub const FUTEX2_SIZE_U32: u32 = 0x02;
+/// The futex is not shared between processes.
+pub const FUTEX2_PRIVATE: u32 = 0x80;
+
+/// A futex of [`sys_futex_waitv`] together with the value it is expected to have
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct futex_waitv {
+	/// Expected value of the futex
+	pub val: u64,
+	/// Address of the futex
+	pub uaddr: u64,
+	/// Has to contain [`FUTEX2_SIZE_U32`]
+	pub flags: u32,
+	pub __reserved: u32,
+}
+
+/// Converts an optional timeout into microseconds. Fails if it is negative.
+unsafe fn timeout_usec(timeout: *const timespec) -> Result<Option<u64>, ()> {
+	if timeout.is_null() {
+		return Ok(None);
+	}
+
+	match unsafe { timeout.read().into_usec() } {
+		Some(usec) if usec >= 0 => Ok(Some(usec as u64)),
+		_ => Err(()),
+	}
+}
+
 /// Like `synch::futex_wait`, but does extra sanity checks and takes a `timespec`.
 ///
 /// Returns -EINVAL if
@@ -23,13 +55,8 @@ pub unsafe extern "C" fn sys_futex_wait(
 	}
 
 	let address = unsafe { &*(address as *const AtomicU32) };
-	let timeout = if timeout.is_null() {
-		None
-	} else {
-		match unsafe { timeout.read().into_usec() } {
-			Some(usec) if usec >= 0 => Some(usec as u64),
-			_ => return -i32::from(Errno::Inval),
-		}
+	let Ok(timeout) = (unsafe { timeout_usec(timeout) }) else {
+		return -i32::from(Errno::Inval);
 	};
 	let Some(flags) = Flags::from_bits(flags) else {
 		return -i32::from(Errno::Inval);
@@ -52,3 +79,230 @@ pub unsafe extern "C" fn sys_futex_wake(address: *mut u32, count: i32) -> i32 {
 
 	synch::futex_wake(address as *const AtomicU32, count)
 }
+
+/// Like `synch::futex_wait_bitset`, but does extra sanity checks and takes a `timespec`.
+///
+/// Returns -EINVAL if
+/// * `address` is null
+/// * `timeout` is negative
+/// * `flags` contains unknown flags
+/// * `bitset` is zero
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_futex_wait_bitset(
+	address: *mut u32,
+	expected: u32,
+	timeout: *const timespec,
+	flags: u32,
+	bitset: u32,
+) -> i32 {
+	if address.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	let address = unsafe { &*(address as *const AtomicU32) };
+	let Ok(timeout) = (unsafe { timeout_usec(timeout) }) else {
+		return -i32::from(Errno::Inval);
+	};
+	let Some(flags) = Flags::from_bits(flags) else {
+		return -i32::from(Errno::Inval);
+	};
+
+	synch::futex_wait_bitset(address, expected, timeout, flags, bitset)
+}
+
+/// Like `synch::futex_wake_bitset`, but does extra sanity checks.
+///
+/// Returns -EINVAL if `address` is null or `bitset` is zero.
+/// `address` is used only for its address.
+/// It is safe to pass a dangling pointer.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_futex_wake_bitset(address: *mut u32, count: i32, bitset: u32) -> i32 {
+	if address.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	synch::futex_wake_bitset(address as *const AtomicU32, count, bitset)
+}
+
+/// Like `synch::futex_requeue`, but does extra sanity checks.
+///
+/// Returns -EINVAL if `address` or `address2` is null.
+/// Both addresses are used only for their address.
+/// It is safe to pass dangling pointers.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_futex_requeue(
+	address: *mut u32,
+	count: i32,
+	address2: *mut u32,
+	requeue_count: i32,
+) -> i32 {
+	if address.is_null() || address2.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	synch::futex_requeue(
+		address as *const AtomicU32,
+		count,
+		address2 as *const AtomicU32,
+		requeue_count,
+	)
+}
+
+/// Like `synch::futex_cmp_requeue`, but does extra sanity checks.
+///
+/// Returns -EINVAL if `address` or `address2` is null.
+/// `address2` is used only for its address.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_futex_cmp_requeue(
+	address: *mut u32,
+	expected: u32,
+	count: i32,
+	address2: *mut u32,
+	requeue_count: i32,
+) -> i32 {
+	if address.is_null() || address2.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	let address = unsafe { &*(address as *const AtomicU32) };
+	synch::futex_cmp_requeue(
+		address,
+		expected,
+		count,
+		address2 as *const AtomicU32,
+		requeue_count,
+	)
+}
+
+/// Like `synch::futex_wake_op`, but does extra sanity checks.
+///
+/// Returns -EINVAL if `address` or `address2` is null.
+/// `address` is used only for its address.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_futex_wake_op(
+	address: *mut u32,
+	count: i32,
+	address2: *mut u32,
+	count2: i32,
+	op: u32,
+) -> i32 {
+	if address.is_null() || address2.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	let address2 = unsafe { &*(address2 as *const AtomicU32) };
+	synch::futex_wake_op(address as *const AtomicU32, count, address2, count2, op)
+}
+
+/// Like `synch::futex_waitv`, but does extra sanity checks and takes a `timespec`.
+/// Waits on the `count` futexes described by `waiters` and returns the index of the futex,
+/// which woke up the thread.
+///
+/// Returns -EINVAL if
+/// * `waiters` is null
+/// * `timeout` is negative
+/// * `flags` contains unknown flags
+/// * a futex is null, misaligned, or its expected value or flags are invalid
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_futex_waitv(
+	waiters: *const futex_waitv,
+	count: u32,
+	timeout: *const timespec,
+	flags: u32,
+) -> i32 {
+	if waiters.is_null() || count as usize > synch::FUTEX_WAITV_MAX {
+		return -i32::from(Errno::Inval);
+	}
+
+	let waiters = unsafe { core::slice::from_raw_parts(waiters, count as usize) };
+	let Ok(timeout) = (unsafe { timeout_usec(timeout) }) else {
+		return -i32::from(Errno::Inval);
+	};
+	let Some(flags) = Flags::from_bits(flags) else {
+		return -i32::from(Errno::Inval);
+	};
+
+	let mut futexes = Vec::with_capacity(waiters.len());
+	for waiter in waiters {
+		let address = ptr::with_exposed_provenance::<AtomicU32>(waiter.uaddr as usize);
+		if address.is_null()
+			|| !address.is_aligned()
+			|| waiter.flags & !FUTEX2_PRIVATE != FUTEX2_SIZE_U32
+			|| waiter.__reserved != 0
+		{
+			return -i32::from(Errno::Inval);
+		}
+		let Ok(expected) = waiter.val.try_into() else {
+			return -i32::from(Errno::Inval);
+		};
+
+		futexes.push(WaitvFutex {
+			address: unsafe { &*address },
+			expected,
+		});
+	}
+
+	synch::futex_waitv(&futexes, timeout, flags)
+}
+
+/// Like `synch::futex_lock_pi`, but does extra sanity checks and takes a `timespec`.
+///
+/// Returns -EINVAL if
+/// * `address` is null
+/// * `timeout` is negative
+/// * `flags` contains unknown flags
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_futex_lock_pi(
+	address: *mut u32,
+	timeout: *const timespec,
+	flags: u32,
+) -> i32 {
+	if address.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	let address = unsafe { &*(address as *const AtomicU32) };
+	let Ok(timeout) = (unsafe { timeout_usec(timeout) }) else {
+		return -i32::from(Errno::Inval);
+	};
+	let Some(flags) = Flags::from_bits(flags) else {
+		return -i32::from(Errno::Inval);
+	};
+
+	synch::futex_lock_pi(address, timeout, flags)
+}
+
+/// Like `synch::futex_trylock_pi`, but does extra sanity checks.
+///
+/// Returns -EINVAL if `address` is null.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_futex_trylock_pi(address: *mut u32) -> i32 {
+	if address.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	let address = unsafe { &*(address as *const AtomicU32) };
+	synch::futex_trylock_pi(address)
+}
+
+/// Like `synch::futex_unlock_pi`, but does extra sanity checks.
+///
+/// Returns -EINVAL if `address` is null.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_futex_unlock_pi(address: *mut u32) -> i32 {
+	if address.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	let address = unsafe { &*(address as *const AtomicU32) };
+	synch::futex_unlock_pi(address)
+}
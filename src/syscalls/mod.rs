@@ -1,6 +1,8 @@
 #![allow(clippy::result_unit_err)]
 
 use alloc::ffi::CString;
+use alloc::string::String;
+use alloc::vec::Vec;
 #[cfg(all(target_os = "none", not(feature = "common-os")))]
 use core::alloc::{GlobalAlloc, Layout};
 use core::ffi::{CStr, c_char};
@@ -21,24 +23,26 @@ pub use self::spinlock::*;
 pub use self::system::*;
 pub use self::tasks::*;
 pub use self::timer::*;
-use crate::env;
 use crate::errno::{Errno, ToErrno};
 use crate::executor::block_on;
+use crate::fd::lock::LockType;
 use crate::fd::{
 	self, AccessOption, AccessPermission, EventFlags, FileDescriptor, OpenOption, PollFd,
 	dup_object, dup_object2, get_object, isatty, remove_object,
 };
-use crate::fs::{self, FileAttr, SeekWhence};
+use crate::fs::{self, AtFlags, FileAttr, SeekWhence, StatFs, XattrFlags};
 #[cfg(all(target_os = "none", not(feature = "common-os")))]
 use crate::mm::ALLOCATOR;
 use crate::syscalls::interfaces::SyscallInterface;
+use crate::time::timespec;
+use crate::{env, io};
 
 mod condvar;
 mod entropy;
 mod futex;
 pub(crate) mod interfaces;
 #[cfg(feature = "mman")]
-mod mman;
+pub(crate) mod mman;
 mod processor;
 #[cfg(feature = "newlib")]
 mod recmutex;
@@ -276,6 +280,99 @@ pub unsafe extern "C" fn sys_rmdir(name: *const c_char) -> i32 {
 	crate::fs::remove_dir(name).map_or_else(|e| -i32::from(e), |()| 0)
 }
 
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_mkdirat(dirfd: FileDescriptor, name: *const c_char, mode: u32) -> i32 {
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -i32::from(Errno::Inval);
+	};
+	let Some(mode) = AccessPermission::from_bits(mode) else {
+		return -i32::from(Errno::Inval);
+	};
+
+	fs::mkdirat(dirfd, name, mode).map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+/// Removes a file or, if `AT_REMOVEDIR` is set in `flags`, an empty directory.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_unlinkat(
+	dirfd: FileDescriptor,
+	name: *const c_char,
+	flags: i32,
+) -> i32 {
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -i32::from(Errno::Inval);
+	};
+	let Some(flags) = AtFlags::from_bits(flags) else {
+		return -i32::from(Errno::Inval);
+	};
+
+	fs::unlinkat(dirfd, name, flags).map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_rename(from: *const c_char, to: *const c_char) -> i32 {
+	unsafe { sys_renameat(fs::AT_FDCWD, from, fs::AT_FDCWD, to) }
+}
+
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_renameat(
+	olddirfd: FileDescriptor,
+	from: *const c_char,
+	newdirfd: FileDescriptor,
+	to: *const c_char,
+) -> i32 {
+	let Ok(from) = unsafe { CStr::from_ptr(from) }.to_str() else {
+		return -i32::from(Errno::Inval);
+	};
+	let Ok(to) = unsafe { CStr::from_ptr(to) }.to_str() else {
+		return -i32::from(Errno::Inval);
+	};
+
+	fs::renameat(olddirfd, from, newdirfd, to).map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_readlink(name: *const c_char, buf: *mut u8, len: usize) -> isize {
+	unsafe { sys_readlinkat(fs::AT_FDCWD, name, buf, len) }
+}
+
+/// Places the target of the symbolic link `name` in `buf`.
+///
+/// Like on Linux, the target is truncated to `len` bytes and not
+/// terminated by a null byte. Returns the number of bytes placed in `buf`.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_readlinkat(
+	dirfd: FileDescriptor,
+	name: *const c_char,
+	buf: *mut u8,
+	len: usize,
+) -> isize {
+	if buf.is_null() || len == 0 {
+		return isize::try_from(-i32::from(Errno::Inval)).unwrap();
+	}
+
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return isize::try_from(-i32::from(Errno::Inval)).unwrap();
+	};
+
+	match fs::readlinkat(dirfd, name) {
+		Ok(target) => {
+			let count = target.len().min(len);
+			unsafe {
+				buf.copy_from_nonoverlapping(target.as_ptr(), count);
+			}
+			count.try_into().unwrap()
+		}
+		Err(e) => isize::try_from(-i32::from(e)).unwrap(),
+	}
+}
+
 #[hermit_macro::system(errno)]
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn sys_stat(name: *const c_char, stat: *mut FileAttr) -> i32 {
@@ -304,6 +401,34 @@ pub unsafe extern "C" fn sys_lstat(name: *const c_char, stat: *mut FileAttr) ->
 	}
 }
 
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_fstatat(
+	dirfd: FileDescriptor,
+	name: *const c_char,
+	stat: *mut FileAttr,
+	flags: i32,
+) -> i32 {
+	if name.is_null() || stat.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -i32::from(Errno::Inval);
+	};
+	let Some(flags) = AtFlags::from_bits(flags) else {
+		return -i32::from(Errno::Inval);
+	};
+
+	fs::fstatat(dirfd, name, flags).map_or_else(
+		|e| -i32::from(e),
+		|v| unsafe {
+			*stat = v;
+			0
+		},
+	)
+}
+
 #[hermit_macro::system(errno)]
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn sys_fstat(fd: FileDescriptor, stat: *mut FileAttr) -> i32 {
@@ -320,6 +445,44 @@ pub unsafe extern "C" fn sys_fstat(fd: FileDescriptor, stat: *mut FileAttr) -> i
 	)
 }
 
+/// Returns statistics about the file system containing the file `name`.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_statfs(name: *const c_char, buf: *mut StatFs) -> i32 {
+	if name.is_null() || buf.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -i32::from(Errno::Inval);
+	};
+
+	fs::statfs(name).map_or_else(
+		|e| -i32::from(e),
+		|v| unsafe {
+			*buf = v;
+			0
+		},
+	)
+}
+
+/// Returns statistics about the file system containing the open file `fd`.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_fstatfs(fd: FileDescriptor, buf: *mut StatFs) -> i32 {
+	if buf.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	crate::fd::fstatfs(fd).map_or_else(
+		|e| -i32::from(e),
+		|v| unsafe {
+			*buf = v;
+			0
+		},
+	)
+}
+
 #[hermit_macro::system(errno)]
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn sys_opendir(name: *const c_char) -> FileDescriptor {
@@ -347,6 +510,28 @@ pub unsafe extern "C" fn sys_open(name: *const c_char, flags: i32, mode: u32) ->
 	}
 }
 
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_openat(
+	dirfd: FileDescriptor,
+	name: *const c_char,
+	flags: i32,
+	mode: u32,
+) -> FileDescriptor {
+	let Some(flags) = OpenOption::from_bits(flags) else {
+		return -i32::from(Errno::Inval);
+	};
+	let Some(mode) = AccessPermission::from_bits(mode) else {
+		return -i32::from(Errno::Inval);
+	};
+
+	if let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() {
+		crate::fs::openat(dirfd, name, flags, mode).unwrap_or_else(|e| -i32::from(e))
+	} else {
+		-i32::from(Errno::Inval)
+	}
+}
+
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn sys_getcwd(buf: *mut c_char, size: usize) -> *const c_char {
@@ -388,8 +573,8 @@ pub unsafe extern "C" fn sys_getcwd(buf: *mut c_char, size: usize) -> *const c_c
 
 #[hermit_macro::system(errno)]
 #[unsafe(no_mangle)]
-pub extern "C" fn sys_fchdir(_fd: FileDescriptor) -> i32 {
-	-i32::from(Errno::Nosys)
+pub extern "C" fn sys_fchdir(fd: FileDescriptor) -> i32 {
+	crate::fs::fchdir(fd).map_or_else(|e| -i32::from(e), |()| 0)
 }
 
 #[hermit_macro::system(errno)]
@@ -415,34 +600,21 @@ pub unsafe extern "C" fn sys_umask(umask: u32) -> u32 {
 pub unsafe extern "C" fn sys_faccessat(
 	dirfd: FileDescriptor,
 	name: *const c_char,
-	_mode: i32,
+	mode: i32,
 	flags: i32,
 ) -> i32 {
-	let Some(access_option) = AccessOption::from_bits(flags) else {
+	let Some(access_option) = AccessOption::from_bits(mode) else {
 		return -i32::from(Errno::Inval);
 	};
-
-	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+	let Some(flags) = AtFlags::from_bits(flags) else {
 		return -i32::from(Errno::Inval);
 	};
 
-	const AT_SYMLINK_NOFOLLOW: i32 = 0x100;
-	const AT_FDCWD: i32 = -100;
-
-	let stat = if name.starts_with("/") || dirfd == AT_FDCWD {
-		let no_follow: bool = (flags & AT_SYMLINK_NOFOLLOW) != 0;
-
-		if no_follow {
-			fs::read_stat(name)
-		} else {
-			fs::read_lstat(name)
-		}
-	} else {
-		warn!("faccessat with directory relative to fd is not implemented!");
-		return -i32::from(Errno::Nosys);
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -i32::from(Errno::Inval);
 	};
 
-	match stat {
+	match fs::fstatat(dirfd, name, flags) {
 		Err(e) => -i32::from(e),
 		Ok(stat) if access_option.can_access(stat.st_mode) => 0,
 		Ok(_) => -i32::from(Errno::Acces),
@@ -483,6 +655,349 @@ pub unsafe extern "C" fn sys_fchmod(fd: FileDescriptor, mode: u32) -> i32 {
 		.unwrap_or_else(|e| -i32::from(e))
 }
 
+/// Converts an owner or group id of `chown`, where `-1` leaves the id unchanged.
+fn owner_id(id: u32) -> Option<u32> {
+	(id != u32::MAX).then_some(id)
+}
+
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_chown(name: *const c_char, uid: u32, gid: u32) -> i32 {
+	unsafe { sys_fchownat(fs::AT_FDCWD, name, uid, gid, 0) }
+}
+
+/// Like `sys_chown`, but changes a symbolic link itself instead of its target.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_lchown(name: *const c_char, uid: u32, gid: u32) -> i32 {
+	unsafe {
+		sys_fchownat(
+			fs::AT_FDCWD,
+			name,
+			uid,
+			gid,
+			AtFlags::AT_SYMLINK_NOFOLLOW.bits(),
+		)
+	}
+}
+
+/// Changes owner and group of `name` relative to `dirfd`.
+///
+/// A symbolic link is only changed itself if `AT_SYMLINK_NOFOLLOW` is set
+/// in `flags`.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_fchownat(
+	dirfd: FileDescriptor,
+	name: *const c_char,
+	uid: u32,
+	gid: u32,
+	flags: i32,
+) -> i32 {
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -i32::from(Errno::Inval);
+	};
+	let Some(flags) = AtFlags::from_bits(flags) else {
+		return -i32::from(Errno::Inval);
+	};
+
+	fs::fchownat(dirfd, name, owner_id(uid), owner_id(gid), flags)
+		.map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_fchown(fd: FileDescriptor, uid: u32, gid: u32) -> i32 {
+	crate::fd::chown(fd, owner_id(uid), owner_id(gid)).map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+/// Changes the access (`times[0]`) and modification time (`times[1]`) of `name`.
+///
+/// If `times` is null, both timestamps are set to the current time.
+/// `tv_nsec` may be `UTIME_NOW` or `UTIME_OMIT` to set a timestamp to
+/// the current time or to leave it unchanged.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_utimensat(
+	dirfd: FileDescriptor,
+	name: *const c_char,
+	times: *const timespec,
+	flags: i32,
+) -> i32 {
+	let Some(flags) = AtFlags::from_bits(flags) else {
+		return -i32::from(Errno::Inval);
+	};
+	let times = unsafe { times.cast::<[timespec; 2]>().as_ref() };
+
+	// Like Linux, a null path refers to the file descriptor itself.
+	if name.is_null() {
+		return fs::futimens(dirfd, times).map_or_else(|e| -i32::from(e), |()| 0);
+	}
+
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -i32::from(Errno::Inval);
+	};
+
+	fs::utimensat(dirfd, name, times, flags).map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_futimens(fd: FileDescriptor, times: *const timespec) -> i32 {
+	let times = unsafe { times.cast::<[timespec; 2]>().as_ref() };
+
+	fs::futimens(fd, times).map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+/// Copies the value of an extended attribute to `buf` and returns its size.
+///
+/// Like on Linux, a `size` of zero only returns the size of the value.
+fn copy_xattr(value: io::Result<Vec<u8>>, buf: *mut u8, size: usize) -> isize {
+	match value {
+		Ok(value) if size == 0 => value.len().try_into().unwrap(),
+		Ok(value) if value.len() > size => isize::try_from(-i32::from(Errno::Range)).unwrap(),
+		Ok(value) => {
+			unsafe {
+				buf.copy_from_nonoverlapping(value.as_ptr(), value.len());
+			}
+			value.len().try_into().unwrap()
+		}
+		Err(e) => isize::try_from(-i32::from(e)).unwrap(),
+	}
+}
+
+/// Converts the names of extended attributes to a list of null-terminated strings.
+fn xattr_list(names: io::Result<Vec<String>>) -> io::Result<Vec<u8>> {
+	let mut list = Vec::new();
+	for name in names? {
+		list.extend_from_slice(name.as_bytes());
+		list.push(0);
+	}
+	Ok(list)
+}
+
+/// Returns the value of an extended attribute, which is passed to `setxattr`.
+unsafe fn xattr_value<'a>(value: *const u8, size: usize) -> &'a [u8] {
+	if size == 0 {
+		&[]
+	} else {
+		unsafe { core::slice::from_raw_parts(value, size) }
+	}
+}
+
+/// Places the value of the extended attribute `attr` of `name` in `value`.
+///
+/// Returns the size of the value. If `size` is zero, only the size is
+/// returned. Fails with `ERANGE` if `value` is too small.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_getxattr(
+	name: *const c_char,
+	attr: *const c_char,
+	value: *mut u8,
+	size: usize,
+) -> isize {
+	unsafe { path_getxattr(name, attr, value, size, true) }
+}
+
+/// Like `sys_getxattr`, but does not follow symbolic links.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_lgetxattr(
+	name: *const c_char,
+	attr: *const c_char,
+	value: *mut u8,
+	size: usize,
+) -> isize {
+	unsafe { path_getxattr(name, attr, value, size, false) }
+}
+
+unsafe fn path_getxattr(
+	name: *const c_char,
+	attr: *const c_char,
+	value: *mut u8,
+	size: usize,
+	follow: bool,
+) -> isize {
+	let (Ok(name), Ok(attr)) = (
+		unsafe { CStr::from_ptr(name) }.to_str(),
+		unsafe { CStr::from_ptr(attr) }.to_str(),
+	) else {
+		return isize::try_from(-i32::from(Errno::Inval)).unwrap();
+	};
+
+	copy_xattr(fs::getxattr(name, attr, follow), value, size)
+}
+
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_fgetxattr(
+	fd: FileDescriptor,
+	attr: *const c_char,
+	value: *mut u8,
+	size: usize,
+) -> isize {
+	let Ok(attr) = unsafe { CStr::from_ptr(attr) }.to_str() else {
+		return isize::try_from(-i32::from(Errno::Inval)).unwrap();
+	};
+
+	copy_xattr(fs::fgetxattr(fd, attr), value, size)
+}
+
+/// Creates or replaces the extended attribute `attr` of `name`.
+///
+/// `XATTR_CREATE` fails if the attribute exists, `XATTR_REPLACE` fails
+/// if it does not exist.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_setxattr(
+	name: *const c_char,
+	attr: *const c_char,
+	value: *const u8,
+	size: usize,
+	flags: i32,
+) -> i32 {
+	unsafe { path_setxattr(name, attr, value, size, flags, true) }
+}
+
+/// Like `sys_setxattr`, but does not follow symbolic links.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_lsetxattr(
+	name: *const c_char,
+	attr: *const c_char,
+	value: *const u8,
+	size: usize,
+	flags: i32,
+) -> i32 {
+	unsafe { path_setxattr(name, attr, value, size, flags, false) }
+}
+
+unsafe fn path_setxattr(
+	name: *const c_char,
+	attr: *const c_char,
+	value: *const u8,
+	size: usize,
+	flags: i32,
+	follow: bool,
+) -> i32 {
+	let Some(flags) = XattrFlags::from_bits(flags) else {
+		return -i32::from(Errno::Inval);
+	};
+	let (Ok(name), Ok(attr)) = (
+		unsafe { CStr::from_ptr(name) }.to_str(),
+		unsafe { CStr::from_ptr(attr) }.to_str(),
+	) else {
+		return -i32::from(Errno::Inval);
+	};
+	let value = unsafe { xattr_value(value, size) };
+
+	fs::setxattr(name, attr, value, flags, follow).map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_fsetxattr(
+	fd: FileDescriptor,
+	attr: *const c_char,
+	value: *const u8,
+	size: usize,
+	flags: i32,
+) -> i32 {
+	let Some(flags) = XattrFlags::from_bits(flags) else {
+		return -i32::from(Errno::Inval);
+	};
+	let Ok(attr) = unsafe { CStr::from_ptr(attr) }.to_str() else {
+		return -i32::from(Errno::Inval);
+	};
+	let value = unsafe { xattr_value(value, size) };
+
+	fs::fsetxattr(fd, attr, value, flags).map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+/// Places the names of all extended attributes of `name` in `list`,
+/// where each name is terminated by a null byte.
+///
+/// Returns the size of the list. If `size` is zero, only the size is
+/// returned. Fails with `ERANGE` if `list` is too small.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_listxattr(
+	name: *const c_char,
+	list: *mut c_char,
+	size: usize,
+) -> isize {
+	unsafe { path_listxattr(name, list, size, true) }
+}
+
+/// Like `sys_listxattr`, but does not follow symbolic links.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_llistxattr(
+	name: *const c_char,
+	list: *mut c_char,
+	size: usize,
+) -> isize {
+	unsafe { path_listxattr(name, list, size, false) }
+}
+
+unsafe fn path_listxattr(
+	name: *const c_char,
+	list: *mut c_char,
+	size: usize,
+	follow: bool,
+) -> isize {
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return isize::try_from(-i32::from(Errno::Inval)).unwrap();
+	};
+
+	copy_xattr(xattr_list(fs::listxattr(name, follow)), list.cast(), size)
+}
+
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_flistxattr(
+	fd: FileDescriptor,
+	list: *mut c_char,
+	size: usize,
+) -> isize {
+	copy_xattr(xattr_list(fs::flistxattr(fd)), list.cast(), size)
+}
+
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_removexattr(name: *const c_char, attr: *const c_char) -> i32 {
+	unsafe { path_removexattr(name, attr, true) }
+}
+
+/// Like `sys_removexattr`, but does not follow symbolic links.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_lremovexattr(name: *const c_char, attr: *const c_char) -> i32 {
+	unsafe { path_removexattr(name, attr, false) }
+}
+
+unsafe fn path_removexattr(name: *const c_char, attr: *const c_char, follow: bool) -> i32 {
+	let (Ok(name), Ok(attr)) = (
+		unsafe { CStr::from_ptr(name) }.to_str(),
+		unsafe { CStr::from_ptr(attr) }.to_str(),
+	) else {
+		return -i32::from(Errno::Inval);
+	};
+
+	fs::removexattr(name, attr, follow).map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_fremovexattr(fd: FileDescriptor, attr: *const c_char) -> i32 {
+	let Ok(attr) = unsafe { CStr::from_ptr(attr) }.to_str() else {
+		return -i32::from(Errno::Inval);
+	};
+
+	fs::fremovexattr(fd, attr).map_or_else(|e| -i32::from(e), |()| 0)
+}
+
 #[hermit_macro::system(errno)]
 #[unsafe(no_mangle)]
 pub extern "C" fn sys_close(fd: FileDescriptor) -> i32 {
@@ -569,6 +1084,24 @@ pub unsafe extern "C" fn sys_ftruncate(fd: FileDescriptor, size: usize) -> i32 {
 	fd::truncate(fd, size).map_or_else(|e| -i32::from(e), |()| 0)
 }
 
+/// Allocates, deallocates or zeroes the byte range `offset..offset + len` of the file `fd`.
+///
+/// Without `mode`, the range is allocated and the file is extended if
+/// necessary. `FALLOC_FL_KEEP_SIZE` preserves the size of the file.
+/// `FALLOC_FL_PUNCH_HOLE` deallocates the range and `FALLOC_FL_ZERO_RANGE`
+/// zeroes it.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_fallocate(fd: FileDescriptor, mode: i32, offset: i64, len: i64) -> i32 {
+	fs::check_fallocate(mode, offset, len)
+		.and_then(|mode| {
+			let offset = usize::try_from(offset).map_err(|_| Errno::Fbig)?;
+			let len = usize::try_from(len).map_err(|_| Errno::Fbig)?;
+			fd::fallocate(fd, mode, offset, len)
+		})
+		.map_or_else(|e| -i32::from(e), |()| 0)
+}
+
 #[hermit_macro::system(errno)]
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn sys_truncate(path: *const c_char, size: usize) -> i32 {
@@ -659,48 +1192,165 @@ pub unsafe extern "C" fn sys_ioctl(
 	}
 }
 
+/// Describes a record lock of `fcntl`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct flock {
+	/// Type of the lock (`F_RDLCK`, `F_WRLCK` or `F_UNLCK`)
+	pub l_type: i16,
+	/// Interpretation of `l_start` (`SEEK_SET`, `SEEK_CUR` or `SEEK_END`)
+	pub l_whence: i16,
+	/// Starting offset of the lock
+	pub l_start: i64,
+	/// Number of bytes to lock, where `0` locks up to the end of the file
+	pub l_len: i64,
+	/// Process holding a conflicting lock (only set by `F_GETLK`)
+	pub l_pid: i32,
+}
+
 /// manipulate file descriptor
+///
+/// The lock commands take a pointer and are provided by [`sys_fcntl_lock`].
 #[hermit_macro::system(errno)]
 #[unsafe(no_mangle)]
 pub extern "C" fn sys_fcntl(fd: i32, cmd: i32, arg: i32) -> i32 {
+	const F_DUPFD: i32 = 0;
+	const F_GETFD: i32 = 1;
 	const F_SETFD: i32 = 2;
 	const F_GETFL: i32 = 3;
 	const F_SETFL: i32 = 4;
-	const FD_CLOEXEC: i32 = 1;
+	const F_DUPFD_CLOEXEC: i32 = 1030;
 
-	if cmd == F_SETFD && arg == FD_CLOEXEC {
-		0
-	} else if cmd == F_GETFL {
-		let obj = get_object(fd);
-		obj.map_or_else(
-			|e| -i32::from(e),
-			|v| {
-				block_on(async { v.read().await.status_flags().await }, None)
-					.map_or_else(|e| -i32::from(e), |status_flags| status_flags.bits())
-			},
-		)
-	} else if cmd == F_SETFL {
-		let obj = get_object(fd);
-		obj.map_or_else(
-			|e| -i32::from(e),
-			|v| {
-				block_on(
-					async {
-						v.write()
-							.await
-							.set_status_flags(fd::StatusFlags::from_bits_retain(arg))
-							.await
-					},
-					None,
-				)
-				.map_or_else(|e| -i32::from(e), |()| 0)
-			},
-		)
-	} else {
-		-i32::from(Errno::Inval)
+	match cmd {
+		F_DUPFD | F_DUPFD_CLOEXEC => {
+			let min_fd = arg;
+			if min_fd < 0 {
+				return -i32::from(Errno::Inval);
+			}
+			let flags = if cmd == F_DUPFD_CLOEXEC {
+				fd::DescriptorFlags::FD_CLOEXEC
+			} else {
+				fd::DescriptorFlags::empty()
+			};
+
+			fd::dup_object_from(fd, min_fd, flags).unwrap_or_else(|e| -i32::from(e))
+		}
+		F_GETFD => fd::descriptor_flags(fd).map_or_else(|e| -i32::from(e), |flags| flags.bits()),
+		F_SETFD => {
+			let flags = fd::DescriptorFlags::from_bits_truncate(arg);
+			fd::set_descriptor_flags(fd, flags).map_or_else(|e| -i32::from(e), |()| 0)
+		}
+		F_GETFL => {
+			let obj = get_object(fd);
+			obj.map_or_else(
+				|e| -i32::from(e),
+				|v| {
+					block_on(async { v.read().await.status_flags().await }, None)
+						.map_or_else(|e| -i32::from(e), |status_flags| status_flags.bits())
+				},
+			)
+		}
+		F_SETFL => {
+			let obj = get_object(fd);
+			obj.map_or_else(
+				|e| -i32::from(e),
+				|v| {
+					block_on(
+						async {
+							v.write()
+								.await
+								.set_status_flags(fd::StatusFlags::from_bits_retain(arg))
+								.await
+						},
+						None,
+					)
+					.map_or_else(|e| -i32::from(e), |()| 0)
+				},
+			)
+		}
+		_ => -i32::from(Errno::Inval),
 	}
 }
 
+/// Places, removes or queries a record lock, which is described by `lock`.
+///
+/// `cmd` is one of `F_GETLK`, `F_SETLK` and `F_SETLKW`. This is the part of
+/// `fcntl`, whose argument is a pointer to a [`flock`] structure.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_fcntl_lock(fd: i32, cmd: i32, lock: *mut flock) -> i32 {
+	const F_GETLK: i32 = 5;
+	const F_SETLK: i32 = 6;
+	const F_SETLKW: i32 = 7;
+
+	match cmd {
+		F_GETLK | F_SETLK | F_SETLKW => {
+			let Some(lock) = (unsafe { lock.as_mut() }) else {
+				return -i32::from(Errno::Fault);
+			};
+			let Ok(kind) = LockType::try_from(lock.l_type) else {
+				return -i32::from(Errno::Inval);
+			};
+			let Ok(whence) = u8::try_from(lock.l_whence)
+				.map_err(|_| ())
+				.and_then(|whence| SeekWhence::try_from(whence).map_err(|_| ()))
+			else {
+				return -i32::from(Errno::Inval);
+			};
+
+			if cmd != F_GETLK {
+				let wait = cmd == F_SETLKW;
+				return fd::setlk(fd, kind, whence, lock.l_start, lock.l_len, wait)
+					.map_or_else(|e| -i32::from(e), |()| 0);
+			}
+
+			match fd::getlk(fd, kind, whence, lock.l_start, lock.l_len) {
+				Ok(Some(conflict)) => {
+					lock.l_type = conflict.kind.into();
+					lock.l_whence = SeekWhence::Set as i16;
+					lock.l_start = conflict.start.try_into().unwrap_or(i64::MAX);
+					lock.l_len = if conflict.end == u64::MAX {
+						0
+					} else {
+						(conflict.end - conflict.start).try_into().unwrap_or(0)
+					};
+					lock.l_pid = 0;
+					0
+				}
+				Ok(None) => {
+					lock.l_type = LockType::Unlock.into();
+					0
+				}
+				Err(e) => -i32::from(e),
+			}
+		}
+		_ => -i32::from(Errno::Inval),
+	}
+}
+
+/// Applies or removes an advisory lock of the whole file.
+///
+/// In contrast to the record locks of `fcntl`, these locks belong to the
+/// open file description and are released when its last descriptor is closed.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_flock(fd: FileDescriptor, operation: i32) -> i32 {
+	const LOCK_SH: i32 = 1;
+	const LOCK_EX: i32 = 2;
+	const LOCK_NB: i32 = 4;
+	const LOCK_UN: i32 = 8;
+
+	let kind = match operation & !LOCK_NB {
+		LOCK_SH => LockType::Read,
+		LOCK_EX => LockType::Write,
+		LOCK_UN => LockType::Unlock,
+		_ => return -i32::from(Errno::Inval),
+	};
+	let wait = operation & LOCK_NB == 0;
+
+	fd::flock(fd, kind, wait).map_or_else(|e| -i32::from(e), |()| 0)
+}
+
 #[hermit_macro::system(errno)]
 #[unsafe(no_mangle)]
 pub extern "C" fn sys_lseek(fd: FileDescriptor, offset: isize, whence: i32) -> isize {
@@ -612,6 +612,11 @@ pub extern "C" fn sys_socket(domain: i32, type_: i32, protocol: i32) -> i32 {
 
 		let socket = Arc::new(async_lock::RwLock::new(socket));
 		let fd = insert_object(socket).expect("FD is already used");
+		if sock_flags.contains(SockFlags::SOCK_CLOEXEC)
+			&& let Err(e) = fd::set_descriptor_flags(fd, fd::DescriptorFlags::FD_CLOEXEC)
+		{
+			return -i32::from(e);
+		}
 
 		return fd;
 	}
@@ -634,6 +639,11 @@ pub extern "C" fn sys_socket(domain: i32, type_: i32, protocol: i32) -> i32 {
 
 				let socket = Arc::new(async_lock::RwLock::new(socket));
 				let fd = insert_object(socket).expect("FD is already used");
+				if sock_flags.contains(SockFlags::SOCK_CLOEXEC)
+					&& let Err(e) = fd::set_descriptor_flags(fd, fd::DescriptorFlags::FD_CLOEXEC)
+				{
+					return -i32::from(e);
+				}
 
 				return fd;
 			}
@@ -650,6 +660,11 @@ pub extern "C" fn sys_socket(domain: i32, type_: i32, protocol: i32) -> i32 {
 
 				let socket = Arc::new(async_lock::RwLock::new(socket));
 				let fd = insert_object(socket).expect("FD is already used");
+				if sock_flags.contains(SockFlags::SOCK_CLOEXEC)
+					&& let Err(e) = fd::set_descriptor_flags(fd, fd::DescriptorFlags::FD_CLOEXEC)
+				{
+					return -i32::from(e);
+				}
 
 				return fd;
 			}
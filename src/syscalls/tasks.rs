@@ -1,4 +1,6 @@
 use alloc::collections::BTreeMap;
+use core::ffi::{CStr, c_char};
+use core::slice;
 
 use hermit_sync::InterruptTicketMutex;
 
@@ -6,10 +8,13 @@ use crate::arch::core_local::*;
 use crate::arch::processor::{get_frequency, get_timestamp};
 use crate::config::USER_STACK_SIZE;
 use crate::errno::Errno;
-use crate::scheduler::PerCoreSchedulerExt;
-use crate::scheduler::task::{Priority, TaskHandle, TaskId};
+use crate::scheduler::deadline::DeadlineParams;
+use crate::scheduler::task::{
+	CpuSet, DEADLINE_PRIO, NORMAL_PRIO, Priority, SchedPolicy, TaskHandle, TaskId, TaskStatus,
+};
+use crate::scheduler::{PerCoreSchedulerExt, SchedAttr, TaskInfo};
 use crate::time::timespec;
-use crate::{arch, scheduler};
+use crate::{arch, io, scheduler};
 
 #[cfg(feature = "newlib")]
 pub type SignalHandler = extern "C" fn(i32);
@@ -21,6 +26,14 @@ pub extern "C" fn sys_getpid() -> Tid {
 	0
 }
 
+/// Returns the identifier of the current thread, which is stored by the owner
+/// of a priority-inheritance futex (see [`sys_futex_lock_pi`](super::sys_futex_lock_pi))
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_gettid() -> Tid {
+	core_scheduler().get_current_task_id().into()
+}
+
 #[cfg(feature = "newlib")]
 #[hermit_macro::system(errno)]
 #[unsafe(no_mangle)]
@@ -70,16 +83,21 @@ pub(super) fn usleep(usecs: u64) {
 		// Enough time to set a wakeup timer and block the current task.
 		debug!("sys_usleep blocking the task for {usecs} microseconds");
 		let wakeup_time = arch::processor::get_timer_ticks() + usecs;
-		let core_scheduler = core_scheduler();
-		core_scheduler.block_current_task(Some(wakeup_time));
 
-		// Switch to the next task.
-		core_scheduler.reschedule();
+		// A wakeup, which was meant for a previous wait of the task, may end
+		// the sleep early.
+		while arch::processor::get_timer_ticks() < wakeup_time {
+			let core_scheduler = core_scheduler();
+			core_scheduler.block_current_task(Some(wakeup_time));
+
+			// Switch to the next task.
+			core_scheduler.reschedule_migratable();
+		}
 	} else if usecs > 0 {
 		// Not enough time to set a wakeup timer, so just do busy-waiting.
 		let end = arch::processor::get_timestamp() + u64::from(get_frequency()) * usecs;
 		while get_timestamp() < end {
-			core_scheduler().reschedule();
+			core_scheduler().reschedule_migratable();
 		}
 	}
 }
@@ -135,7 +153,7 @@ pub unsafe extern "C" fn sys_clone(id: *mut Tid, func: extern "C" fn(usize), arg
 #[hermit_macro::system(errno)]
 #[unsafe(no_mangle)]
 pub extern "C" fn sys_yield() {
-	core_scheduler().reschedule();
+	core_scheduler().yield_now();
 }
 
 #[cfg(feature = "newlib")]
@@ -163,7 +181,10 @@ pub unsafe extern "C" fn sys_spawn2(
 	stack_size: usize,
 	selector: isize,
 ) -> Tid {
-	unsafe { scheduler::spawn(func, arg, Priority::from(prio), stack_size, selector).into() }
+	match unsafe { scheduler::spawn(func, arg, Priority::from(prio), stack_size, selector) } {
+		Ok(id) => id.into(),
+		Err(err) => -i32::from(err),
+	}
 }
 
 #[hermit_macro::system]
@@ -175,8 +196,11 @@ pub unsafe extern "C" fn sys_spawn(
 	prio: u8,
 	selector: isize,
 ) -> i32 {
-	let new_id = unsafe {
-		scheduler::spawn(func, arg, Priority::from(prio), USER_STACK_SIZE, selector).into()
+	let new_id = match unsafe {
+		scheduler::spawn(func, arg, Priority::from(prio), USER_STACK_SIZE, selector)
+	} {
+		Ok(id) => id.into(),
+		Err(err) => return -i32::from(err),
 	};
 
 	if !id.is_null() {
@@ -191,12 +215,190 @@ pub unsafe extern "C" fn sys_spawn(
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
 pub extern "C" fn sys_join(id: Tid) -> i32 {
-	match scheduler::join(TaskId::from(id)) {
+	match scheduler::join_timeout(TaskId::from(id), None) {
+		Ok(_) => 0,
+		Err(err) => -i32::from(err),
+	}
+}
+
+/// Waits until the thread `id` has finished and stores its exit status at
+/// `status`, if it is not null. If `timeout` is not null, it specifies a
+/// relative timeout, after which -ETIMEDOUT is returned.
+///
+/// Returns -ESRCH if the thread does not exist or has already been joined,
+/// -EINVAL if it is detached or the timeout is invalid and -EDEADLK if the
+/// thread joins itself.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_join_timeout(
+	id: Tid,
+	status: *mut i32,
+	timeout: *const timespec,
+) -> i32 {
+	let timeout = if timeout.is_null() {
+		None
+	} else {
+		match unsafe { timeout.read().into_usec() } {
+			Some(usec) if usec >= 0 => Some(usec as u64),
+			_ => return -i32::from(Errno::Inval),
+		}
+	};
+
+	match scheduler::join_timeout(TaskId::from(id), timeout) {
+		Ok(exit_status) => {
+			if !status.is_null() {
+				unsafe {
+					*status = exit_status;
+				}
+			}
+			0
+		}
+		Err(err) => -i32::from(err),
+	}
+}
+
+/// Detaches the thread `id`, so that its resources are released as soon as
+/// it has finished. A detached thread cannot be joined.
+///
+/// Returns -ESRCH if the thread does not exist and -EINVAL if it is already
+/// detached.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_detach(id: Tid) -> i32 {
+	match scheduler::detach(TaskId::from(id)) {
 		Ok(()) => 0,
-		_ => -i32::from(Errno::Inval),
+		Err(err) => -i32::from(err),
 	}
 }
 
+/// Maximal length of a thread name including the terminating NUL byte
+pub const THREAD_NAME_LEN: usize = 16;
+
+/// Sets the name of the thread `id` (or the current thread if `id` is 0) to
+/// the NUL-terminated string `name`.
+///
+/// Returns -EINVAL if `name` is null or not valid UTF-8, -ERANGE if it is
+/// longer than [`THREAD_NAME_LEN`] - 1 bytes and -ESRCH if the thread does
+/// not exist.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_set_thread_name(id: Tid, name: *const c_char) -> i32 {
+	if name.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+		return -i32::from(Errno::Inval);
+	};
+	if name.len() >= THREAD_NAME_LEN {
+		return -i32::from(Errno::Range);
+	}
+
+	match scheduler::set_task_name(task_id(id), name.into()) {
+		Ok(()) => 0,
+		Err(err) => -i32::from(err),
+	}
+}
+
+/// Copies the name of the thread `id` (or the current thread if `id` is 0) as
+/// NUL-terminated string into the buffer `buf` of `len` bytes. The name of an
+/// unnamed thread is empty.
+///
+/// Returns -EINVAL if `buf` is null, -ERANGE if the buffer is too small and
+/// -ESRCH if the thread does not exist.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_get_thread_name(id: Tid, buf: *mut c_char, len: usize) -> i32 {
+	if buf.is_null() {
+		return -i32::from(Errno::Inval);
+	}
+
+	let name = match scheduler::task_name(task_id(id)) {
+		Ok(name) => name.unwrap_or_default(),
+		Err(err) => return -i32::from(err),
+	};
+	if name.len() >= len {
+		return -i32::from(Errno::Range);
+	}
+
+	let buf = unsafe { slice::from_raw_parts_mut(buf.cast::<u8>(), len) };
+	buf[..name.len()].copy_from_slice(name.as_bytes());
+	buf[name.len()] = 0;
+
+	0
+}
+
+/// The thread is ready to run.
+pub const TASK_READY: u32 = TaskStatus::Ready as u32;
+/// The thread is running.
+pub const TASK_RUNNING: u32 = TaskStatus::Running as u32;
+/// The thread waits for an event.
+pub const TASK_BLOCKED: u32 = TaskStatus::Blocked as u32;
+/// The thread has finished, but has not been joined yet.
+pub const TASK_FINISHED: u32 = TaskStatus::Finished as u32;
+/// The thread is the idle thread of a core.
+pub const TASK_IDLE: u32 = TaskStatus::Idle as u32;
+
+/// Information about a thread, see [`sys_get_tasks`]
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct task_info {
+	pub id: Tid,
+	/// Core, on which the thread runs
+	pub core_id: u32,
+	/// One of the `TASK_*` constants
+	pub status: u32,
+	pub priority: u32,
+	/// Consumed processing time in microseconds
+	pub cpu_time: u64,
+	/// Size of the user stack in bytes
+	pub stack_size: u64,
+	/// NUL-terminated name, which is empty if the thread is unnamed
+	pub name: [u8; THREAD_NAME_LEN],
+}
+
+impl task_info {
+	fn new(info: &TaskInfo) -> Self {
+		let mut name = [0; THREAD_NAME_LEN];
+		if let Some(task_name) = &info.name {
+			let len = task_name.len().min(THREAD_NAME_LEN - 1);
+			name[..len].copy_from_slice(&task_name.as_bytes()[..len]);
+		}
+
+		Self {
+			id: info.id.into(),
+			core_id: info.core_id,
+			status: info.status as u32,
+			priority: u32::from(info.prio.into()),
+			cpu_time: info.cpu_time,
+			stack_size: info.stack_size.try_into().unwrap(),
+			name,
+		}
+	}
+}
+
+/// Stores information about up to `len` threads in the array `tasks` and
+/// returns the total number of threads, which may be larger than `len`. The
+/// threads include finished threads, which have not been joined yet.
+///
+/// Returns -EINVAL if `tasks` is null, but `len` is not zero.
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_get_tasks(tasks: *mut task_info, len: usize) -> isize {
+	if tasks.is_null() && len != 0 {
+		return -i32::from(Errno::Inval) as isize;
+	}
+
+	let infos = scheduler::task_infos();
+	for (i, info) in infos.iter().take(len).enumerate() {
+		unsafe {
+			tasks.add(i).write(task_info::new(info));
+		}
+	}
+
+	infos.len().try_into().unwrap()
+}
+
 /// Mapping between blocked tasks and their TaskHandle
 static BLOCKED_TASKS: InterruptTicketMutex<BTreeMap<TaskId, TaskHandle>> =
 	InterruptTicketMutex::new(BTreeMap::new());
@@ -243,16 +445,22 @@ pub extern "C" fn sys_get_priority() -> u8 {
 	core_scheduler().get_current_task_prio().into()
 }
 
-/// Set priority of the thread with the identifier `id`
+/// Returns `true` if `prio` can be assigned by [`sys_set_priority`]. The idle
+/// priority and the priority of deadline tasks are reserved.
+fn is_valid_priority(prio: u8) -> bool {
+	prio > 0 && Priority::from(prio) < DEADLINE_PRIO
+}
+
+/// Set priority of the thread with the identifier `id`. The priority of a
+/// thread, which does not use `SCHED_OTHER`, is only changed by
+/// [`sys_sched_setscheduler`] and [`sys_sched_setattr`].
 #[hermit_macro::system(errno)]
 #[unsafe(no_mangle)]
 pub extern "C" fn sys_set_priority(id: Tid, prio: u8) {
-	if prio > 0 {
-		core_scheduler()
-			.set_priority(TaskId::from(id), Priority::from(prio))
-			.expect("Unable to set priority");
-	} else {
-		panic!("Invalid priority {}", prio);
+	if !is_valid_priority(prio) {
+		warn!("Invalid priority {prio}");
+	} else if scheduler::set_priority(TaskId::from(id), Priority::from(prio)).is_err() {
+		warn!("Unable to set priority of task {id}");
 	}
 }
 
@@ -260,9 +468,361 @@ pub extern "C" fn sys_set_priority(id: Tid, prio: u8) {
 #[hermit_macro::system]
 #[unsafe(no_mangle)]
 pub extern "C" fn sys_set_current_task_priority(prio: u8) {
-	if prio > 0 {
-		core_scheduler().set_current_task_priority(Priority::from(prio));
+	let id = core_scheduler().get_current_task_id();
+	if !is_valid_priority(prio) {
+		warn!("Invalid priority {prio}");
+	} else if scheduler::set_priority(id, Priority::from(prio)).is_err() {
+		warn!("Unable to set priority of task {id}");
+	}
+}
+
+/// Returns the identifier of the thread `id` or of the current thread if `id` is 0
+fn task_id(id: Tid) -> TaskId {
+	if id == 0 {
+		core_scheduler().get_current_task_id()
 	} else {
-		panic!("Invalid priority {}", prio);
+		TaskId::from(id)
+	}
+}
+
+/// Restricts the thread `id` (or the current thread if `id` is 0) to the cores
+/// in the bit mask `mask`, which has a size of `cpusetsize` bytes.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_sched_setaffinity(id: Tid, cpusetsize: usize, mask: *const u8) -> i32 {
+	if mask.is_null() {
+		return -i32::from(Errno::Fault);
+	}
+
+	let mask = unsafe { slice::from_raw_parts(mask, cpusetsize) };
+	scheduler::set_affinity(task_id(id), CpuSet::from_bytes(mask))
+		.map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+/// Stores the cores, on which the thread `id` (or the current thread if `id`
+/// is 0) is allowed to run, as bit mask in `mask`, which has a size of
+/// `cpusetsize` bytes.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_sched_getaffinity(id: Tid, cpusetsize: usize, mask: *mut u8) -> i32 {
+	if mask.is_null() {
+		return -i32::from(Errno::Fault);
+	}
+
+	// The mask has to be large enough to describe all cores.
+	let processor_count = usize::try_from(arch::get_processor_count()).unwrap();
+	if cpusetsize < processor_count.div_ceil(8) {
+		return -i32::from(Errno::Inval);
+	}
+
+	match scheduler::get_affinity(task_id(id)) {
+		Ok(affinity) => {
+			let mask = unsafe { slice::from_raw_parts_mut(mask, cpusetsize) };
+			affinity.to_bytes(mask);
+			0
+		}
+		Err(e) => -i32::from(e),
+	}
+}
+
+/// Returns the core, on which the current thread is running
+#[hermit_macro::system]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_sched_getcpu() -> i32 {
+	core_id().try_into().unwrap()
+}
+
+pub(crate) const SCHED_OTHER: i32 = 0;
+pub(crate) const SCHED_FIFO: i32 = 1;
+pub(crate) const SCHED_RR: i32 = 2;
+pub(crate) const SCHED_DEADLINE: i32 = 6;
+
+/// Size of the first version of [`sched_attr`]
+const SCHED_ATTR_SIZE_VER0: u32 = 48;
+
+/// Scheduling parameters of [`sys_sched_setscheduler`]
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct sched_param {
+	pub sched_priority: i32,
+}
+
+/// Scheduling policy and parameters of [`sys_sched_setattr`]. The times of
+/// deadline tasks are given in nanoseconds.
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)]
+pub struct sched_attr {
+	pub size: u32,
+	pub sched_policy: u32,
+	pub sched_flags: u64,
+	pub sched_nice: i32,
+	pub sched_priority: u32,
+	pub sched_runtime: u64,
+	pub sched_deadline: u64,
+	pub sched_period: u64,
+}
+
+fn policy_from_raw(policy: i32) -> io::Result<SchedPolicy> {
+	match policy {
+		SCHED_OTHER => Ok(SchedPolicy::Other),
+		SCHED_FIFO => Ok(SchedPolicy::Fifo),
+		SCHED_RR => Ok(SchedPolicy::RoundRobin),
+		SCHED_DEADLINE => Ok(SchedPolicy::Deadline),
+		_ => Err(Errno::Inval),
+	}
+}
+
+fn policy_into_raw(policy: SchedPolicy) -> i32 {
+	match policy {
+		SchedPolicy::Other => SCHED_OTHER,
+		SchedPolicy::Fifo => SCHED_FIFO,
+		SchedPolicy::RoundRobin => SCHED_RR,
+		SchedPolicy::Deadline => SCHED_DEADLINE,
+	}
+}
+
+/// Returns the range of the priorities of `policy`. Only FIFO and round-robin
+/// tasks have a priority, which is mapped to the priorities of Hermit by
+/// [`rt_prio`].
+fn priority_range(policy: SchedPolicy) -> (i32, i32) {
+	match policy {
+		SchedPolicy::Fifo | SchedPolicy::RoundRobin => (1, rt_priority(DEADLINE_PRIO) - 1),
+		SchedPolicy::Other | SchedPolicy::Deadline => (0, 0),
+	}
+}
+
+/// Maps the priority `priority` of a FIFO or round-robin thread above
+/// [`NORMAL_PRIO`], so that it preempts all threads with the default priority.
+fn rt_prio(priority: i32) -> Priority {
+	Priority::from(NORMAL_PRIO.into() + u8::try_from(priority).unwrap())
+}
+
+/// Inverse of [`rt_prio`]
+fn rt_priority(prio: Priority) -> i32 {
+	i32::from(prio.into()) - i32::from(NORMAL_PRIO.into())
+}
+
+/// Builds the scheduling attributes of the task `id` from the POSIX parameters.
+/// A task, which becomes a normal task again, gets the default priority.
+fn sched_attr_of(
+	id: TaskId,
+	policy: SchedPolicy,
+	priority: i32,
+	deadline: Option<DeadlineParams>,
+) -> io::Result<SchedAttr> {
+	let (min, max) = priority_range(policy);
+	if !(min..=max).contains(&priority) {
+		return Err(Errno::Inval);
+	}
+
+	let prio = match policy {
+		SchedPolicy::Other => {
+			let old = scheduler::get_scheduler(id)?;
+			if old.policy == SchedPolicy::Other {
+				old.prio
+			} else {
+				NORMAL_PRIO
+			}
+		}
+		SchedPolicy::Fifo | SchedPolicy::RoundRobin => rt_prio(priority),
+		SchedPolicy::Deadline => DEADLINE_PRIO,
+	};
+
+	Ok(SchedAttr {
+		policy,
+		prio,
+		deadline,
+	})
+}
+
+/// Sets the scheduling policy `policy` with the parameters `param` for the
+/// thread `id` (or the current thread if `id` is 0). `SCHED_DEADLINE` can
+/// only be set by [`sys_sched_setattr`].
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_sched_setscheduler(
+	id: Tid,
+	policy: i32,
+	param: *const sched_param,
+) -> i32 {
+	let Some(param) = (unsafe { param.as_ref() }) else {
+		return -i32::from(Errno::Fault);
+	};
+
+	let id = task_id(id);
+	policy_from_raw(policy)
+		.and_then(|policy| {
+			if policy == SchedPolicy::Deadline {
+				return Err(Errno::Inval);
+			}
+			sched_attr_of(id, policy, param.sched_priority, None)
+		})
+		.and_then(|attr| scheduler::set_scheduler(id, attr))
+		.map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+/// Returns the scheduling policy of the thread `id` (or the current thread if
+/// `id` is 0).
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_sched_getscheduler(id: Tid) -> i32 {
+	scheduler::get_scheduler(task_id(id))
+		.map_or_else(|e| -i32::from(e), |attr| policy_into_raw(attr.policy))
+}
+
+/// Changes the priority of the thread `id` (or the current thread if `id` is
+/// 0) within its scheduling policy.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_sched_setparam(id: Tid, param: *const sched_param) -> i32 {
+	let Some(param) = (unsafe { param.as_ref() }) else {
+		return -i32::from(Errno::Fault);
+	};
+
+	let id = task_id(id);
+	scheduler::get_scheduler(id)
+		.and_then(|attr| sched_attr_of(id, attr.policy, param.sched_priority, attr.deadline))
+		.and_then(|attr| scheduler::set_scheduler(id, attr))
+		.map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+/// Stores the priority of the thread `id` (or the current thread if `id` is
+/// 0) within its scheduling policy in `param`.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_sched_getparam(id: Tid, param: *mut sched_param) -> i32 {
+	let Some(param) = (unsafe { param.as_mut() }) else {
+		return -i32::from(Errno::Fault);
+	};
+
+	match scheduler::get_scheduler(task_id(id)) {
+		Ok(attr) => {
+			param.sched_priority = match attr.policy {
+				SchedPolicy::Fifo | SchedPolicy::RoundRobin => rt_priority(attr.prio),
+				SchedPolicy::Other | SchedPolicy::Deadline => 0,
+			};
+			0
+		}
+		Err(e) => -i32::from(e),
+	}
+}
+
+/// Sets the scheduling policy and the parameters in `attr` for the thread
+/// `id` (or the current thread if `id` is 0). A deadline task is rejected
+/// with `EBUSY`, if its core cannot provide the requested bandwidth.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_sched_setattr(id: Tid, attr: *const sched_attr, flags: u32) -> i32 {
+	let Some(attr) = (unsafe { attr.as_ref() }) else {
+		return -i32::from(Errno::Fault);
+	};
+	if attr.size < SCHED_ATTR_SIZE_VER0 || flags != 0 {
+		return -i32::from(Errno::Inval);
+	}
+
+	let id = task_id(id);
+	let policy = match i32::try_from(attr.sched_policy) {
+		Ok(policy) => policy_from_raw(policy),
+		Err(_) => Err(Errno::Inval),
+	};
+	let priority = i32::try_from(attr.sched_priority).unwrap_or(i32::MAX);
+
+	policy
+		.and_then(|policy| {
+			let deadline = if policy == SchedPolicy::Deadline {
+				Some(DeadlineParams::new(
+					attr.sched_runtime / 1000,
+					attr.sched_deadline / 1000,
+					attr.sched_period / 1000,
+				)?)
+			} else {
+				None
+			};
+			sched_attr_of(id, policy, priority, deadline)
+		})
+		.and_then(|attr| scheduler::set_scheduler(id, attr))
+		.map_or_else(|e| -i32::from(e), |()| 0)
+}
+
+/// Stores the scheduling policy and the parameters of the thread `id` (or the
+/// current thread if `id` is 0) in `attr`, which has a size of `size` bytes.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_sched_getattr(
+	id: Tid,
+	attr: *mut sched_attr,
+	size: u32,
+	flags: u32,
+) -> i32 {
+	let Some(attr) = (unsafe { attr.as_mut() }) else {
+		return -i32::from(Errno::Fault);
+	};
+	if size < SCHED_ATTR_SIZE_VER0 || flags != 0 {
+		return -i32::from(Errno::Inval);
+	}
+
+	match scheduler::get_scheduler(task_id(id)) {
+		Ok(sched) => {
+			let params = sched.deadline.unwrap_or(DeadlineParams {
+				runtime: 0,
+				deadline: 0,
+				period: 0,
+			});
+			let sched_priority = match sched.policy {
+				SchedPolicy::Fifo | SchedPolicy::RoundRobin => {
+					rt_priority(sched.prio).try_into().unwrap()
+				}
+				SchedPolicy::Other | SchedPolicy::Deadline => 0,
+			};
+			*attr = sched_attr {
+				size: SCHED_ATTR_SIZE_VER0,
+				sched_policy: policy_into_raw(sched.policy).try_into().unwrap(),
+				sched_flags: 0,
+				sched_nice: 0,
+				sched_priority,
+				sched_runtime: params.runtime * 1000,
+				sched_deadline: params.deadline * 1000,
+				sched_period: params.period * 1000,
+			};
+			0
+		}
+		Err(e) => -i32::from(e),
+	}
+}
+
+/// Returns the highest priority of the scheduling policy `policy`
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_sched_get_priority_max(policy: i32) -> i32 {
+	policy_from_raw(policy).map_or_else(|e| -i32::from(e), |policy| priority_range(policy).1)
+}
+
+/// Returns the lowest priority of the scheduling policy `policy`
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub extern "C" fn sys_sched_get_priority_min(policy: i32) -> i32 {
+	policy_from_raw(policy).map_or_else(|e| -i32::from(e), |policy| priority_range(policy).0)
+}
+
+/// Stores the length of a time slice of the thread `id` (or the current
+/// thread if `id` is 0) in `interval`. It is zero, if the thread is not
+/// preempted in favor of threads with the same priority.
+#[hermit_macro::system(errno)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sys_sched_rr_get_interval(id: Tid, interval: *mut timespec) -> i32 {
+	let Some(interval) = (unsafe { interval.as_mut() }) else {
+		return -i32::from(Errno::Fault);
+	};
+
+	match scheduler::get_scheduler(task_id(id)) {
+		Ok(attr) => {
+			let time_slice = match attr.policy {
+				SchedPolicy::Other | SchedPolicy::RoundRobin => scheduler::time_slice(),
+				SchedPolicy::Fifo | SchedPolicy::Deadline => None,
+			};
+			*interval = timespec::from_usec(time_slice.unwrap_or(0).try_into().unwrap());
+			0
+		}
+		Err(e) => -i32::from(e),
 	}
 }
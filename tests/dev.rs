@@ -0,0 +1,105 @@
+#![feature(test)]
+#![no_std]
+#![no_main]
+#![test_runner(common::test_case_runner)]
+#![feature(custom_test_frameworks)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate hermit;
+
+use core::ffi::CStr;
+
+use hermit::errno::Errno;
+use hermit::fd::{AccessPermission, OpenOption};
+use hermit::fs::FileAttr;
+use hermit::syscalls::{sys_close, sys_fstat, sys_open, sys_read, sys_write};
+
+mod common;
+
+/// Opens the device `path` and returns the descriptor.
+fn open(path: &CStr, flags: OpenOption) -> i32 {
+	let fd = unsafe { sys_open(path.as_ptr(), flags.bits(), 0) };
+	assert!(fd >= 0, "unable to open {path:?}: {fd}");
+	fd
+}
+
+/// Encodes a device number with a major and minor number below 256.
+const fn makedev(major: u64, minor: u64) -> u64 {
+	(major << 8) | minor
+}
+
+/// Checks that `path` is a character device with the device number `rdev`.
+fn check_device(path: &CStr, rdev: u64) {
+	let fd = open(path, OpenOption::O_RDONLY);
+	let mut attr = FileAttr::default();
+	assert_eq!(unsafe { sys_fstat(fd, &mut attr) }, 0);
+	assert_eq!(
+		(attr.st_mode & AccessPermission::S_IFMT).bits(),
+		AccessPermission::S_IFCHR.bits()
+	);
+	assert_eq!(attr.st_rdev, rdev);
+	assert_eq!(sys_close(fd), 0);
+}
+
+#[test_case]
+fn test_zero() {
+	let fd = open(c"/dev/zero", OpenOption::O_RDONLY);
+	let mut buf = [0xffu8; 64];
+	let len = unsafe { sys_read(fd, buf.as_mut_ptr(), buf.len()) };
+	assert_eq!(len, 64);
+	assert!(buf.iter().all(|&byte| byte == 0));
+	assert_eq!(sys_close(fd), 0);
+}
+
+#[test_case]
+fn test_urandom() {
+	let fd = open(c"/dev/urandom", OpenOption::O_RDONLY);
+	let mut buf = [0u8; 64];
+	let len = unsafe { sys_read(fd, buf.as_mut_ptr(), buf.len()) };
+	assert_eq!(len, 64);
+	// The probability of 64 random zero bytes is negligible.
+	assert!(buf.iter().any(|&byte| byte != 0));
+	assert_eq!(sys_close(fd), 0);
+}
+
+#[test_case]
+fn test_null() {
+	let fd = open(c"/dev/null", OpenOption::O_WRONLY);
+	let buf = [0x55u8; 64];
+	let len = unsafe { sys_write(fd, buf.as_ptr(), buf.len()) };
+	assert_eq!(len, 64);
+
+	let fd_read = open(c"/dev/null", OpenOption::O_RDONLY);
+	let mut buf = [0u8; 64];
+	let len = unsafe { sys_read(fd_read, buf.as_mut_ptr(), buf.len()) };
+	assert_eq!(len, 0);
+
+	assert_eq!(sys_close(fd_read), 0);
+	assert_eq!(sys_close(fd), 0);
+}
+
+#[test_case]
+fn test_full() {
+	let fd = open(c"/dev/full", OpenOption::O_WRONLY);
+	let buf = [0x55u8; 64];
+	let ret = unsafe { sys_write(fd, buf.as_ptr(), buf.len()) };
+	assert_eq!(ret, (-i32::from(Errno::Nospc)).try_into().unwrap());
+	assert_eq!(sys_close(fd), 0);
+}
+
+#[test_case]
+fn test_fstat() {
+	check_device(c"/dev/null", makedev(1, 3));
+	check_device(c"/dev/zero", makedev(1, 5));
+	check_device(c"/dev/full", makedev(1, 7));
+	check_device(c"/dev/urandom", makedev(1, 9));
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn runtime_entry(_argc: i32, _argv: *const *const u8, _env: *const *const u8) -> ! {
+	test_main();
+	common::exit(false)
+}
@@ -0,0 +1,129 @@
+#![feature(test)]
+#![no_std]
+#![no_main]
+#![test_runner(common::test_case_runner)]
+#![feature(custom_test_frameworks)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate hermit;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::{CStr, c_char};
+use core::mem::offset_of;
+
+use hermit::fd::OpenOption;
+use hermit::syscalls::{Dirent64, sys_close, sys_getdents64, sys_open, sys_read, sys_readlink};
+
+mod common;
+
+/// Opens `path`, which has to be null-terminated, and returns the descriptor.
+fn open(path: &CStr, flags: OpenOption) -> i32 {
+	let fd = unsafe { sys_open(path.as_ptr(), flags.bits(), 0) };
+	assert!(fd >= 0, "unable to open {path:?}: {fd}");
+	fd
+}
+
+fn read_to_string(path: &CStr) -> String {
+	let fd = open(path, OpenOption::O_RDONLY);
+	let mut content = Vec::new();
+	let mut buf = [0u8; 256];
+	loop {
+		let len = unsafe { sys_read(fd, buf.as_mut_ptr(), buf.len()) };
+		assert!(len >= 0, "unable to read {path:?}: {len}");
+		if len == 0 {
+			break;
+		}
+		content.extend_from_slice(&buf[..len.try_into().unwrap()]);
+	}
+	assert_eq!(sys_close(fd), 0);
+	String::from_utf8(content).unwrap()
+}
+
+fn readlink(path: &CStr) -> String {
+	let mut buf = [0u8; 256];
+	let len = unsafe { sys_readlink(path.as_ptr(), buf.as_mut_ptr(), buf.len()) };
+	assert!(len >= 0, "unable to read the link {path:?}: {len}");
+	String::from_utf8(buf[..len.try_into().unwrap()].to_vec()).unwrap()
+}
+
+/// Returns the names of the entries of the directory `path`.
+fn read_dir(path: &CStr) -> Vec<String> {
+	let fd = open(path, OpenOption::O_RDONLY | OpenOption::O_DIRECTORY);
+	let mut names = Vec::new();
+	let mut buf = [0u64; 128];
+	loop {
+		let len =
+			unsafe { sys_getdents64(fd, buf.as_mut_ptr().cast::<Dirent64>(), size_of_val(&buf)) };
+		assert!(len >= 0, "unable to read the directory {path:?}: {len}");
+		if len == 0 {
+			break;
+		}
+
+		let bytes = buf.as_ptr().cast::<u8>();
+		let mut offset = 0;
+		while offset < usize::try_from(len).unwrap() {
+			let dirent = unsafe { &*bytes.add(offset).cast::<Dirent64>() };
+			let name = unsafe {
+				CStr::from_ptr(
+					bytes
+						.add(offset + offset_of!(Dirent64, d_name))
+						.cast::<c_char>(),
+				)
+			};
+			names.push(String::from(name.to_str().unwrap()));
+			offset += usize::from(dirent.d_reclen);
+		}
+	}
+	assert_eq!(sys_close(fd), 0);
+	names
+}
+
+#[test_case]
+fn test_meminfo() {
+	let meminfo = read_to_string(c"/proc/meminfo");
+	let total = meminfo
+		.lines()
+		.find_map(|line| line.strip_prefix("MemTotal:"))
+		.expect("MemTotal is missing");
+	let total: u64 = total.trim().trim_end_matches("kB").trim().parse().unwrap();
+	assert!(total > 0);
+	assert!(meminfo.lines().any(|line| line.starts_with("MemFree:")));
+}
+
+#[test_case]
+fn test_uptime() {
+	let uptime = read_to_string(c"/proc/uptime");
+	let (seconds, idle) = uptime.trim_end().split_once(' ').unwrap();
+	let seconds: f64 = seconds.parse().unwrap();
+	let idle: f64 = idle.parse().unwrap();
+	assert!(seconds > 0.0);
+	assert!(idle >= 0.0);
+}
+
+#[test_case]
+fn test_self_fd() {
+	let fd = open(c"/proc/meminfo", OpenOption::O_RDONLY);
+
+	let names = read_dir(c"/proc/self/fd");
+	for expected in ["0", "1", "2"] {
+		assert!(names.iter().any(|name| name == expected), "{names:?}");
+	}
+	assert!(names.contains(&format!("{fd}")), "{names:?}");
+
+	let link = format!("/proc/self/fd/{fd}\0");
+	let link = CStr::from_bytes_with_nul(link.as_bytes()).unwrap();
+	assert_eq!(readlink(link), "/proc/meminfo");
+
+	assert_eq!(sys_close(fd), 0);
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn runtime_entry(_argc: i32, _argv: *const *const u8, _env: *const *const u8) -> ! {
+	test_main();
+	common::exit(false)
+}
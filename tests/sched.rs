@@ -0,0 +1,226 @@
+#![feature(test)]
+#![no_std]
+#![no_main]
+#![test_runner(common::test_case_runner)]
+#![feature(custom_test_frameworks)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate hermit;
+
+use core::hint;
+use core::sync::atomic::Ordering::Relaxed;
+use core::sync::atomic::{AtomicBool, AtomicU64};
+
+mod common;
+
+use alloc::vec::Vec;
+
+use hermit::errno::Errno;
+use hermit::syscalls::{
+	sched_attr, sched_param, sys_clock_gettime, sys_join, sys_sched_get_priority_max,
+	sys_sched_getattr, sys_sched_getcpu, sys_sched_getparam, sys_sched_getscheduler,
+	sys_sched_setaffinity, sys_sched_setattr, sys_sched_setscheduler, sys_spawn2, sys_yield,
+};
+use hermit::time::timespec;
+
+const USER_STACK_SIZE: usize = 0x0010_0000;
+const NORMAL_PRIO: u8 = 2;
+
+const SCHED_OTHER: i32 = 0;
+const SCHED_FIFO: i32 = 1;
+const SCHED_DEADLINE: i32 = 6;
+const CLOCK_MONOTONIC: i32 = 4;
+
+/// Returns the monotonic time in microseconds.
+fn now() -> u64 {
+	let mut tp = timespec::default();
+	let ret = unsafe { sys_clock_gettime(CLOCK_MONOTONIC, &raw mut tp) };
+	assert_eq!(ret, 0);
+	tp.into_usec().unwrap().try_into().unwrap()
+}
+
+fn spin(usecs: u64) {
+	let end = now() + usecs;
+	while now() < end {
+		hint::spin_loop();
+	}
+}
+
+fn set_deadline(runtime: u64, period: u64) -> i32 {
+	let attr = sched_attr {
+		size: size_of::<sched_attr>().try_into().unwrap(),
+		sched_policy: SCHED_DEADLINE.try_into().unwrap(),
+		sched_runtime: runtime * 1000,
+		sched_deadline: period * 1000,
+		sched_period: period * 1000,
+		..Default::default()
+	};
+	unsafe { sys_sched_setattr(0, &raw const attr, 0) }
+}
+
+fn set_other() {
+	let param = sched_param { sched_priority: 0 };
+	let ret = unsafe { sys_sched_setscheduler(0, SCHED_OTHER, &raw const param) };
+	assert_eq!(ret, 0);
+}
+
+#[test_case]
+fn test_fifo() {
+	assert_eq!(sys_sched_getscheduler(0), SCHED_OTHER);
+
+	let max = sys_sched_get_priority_max(SCHED_FIFO);
+	let param = sched_param {
+		sched_priority: max + 1,
+	};
+	let ret = unsafe { sys_sched_setscheduler(0, SCHED_FIFO, &raw const param) };
+	assert_eq!(ret, -i32::from(Errno::Inval));
+
+	let param = sched_param {
+		sched_priority: max,
+	};
+	let ret = unsafe { sys_sched_setscheduler(0, SCHED_FIFO, &raw const param) };
+	assert_eq!(ret, 0);
+	assert_eq!(sys_sched_getscheduler(0), SCHED_FIFO);
+
+	let mut param = sched_param::default();
+	let ret = unsafe { sys_sched_getparam(0, &raw mut param) };
+	assert_eq!(ret, 0);
+	assert_eq!(param.sched_priority, max);
+
+	set_other();
+	assert_eq!(sys_sched_getscheduler(0), SCHED_OTHER);
+}
+
+#[test_case]
+fn test_deadline_admission() {
+	assert_eq!(set_deadline(0, 10_000), -i32::from(Errno::Inval));
+	assert_eq!(set_deadline(20_000, 10_000), -i32::from(Errno::Inval));
+	assert_eq!(set_deadline(9_900, 10_000), -i32::from(Errno::Busy));
+	assert_eq!(set_deadline(1_000, 10_000), 0);
+
+	let mut attr = sched_attr::default();
+	let size = size_of::<sched_attr>().try_into().unwrap();
+	let ret = unsafe { sys_sched_getattr(0, &raw mut attr, size, 0) };
+	assert_eq!(ret, 0);
+	assert_eq!(attr.sched_policy, SCHED_DEADLINE.try_into().unwrap());
+	assert_eq!(attr.sched_runtime, 1_000_000);
+	assert_eq!(attr.sched_period, 10_000_000);
+
+	set_other();
+}
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn hog(_arg: usize) {
+	while !STOP.load(Relaxed) {
+		hint::spin_loop();
+	}
+}
+
+/// A deadline task finishes each job before its deadline, even if normal
+/// tasks keep its core busy.
+#[test_case]
+fn test_deadline_under_load() {
+	const RUNTIME: u64 = 3_000;
+	const PERIOD: u64 = 10_000;
+	const JOBS: usize = 20;
+
+	let core_id = sys_sched_getcpu();
+	STOP.store(false, Relaxed);
+	let hogs: Vec<_> = (0..2)
+		.map(|_| unsafe {
+			sys_spawn2(
+				hog,
+				0,
+				NORMAL_PRIO,
+				USER_STACK_SIZE,
+				core_id.try_into().unwrap(),
+			)
+		})
+		.collect();
+
+	assert_eq!(set_deadline(RUNTIME, PERIOD), 0);
+
+	let mut finished = Vec::with_capacity(JOBS);
+	for _ in 0..JOBS {
+		spin(RUNTIME / 3);
+		finished.push(now());
+		// Finish the job and wait for the next period.
+		sys_yield();
+	}
+
+	set_other();
+	STOP.store(true, Relaxed);
+	for hog in hogs {
+		assert_eq!(sys_join(hog), 0);
+	}
+
+	// Consecutive jobs finish at most one period plus one relative deadline apart.
+	for pair in finished.windows(2) {
+		let distance = pair[1] - pair[0];
+		assert!(distance < 2 * PERIOD, "jobs finished {distance} us apart");
+	}
+}
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+extern "C" fn count(_arg: usize) {
+	while !STOP.load(Relaxed) {
+		COUNTER.fetch_add(1, Relaxed);
+	}
+}
+
+/// Restricts the current thread to the core `core_id` or, if `core_id` is
+/// `None`, allows all cores again.
+fn pin(core_id: Option<i32>) {
+	let mut mask = [0u8; 32];
+	match core_id {
+		Some(core_id) => {
+			let i = usize::try_from(core_id).unwrap();
+			mask[i / 8] |= 1 << (i % 8);
+		}
+		None => mask.fill(u8::MAX),
+	}
+	let ret = unsafe { sys_sched_setaffinity(0, mask.len(), mask.as_ptr()) };
+	assert_eq!(ret, 0);
+}
+
+/// A FIFO thread with the lowest priority is not preempted by a normal thread.
+#[test_case]
+fn test_fifo_above_normal() {
+	let core_id = sys_sched_getcpu();
+	// The spawned thread inherits the affinity and stays on this core.
+	pin(Some(core_id));
+
+	let param = sched_param { sched_priority: 1 };
+	let ret = unsafe { sys_sched_setscheduler(0, SCHED_FIFO, &raw const param) };
+	assert_eq!(ret, 0);
+
+	STOP.store(false, Relaxed);
+	COUNTER.store(0, Relaxed);
+	let counter = unsafe {
+		sys_spawn2(
+			count,
+			0,
+			NORMAL_PRIO,
+			USER_STACK_SIZE,
+			core_id.try_into().unwrap(),
+		)
+	};
+	spin(20_000);
+	assert_eq!(COUNTER.load(Relaxed), 0);
+
+	STOP.store(true, Relaxed);
+	set_other();
+	assert_eq!(sys_join(counter), 0);
+	pin(None);
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn runtime_entry(_argc: i32, _argv: *const *const u8, _env: *const *const u8) -> ! {
+	test_main();
+	common::exit(false)
+}
@@ -11,6 +11,7 @@ extern crate alloc;
 #[macro_use]
 extern crate hermit;
 
+use core::ffi::CStr;
 use core::ptr;
 use core::sync::atomic::AtomicU32;
 use core::sync::atomic::Ordering::Relaxed;
@@ -18,9 +19,17 @@ use core::sync::atomic::Ordering::Relaxed;
 mod common;
 
 use alloc::vec;
+use alloc::vec::Vec;
 
 use hermit::errno::Errno;
-use hermit::syscalls::{sys_futex_wait, sys_futex_wake, sys_join, sys_spawn2, sys_usleep};
+use hermit::syscalls::{
+	FUTEX2_SIZE_U32, TASK_FINISHED, THREAD_NAME_LEN, futex_waitv, sys_detach,
+	sys_futex_cmp_requeue, sys_futex_lock_pi, sys_futex_trylock_pi, sys_futex_unlock_pi,
+	sys_futex_wait, sys_futex_wait_bitset, sys_futex_waitv, sys_futex_wake, sys_futex_wake_bitset,
+	sys_futex_wake_op, sys_get_priority, sys_get_tasks, sys_get_thread_name, sys_gettid, sys_join,
+	sys_join_timeout, sys_sched_getcpu, sys_set_thread_name, sys_spawn2, sys_thread_exit,
+	sys_usleep, task_info,
+};
 use hermit::time::timespec;
 
 const USER_STACK_SIZE: usize = 0x0010_0000;
@@ -93,6 +102,320 @@ pub fn test_futex() {
 	assert_eq!(ret, 0);
 }
 
+unsafe extern "C" fn bitset_waker_func(futex: usize) {
+	let futex = unsafe { &*(futex as *const AtomicU32) };
+
+	sys_usleep(100_000);
+
+	let ret = unsafe { sys_futex_wake_bitset(futex.as_ptr(), i32::MAX, 0b10) };
+	assert_eq!(ret, 0);
+
+	futex.store(1, Relaxed);
+	let ret = unsafe { sys_futex_wake_bitset(futex.as_ptr(), i32::MAX, 0b01) };
+	assert_eq!(ret, 1);
+}
+
+#[test_case]
+pub fn test_futex_bitset() {
+	let futex = AtomicU32::new(0);
+	let futex_ptr = futex.as_ptr();
+
+	let ret = unsafe { sys_futex_wait_bitset(futex_ptr, 0, ptr::null(), 0, 0) };
+	assert_eq!(ret, -i32::from(Errno::Inval));
+
+	let waker = unsafe {
+		sys_spawn2(
+			bitset_waker_func,
+			futex_ptr as usize,
+			NORMAL_PRIO,
+			USER_STACK_SIZE,
+			-1,
+		)
+	};
+	assert!(waker >= 0);
+
+	let ret = unsafe { sys_futex_wait_bitset(futex_ptr, 0, ptr::null(), 0, 0b11) };
+	assert_eq!(ret, 0);
+	assert_eq!(futex.load(Relaxed), 1);
+
+	let ret = sys_join(waker);
+	assert_eq!(ret, 0);
+}
+
+unsafe extern "C" fn waiter_func(futex: usize) {
+	let futex = unsafe { &*(futex as *const AtomicU32) };
+
+	let value = futex.load(Relaxed);
+	let ret = unsafe { sys_futex_wait(futex.as_ptr(), value, ptr::null(), 0) };
+	assert_eq!(ret, 0);
+}
+
+#[test_case]
+pub fn test_futex_requeue() {
+	let futex = AtomicU32::new(0);
+	let futex2 = AtomicU32::new(0);
+	let futex_ptr = futex.as_ptr();
+	let futex2_ptr = futex2.as_ptr();
+
+	let waiters: Vec<_> = (0..2)
+		.map(|_| unsafe {
+			sys_spawn2(
+				waiter_func,
+				futex_ptr as usize,
+				NORMAL_PRIO,
+				USER_STACK_SIZE,
+				-1,
+			)
+		})
+		.collect();
+	sys_usleep(100_000);
+
+	let ret = unsafe { sys_futex_cmp_requeue(futex_ptr, 1, 1, futex2_ptr, i32::MAX) };
+	assert_eq!(ret, -i32::from(Errno::Again));
+
+	// One waiter is woken up and the other one is moved to the second futex.
+	let ret = unsafe { sys_futex_cmp_requeue(futex_ptr, 0, 1, futex2_ptr, i32::MAX) };
+	assert_eq!(ret, 2);
+	let ret = unsafe { sys_futex_wake(futex_ptr, i32::MAX) };
+	assert_eq!(ret, 0);
+	let ret = unsafe { sys_futex_wake(futex2_ptr, i32::MAX) };
+	assert_eq!(ret, 1);
+
+	for waiter in waiters {
+		assert_eq!(sys_join(waiter), 0);
+	}
+}
+
+const FUTEX_OP_SET: u32 = 0;
+const FUTEX_OP_ADD: u32 = 1;
+const FUTEX_OP_CMP_EQ: u32 = 0;
+
+const fn futex_op(op: u32, oparg: u32, cmp: u32, cmparg: u32) -> u32 {
+	(op << 28) | (cmp << 24) | (oparg << 12) | cmparg
+}
+
+#[test_case]
+pub fn test_futex_wake_op() {
+	let futex = AtomicU32::new(0);
+	let futex2 = AtomicU32::new(0);
+	let futex_ptr = futex.as_ptr();
+	let futex2_ptr = futex2.as_ptr();
+
+	let op = futex_op(FUTEX_OP_ADD, 1, FUTEX_OP_CMP_EQ, 0);
+	let ret = unsafe { sys_futex_wake_op(futex_ptr, 1, futex2_ptr, 1, op) };
+	assert_eq!(ret, 0);
+	assert_eq!(futex2.load(Relaxed), 1);
+
+	let waiter = unsafe {
+		sys_spawn2(
+			waiter_func,
+			futex2_ptr as usize,
+			NORMAL_PRIO,
+			USER_STACK_SIZE,
+			-1,
+		)
+	};
+	assert!(waiter >= 0);
+	sys_usleep(100_000);
+
+	// The old value matches, so that the waiter of the second futex is woken up.
+	let op = futex_op(FUTEX_OP_SET, 2, FUTEX_OP_CMP_EQ, 1);
+	let ret = unsafe { sys_futex_wake_op(futex_ptr, 1, futex2_ptr, 1, op) };
+	assert_eq!(ret, 1);
+	assert_eq!(futex2.load(Relaxed), 2);
+
+	let ret = sys_join(waiter);
+	assert_eq!(ret, 0);
+}
+
+#[test_case]
+pub fn test_futex_waitv() {
+	let futex = AtomicU32::new(0);
+	let futex2 = AtomicU32::new(0);
+	let futex2_ptr = futex2.as_ptr();
+
+	let mut waiters = [&futex, &futex2].map(|futex| futex_waitv {
+		val: 0,
+		uaddr: futex.as_ptr() as u64,
+		flags: FUTEX2_SIZE_U32,
+		__reserved: 0,
+	});
+
+	let ret = unsafe { sys_futex_waitv(waiters.as_ptr(), 0, ptr::null(), 0) };
+	assert_eq!(ret, -i32::from(Errno::Inval));
+
+	waiters[0].val = 1;
+	let ret = unsafe { sys_futex_waitv(waiters.as_ptr(), 2, ptr::null(), 0) };
+	assert_eq!(ret, -i32::from(Errno::Again));
+	waiters[0].val = 0;
+
+	let timeout = timespec {
+		tv_sec: 0,
+		tv_nsec: 100_000_000,
+	};
+	let ret = unsafe { sys_futex_waitv(waiters.as_ptr(), 2, &raw const timeout, 1) };
+	assert_eq!(ret, -i32::from(Errno::Timedout));
+
+	let waker = unsafe {
+		sys_spawn2(
+			waker_func,
+			futex2_ptr as usize,
+			NORMAL_PRIO,
+			USER_STACK_SIZE,
+			-1,
+		)
+	};
+	assert!(waker >= 0);
+
+	// The thread is woken up by the second futex.
+	let ret = unsafe { sys_futex_waitv(waiters.as_ptr(), 2, ptr::null(), 0) };
+	assert_eq!(ret, 1);
+	assert_eq!(futex2.load(Relaxed), 1);
+
+	let ret = sys_join(waker);
+	assert_eq!(ret, 0);
+}
+
+const FUTEX_WAITERS: u32 = 0x8000_0000;
+const FUTEX_TID_MASK: u32 = 0x3fff_ffff;
+
+fn gettid() -> u32 {
+	sys_gettid().try_into().unwrap()
+}
+
+unsafe extern "C" fn pi_waiter_func(futex: usize) {
+	let futex = unsafe { &*(futex as *const AtomicU32) };
+
+	let ret = unsafe { sys_futex_lock_pi(futex.as_ptr(), ptr::null(), 0) };
+	assert_eq!(ret, 0);
+	assert_eq!(futex.load(Relaxed) & FUTEX_TID_MASK, gettid());
+
+	let ret = unsafe { sys_futex_unlock_pi(futex.as_ptr()) };
+	assert_eq!(ret, 0);
+}
+
+#[test_case]
+pub fn test_futex_pi() {
+	let futex = AtomicU32::new(0);
+	let futex_ptr = futex.as_ptr();
+
+	let ret = unsafe { sys_futex_trylock_pi(futex_ptr) };
+	assert_eq!(ret, 0);
+	assert_eq!(futex.load(Relaxed), gettid());
+
+	let ret = unsafe { sys_futex_trylock_pi(futex_ptr) };
+	assert_eq!(ret, -i32::from(Errno::Deadlk));
+
+	// The owner inherits the priority of the waiting thread.
+	let prio = sys_get_priority();
+	let core_id = sys_sched_getcpu().try_into().unwrap();
+	let waiter = unsafe {
+		sys_spawn2(
+			pi_waiter_func,
+			futex_ptr as usize,
+			prio + 1,
+			USER_STACK_SIZE,
+			core_id,
+		)
+	};
+	assert!(waiter >= 0);
+
+	while futex.load(Relaxed) & FUTEX_WAITERS == 0 {
+		sys_usleep(1_000);
+	}
+	assert_eq!(sys_get_priority(), prio + 1);
+
+	let ret = unsafe { sys_futex_unlock_pi(futex_ptr) };
+	assert_eq!(ret, 0);
+	assert_eq!(sys_get_priority(), prio);
+
+	let ret = sys_join(waiter);
+	assert_eq!(ret, 0);
+	assert_eq!(futex.load(Relaxed), 0);
+
+	let ret = unsafe { sys_futex_unlock_pi(futex_ptr) };
+	assert_eq!(ret, -i32::from(Errno::Perm));
+}
+
+extern "C" fn exit_func(status: usize) {
+	sys_thread_exit(status.try_into().unwrap());
+}
+
+extern "C" fn sleep_func(usecs: usize) {
+	sys_usleep(usecs.try_into().unwrap());
+}
+
+#[test_case]
+pub fn test_join_status() {
+	let ret = unsafe { sys_join_timeout(sys_gettid(), ptr::null_mut(), ptr::null()) };
+	assert_eq!(ret, -i32::from(Errno::Deadlk));
+
+	let child = unsafe { sys_spawn2(exit_func, 42, NORMAL_PRIO, USER_STACK_SIZE, -1) };
+	assert!(child >= 0);
+
+	let mut status = 0;
+	let ret = unsafe { sys_join_timeout(child, &raw mut status, ptr::null()) };
+	assert_eq!(ret, 0);
+	assert_eq!(status, 42);
+
+	// The exit status is released by the first join.
+	let ret = sys_join(child);
+	assert_eq!(ret, -i32::from(Errno::Srch));
+}
+
+#[test_case]
+pub fn test_join_timeout_and_detach() {
+	let child = unsafe { sys_spawn2(sleep_func, 200_000, NORMAL_PRIO, USER_STACK_SIZE, -1) };
+	assert!(child >= 0);
+
+	let timeout = timespec {
+		tv_sec: 0,
+		tv_nsec: 10_000_000,
+	};
+	let ret = unsafe { sys_join_timeout(child, ptr::null_mut(), &raw const timeout) };
+	assert_eq!(ret, -i32::from(Errno::Timedout));
+
+	assert_eq!(sys_detach(child), 0);
+	assert_eq!(sys_detach(child), -i32::from(Errno::Inval));
+	assert_eq!(sys_join(child), -i32::from(Errno::Inval));
+
+	// The detached thread is cleaned up after it has finished.
+	sys_usleep(400_000);
+	assert_eq!(sys_detach(child), -i32::from(Errno::Srch));
+}
+
+#[test_case]
+pub fn test_thread_name() {
+	let ret = unsafe { sys_set_thread_name(0, c"a-very-long-thread-name".as_ptr()) };
+	assert_eq!(ret, -i32::from(Errno::Range));
+
+	let ret = unsafe { sys_set_thread_name(0, c"test".as_ptr()) };
+	assert_eq!(ret, 0);
+
+	let mut buf = [0; THREAD_NAME_LEN];
+	let ret = unsafe { sys_get_thread_name(0, buf.as_mut_ptr(), buf.len()) };
+	assert_eq!(ret, 0);
+	assert_eq!(unsafe { CStr::from_ptr(buf.as_ptr()) }, c"test");
+
+	let child = unsafe { sys_spawn2(sleep_func, 100_000, NORMAL_PRIO, USER_STACK_SIZE, -1) };
+	assert!(child >= 0);
+	let ret = unsafe { sys_set_thread_name(child, c"sleeper".as_ptr()) };
+	assert_eq!(ret, 0);
+
+	let len = unsafe { sys_get_tasks(ptr::null_mut(), 0) };
+	assert!(len >= 2);
+	let mut tasks = vec![task_info::default(); len.try_into().unwrap()];
+	let ret = unsafe { sys_get_tasks(tasks.as_mut_ptr(), tasks.len()) };
+	assert!(ret >= len);
+
+	let info = tasks.iter().find(|info| info.id == child).unwrap();
+	assert_eq!(&info.name[..8], b"sleeper\0");
+	assert_ne!(info.status, TASK_FINISHED);
+	assert_eq!(info.stack_size, USER_STACK_SIZE.try_into().unwrap());
+
+	assert_eq!(sys_join(child), 0);
+}
+
 #[test_case]
 pub fn test_thread_local() {
 	#[repr(C, align(0x10))]
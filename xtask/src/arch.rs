@@ -153,9 +153,30 @@ impl Arch {
 
 	pub fn rustflags(&self) -> &'static [&'static str] {
 		match self {
-			Self::X86_64 => &[],
-			Self::Aarch64 | Self::Aarch64Be => &["-Crelocation-model=pic"],
-			Self::Riscv64 => &["-Cno-redzone", "-Crelocation-model=pic"],
+			Self::X86_64 => &["-Cforce-frame-pointers=yes"],
+			Self::Aarch64 | Self::Aarch64Be => {
+				&["-Cforce-frame-pointers=yes", "-Crelocation-model=pic"]
+			}
+			Self::Riscv64 => &[
+				"-Cforce-frame-pointers=yes",
+				"-Cno-redzone",
+				"-Crelocation-model=pic",
+			],
+		}
+	}
+
+	/// Arguments for compiling LLVM IR to objects, which can be linked with the kernel.
+	pub fn llc_args(&self) -> &'static [&'static str] {
+		match self {
+			Self::X86_64 => &["-mtriple=x86_64-unknown-none-elf", "-relocation-model=pic"],
+			Self::Aarch64 => &["-mtriple=aarch64-unknown-none", "-relocation-model=pic"],
+			Self::Aarch64Be => &["-mtriple=aarch64_be-unknown-none", "-relocation-model=pic"],
+			Self::Riscv64 => &[
+				"-mtriple=riscv64-unknown-none-elf",
+				"-mattr=+m,+a,+f,+d,+c",
+				"-target-abi=lp64d",
+				"-relocation-model=pic",
+			],
 		}
 	}
 
@@ -7,6 +7,9 @@ use goblin::archive::Archive as GoblinArchive;
 use goblin::elf64::header;
 use xshell::cmd;
 
+use crate::arch::Arch;
+use crate::symtab::SymbolTable;
+
 pub struct Archive(PathBuf);
 
 impl From<PathBuf> for Archive {
@@ -81,6 +84,50 @@ impl Archive {
 		Ok(())
 	}
 
+	/// Appends a symbol table of the kernel's functions for symbolizing backtraces.
+	///
+	/// The table refers to the functions by relocations, which are resolved
+	/// when the application is linked.
+	/// Local functions are made global under a unique name, so that the table
+	/// can refer to them.
+	pub fn append_symbol_table(&self, arch: Arch) -> Result<()> {
+		let sh = crate::sh()?;
+		let archive = self.as_ref();
+		let members_dir = archive.with_extension("members");
+		let object = archive.with_extension("symtab.o");
+
+		let ar = crate::binutil("ar").unwrap();
+		sh.create_dir(&members_dir)?;
+		cmd!(sh, "{ar} x --output={members_dir} {archive}").run()?;
+
+		// Only functions of the kernel itself are included.
+		let mut members = sh
+			.read_dir(&members_dir)?
+			.into_iter()
+			.filter(|member| {
+				member
+					.file_name()
+					.and_then(|name| name.to_str())
+					.is_some_and(|name| name.starts_with("hermit-"))
+			})
+			.collect::<Vec<_>>();
+		members.sort();
+
+		let mut symbol_table = SymbolTable::default();
+		for (index, member) in members.iter().enumerate() {
+			symbol_table.add_object(member, index)?;
+		}
+		cmd!(sh, "{ar} r {archive} {members...}").run()?;
+
+		symbol_table.compile(arch, &object)?;
+		cmd!(sh, "{ar} q {archive} {object}").run()?;
+
+		sh.remove_path(&members_dir)?;
+		sh.remove_path(&object)?;
+
+		Ok(())
+	}
+
 	pub fn append(&self, file: &Self) -> Result<()> {
 		let sh = crate::sh()?;
 		let archive = self.as_ref();
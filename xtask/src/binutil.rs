@@ -26,7 +26,7 @@ impl LlvmTools {
 			.iter()
 			.collect::<PathBuf>();
 
-		let example_exe = exe("objdump");
+		let example_exe = exe("llvm-objdump");
 		for entry in rustlib.read_dir()? {
 			let bin = entry?.path().join("bin");
 			if bin.join(&example_exe).exists() {
@@ -42,12 +42,15 @@ impl LlvmTools {
 	}
 
 	pub fn tool(&self, name: &str) -> Option<PathBuf> {
-		let path = self.bin.join(exe(name));
-		path.exists().then_some(path)
+		// Most tools are prefixed with `llvm-`, but some, such as `llc`, are not.
+		[exe(&format!("llvm-{name}")), exe(name)]
+			.into_iter()
+			.map(|exe| self.bin.join(exe))
+			.find(|path| path.exists())
 	}
 }
 
 fn exe(name: &str) -> String {
 	let exe_suffix = std::env::consts::EXE_SUFFIX;
-	format!("llvm-{name}{exe_suffix}")
+	format!("{name}{exe_suffix}")
 }
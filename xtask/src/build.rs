@@ -19,6 +19,13 @@ pub struct Build {
 	/// Enable the `-Z randomize-layout` flag.
 	#[arg(long)]
 	pub randomize_layout: bool,
+
+	/// Append a symbol table for symbolizing kernel backtraces.
+	///
+	/// The table refers to every kernel function, so that the application
+	/// linker cannot remove unused ones. Compiling the table requires `llc`.
+	#[arg(long)]
+	pub symtab: bool,
 }
 
 impl Build {
@@ -55,6 +62,11 @@ impl Build {
 		sh.create_dir(dist_archive.as_ref().parent().unwrap())?;
 		sh.copy_file(&build_archive, &dist_archive)?;
 
+		if self.symtab {
+			eprintln!("Generating symbol table");
+			dist_archive.append_symbol_table(self.cargo_build.artifact.arch)?;
+		}
+
 		eprintln!("Exporting symbols");
 		self.export_syms()?;
 
@@ -106,6 +118,11 @@ impl Build {
 
 		rustflags.extend(self.cargo_build.artifact.arch.rustflags());
 
+		if self.symtab {
+			// The kernel refers to the symbol table, which is appended after building.
+			rustflags.push("--cfg=hermit_symtab");
+		}
+
 		Ok(rustflags.join("\x1f"))
 	}
 
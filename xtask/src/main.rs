@@ -9,6 +9,7 @@ mod cargo_build;
 mod ci;
 mod clippy;
 mod doc;
+mod symtab;
 
 use std::env;
 use std::path::{Path, PathBuf};
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use xshell::cmd;
+
+use crate::arch::Arch;
+
+/// Symbol table for symbolizing kernel backtraces.
+///
+/// The table is compiled to an object, which defines `hermit_symtab`:
+///
+/// ```text
+/// struct SymbolTable {
+///     symbols: *const Symbol,
+///     len: usize,
+///     names: *const u8,
+///     names_len: usize,
+/// }
+///
+/// struct Symbol {
+///     addr: usize,
+///     size: usize,
+///     name_offset: usize,
+///     name_len: usize,
+/// }
+/// ```
+#[derive(Default)]
+pub struct SymbolTable {
+	declarations: String,
+	entries: Vec<String>,
+	names: String,
+}
+
+impl SymbolTable {
+	/// Adds the functions, which are defined by `object`.
+	///
+	/// Local functions are renamed with the unique `suffix` and made global.
+	pub fn add_object(&mut self, object: &Path, suffix: usize) -> Result<()> {
+		let sh = crate::sh()?;
+
+		let nm = crate::binutil("nm").unwrap();
+		let symbols = cmd!(sh, "{nm} --defined-only --format=posix {object}").read()?;
+		let demangled =
+			cmd!(sh, "{nm} --defined-only --format=posix --demangle {object}").read()?;
+
+		let functions = symbols
+			.lines()
+			.zip(demangled.lines())
+			.filter_map(|(line, demangled)| {
+				let (symbol, kind, size) = posix_symbol(line)?;
+				let (name, _, _) = posix_symbol(demangled)?;
+				let size = u64::from_str_radix(size, 16).ok()?;
+				(matches!(kind, "T" | "t") && size > 0).then_some((symbol, kind, size, name))
+			})
+			.collect::<Vec<_>>();
+
+		// Local symbols are not necessarily unique.
+		let mut counts = HashMap::<&str, usize>::new();
+		for (symbol, ..) in &functions {
+			*counts.entry(symbol).or_default() += 1;
+		}
+
+		let mut renames = String::new();
+		let mut globals = String::new();
+		for (symbol, kind, size, name) in functions {
+			if counts[symbol] > 1 {
+				continue;
+			}
+
+			let symbol = if kind == "t" {
+				let global = format!("{symbol}.symtab.{suffix}");
+				let _ = writeln!(renames, "{symbol} {global}");
+				let _ = writeln!(globals, "{global}");
+				global
+			} else {
+				symbol.to_string()
+			};
+
+			let name = clean_name(name);
+			let _ = writeln!(self.declarations, "declare void @\"{symbol}\"()");
+			self.entries.push(format!(
+				"{{ ptr, i64, i64, i64 }} {{ ptr @\"{symbol}\", i64 {size}, i64 {}, i64 {} }}",
+				self.names.len(),
+				name.len()
+			));
+			self.names.push_str(&name);
+		}
+
+		if !renames.is_empty() {
+			let renames_path = object.with_extension("redefine-syms");
+			let globals_path = object.with_extension("globalize-syms");
+			sh.write_file(&renames_path, renames)?;
+			sh.write_file(&globals_path, globals)?;
+
+			let objcopy = crate::binutil("objcopy").unwrap();
+			cmd!(sh, "{objcopy} --redefine-syms={renames_path} {object}").run()?;
+			cmd!(sh, "{objcopy} --globalize-symbols={globals_path} {object}").run()?;
+
+			sh.remove_path(&renames_path)?;
+			sh.remove_path(&globals_path)?;
+		}
+
+		Ok(())
+	}
+
+	/// Compiles the symbol table to `object`.
+	pub fn compile(&self, arch: Arch, object: &Path) -> Result<()> {
+		let sh = crate::sh()?;
+
+		let len = self.entries.len();
+		let names_len = self.names.len();
+		let mut ir = self.declarations.clone();
+		let _ = writeln!(
+			ir,
+			"@symbols = private unnamed_addr constant [{len} x {{ ptr, i64, i64, i64 }}] [{}]",
+			self.entries.join(", ")
+		);
+		let _ = writeln!(
+			ir,
+			"@names = private unnamed_addr constant [{names_len} x i8] c\"{}\"",
+			escape_ir_string(&self.names)
+		);
+		let _ = writeln!(
+			ir,
+			"@hermit_symtab = constant {{ ptr, i64, ptr, i64 }} {{ ptr @symbols, i64 {len}, ptr @names, i64 {names_len} }}"
+		);
+
+		let ir_path = object.with_extension("ll");
+		sh.write_file(&ir_path, ir)?;
+
+		let llc = crate::binutil("llc").unwrap();
+		let llc_args = arch.llc_args();
+		cmd!(
+			sh,
+			"{llc} {llc_args...} -filetype=obj -o {object} {ir_path}"
+		)
+		.run()?;
+
+		sh.remove_path(&ir_path)?;
+
+		Ok(())
+	}
+}
+
+/// Splits a line of `nm --format=posix` into the name, type and size of the symbol.
+///
+/// Demangled names may contain spaces, so the line is split from the right.
+fn posix_symbol(line: &str) -> Option<(&str, &str, &str)> {
+	let mut fields = line.rsplitn(4, ' ');
+	let size = fields.next()?;
+	let _value = fields.next()?;
+	let kind = fields.next()?;
+	let name = fields.next()?;
+	Some((name, kind, size))
+}
+
+/// Cleans up a name demangled by `nm`.
+///
+/// `nm` demangles legacy Rust symbols like C++ symbols, which keeps the hash
+/// and the escape sequences of Rust's legacy mangling.
+fn clean_name(name: &str) -> String {
+	let name = match name.rsplit_once("::h") {
+		Some((path, hash)) if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) => {
+			path
+		}
+		_ => name,
+	};
+
+	let mut name = name.replace("..", "::").replace("::_$", "::$");
+	if let Some(stripped) = name.strip_prefix("_$") {
+		name = format!("${stripped}");
+	}
+	for (escape, c) in [
+		("$SP$", "@"),
+		("$BP$", "*"),
+		("$RF$", "&"),
+		("$LT$", "<"),
+		("$GT$", ">"),
+		("$LP$", "("),
+		("$RP$", ")"),
+		("$C$", ","),
+		("$u20$", " "),
+		("$u27$", "'"),
+		("$u5b$", "["),
+		("$u5d$", "]"),
+		("$u7b$", "{"),
+		("$u7d$", "}"),
+		("$u7e$", "~"),
+	] {
+		name = name.replace(escape, c);
+	}
+	name
+}
+
+/// Escapes `s` for a string constant in LLVM IR.
+fn escape_ir_string(s: &str) -> String {
+	s.bytes().fold(String::new(), |mut output, byte| {
+		if (byte.is_ascii_graphic() || byte == b' ') && byte != b'"' && byte != b'\\' {
+			output.push(char::from(byte));
+		} else {
+			let _ = write!(output, "\\{byte:02X}");
+		}
+		output
+	})
+}